@@ -1,3 +1,4 @@
+use fly::tls;
 use openssl::{ec, pkey::PKey, rsa, ssl, x509};
 use std::collections::HashMap;
 use std::sync::RwLock;
@@ -15,7 +16,7 @@ lazy_static! {
     static ref DEFAULT_CTX: ssl::SslContext = {
         let mut builder = ssl::SslContextBuilder::new(ssl::SslMethod::tls()).unwrap();
 
-        setup_base_ctx(&mut builder);
+        tls::setup_base_ctx(&mut builder);
 
         let certs_path = {
             match GLOBAL_SETTINGS.read().unwrap().certs_path {
@@ -51,23 +52,6 @@ lazy_static! {
     };
 }
 
-fn setup_base_ctx(builder: &mut ssl::SslContextBuilder) {
-    builder.options().insert(ssl::SslOptions::NO_TICKET);
-    builder.set_alpn_protos(b"\x02h2\x08http/1.1").unwrap();
-    builder.set_alpn_select_callback(|_, client| {
-        ssl::select_next_proto(b"\x02h2\x08http/1.1", client).ok_or(ssl::AlpnError::NOACK)
-    });
-    // builder.set_session_cache_mode(
-    //     ssl::SslSessionCacheMode::SERVER | ssl::SslSessionCacheMode::NO_INTERNAL,
-    // );
-
-    // builder.set_new_session_callback(new_session_callback);
-    // unsafe {
-    //     builder.set_get_session_callback(get_session_callback);
-    // }
-    // builder.set_remove_session_callback(remove_session_callback);
-}
-
 // fn new_session_callback(_ssl_ref: &mut ssl::SslRef, sess: ssl::SslSession) {
 //     info!("NEW SESSION callback! id: {:?}", sess.id());
 //     let mut w = match SESSION_CACHE.write() {
@@ -118,19 +102,11 @@ pub fn get_ctx(servername: &str) -> Result<Option<ssl::SslContext>, String> {
         return Ok(Some(ctx));
     }
 
-    let mut wildcard: Option<String> = None;
-    let mut splitted = servername.split(".");
-    let parts = splitted.clone().count();
-    if parts > 2 {
-        let first = splitted.next().unwrap();
-        if first != "*" {
-            let mut skipped = splitted.skip(parts - 3); // we've already read 1, so skip 1 less than 2 (-3)
-            let wc = format!("*.{}.{}", skipped.next().unwrap(), skipped.next().unwrap());
-
-            if let Some(ctx) = get_cached_ctx(wc.as_str()) {
-                return Ok(Some(ctx));
-            }
-            wildcard = Some(wc);
+    // one label only, a.b.example.com isn't covered by *.example.com
+    let wildcard = tls::wildcard_name(servername);
+    if let Some(ref wc) = wildcard {
+        if let Some(ctx) = get_cached_ctx(wc.as_str()) {
+            return Ok(Some(ctx));
         }
     }
 
@@ -148,7 +124,7 @@ pub fn get_ctx(servername: &str) -> Result<Option<ssl::SslContext>, String> {
                 Err(e) => Err(format!("{}", e)),
                 Ok(mut builder) => {
                     debug!("building ssl ctx");
-                    setup_base_ctx(&mut builder);
+                    tls::setup_base_ctx(&mut builder);
                     let mut added = 0;
                     for c in res.iter() {
                         if !c.is_empty() {
@@ -96,6 +96,20 @@ fn main() {
         }
     };
 
+    // apps get the same fetch settings, a bad one stops the node rather than every app
+    {
+        let s = GLOBAL_SETTINGS.read().unwrap();
+        let settings = fly::settings::Settings {
+            egress: s.egress.clone(),
+            http_client: s.http_client.clone(),
+            ..Default::default()
+        };
+        if let Err(e) = fly::http_client::check_settings(&settings) {
+            error!("invalid fetch settings: {}", e);
+            std::process::exit(1);
+        }
+    }
+
     let addr = {
         let s = GLOBAL_SETTINGS.read().unwrap();
         format!(
@@ -143,11 +157,7 @@ fn main() {
         }
     });
 
-    tls_builder.set_alpn_protos(b"\x02h2\x08http/1.1").unwrap();
-    tls_builder.set_alpn_select_callback(|_, client| {
-        openssl::ssl::select_next_proto(b"\x02h2\x08http/1.1", client)
-            .ok_or(openssl::ssl::AlpnError::NOACK)
-    });
+    fly::tls::setup_base_ctx(&mut tls_builder);
 
     let certs_path = {
         match GLOBAL_SETTINGS.read().unwrap().certs_path {
@@ -289,15 +299,21 @@ fn main() {
     }));
 }
 
-use std::time;
+use fly::standard_runtime_manager::DEFAULT_DRAIN_TIMEOUT;
 
-static MAX_RUNTIME_IDLE_SECONDS: usize = 5 * 60;
+const DEFAULT_RUNTIME_IDLE_TTL: Duration = Duration::from_secs(5 * 60);
 
 fn runtime_monitoring() -> impl Future<Item = (), Error = ()> + Send + 'static {
+    let idle_ttl = GLOBAL_SETTINGS
+        .read()
+        .unwrap()
+        .runtime_idle_ttl_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_RUNTIME_IDLE_TTL);
     Interval::new_interval(Duration::from_secs(15))
         .map_err(|e| error!("timer error: {}", e))
         .take_while(|_| Ok(unsafe { SELECTOR.is_some() }))
-        .for_each(|_| {
+        .for_each(move |_| {
             match unsafe { SELECTOR.as_ref().unwrap() }.runtimes.read() {
                 Err(e) => error!("error getting read lock on runtime selector: {}", e),
                 Ok(guard) => {
@@ -326,13 +342,10 @@ fn runtime_monitoring() -> impl Future<Item = (), Error = ()> + Send + 'static {
                         );
 
                         // teardown idle runtimes.
-                        if let Ok(epoch) = time::SystemTime::now().duration_since(time::UNIX_EPOCH)
-                        {
-                            if epoch.as_secs() as usize - rt.last_event_at.load(Ordering::SeqCst)
-                                > MAX_RUNTIME_IDLE_SECONDS
-                            {
-                                let key = k.clone();
-                                tokio::spawn(future::lazy(move || {
+                        if rt.idle_for() >= idle_ttl {
+                            let key = k.clone();
+                            tokio::spawn(future::lazy(move || {
+                                let idle = {
                                     let mut w = match unsafe { SELECTOR.as_ref().unwrap() }
                                         .runtimes
                                         .write()
@@ -346,15 +359,22 @@ fn runtime_monitoring() -> impl Future<Item = (), Error = ()> + Send + 'static {
                                             poisoned.into_inner()
                                         }
                                     };
-                                    match w.remove(&key) {
-                                        None => {}
-                                        Some(mut rt) => {
-                                            rt.dispose();
-                                        }
-                                    };
-                                    Ok(())
-                                }));
-                            }
+                                    // it might have gotten an event since it was checked
+                                    let still_idle = w.get(&key).map_or(false, |rt| {
+                                        rt.idle_for() >= idle_ttl && rt.pending_events() == 0
+                                    });
+                                    if still_idle {
+                                        w.remove(&key)
+                                    } else {
+                                        None
+                                    }
+                                };
+                                // drained without holding the selector lock
+                                if let Some(mut rt) = idle {
+                                    rt.shutdown(DEFAULT_DRAIN_TIMEOUT);
+                                }
+                                Ok(())
+                            }));
                         }
                     });
                 }
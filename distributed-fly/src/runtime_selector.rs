@@ -2,11 +2,13 @@ use futures::Future;
 
 use fly::{
     runtime::{Runtime, RuntimeConfig},
+    runtime_permissions::RuntimePermissions,
     RuntimeSelector, SelectorError,
 };
 
 use std::collections::HashMap;
 use std::sync::RwLock;
+use std::time::Duration;
 
 use crate::libs::fetch_libs;
 use crate::release::Release;
@@ -55,7 +57,8 @@ impl RuntimeSelector for DistributedRuntimeSelector {
 
         let exists = {
             match runtimes.read() {
-                Ok(guard) => guard.contains_key(&key),
+                // one that ran out of heap gets replaced
+                Ok(guard) => guard.get(&key).map_or(false, |rt| !rt.is_poisoned()),
                 Err(e) => return Err(SelectorError::Failure(format!("{}", e))),
             }
         };
@@ -122,9 +125,37 @@ impl RuntimeSelector for DistributedRuntimeSelector {
                         url: global_settings.redis_url.clone(),
                         namespace: None,
                     })),
+                    heap_limits: global_settings.heap_limits,
+                    isolate_pool_size: None,
+                    event_loop_threads: None,
+                    admin_bind_addr: None,
+                    admin_token: None,
+                    code_cache: None,
+                    stream_buffer_limit_mb: None,
+                    egress: global_settings.egress.clone(),
+                    fetch_timeouts: None,
+                    http_client: global_settings.http_client.clone(),
+                    compression: None,
+                    cpu_budget: global_settings.cpu_budget,
+                    runtime_idle_ttl_secs: global_settings.runtime_idle_ttl_secs,
                 }
             };
 
+            let merged_conf = rel.clone().parsed_config().unwrap();
+            let base_permissions = GLOBAL_SETTINGS
+                .read()
+                .unwrap()
+                .permissions
+                .clone()
+                .unwrap_or_default();
+            let permissions = serde_json::from_str(&merged_conf)
+                .map_err(|e| format!("invalid config: {}", e))
+                .and_then(|conf| {
+                    RuntimePermissions::from_app_config(&conf, &base_permissions)
+                        .map_err(|e| e.to_string())
+                })
+                .map_err(|e| SelectorError::Failure(format!("app {}: {}", rel.app, e)))?;
+
             let mut rt = Runtime::new(RuntimeConfig {
                 name: Some(rel.app_id.to_string()),
                 version: Some(rel.version.to_string()),
@@ -132,10 +163,13 @@ impl RuntimeSelector for DistributedRuntimeSelector {
                 module_resolvers: Some(vec![]),
                 app_logger: &slog_scope::logger(),
                 msg_handler: None,
-                permissions: None,
+                ops: None,
+                permissions: Some(permissions),
+                cpu_budget: None,
+                heap_limits: None,
                 dev_tools: false,
+                snapshot: None,
             });
-            let merged_conf = rel.clone().parsed_config().unwrap();
             rt.eval(
                 "<app config>",
                 &format!(
@@ -174,7 +208,9 @@ impl RuntimeSelector for DistributedRuntimeSelector {
                 // runtimes.write().unwrap().remove(&key2);
                 Ok(())
             }));
-            writer.insert(key.clone(), rt);
+            if let Some(mut poisoned) = writer.insert(key.clone(), rt) {
+                poisoned.shutdown(Duration::from_secs(0));
+            }
         }
 
         let runtimes = runtimes.read().unwrap(); // TODO: no unwrap
@@ -1,10 +1,17 @@
 extern crate config;
 
+use fly::runtime_permissions::RuntimePermissions;
+use fly::settings::{EgressConfig, HeapLimits, HttpClientConfig};
+use fly::watchdog::CpuBudget;
 use std::sync::RwLock;
 
 lazy_static! {
     pub static ref GLOBAL_SETTINGS: RwLock<GlobalSettings> = {
         let mut settings = config::Config::new();
+        // settings that are more than a string, like permissions, only fit in the file
+        settings
+            .merge(config::File::with_name(".fly").required(false))
+            .unwrap();
         settings.merge(config::Environment::new()).unwrap();
         RwLock::new(settings.try_into().unwrap())
     };
@@ -44,6 +51,16 @@ pub struct GlobalSettings {
     // pub logger_host: String,
     // pub logger_port: String,
     pub sentry_dsn: Option<String>,
+    // what apps are allowed to do, their config can only narrow it. No os access when unset.
+    pub permissions: Option<RuntimePermissions>,
+    pub cpu_budget: Option<CpuBudget>,
+    pub heap_limits: Option<HeapLimits>,
+    // addresses fetch can connect to once hosts are resolved
+    pub egress: Option<EgressConfig>,
+    // how fetch connects to origins
+    pub http_client: Option<HttpClientConfig>,
+    // seconds a runtime may go without events before it's torn down, defaults to 5 minutes
+    pub runtime_idle_ttl_secs: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -4,6 +4,8 @@
 #include <libplatform/libplatform.h>
 #include "allocator.h"
 #include "file_output_stream.h"
+#include "cpu_profile.h"
+#include "inspector.h"
 
 #define ISOLATE_SCOPE(iso)                                                    \
   v8::Locker locker(iso);                /* Lock to current thread.        */ \
@@ -215,6 +217,12 @@ void Print(const v8::FunctionCallbackInfo<v8::Value> &args)
   v8::HandleScope handle_scope(isolate);
   v8::String::Utf8Value msg(isolate, args[1]);
 
+  if (rt == nullptr)
+  {
+    // creating a snapshot, there's no runtime to log to
+    printf("%s\n", *msg);
+    return;
+  }
   rt->print_cb(rt, lvl, *msg);
 }
 
@@ -322,6 +330,70 @@ bool ExecuteV8StringSource(v8::Local<v8::Context> context,
   return true;
 }
 
+// Like ExecuteV8StringSource, compiling with cached data when there is some, and producing
+// it when there was none or v8 rejected it.
+js_eval_result ExecuteV8CachedSource(v8::Local<v8::Context> context,
+                                     const char *filename,
+                                     const char *code,
+                                     fly_simple_buf cached_data)
+{
+  auto *isolate = context->GetIsolate();
+  v8::Isolate::Scope isolate_scope(isolate);
+  v8::HandleScope handle_scope(isolate);
+
+  v8::Context::Scope context_scope(context);
+
+  v8::TryCatch try_catch(isolate);
+
+  v8::ScriptOrigin origin(v8_str(filename));
+
+  bool use_cache = cached_data.ptr != nullptr && cached_data.len > 0;
+  // the source doesn't own the cached data, it stays with the caller
+  v8::ScriptCompiler::Source source(
+      v8_str(code),
+      origin,
+      use_cache ? new v8::ScriptCompiler::CachedData(reinterpret_cast<const uint8_t *>(cached_data.ptr), cached_data.len) : nullptr);
+
+  auto script = v8::ScriptCompiler::Compile(
+      context,
+      &source,
+      use_cache ? v8::ScriptCompiler::kConsumeCodeCache : v8::ScriptCompiler::kNoCompileOptions);
+
+  if (script.IsEmpty())
+  {
+    // DCHECK(try_catch.HasCaught());
+    HandleException(context, try_catch.Exception());
+    return js_eval_result{false, false, fly_simple_buf{nullptr, 0}};
+  }
+
+  auto script_local = script.ToLocalChecked();
+
+  bool rejected = use_cache && source.GetCachedData()->rejected;
+  fly_simple_buf code_cache{nullptr, 0};
+  if (!use_cache || rejected)
+  {
+    v8::ScriptCompiler::CachedData *data = v8::ScriptCompiler::CreateCodeCache(script_local->GetUnboundScript());
+    if (data != nullptr)
+    {
+      char *bytes = new char[data->length];
+      memcpy(bytes, data->data, data->length);
+      code_cache = fly_simple_buf{bytes, data->length};
+      delete data;
+    }
+  }
+
+  auto result = script_local->Run(context);
+
+  if (result.IsEmpty())
+  {
+    // DCHECK(try_catch.HasCaught());
+    HandleException(context, try_catch.Exception());
+    return js_eval_result{false, rejected, code_cache};
+  }
+
+  return js_eval_result{true, rejected, code_cache};
+}
+
 v8::MaybeLocal<v8::Module> ModuleImportCallback(
     v8::Local<v8::Context> context,
     v8::Local<v8::String> specifier,
@@ -335,6 +407,12 @@ v8::MaybeLocal<v8::Module> ModuleImportCallback(
 
   js_compiled_module module_data = rt->resolve_cb(rt, *specifier_utf_val, referrer->GetIdentityHash());
 
+  // resolution failed, the callback has thrown the error already
+  if (module_data.ptr == nullptr)
+  {
+    return v8::MaybeLocal<v8::Module>();
+  }
+
   v8::Persistent<v8::Module> *module_persistent = static_cast<v8::Persistent<v8::Module> *>(module_data.ptr);
 
   v8::Local<v8::Module> module_local = module_persistent->Get(isolate);
@@ -358,7 +436,7 @@ bool ExecuteV8Module(v8::Local<v8::Context> context, js_compiled_module module_d
 
   auto module_instantiated = module_local->InstantiateModule(context, &ModuleImportCallback);
 
-  if (module_instantiated.FromMaybe(false))
+  if (!module_instantiated.FromMaybe(false))
   {
     // DCHECK(try_catch.HasCaught());
     HandleException(context, try_catch.Exception());
@@ -377,7 +455,7 @@ bool ExecuteV8Module(v8::Local<v8::Context> context, js_compiled_module module_d
   return true;
 }
 
-js_compile_module_result CompileV8Module(v8::Local<v8::Context> context, js_module_data module_data)
+js_compile_module_result CompileV8Module(v8::Local<v8::Context> context, js_module_data module_data, fly_simple_buf cached_data)
 {
   auto *isolate = context->GetIsolate();
   v8::Isolate::Scope isolate_scope(isolate);
@@ -397,33 +475,77 @@ js_compile_module_result CompileV8Module(v8::Local<v8::Context> context, js_modu
                           v8::Boolean::New(isolate, module_data.is_wasm), // is WASM
                           True(isolate));                                 // is ES6 module
 
-  v8::ScriptCompiler::Source module_source(v8_str_from_fly_simple_buf(isolate, module_data.source_code), origin);
+  bool use_cache = cached_data.ptr != nullptr && cached_data.len > 0;
+  // the source doesn't own the cached data, it stays with the caller
+  v8::ScriptCompiler::Source module_source(
+      v8_str_from_fly_simple_buf(isolate, module_data.source_code),
+      origin,
+      use_cache ? new v8::ScriptCompiler::CachedData(reinterpret_cast<const uint8_t *>(cached_data.ptr), cached_data.len) : nullptr);
 
-  auto module = v8::ScriptCompiler::CompileModule(isolate, &module_source);
+  auto module = v8::ScriptCompiler::CompileModule(
+      isolate,
+      &module_source,
+      use_cache ? v8::ScriptCompiler::kConsumeCodeCache : v8::ScriptCompiler::kNoCompileOptions);
 
   if (module.IsEmpty())
   {
     // DCHECK(try_catch.HasCaught());
-    HandleException(context, try_catch.Exception());
+    // kept for the import that asked for this module, it fails with them
+    js_runtime *rt = FromIsolate(isolate);
+    if (rt != nullptr)
+    {
+      auto message = try_catch.Message();
+      v8::String::Utf8Value exception_str(isolate, try_catch.Exception());
+      std::string diagnostics = *exception_str;
+      if (!message.IsEmpty())
+      {
+        v8::String::Utf8Value script_name(isolate, message->GetScriptResourceName());
+        char buf[64];
+        snprintf(buf, sizeof(buf), ":%d:%d",
+                 message->GetLineNumber(context).FromMaybe(0),
+                 message->GetStartColumn(context).FromMaybe(0));
+        diagnostics += "\n    at ";
+        diagnostics += *script_name;
+        diagnostics += buf;
+      }
+      rt->last_exception = diagnostics;
+    }
     return js_compile_module_result{
-        NULL,
+        js_compiled_module{0, module_data, nullptr},
+        false,
         false,
+        fly_simple_buf{nullptr, 0},
     };
   }
 
   auto module_local = module.ToLocalChecked();
 
-  v8::Persistent<v8::Module> module_persistent(isolate, module_local);
+  bool rejected = use_cache && module_source.GetCachedData()->rejected;
+  fly_simple_buf code_cache{nullptr, 0};
+  if (!use_cache || rejected)
+  {
+    v8::ScriptCompiler::CachedData *data = v8::ScriptCompiler::CreateCodeCache(module_local->GetUnboundModuleScript());
+    if (data != nullptr)
+    {
+      char *bytes = new char[data->length];
+      memcpy(bytes, data->data, data->length);
+      code_cache = fly_simple_buf{bytes, data->length};
+      delete data;
+    }
+  }
 
-  auto compiled_module = js_compiled_module {
-    module_local->GetIdentityHash(),
-    module_data,
-    &module_persistent,
-  };
+  // lives as long as the isolate, modules are never unloaded
+  auto module_persistent = new v8::Persistent<v8::Module>(isolate, module_local);
 
   return js_compile_module_result{
-      &compiled_module,
+      js_compiled_module{
+          module_local->GetIdentityHash(),
+          module_data,
+          module_persistent,
+      },
       true,
+      rejected,
+      code_cache,
   };
 }
 
@@ -453,6 +575,40 @@ void Recv(const v8::FunctionCallbackInfo<v8::Value> &args)
   rt->recv.Reset(isolate, func);
 }
 
+// set in the isolate's second data slot while creating an app snapshot
+struct snapshot_sender
+{
+  fly_snapshot_send_cb cb;
+  void *data;
+};
+
+// Creating a snapshot, there's no runtime. Messages go to the snapshot's send callback,
+// which only handles what evaluating an app needs (loading modules, adding listeners).
+static void SendFromSnapshot(const v8::FunctionCallbackInfo<v8::Value> &args)
+{
+  v8::Isolate *isolate = args.GetIsolate();
+  auto sender = static_cast<snapshot_sender *>(isolate->GetData(1));
+  if (sender == nullptr)
+  {
+    isolate->ThrowException(v8::Exception::Error(v8_str("libfly.send is not available while creating a snapshot.")));
+    return;
+  }
+
+  v8::HandleScope handle_scope(isolate);
+  auto view = v8::Local<v8::ArrayBufferView>::Cast(args[0]);
+  auto contents = view->Buffer()->GetContents();
+  fly_simple_buf msg{static_cast<const char *>(contents.Data()) + view->ByteOffset(),
+                     static_cast<int>(view->ByteLength())};
+
+  fly_simple_buf res = sender->cb(sender->data, msg);
+  if (res.len > 0)
+  {
+    auto ab = v8::ArrayBuffer::New(isolate, res.len);
+    memcpy(ab->GetContents().Data(), res.ptr, res.len);
+    args.GetReturnValue().Set(ab);
+  }
+}
+
 void Send(const v8::FunctionCallbackInfo<v8::Value> &args)
 {
   v8::Isolate *isolate = args.GetIsolate();
@@ -463,6 +619,12 @@ void Send(const v8::FunctionCallbackInfo<v8::Value> &args)
   js_runtime *rt = static_cast<js_runtime *>(isolate->GetData(0));
   // DCHECK_EQ(d->isolate, isolate);
 
+  if (rt == nullptr)
+  {
+    SendFromSnapshot(args);
+    return;
+  }
+
   v8::Locker locker(rt->isolate);
   v8::EscapableHandleScope handle_scope(isolate);
 
@@ -554,9 +716,13 @@ extern "C"
     return v8::V8::GetVersion();
   }
 
+  // kept around for the inspector, which needs to pump its message loop
+  static v8::Platform *default_platform = nullptr;
+
   void js_init()
   {
     auto p = v8::platform::CreateDefaultPlatform();
+    default_platform = p;
     v8::V8::InitializePlatform(p);
     v8::V8::Initialize();
     return;
@@ -608,13 +774,22 @@ extern "C"
     }
   }
 
-  const js_runtime *js_runtime_new(js_runtime_options options)
+  // Called by v8 right before it would abort the process for running out of
+  // heap. Execution is terminated and the limit bumped so the stack can unwind.
+  size_t near_heap_limit_cb(void *data, size_t current_heap_limit, size_t initial_heap_limit)
   {
-    js_runtime *rt = new js_runtime;
+    js_runtime *rt = static_cast<js_runtime *>(data);
+    rt->heap_limit_reached = true;
+    rt->isolate->TerminateExecution();
+    return current_heap_limit + initial_heap_limit / 2;
+  }
 
-    rt->recv_cb = options.recv_cb;
-    rt->print_cb = options.print_cb;
-    rt->allocator = new LimitedAllocator(options.soft_memory_limit * 1024 * 1024, options.hard_memory_limit * 1024 * 1024);
+  void InitIsolate(js_runtime *rt)
+  {
+    rt->heap_limit_reached = false;
+    rt->inspector = nullptr;
+    rt->cpu_profiler = nullptr;
+    rt->allocator = new LimitedAllocator(rt->soft_memory_limit * 1024 * 1024, rt->hard_memory_limit * 1024 * 1024);
     v8::Isolate::CreateParams params;
 
     params.array_buffer_allocator = rt->allocator;
@@ -622,14 +797,14 @@ extern "C"
     // if (snapshot.len > 0)
     // {
     auto *blob = new v8::StartupData;
-    blob->data = options.snapshot.ptr;
-    blob->raw_size = static_cast<int>(options.snapshot.len);
+    blob->data = rt->snapshot.ptr;
+    blob->raw_size = static_cast<int>(rt->snapshot.len);
     params.snapshot_blob = blob;
     params.external_references = ext_refs;
     // }
 
     v8::ResourceConstraints rc;
-    rc.set_max_old_space_size(options.soft_memory_limit);
+    rc.set_max_old_space_size(rt->soft_memory_limit);
 
     params.constraints = rc;
 
@@ -641,6 +816,7 @@ extern "C"
     isolate->SetEventLogger(log_event_cb);
     isolate->AddMicrotasksCompletedCallback(microtasks_completed_cb);
     isolate->SetPromiseRejectCallback(promise_rejected_cb);
+    isolate->AddNearHeapLimitCallback(near_heap_limit_cb, rt);
 
     v8::Locker locker(isolate);
     v8::Isolate::Scope isolate_scope(isolate);
@@ -653,13 +829,56 @@ extern "C"
       rt->context.Reset(rt->isolate, context);
     }
 
-    rt->data = options.data;
-
     delete blob;
+  }
+
+  const js_runtime *js_runtime_new(js_runtime_options options)
+  {
+    js_runtime *rt = new js_runtime;
+
+    rt->recv_cb = options.recv_cb;
+    rt->print_cb = options.print_cb;
+    rt->resolve_cb = options.resolve_cb;
+    rt->snapshot = options.snapshot;
+    rt->soft_memory_limit = options.soft_memory_limit;
+    rt->hard_memory_limit = options.hard_memory_limit;
+
+    InitIsolate(rt);
+
+    rt->data = options.data;
 
     return rt;
   }
 
+  bool js_runtime_heap_limit_reached(const js_runtime *rt)
+  {
+    return rt->heap_limit_reached;
+  }
+
+  // Throws away the isolate and everything in it, replacing it with a fresh
+  // one built from the same snapshot. The runtime pointer stays valid.
+  void js_runtime_reset(const js_runtime *rt)
+  {
+    js_runtime *mrt = const_cast<js_runtime *>(rt);
+    if (mrt->isolate)
+    {
+      {
+        v8::Locker locker(mrt->isolate);
+        v8::Isolate::Scope isolate_scope(mrt->isolate);
+        delete mrt->inspector;
+        if (mrt->cpu_profiler)
+          mrt->cpu_profiler->Dispose();
+        mrt->recv.Reset();
+        mrt->global_error_handler.Reset();
+        mrt->context.Reset();
+      }
+      mrt->isolate->Dispose();
+      delete mrt->allocator;
+    }
+    mrt->last_exception.clear();
+    InitIsolate(mrt);
+  }
+
   const void *js_get_data(const js_runtime *rt)
   {
     return rt->data;
@@ -705,6 +924,12 @@ extern "C"
 
     delete[] args;
 
+    if (try_catch.HasTerminated())
+    {
+      rt->isolate->CancelTerminateExecution();
+      return 0;
+    }
+
     if (try_catch.HasCaught())
     {
       HandleException(context, try_catch.Exception());
@@ -726,16 +951,27 @@ extern "C"
     rt->current_args->GetReturnValue().Set(ab);
   }
 
-  void js_runtime_terminate(js_runtime *rt)
+  // Safe to call from any thread, interrupts whatever javascript is currently
+  // running in the isolate. Execution is resumed on the next js_send.
+  void js_runtime_terminate(const js_runtime *rt)
   {
     if (!rt->isolate)
     {
       printf("isolate has been disposed\n");
       return;
     }
-    rt->context.Reset();
-    rt->isolate->Dispose();
-    free(rt);
+    rt->isolate->TerminateExecution();
+  }
+
+  // Drops a termination that hasn't interrupted anything yet. Safe to call from any thread.
+  void js_runtime_cancel_terminate(const js_runtime *rt)
+  {
+    if (!rt->isolate)
+    {
+      printf("isolate has been disposed\n");
+      return;
+    }
+    rt->isolate->CancelTerminateExecution();
   }
 
   void js_runtime_run_micro_tasks(const js_runtime *rt)
@@ -780,6 +1016,17 @@ extern "C"
     // }
   }
 
+  js_eval_result js_eval_cached(const js_runtime *rt, const char *filename, const char *code, fly_simple_buf cached_data)
+  {
+    if (!rt->isolate)
+    {
+      printf("isolate has been disposed\n");
+      return js_eval_result{false, false, fly_simple_buf{nullptr, 0}};
+    }
+    VALUE_SCOPE(rt->isolate, rt->context);
+    return ExecuteV8CachedSource(ctx, filename, code, cached_data);
+  }
+
   bool js_run_module(const js_runtime *rt, js_compiled_module module_data)
   {
     VALUE_SCOPE(rt->isolate, rt->context);
@@ -792,20 +1039,8 @@ extern "C"
   //   return StartupData{data.data, data.raw_size};
   // }
 
-  js_heap_stats js_runtime_heap_statistics(const js_runtime *rt)
+  static js_heap_stats heap_stats(const js_runtime *rt, v8::HeapStatistics &hs)
   {
-    if (!rt->isolate)
-    {
-      printf("isolate has been disposed\n");
-      return js_heap_stats{};
-    }
-    v8::Isolate *isolate = rt->isolate;
-    v8::HeapStatistics hs;
-    {
-      v8::Locker locker(isolate);
-      isolate->LowMemoryNotification();
-    }
-    isolate->GetHeapStatistics(&hs);
     return js_heap_stats{
         hs.total_heap_size(),
         hs.total_heap_size_executable(),
@@ -822,11 +1057,43 @@ extern "C"
     };
   }
 
+  js_heap_stats js_runtime_heap_statistics(const js_runtime *rt)
+  {
+    if (!rt->isolate)
+    {
+      printf("isolate has been disposed\n");
+      return js_heap_stats{};
+    }
+    v8::Isolate *isolate = rt->isolate;
+    v8::HeapStatistics hs;
+    {
+      v8::Locker locker(isolate);
+      isolate->LowMemoryNotification();
+      isolate->GetHeapStatistics(&hs);
+    }
+    return heap_stats(rt, hs);
+  }
+
+  // Doesn't collect garbage first, cheap enough to call while serving.
+  js_heap_stats js_runtime_current_heap_statistics(const js_runtime *rt)
+  {
+    if (!rt->isolate)
+      return js_heap_stats{};
+    v8::HeapStatistics hs;
+    {
+      v8::Locker locker(rt->isolate);
+      rt->isolate->GetHeapStatistics(&hs);
+    }
+    return heap_stats(rt, hs);
+  }
+
   bool js_dump_heap_snapshot(const js_runtime *rt, const char *filename)
   {
     FILE *fp = fopen(filename, "w");
     if (fp == NULL)
       return false;
+    ISOLATE_SCOPE(rt->isolate);
+    v8::HandleScope handle_scope(rt->isolate);
     auto hp = rt->isolate->GetHeapProfiler();
     const v8::HeapSnapshot *const snap = hp->TakeHeapSnapshot();
     FileOutputStream stream(fp);
@@ -839,6 +1106,35 @@ extern "C"
     return true;
   }
 
+  void js_cpu_profile_start(const js_runtime *rt, int sampling_interval_us)
+  {
+    js_runtime *mrt = const_cast<js_runtime *>(rt);
+    VALUE_SCOPE(rt->isolate, rt->context);
+    if (!mrt->cpu_profiler)
+      mrt->cpu_profiler = v8::CpuProfiler::New(rt->isolate);
+    mrt->cpu_profiler->SetSamplingInterval(sampling_interval_us);
+    mrt->cpu_profiler->StartProfiling(v8::String::NewFromUtf8(rt->isolate, kCpuProfileTitle, v8::NewStringType::kNormal).ToLocalChecked(), true);
+  }
+
+  bool js_cpu_profile_stop(const js_runtime *rt, const char *filename)
+  {
+    if (!rt->cpu_profiler)
+      return false;
+    VALUE_SCOPE(rt->isolate, rt->context);
+    v8::CpuProfile *profile = rt->cpu_profiler->StopProfiling(v8::String::NewFromUtf8(rt->isolate, kCpuProfileTitle, v8::NewStringType::kNormal).ToLocalChecked());
+    if (profile == nullptr)
+      return false;
+    FILE *fp = fopen(filename, "w");
+    if (fp == NULL)
+    {
+      profile->Delete();
+      return false;
+    }
+    WriteCpuProfile(fp, profile);
+    profile->Delete();
+    return fclose(fp) == 0;
+  }
+
   void js_runtime_dispose(const runtime *rt)
   {
     if (!rt->isolate)
@@ -846,6 +1142,13 @@ extern "C"
       printf("isolate has been disposed\n");
       return;
     }
+    if (rt->inspector || rt->cpu_profiler)
+    {
+      ISOLATE_SCOPE(rt->isolate);
+      delete rt->inspector;
+      if (rt->cpu_profiler)
+        rt->cpu_profiler->Dispose();
+    }
     rt->isolate->Dispose();
     delete rt;
   }
@@ -875,9 +1178,126 @@ extern "C"
     return fly_simple_buf{blob.data, blob.raw_size};
   }
 
-  js_compile_module_result js_compile_module(const runtime *rt, js_module_data module_data)
+  fly_simple_buf js_create_app_snapshot(fly_simple_buf base, const js_script *scripts, int count, fly_snapshot_send_cb send_cb, void *send_data)
+  {
+    v8::StartupData base_blob{base.ptr, base.len};
+    v8::StartupData blob;
+    snapshot_sender sender{send_cb, send_data};
+    bool ok = true;
+    {
+      v8::SnapshotCreator creator(ext_refs, &base_blob);
+      v8::Isolate *isolate = creator.GetIsolate();
+      isolate->SetData(1, &sender);
+      {
+        v8::HandleScope handle_scope(isolate);
+        v8::Local<v8::Context> context = v8::Context::New(isolate, nullptr, v8::MaybeLocal<v8::ObjectTemplate>(), v8::MaybeLocal<v8::Value>(), v8::DeserializeInternalFieldsCallback(DeserializeInternalFields, nullptr));
+
+        v8::Context::Scope context_scope(context);
+
+        InitContext(isolate, context);
+        for (int i = 0; ok && i < count; i++)
+        {
+          ok = ExecuteV8StringSource(context, scripts[i].filename, scripts[i].code);
+        }
+        // module promises and the like settle before the heap is saved
+        isolate->RunMicrotasks();
+
+        creator.SetDefaultContext(context, v8::SerializeInternalFieldsCallback(
+                                               SerializeInternalFields, nullptr));
+      }
+      isolate->SetData(1, nullptr);
+      // compiled app code is kept, that's most of what we're saving on startup
+      blob =
+          creator.CreateBlob(v8::SnapshotCreator::FunctionCodeHandling::kKeep);
+    }
+
+    if (!ok)
+    {
+      delete[] blob.data;
+      return fly_simple_buf{nullptr, 0};
+    }
+    return fly_simple_buf{blob.data, blob.raw_size};
+  }
+
+  void js_snapshot_free(fly_simple_buf snapshot)
+  {
+    delete[] snapshot.ptr;
+  }
+
+  js_compile_module_result js_compile_module(const runtime *rt, js_module_data module_data, fly_simple_buf cached_data)
   {
     VALUE_SCOPE(rt->isolate, rt->context);
-    return CompileV8Module(ctx, module_data);
+    return CompileV8Module(ctx, module_data, cached_data);
+  }
+
+  void js_code_cache_free(fly_simple_buf code_cache)
+  {
+    delete[] code_cache.ptr;
+  }
+
+  const char *js_runtime_last_exception(const runtime *rt)
+  {
+    return rt->last_exception.c_str();
+  }
+
+  void js_throw_module_error(const runtime *rt, const char *message, const char *details_json)
+  {
+    VALUE_SCOPE(rt->isolate, rt->context);
+    auto error = v8::Exception::Error(v8_str(message)).As<v8::Object>();
+    v8::Local<v8::Value> details;
+    if (v8::JSON::Parse(ctx, v8_str(details_json)).ToLocal(&details) && details->IsObject())
+    {
+      auto details_obj = details.As<v8::Object>();
+      auto keys = details_obj->GetOwnPropertyNames(ctx).ToLocalChecked();
+      for (uint32_t i = 0; i < keys->Length(); i++)
+      {
+        auto key = keys->Get(ctx, i).ToLocalChecked();
+        error->Set(ctx, key, details_obj->Get(ctx, key).ToLocalChecked()).FromJust();
+      }
+    }
+    rt->isolate->ThrowException(error);
+  }
+
+  void js_inspector_start(const js_runtime *rt, fly_inspector_send_cb send_cb, fly_inspector_wait_cb wait_cb, bool wait_for_debugger)
+  {
+    if (!rt->isolate)
+    {
+      printf("isolate has been disposed\n");
+      return;
+    }
+    js_runtime *mrt = const_cast<js_runtime *>(rt);
+    VALUE_SCOPE(rt->isolate, rt->context);
+    mrt->inspector_send_cb = send_cb;
+    mrt->inspector_wait_cb = wait_cb;
+    mrt->inspector = new InspectorClient(mrt, default_platform);
+    if (wait_for_debugger)
+    {
+      mrt->inspector->WaitForDebugger();
+      mrt->inspector->PauseOnNextStatement();
+    }
+  }
+
+  void js_inspector_dispatch(const js_runtime *rt, fly_simple_buf message)
+  {
+    if (!rt->isolate || !rt->inspector)
+    {
+      return;
+    }
+    VALUE_SCOPE(rt->isolate, rt->context);
+    rt->inspector->Dispatch(message.ptr, message.len);
+  }
+
+  // Dispatches queued frontend messages. The isolate is locked before taking any
+  // message so one can't be taken while another thread is paused waiting for it.
+  void js_inspector_dispatch_pending(const js_runtime *rt)
+  {
+    if (!rt->isolate || !rt->inspector)
+    {
+      return;
+    }
+    VALUE_SCOPE(rt->isolate, rt->context);
+    while (rt->inspector_wait_cb(const_cast<js_runtime *>(rt), false))
+    {
+    }
   }
 }
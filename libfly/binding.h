@@ -49,8 +49,26 @@ struct js_compiled_module {
 };
 
 struct js_compile_module_result {
-  const js_compiled_module *compiled_module;
+  js_compiled_module compiled_module;
   bool success;
+  // set when the cached data passed in didn't match the source or v8 build
+  bool cache_rejected;
+  // produced when no usable cached data was passed in, free with js_code_cache_free
+  fly_simple_buf code_cache;
+};
+
+struct js_eval_result {
+  bool success;
+  // set when the cached data passed in didn't match the source or v8 build
+  bool cache_rejected;
+  // produced when no usable cached data was passed in, free with js_code_cache_free
+  fly_simple_buf code_cache;
+};
+
+struct js_script
+{
+  const char *filename;
+  const char *code;
 };
 
 struct js_runtime;
@@ -62,6 +80,15 @@ typedef void (*fly_print_cb)(runtime *rt, int8_t lvl, const char *msg);
 
 typedef js_compiled_module (*fly_resolve_cb)(runtime *rt, const char *specifier, int referer_identity_hash);
 
+// Handles libfly.send while a snapshot is created, there's no runtime to send to. Returns
+// the synchronous response, empty for none. The callback keeps ownership of it.
+typedef fly_simple_buf (*fly_snapshot_send_cb)(void *data, fly_simple_buf msg);
+
+typedef void (*fly_inspector_send_cb)(runtime *rt, fly_simple_buf message);
+// Dispatches the next message from the frontend, blocking for one if `block` is set.
+// Returns false once there's nothing left to wait for.
+typedef bool (*fly_inspector_wait_cb)(runtime *rt, bool block);
+
 struct js_runtime_options
 {
   fly_simple_buf snapshot;
@@ -78,10 +105,17 @@ extern "C" uint32_t c_get_next_stream_id();
 extern "C"
 {
   extern fly_simple_buf js_create_snapshot(const char *filename, const char *code);
+  // Evaluates scripts on top of the `base` snapshot and snapshots the result. Messages they
+  // send go to `send_cb`. Returns an empty buffer if one of them throws.
+  extern fly_simple_buf js_create_app_snapshot(fly_simple_buf base, const js_script *scripts, int count, fly_snapshot_send_cb send_cb, void *send_data);
+  extern void js_snapshot_free(fly_simple_buf snapshot);
 
   extern bool js_dump_heap_snapshot(const runtime *rt, const char *filename);
+  extern void js_cpu_profile_start(const runtime *rt, int sampling_interval_us);
+  extern bool js_cpu_profile_stop(const runtime *rt, const char *filename);
 
   extern bool js_eval(const runtime *rt, const char *filename, const char *code);
+  extern js_eval_result js_eval_cached(const runtime *rt, const char *filename, const char *code, fly_simple_buf cached_data);
 
   extern bool js_run_module(const runtime *rt, js_compiled_module module_data);
 
@@ -90,6 +124,7 @@ extern "C"
   extern void js_init();
 
   extern js_heap_stats js_runtime_heap_statistics(const runtime *rt);
+  extern js_heap_stats js_runtime_current_heap_statistics(const runtime *rt);
 
   extern const runtime *js_runtime_new(js_runtime_options);
 
@@ -98,11 +133,24 @@ extern "C"
   extern void js_set_response(const runtime *rt, fly_buf buf);
 
   extern void js_runtime_dispose(const runtime *rt);
+  extern void js_runtime_terminate(const runtime *rt);
+  extern void js_runtime_cancel_terminate(const runtime *rt);
+  extern bool js_runtime_heap_limit_reached(const runtime *rt);
+  extern void js_runtime_reset(const runtime *rt);
   extern void js_runtime_run_micro_tasks(const runtime *rt);
 
   extern const char *js_version();
 
-  extern js_compile_module_result js_compile_module(const runtime *rt, js_module_data module_data);
+  extern js_compile_module_result js_compile_module(const runtime *rt, js_module_data module_data, fly_simple_buf cached_data);
+  extern void js_code_cache_free(fly_simple_buf code_cache);
+  // Message of the last uncaught exception, or of the last failed module compilation.
+  extern const char *js_runtime_last_exception(const runtime *rt);
+  // Throws an Error with the properties of the `details_json` object copied onto it.
+  extern void js_throw_module_error(const runtime *rt, const char *message, const char *details_json);
+
+  extern void js_inspector_start(const runtime *rt, fly_inspector_send_cb send_cb, fly_inspector_wait_cb wait_cb, bool wait_for_debugger);
+  extern void js_inspector_dispatch(const runtime *rt, fly_simple_buf message);
+  extern void js_inspector_dispatch_pending(const runtime *rt);
 
 } // extern "C"
 
@@ -0,0 +1,83 @@
+#pragma once
+#include <cstdio>
+#include <v8-profiler.h>
+
+static const char kCpuProfileTitle[] = "fly";
+
+static void WriteJsonString(FILE *fp, const char *str)
+{
+  fputc('"', fp);
+  for (const char *c = str; c && *c; c++)
+  {
+    switch (*c)
+    {
+    case '"':
+      fputs("\\\"", fp);
+      break;
+    case '\\':
+      fputs("\\\\", fp);
+      break;
+    case '\n':
+      fputs("\\n", fp);
+      break;
+    case '\r':
+      fputs("\\r", fp);
+      break;
+    case '\t':
+      fputs("\\t", fp);
+      break;
+    default:
+      if (static_cast<unsigned char>(*c) < 0x20)
+        fprintf(fp, "\\u%04x", *c);
+      else
+        fputc(*c, fp);
+    }
+  }
+  fputc('"', fp);
+}
+
+static void WriteCpuProfileNode(FILE *fp, const v8::CpuProfileNode *node, bool first)
+{
+  if (!first)
+    fputc(',', fp);
+  fprintf(fp, "{\"id\":%u,\"callFrame\":{\"functionName\":", node->GetNodeId());
+  WriteJsonString(fp, node->GetFunctionNameStr());
+  fprintf(fp, ",\"scriptId\":\"%d\",\"url\":", node->GetScriptId());
+  WriteJsonString(fp, node->GetScriptResourceNameStr());
+  // devtools expects zero based positions
+  fprintf(fp, ",\"lineNumber\":%d,\"columnNumber\":%d},\"hitCount\":%u,\"children\":[",
+          node->GetLineNumber() - 1, node->GetColumnNumber() - 1, node->GetHitCount());
+  int count = node->GetChildrenCount();
+  for (int i = 0; i < count; i++)
+  {
+    fprintf(fp, i == 0 ? "%u" : ",%u", node->GetChild(i)->GetNodeId());
+  }
+  fputs("]}", fp);
+  for (int i = 0; i < count; i++)
+  {
+    WriteCpuProfileNode(fp, node->GetChild(i), false);
+  }
+}
+
+// Writes the profile in the .cpuprofile format Chrome DevTools loads.
+static void WriteCpuProfile(FILE *fp, const v8::CpuProfile *profile)
+{
+  fputs("{\"nodes\":[", fp);
+  WriteCpuProfileNode(fp, profile->GetTopDownRoot(), true);
+  fprintf(fp, "],\"startTime\":%lld,\"endTime\":%lld,\"samples\":[",
+          static_cast<long long>(profile->GetStartTime()), static_cast<long long>(profile->GetEndTime()));
+  int count = profile->GetSamplesCount();
+  for (int i = 0; i < count; i++)
+  {
+    fprintf(fp, i == 0 ? "%u" : ",%u", profile->GetSample(i)->GetNodeId());
+  }
+  fputs("],\"timeDeltas\":[", fp);
+  int64_t last = profile->GetStartTime();
+  for (int i = 0; i < count; i++)
+  {
+    int64_t ts = profile->GetSampleTimestamp(i);
+    fprintf(fp, i == 0 ? "%lld" : ",%lld", static_cast<long long>(ts - last));
+    last = ts;
+  }
+  fputs("]}", fp);
+}
@@ -0,0 +1,136 @@
+#pragma once
+#include <memory>
+#include <string>
+#include <v8.h>
+#include <v8-inspector.h>
+#include <libplatform/libplatform.h>
+#include "binding.h"
+#include "runtime.h"
+
+using namespace v8;
+
+static const int kInspectorContextGroupId = 1;
+
+static std::string StringViewToUtf8(Isolate *isolate, const v8_inspector::StringView &view)
+{
+  HandleScope handle_scope(isolate);
+  Local<String> str = view.is8Bit()
+                          ? String::NewFromOneByte(isolate, view.characters8(), NewStringType::kNormal, static_cast<int>(view.length())).ToLocalChecked()
+                          : String::NewFromTwoByte(isolate, view.characters16(), NewStringType::kNormal, static_cast<int>(view.length())).ToLocalChecked();
+  String::Utf8Value utf8(isolate, str);
+  return std::string(*utf8, utf8.length());
+}
+
+class InspectorChannel : public v8_inspector::V8Inspector::Channel
+{
+private:
+  js_runtime *rt;
+
+  void Send(const v8_inspector::StringView &message)
+  {
+    std::string utf8 = StringViewToUtf8(rt->isolate, message);
+    rt->inspector_send_cb(rt, fly_simple_buf{utf8.c_str(), static_cast<int>(utf8.length())});
+  }
+
+public:
+  explicit InspectorChannel(js_runtime *rt) : rt(rt) {}
+
+  void sendResponse(int callId, std::unique_ptr<v8_inspector::StringBuffer> message) override
+  {
+    Send(message->string());
+  }
+
+  void sendNotification(std::unique_ptr<v8_inspector::StringBuffer> message) override
+  {
+    Send(message->string());
+  }
+
+  void flushProtocolNotifications() override {}
+};
+
+/**
+ * Bridges a single Chrome DevTools session to the isolate. While paused, v8 calls
+ * runMessageLoopOnPause and we block on the wait callback, which dispatches the
+ * next message from the frontend, until the frontend resumes execution.
+ */
+class InspectorClient : public v8_inspector::V8InspectorClient
+{
+private:
+  js_runtime *rt;
+  Platform *v8_platform;
+  bool paused = false;
+  std::unique_ptr<InspectorChannel> channel;
+  std::unique_ptr<v8_inspector::V8Inspector> inspector;
+  std::unique_ptr<v8_inspector::V8InspectorSession> session;
+
+public:
+  bool waiting_for_debugger = false;
+
+  InspectorClient(js_runtime *rt, Platform *v8_platform) : rt(rt), v8_platform(v8_platform)
+  {
+    channel.reset(new InspectorChannel(rt));
+    inspector = v8_inspector::V8Inspector::create(rt->isolate, this);
+    session = inspector->connect(kInspectorContextGroupId, channel.get(), v8_inspector::StringView());
+    const uint8_t name[] = "fly";
+    inspector->contextCreated(v8_inspector::V8ContextInfo(rt->context.Get(rt->isolate), kInspectorContextGroupId, v8_inspector::StringView(name, sizeof(name) - 1)));
+  }
+
+  void Dispatch(const char *message, int len)
+  {
+    Local<String> str = String::NewFromUtf8(rt->isolate, message, NewStringType::kNormal, len).ToLocalChecked();
+    String::Value utf16(rt->isolate, str);
+    session->dispatchProtocolMessage(v8_inspector::StringView(*utf16, utf16.length()));
+  }
+
+  void PauseOnNextStatement()
+  {
+    const uint8_t reason[] = "Break on start";
+    session->schedulePauseOnNextStatement(v8_inspector::StringView(reason, sizeof(reason) - 1), v8_inspector::StringView());
+  }
+
+  // Blocks until the frontend sends Runtime.runIfWaitingForDebugger
+  void WaitForDebugger()
+  {
+    waiting_for_debugger = true;
+    while (waiting_for_debugger)
+    {
+      if (!rt->inspector_wait_cb(rt, true))
+        break;
+    }
+    waiting_for_debugger = false;
+  }
+
+  void runMessageLoopOnPause(int contextGroupId) override
+  {
+    paused = true;
+    while (paused)
+    {
+      while (v8::platform::PumpMessageLoop(v8_platform, rt->isolate))
+      {
+      }
+      if (!rt->inspector_wait_cb(rt, true))
+        break;
+    }
+    paused = false;
+  }
+
+  void quitMessageLoopOnPause() override
+  {
+    paused = false;
+  }
+
+  void runIfWaitingForDebugger(int contextGroupId) override
+  {
+    waiting_for_debugger = false;
+  }
+
+  Local<Context> ensureDefaultContextInGroup(int contextGroupId) override
+  {
+    return rt->context.Get(rt->isolate);
+  }
+
+  double currentTimeMS() override
+  {
+    return v8_platform->CurrentClockTimeMillis();
+  }
+};
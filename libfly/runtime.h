@@ -3,10 +3,13 @@
 
 #include "allocator.h"
 #include <v8.h>
+#include <v8-profiler.h>
 #include <string>
 #include <sstream>
 #include <iostream>
 
+class InspectorClient;
+
 extern "C"
 {
   struct js_runtime
@@ -22,6 +25,14 @@ extern "C"
     fly_print_cb print_cb;
     std::string last_exception;
     fly_resolve_cb resolve_cb;
+    fly_simple_buf snapshot;
+    size_t soft_memory_limit;
+    size_t hard_memory_limit;
+    bool heap_limit_reached;
+    InspectorClient *inspector;
+    fly_inspector_send_cb inspector_send_cb;
+    fly_inspector_wait_cb inspector_wait_cb;
+    v8::CpuProfiler *cpu_profiler;
   };
 }
 
@@ -12,6 +12,12 @@ pub struct fly_simple_buf {
 unsafe impl Send for fly_simple_buf {}
 unsafe impl Sync for fly_simple_buf {}
 
+#[repr(C)]
+pub struct js_script {
+    pub filename: *const c_char,
+    pub code: *const c_char,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct fly_buf {
@@ -58,6 +64,15 @@ pub struct js_compiled_module {
 pub struct js_compile_module_result {
     pub compiled_module: js_compiled_module,
     pub success: bool,
+    pub cache_rejected: bool,
+    pub code_cache: fly_simple_buf,
+}
+
+#[repr(C)]
+pub struct js_eval_result {
+    pub success: bool,
+    pub cache_rejected: bool,
+    pub code_cache: fly_simple_buf,
 }
 
 #[repr(C)]
@@ -94,23 +109,62 @@ pub fn version() -> String {
 type RecvCb = unsafe extern "C" fn(rt: *const js_runtime, buf: fly_buf, data_buf: fly_buf);
 type PrintCb = unsafe extern "C" fn(rt: *const js_runtime, lvl: i8, msg: *const c_char);
 type ResolveCb = unsafe extern "C" fn(rt: *const js_runtime, specifier: *const c_char, referer_identity_hash: i32) -> js_compiled_module;
+pub type SnapshotSendCb = extern "C" fn(data: *mut c_void, msg: fly_simple_buf) -> fly_simple_buf;
+pub type InspectorSendCb = extern "C" fn(rt: *const js_runtime, message: fly_simple_buf);
+pub type InspectorWaitCb = extern "C" fn(rt: *const js_runtime, block: bool) -> bool;
 
 extern "C" {
     pub fn js_init();
     pub fn js_version() -> *const c_char;
     pub fn js_runtime_new(options: js_runtime_options) -> *const js_runtime;
     pub fn js_runtime_dispose(rt: *const js_runtime) -> *const c_void;
+    pub fn js_runtime_terminate(rt: *const js_runtime);
+    pub fn js_runtime_cancel_terminate(rt: *const js_runtime);
+    pub fn js_runtime_heap_limit_reached(rt: *const js_runtime) -> bool;
+    pub fn js_runtime_reset(rt: *const js_runtime);
     pub fn js_runtime_run_micro_tasks(rt: *const js_runtime) -> *const c_void;
     pub fn js_get_data(rt: *const js_runtime) -> *const c_void;
     pub fn js_set_response(rt: *const js_runtime, buf: fly_buf);
     pub fn js_send(rt: *const js_runtime, buf: fly_buf, raw: fly_buf) -> c_int;
     pub fn js_runtime_heap_statistics(rt: *const js_runtime) -> js_heap_stats;
+    pub fn js_runtime_current_heap_statistics(rt: *const js_runtime) -> js_heap_stats;
     pub fn js_create_snapshot(filename: *const c_char, code: *const c_char) -> fly_simple_buf;
+    pub fn js_create_app_snapshot(
+        base: fly_simple_buf,
+        scripts: *const js_script,
+        count: c_int,
+        send_cb: SnapshotSendCb,
+        send_data: *mut c_void,
+    ) -> fly_simple_buf;
+    pub fn js_snapshot_free(snapshot: fly_simple_buf);
     pub fn js_dump_heap_snapshot(rt: *const js_runtime, filename: *const c_char) -> bool;
+    pub fn js_cpu_profile_start(rt: *const js_runtime, sampling_interval_us: c_int);
+    pub fn js_cpu_profile_stop(rt: *const js_runtime, filename: *const c_char) -> bool;
 
     pub fn js_eval(rt: *const js_runtime, filename: *const c_char, code: *const c_char) -> bool;
+    pub fn js_eval_cached(
+        rt: *const js_runtime,
+        filename: *const c_char,
+        code: *const c_char,
+        cached_data: fly_simple_buf,
+    ) -> js_eval_result;
     pub fn js_run_module(rt: *const js_runtime, module_data: js_compiled_module) -> bool;
-    pub fn js_compile_module(rt: *const js_runtime, module_data: js_module_data) -> js_compile_module_result;
+    pub fn js_compile_module(
+        rt: *const js_runtime,
+        module_data: js_module_data,
+        cached_data: fly_simple_buf,
+    ) -> js_compile_module_result;
+    pub fn js_code_cache_free(code_cache: fly_simple_buf);
+    pub fn js_runtime_last_exception(rt: *const js_runtime) -> *const c_char;
+    pub fn js_throw_module_error(
+        rt: *const js_runtime,
+        message: *const c_char,
+        details_json: *const c_char,
+    );
+
+    pub fn js_inspector_start(rt: *const js_runtime, send_cb: InspectorSendCb, wait_cb: InspectorWaitCb, wait_for_debugger: bool);
+    pub fn js_inspector_dispatch(rt: *const js_runtime, message: fly_simple_buf);
+    pub fn js_inspector_dispatch_pending(rt: *const js_runtime);
 }
 
 #[no_mangle]
@@ -0,0 +1,329 @@
+//! Admin listener for diagnosing live runtimes, kept apart from the app port.
+//!
+//! - `GET /runtimes` lists runtimes
+//! - `GET /runtimes/:id/heap-snapshot` returns a `.heapsnapshot`
+//! - `GET /runtimes/:id/cpu-profile?seconds=N` samples for N seconds and returns a `.cpuprofile`
+//!
+//! `:id` is a runtime's uuid, a hostname bound to it or its name. With a token, requests
+//! have to carry it as `Authorization: Bearer <token>`, without one only loopback
+//! addresses can be bound.
+
+use futures::{future, sync::mpsc, sync::oneshot, Future, Sink, Stream};
+use hyper::service::service_fn;
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::errors::{FlyError, FlyResult};
+use crate::runtime::Runtime;
+use crate::standard_runtime_manager::StandardRuntimeManager;
+
+type BoxedResponseFuture = Box<Future<Item = Response<Body>, Error = hyper::Error> + Send>;
+type RuntimeRef = Arc<RwLock<Box<Runtime>>>;
+
+const DEFAULT_PROFILE_SECONDS: u64 = 10;
+const MAX_PROFILE_SECONDS: u64 = 300;
+const PROFILE_SAMPLING_INTERVAL: Duration = Duration::from_micros(1000);
+const CHUNK_SIZE: usize = 64 * 1024;
+
+lazy_static! {
+    // uuids of runtimes being profiled, v8 only runs one profile at a time per isolate
+    static ref PROFILING: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Starts the admin server on its own thread.
+pub fn listen(
+    addr: SocketAddr,
+    token: Option<String>,
+    rt_manager: Arc<RwLock<StandardRuntimeManager>>,
+) -> FlyResult<()> {
+    if token.is_none() && !addr.ip().is_loopback() {
+        return Err(FlyError::from(format!(
+            "admin listener on {} needs a token, anyone who can reach it could read app memory",
+            addr
+        )));
+    }
+    thread::Builder::new()
+        .name("admin-server".to_string())
+        .spawn(move || {
+            let server = match Server::try_bind(&addr) {
+                Ok(b) => b.serve(move || {
+                    let rt_manager = rt_manager.clone();
+                    let token = token.clone();
+                    service_fn(move |req| {
+                        if !authorized(&req, token.as_ref().map(String::as_str)) {
+                            return error_response(StatusCode::UNAUTHORIZED, "unauthorized");
+                        }
+                        serve_admin_http(req, rt_manager.clone())
+                    })
+                }),
+                Err(e) => {
+                    error!("error binding admin server to {}: {}", addr, e);
+                    return;
+                }
+            };
+            info!("Admin listening on http://{}", addr);
+            tokio::run(server.map_err(|e| error!("admin server error: {}", e)));
+        })
+        .unwrap();
+    Ok(())
+}
+
+// Whether the request carries the token, when there's one.
+fn authorized<T>(req: &Request<T>, token: Option<&str>) -> bool {
+    let token = match token {
+        Some(t) => t,
+        None => return true,
+    };
+    match req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+    {
+        Some(value) if value.starts_with("Bearer ") => {
+            constant_time_eq(value["Bearer ".len()..].as_bytes(), token.as_bytes())
+        }
+        _ => false,
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn serve_admin_http(
+    req: Request<Body>,
+    rt_manager: Arc<RwLock<StandardRuntimeManager>>,
+) -> BoxedResponseFuture {
+    if *req.method() != Method::GET {
+        return error_response(StatusCode::METHOD_NOT_ALLOWED, "method not allowed");
+    }
+
+    let segments: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["runtimes"] => list_runtimes(&rt_manager.read().unwrap()),
+        ["runtimes", id, action] => {
+            let rt = match rt_manager.read().unwrap().find_runtime(id) {
+                Some(rt) => rt,
+                None => return error_response(StatusCode::NOT_FOUND, "runtime not found"),
+            };
+            match *action {
+                "heap-snapshot" => heap_snapshot(rt),
+                "cpu-profile" => {
+                    let seconds = match profile_seconds(req.uri().query()) {
+                        Ok(s) => s,
+                        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+                    };
+                    cpu_profile(rt, Duration::from_secs(seconds))
+                }
+                _ => error_response(StatusCode::NOT_FOUND, "not found"),
+            }
+        }
+        _ => error_response(StatusCode::NOT_FOUND, "not found"),
+    }
+}
+
+fn list_runtimes(rt_manager: &StandardRuntimeManager) -> BoxedResponseFuture {
+    let runtimes: Vec<serde_json::Value> = rt_manager
+        .live_runtimes()
+        .iter()
+        .map(|rt| {
+            let rt = rt.read().unwrap();
+            let stats = rt.current_heap_statistics();
+            json!({
+                "uuid": rt.get_uuid(),
+                "name": rt.name,
+                "version": rt.version,
+                "pending_events": rt.pending_events(),
+                "used_heap_size": stats.used_heap_size,
+                "total_heap_size": stats.total_heap_size,
+            })
+        })
+        .collect();
+    Box::new(future::ok(
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::Value::Array(runtimes).to_string()))
+            .unwrap(),
+    ))
+}
+
+fn profile_seconds(query: Option<&str>) -> Result<u64, String> {
+    let value = query.and_then(|q| {
+        url::form_urlencoded::parse(q.as_bytes())
+            .find(|(k, _)| k == "seconds")
+            .map(|(_, v)| v.into_owned())
+    });
+    let seconds = match value {
+        Some(v) => v
+            .parse::<u64>()
+            .map_err(|e| format!("invalid seconds: {}", e))?,
+        None => DEFAULT_PROFILE_SECONDS,
+    };
+    if seconds == 0 || seconds > MAX_PROFILE_SECONDS {
+        return Err(format!(
+            "seconds must be between 1 and {}",
+            MAX_PROFILE_SECONDS
+        ));
+    }
+    Ok(seconds)
+}
+
+fn heap_snapshot(rt: RuntimeRef) -> BoxedResponseFuture {
+    let (filename, _) = artifact_name(&rt, "heapsnapshot");
+    run_blocking(filename, move |path| {
+        if rt.read().unwrap().dump_heap_snapshot(path) {
+            Ok(())
+        } else {
+            Err("error writing heap snapshot".to_string())
+        }
+    })
+}
+
+fn cpu_profile(rt: RuntimeRef, duration: Duration) -> BoxedResponseFuture {
+    let (filename, uuid) = artifact_name(&rt, "cpuprofile");
+    if !PROFILING.lock().unwrap().insert(uuid.clone()) {
+        return error_response(StatusCode::CONFLICT, "runtime is already being profiled");
+    }
+    run_blocking(filename, move |path| {
+        rt.read()
+            .unwrap()
+            .start_cpu_profile(PROFILE_SAMPLING_INTERVAL);
+        thread::sleep(duration);
+        let ok = rt.read().unwrap().stop_cpu_profile(path);
+        PROFILING.lock().unwrap().remove(&uuid);
+        if ok {
+            Ok(())
+        } else {
+            Err("error writing cpu profile".to_string())
+        }
+    })
+}
+
+fn artifact_name(rt: &RuntimeRef, extension: &str) -> (String, String) {
+    let rt = rt.read().unwrap();
+    let uuid = rt.get_uuid();
+    (format!("{}-{}.{}", rt.name, uuid, extension), uuid)
+}
+
+// Runs `write` on its own thread against a temporary file, then streams the file back
+// as an attachment named `filename`.
+fn run_blocking<F>(filename: String, write: F) -> BoxedResponseFuture
+where
+    F: FnOnce(&str) -> Result<(), String> + Send + 'static,
+{
+    let (tx, rx) = oneshot::channel::<Result<File, String>>();
+    thread::spawn(move || {
+        let res = tempfile::NamedTempFile::new()
+            .map_err(|e| format!("error creating temporary file: {}", e))
+            .and_then(|tmp| {
+                write(&tmp.path().to_string_lossy())?;
+                // the open handle outlives the path, which goes away with `tmp`
+                tmp.reopen()
+                    .map_err(|e| format!("error opening temporary file: {}", e))
+            });
+        let _ = tx.send(res);
+    });
+
+    Box::new(rx.then(move |res| -> BoxedResponseFuture {
+        let file = match res {
+            Ok(Ok(file)) => file,
+            Ok(Err(e)) => {
+                error!("admin: {}", e);
+                return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e);
+            }
+            Err(_) => {
+                return error_response(StatusCode::INTERNAL_SERVER_ERROR, "operation aborted")
+            }
+        };
+        Box::new(future::ok(
+            Response::builder()
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", filename).as_str(),
+                )
+                .body(file_body(file))
+                .unwrap(),
+        ))
+    }))
+}
+
+fn file_body(mut file: File) -> Body {
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, io::Error>>(4);
+    thread::spawn(move || {
+        let mut tx = tx.wait();
+        loop {
+            let mut buf = vec![0; CHUNK_SIZE];
+            let chunk = match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    buf.truncate(n);
+                    Ok(buf)
+                }
+                Err(e) => Err(e),
+            };
+            let failed = chunk.is_err();
+            if tx.send(chunk).is_err() || failed {
+                break;
+            }
+        }
+    });
+    Body::wrap_stream(rx.then(|res| match res {
+        Ok(chunk) => chunk,
+        Err(_) => Err(io::Error::new(io::ErrorKind::Other, "body channel closed")),
+    }))
+}
+
+fn error_response(status: StatusCode, message: &str) -> BoxedResponseFuture {
+    Box::new(future::ok(
+        Response::builder()
+            .status(status)
+            .body(Body::from(format!("{}\n", message)))
+            .unwrap(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_seconds() {
+        assert_eq!(profile_seconds(None), Ok(DEFAULT_PROFILE_SECONDS));
+        assert_eq!(profile_seconds(Some("seconds=3")), Ok(3));
+        assert_eq!(profile_seconds(Some("foo=bar&seconds=30")), Ok(30));
+        assert!(profile_seconds(Some("seconds=0")).is_err());
+        assert!(profile_seconds(Some("seconds=abc")).is_err());
+        assert!(profile_seconds(Some("seconds=100000")).is_err());
+    }
+
+    #[test]
+    fn test_authorized() {
+        let req = |auth: Option<&str>| {
+            let mut builder = Request::builder();
+            if let Some(auth) = auth {
+                builder.header(header::AUTHORIZATION, auth);
+            }
+            builder.body(()).unwrap()
+        };
+        assert!(authorized(&req(None), None));
+        assert!(authorized(&req(Some("Bearer s3cret")), Some("s3cret")));
+        assert!(!authorized(&req(None), Some("s3cret")));
+        assert!(!authorized(&req(Some("Bearer s3cre")), Some("s3cret")));
+        assert!(!authorized(&req(Some("Bearer wrong!")), Some("s3cret")));
+        assert!(!authorized(&req(Some("s3cret")), Some("s3cret")));
+    }
+
+    #[test]
+    fn test_refuses_public_bind_without_token() {
+        let rt_manager = StandardRuntimeManager::new();
+        assert!(listen("0.0.0.0:0".parse().unwrap(), None, rt_manager).is_err());
+    }
+}
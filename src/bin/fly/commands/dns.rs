@@ -14,12 +14,17 @@ extern crate libfly;
 use fly::module_resolver::{JsonSecretsResolver, LocalDiskModuleResolver, ModuleResolver};
 use fly::runtime::*;
 use fly::settings::SETTINGS;
-use fly::{dns_server::DnsServer, standard_runtime_manager::StandardRuntimeManager, runtime_manager::RuntimeManager, runtime_manager::RuntimeManagerError};
+use fly::{dns_server::DnsServer, standard_runtime_manager::{StandardRuntimeManager, CATCH_ALL_HOSTNAME}, runtime_manager::RuntimeManager, runtime_manager::RuntimeManagerError};
 extern crate clap;
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::watch;
 
 pub fn cli() -> App {
-    subcommand("dns")
+    inspect_args(subcommand("dns")
         .about("Fly DNS server")
         .arg(
             Arg::with_name("path")
@@ -39,50 +44,28 @@ pub fn cli() -> App {
                 .long("bind")
                 .takes_value(true),
         )
+        .arg(
+            clap::Arg::with_name("watch")
+                .long("watch")
+                .help("Reload the app when its source files change"),
+        )
+        .arg(admin_arg()))
 }
 
 pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
     debug!("V8 version: {}", libfly::version());
 
-    let mut module_resolvers: Vec<Box<ModuleResolver>> = std::vec::Vec::new();
-    let mut test_service_module_resolvers: Vec<Box<ModuleResolver>> = std::vec::Vec::new();
+    check_settings()?;
+    let inspect = inspect_options(args)?;
 
     let secrets_file = match args.value_of("secrets-file") {
         Some(v) => v,
         None => "./secrets.json",
     };
 
-    let secrets_file_path = PathBuf::from(secrets_file);
-    info!(
-        "Loading secrets file from path {}",
-        secrets_file_path.to_str().unwrap().to_string()
-    );
-    match secrets_file_path.is_file() {
-        true => {
-            let secrets_json =
-                match std::fs::read_to_string(&secrets_file_path.to_str().unwrap().to_string()) {
-                    Ok(v) => v,
-                    Err(_err) => {
-                        info!("Failed to load secrets file!");
-                        "{}".to_string()
-                    }
-                };
-            let json_value: serde_json::Value = match serde_json::from_str(secrets_json.as_str()) {
-                Ok(v) => v,
-                Err(_err) => {
-                    // TODO: actual error output
-                    info!("Failed to parse json");
-                    serde_json::from_str("{}").unwrap()
-                }
-            };
-            module_resolvers.push(Box::new(JsonSecretsResolver::new(json_value)));
-        }
-        false => {
-            info!("Secrets file invalid");
-        }
-    };
-
-    module_resolvers.push(Box::new(LocalDiskModuleResolver::new(None)));
+    let resolved_paths = Arc::new(Mutex::new(HashSet::new()));
+    let module_resolvers = app_module_resolvers(secrets_file, resolved_paths.clone());
+    let mut test_service_module_resolvers: Vec<Box<ModuleResolver>> = std::vec::Vec::new();
     test_service_module_resolvers.push(Box::new(LocalDiskModuleResolver::new(None)));
 
     info!(
@@ -93,6 +76,7 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
     let entry_file = args.value_of("path").unwrap();
 
     let rt_manager = StandardRuntimeManager::new();
+    start_admin(args, rt_manager.clone())?;
 
     let runtime = rt_manager.write().unwrap().new_runtime(RuntimeConfig {
         name: None,
@@ -101,8 +85,12 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
         module_resolvers: Some(module_resolvers),
         app_logger: &slog_scope::logger(),
         msg_handler: None,
+        ops: None,
         permissions: None,
+        cpu_budget: None,
+        heap_limits: None,
         dev_tools: true,
+        snapshot: None,
     });
 
     let test_service_runtime = rt_manager.write().unwrap().new_runtime(RuntimeConfig {
@@ -112,13 +100,20 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
         module_resolvers: Some(test_service_module_resolvers),
         app_logger: &slog_scope::logger(),
         msg_handler: None,
+        ops: None,
         permissions: None,
+        cpu_budget: None,
+        heap_limits: None,
         dev_tools: true,
+        snapshot: None,
     });
 
     {
         let rt_arc_clone = runtime.clone();
         let rt_lock = rt_arc_clone.read().unwrap();
+        if let Some((_, brk)) = inspect {
+            fly::inspector::inspect(&rt_lock, brk);
+        }
         rt_lock.eval_file_with_dev_tools(entry_file);
         let test_rt_arc_clone = test_service_runtime.clone();
         let test_rt_lock = test_rt_arc_clone.read().unwrap();
@@ -139,10 +134,63 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
                 debug!("Failed to assign: {}", e);
             },
         }
+        // queries for any other name are answered by the app too, reloads move both bindings
+        if let Err(RuntimeManagerError::Failure(e)) = rt_manager.write().unwrap().bind_hostname_to(uuid::Uuid::parse_str(rt_lock.get_uuid().as_str()).unwrap(), CATCH_ALL_HOSTNAME) {
+            return Err(FlyCliError::from(format!("error binding app: {}", e).as_str()));
+        }
+    }
+
+    if args.is_present("watch") {
+        resolved_paths.lock().unwrap().insert(PathBuf::from(entry_file));
+        let rt_manager = rt_manager.clone();
+        let entry_file = entry_file.to_string();
+        let secrets_file = secrets_file.to_string();
+        let current_uuid = Mutex::new(Uuid::parse_str(&runtime.read().unwrap().get_uuid()).unwrap());
+        let watched_paths = resolved_paths.clone();
+        watch::watch(resolved_paths, move || {
+            println!("Reloading app {}", entry_file);
+            let new_runtime = rt_manager.write().unwrap().new_runtime(RuntimeConfig {
+                name: None,
+                version: None,
+                settings: &SETTINGS.read().unwrap(),
+                module_resolvers: Some(app_module_resolvers(&secrets_file, watched_paths.clone())),
+                app_logger: &slog_scope::logger(),
+                msg_handler: None,
+                ops: None,
+                permissions: None,
+                cpu_budget: None,
+                heap_limits: None,
+                dev_tools: true,
+                snapshot: None,
+            });
+            let uuid = {
+                let mut rt_lock = new_runtime.write().unwrap();
+                let uuid = Uuid::parse_str(&rt_lock.get_uuid()).unwrap();
+                if inspect.is_some() {
+                    fly::inspector::inspect(&rt_lock, false);
+                }
+                if rt_lock.eval_file_with_dev_tools(&entry_file) {
+                    let _ = rt_lock.run();
+                    Some(uuid)
+                } else {
+                    println!("Error loading app, still serving the previous version");
+                    drop(rt_lock);
+                    if let Err(RuntimeManagerError::Failure(e)) = rt_manager.read().unwrap().remove_runtime(uuid) {
+                        error!("error removing failed runtime: {}", e);
+                    }
+                    None
+                }
+            };
+            if let Some(uuid) = uuid {
+                let mut current_uuid = current_uuid.lock().unwrap();
+                match rt_manager.read().unwrap().replace_runtime(*current_uuid, uuid) {
+                    Ok(_) => *current_uuid = uuid,
+                    Err(RuntimeManagerError::Failure(e)) => error!("error replacing runtime: {}", e),
+                }
+            }
+        });
     }
-    
 
-    
     let port: u16 = match args.value_of("port") {
         Some(pstr) => pstr.parse::<u16>().unwrap(),
         None => 8053,
@@ -165,3 +213,40 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
 
     Ok(())
 }
+
+fn app_module_resolvers(secrets_file: &str, resolved_paths: Arc<Mutex<HashSet<PathBuf>>>) -> Vec<Box<ModuleResolver>> {
+    let mut module_resolvers: Vec<Box<ModuleResolver>> = std::vec::Vec::new();
+
+    let secrets_file_path = PathBuf::from(secrets_file);
+    info!(
+        "Loading secrets file from path {}",
+        secrets_file_path.to_str().unwrap().to_string()
+    );
+    match secrets_file_path.is_file() {
+        true => {
+            let secrets_json =
+                match std::fs::read_to_string(&secrets_file_path.to_str().unwrap().to_string()) {
+                    Ok(v) => v,
+                    Err(_err) => {
+                        info!("Failed to load secrets file!");
+                        "{}".to_string()
+                    }
+                };
+            let json_value: serde_json::Value = match serde_json::from_str(secrets_json.as_str()) {
+                Ok(v) => v,
+                Err(_err) => {
+                    // TODO: actual error output
+                    info!("Failed to parse json");
+                    serde_json::from_str("{}").unwrap()
+                }
+            };
+            module_resolvers.push(Box::new(JsonSecretsResolver::new(json_value)));
+        }
+        false => {
+            info!("Secrets file invalid");
+        }
+    };
+
+    module_resolvers.push(Box::new(LocalDiskModuleResolver::new(None).with_resolved_paths(resolved_paths)));
+    module_resolvers
+}
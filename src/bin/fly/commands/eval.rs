@@ -6,15 +6,17 @@ use fly::settings::SETTINGS;
 use futures::Future;
 
 pub fn cli() -> App {
-    subcommand("eval").about("Run a file").arg(
+    inspect_args(subcommand("eval").about("Run a file").arg(
         Arg::with_name("input")
             .help("the input file to use")
             .required(true)
             .index(1),
-    )
+    ))
 }
 
 pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
+    let inspect = inspect_options(args)?;
+
     let mut runtime = Runtime::new(RuntimeConfig {
         name: None,
         version: None,
@@ -22,10 +24,18 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
         module_resolvers: None,
         app_logger: &slog_scope::logger(),
         msg_handler: None,
+        ops: None,
         permissions: None,
+        cpu_budget: None,
+        heap_limits: None,
         dev_tools: true,
+        snapshot: None,
     });
 
+    if let Some((_, brk)) = inspect {
+        fly::inspector::inspect(&runtime, brk);
+    }
+
     let entry_file = args.value_of("input").unwrap();
     runtime.eval_file_with_dev_tools(entry_file);
     runtime.run().wait().unwrap();
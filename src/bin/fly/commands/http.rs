@@ -7,15 +7,34 @@ use hyper::server::conn::AddrStream;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::Server;
 
+use tokio::net::{TcpListener, TcpStream};
 use tokio::prelude::*;
+use tokio_openssl::{SslAcceptorExt, SslStream};
+use openssl::ssl::SslAcceptor;
 
-use fly::{dns_server::DnsServer, standard_runtime_manager::StandardRuntimeManager, runtime_manager::RuntimeManager};
+use fly::{dns_server::DnsServer, standard_runtime_manager::{StandardRuntimeManager, CATCH_ALL_HOSTNAME}, runtime_manager::RuntimeManager, runtime_manager::RuntimeManagerError};
 use fly::http_server::serve_http;
+use fly::module_resolver::{LocalDiskModuleResolver, ModuleResolver};
 use fly::runtime::*;
 use fly::settings::SETTINGS;
+use fly::snapshot::AppSnapshot;
+use fly::tls;
+
+use crate::watch;
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use uuid::Uuid;
+
+// TLS handshakes in progress at once.
+const TLS_HANDSHAKES: usize = 128;
 
 pub fn cli() -> App {
-    subcommand("http")
+    inspect_args(subcommand("http")
         .about("Fly HTTP server")
         .arg(
             Arg::with_name("path")
@@ -43,6 +62,49 @@ pub fn cli() -> App {
                 .takes_value(true)
                 .multiple(true),
         )
+        .arg(
+            clap::Arg::with_name("pool-size")
+                .long("pool-size")
+                .help("Number of isolates serving the app")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("watch")
+                .long("watch")
+                .help("Reload the app when its source files change"),
+        )
+        .arg(
+            clap::Arg::with_name("snapshot")
+                .long("snapshot")
+                .help("Start from a snapshot made with `fly snapshot` instead of evaluating the app")
+                .takes_value(true)
+                .conflicts_with_all(&["lib", "watch"]),
+        )
+        .arg(
+            clap::Arg::with_name("tls-cert")
+                .long("tls-cert")
+                .help("Serve HTTPS with this PEM certificate, followed by its chain")
+                .value_name("file")
+                .takes_value(true)
+                .requires("tls-key"),
+        )
+        .arg(
+            clap::Arg::with_name("tls-key")
+                .long("tls-key")
+                .help("PEM private key for --tls-cert")
+                .value_name("file")
+                .takes_value(true)
+                .requires("tls-cert"),
+        )
+        .arg(
+            clap::Arg::with_name("tls-certs-dir")
+                .long("tls-certs-dir")
+                .help("Directory of per-hostname certificates (<hostname>.crt and <hostname>.key) picked by SNI")
+                .value_name("dir")
+                .takes_value(true)
+                .requires("tls-cert"),
+        )
+        .arg(admin_arg()))
 }
 
 pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
@@ -50,34 +112,97 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
 
     let rt_manager = StandardRuntimeManager::new();
 
-    let runtime = rt_manager.write().unwrap().new_runtime(RuntimeConfig {
-        name: None,
-        version: None,
-        settings: &SETTINGS.read().unwrap(),
-        module_resolvers: None,
-        app_logger: &slog_scope::logger(),
-        msg_handler: None,
-        permissions: None,
-        dev_tools: true,
-    });
+    check_settings()?;
+    let inspect = inspect_options(args)?;
+    start_admin(args, rt_manager.clone())?;
 
-    {
-        let rt_ref_clone = runtime.clone();
-        let rt_lock = rt_ref_clone.read().unwrap();
-        if args.is_present("lib") {
-            for lib_path in glob(args.values_of("lib").unwrap().collect(), None)? {
-                rt_lock.eval_file(&lib_path);
-            }
-        }
+    let pool_size = match args.value_of("pool-size") {
+        Some(s) => s
+            .parse::<usize>()
+            .map_err(|e| FlyCliError::from(format!("invalid pool size: {}", e).as_str()))?,
+        None => SETTINGS.read().unwrap().isolate_pool_size.unwrap_or(1),
+    };
+
+    let tls_acceptor = tls_acceptor(args)?;
+
+    let lib_paths = if args.is_present("lib") {
+        glob(args.values_of("lib").unwrap().collect(), None)?
+    } else {
+        vec![]
+    };
 
-        if let Some(path) = glob(vec![args.value_of("path").unwrap()], Some(1))?.first() {
-            println!("Running app {}", path);
-            rt_lock.eval_file_with_dev_tools(path);
-        } else {
-            return Err(FlyCliError::from("No source code found"));
+    let snapshot = match args.value_of("snapshot") {
+        Some(path) => Some(
+            AppSnapshot::read(path)
+                .map_err(|e| FlyCliError::from(format!("error loading snapshot {}: {}", path, e).as_str()))?,
+        ),
+        None => None,
+    };
+
+    let app_path = match snapshot {
+        Some(_) => args.value_of("snapshot").unwrap().to_owned(),
+        None => match glob(vec![args.value_of("path").unwrap()], Some(1))?.first() {
+            Some(path) => path.to_owned(),
+            None => return Err(FlyCliError::from("No source code found")),
+        },
+    };
+    println!("Running app {}", app_path);
+
+    let resolved_paths = Arc::new(Mutex::new(HashSet::new()));
+
+    let (app_uuid, _) = start_app_pool(
+        &rt_manager.read().unwrap(),
+        pool_size,
+        lib_paths.clone(),
+        app_path.clone(),
+        resolved_paths.clone(),
+        inspect.map(|(_, brk)| brk),
+        snapshot,
+    );
+    // every host is served by the app, reloads move the binding to the new pool
+    if let Err(RuntimeManagerError::Failure(e)) = rt_manager.write().unwrap().bind_hostname_to(app_uuid, CATCH_ALL_HOSTNAME) {
+        return Err(FlyCliError::from(format!("error binding app: {}", e).as_str()));
+    }
+
+    let idle_ttl = SETTINGS.read().unwrap().runtime_idle_ttl_secs.map(Duration::from_secs);
+    rt_manager.read().unwrap().start_reaper(idle_ttl);
+
+    if args.is_present("watch") {
+        {
+            let mut paths = resolved_paths.lock().unwrap();
+            paths.insert(PathBuf::from(&app_path));
+            paths.extend(lib_paths.iter().map(PathBuf::from));
         }
+        let rt_manager = rt_manager.clone();
+        let current_uuid = Mutex::new(app_uuid);
+        let watched_paths = resolved_paths.clone();
+        watch::watch(resolved_paths, move || {
+            println!("Reloading app {}", app_path);
+            let rt_manager = rt_manager.read().unwrap();
+            let (uuid, ok) = start_app_pool(
+                &rt_manager,
+                pool_size,
+                lib_paths.clone(),
+                app_path.clone(),
+                watched_paths.clone(),
+                inspect.map(|_| false),
+                None,
+            );
+            if !ok {
+                println!("Error loading app, still serving the previous version");
+                if let Err(RuntimeManagerError::Failure(e)) = rt_manager.remove_runtime(uuid) {
+                    error!("error removing failed runtime: {}", e);
+                }
+                return;
+            }
+            let mut current_uuid = current_uuid.lock().unwrap();
+            match rt_manager.replace_runtime(*current_uuid, uuid) {
+                Ok(_) => *current_uuid = uuid,
+                Err(RuntimeManagerError::Failure(e)) => error!("error replacing runtime: {}", e),
+            }
+        });
     }
-    
+
 
     let bind = match args.value_of("bind") {
         Some(b) => b,
@@ -90,43 +215,155 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
 
     let addr = format!("{}:{}", bind, port).parse().unwrap();
 
+    let scheme = if tls_acceptor.is_some() { "https" } else { "http" };
+
     let (sigfut, sigrx) = fly::utils::signal_monitor();
 
-    let server = Server::bind(&addr)
-        .serve(make_service_fn(move |conn: &AddrStream| {
-            let remote_addr = conn.remote_addr();
-            let rt_manager_clone = rt_manager.clone();
-            service_fn(move |req| {
-                serve_http(
-                    false,
-                    req,
-                    rt_manager_clone.clone(),
-                    remote_addr,
-                )
-            })
-        }))
-        .with_graceful_shutdown(sigrx)
-        .map_err(|e| error!("server error: {}", e))
-        .and_then(|_| {
-            info!("HTTP server closed.");
-            Ok(())
-        });
+    let server: Box<Future<Item = (), Error = ()> + Send> = match tls_acceptor {
+        None => Box::new(
+            Server::bind(&addr)
+                .serve(make_service_fn(move |conn: &AddrStream| {
+                    let remote_addr = conn.remote_addr();
+                    let rt_manager_clone = rt_manager.clone();
+                    service_fn(move |req| {
+                        serve_http(
+                            false,
+                            req,
+                            rt_manager_clone.clone(),
+                            remote_addr,
+                        )
+                    })
+                }))
+                .with_graceful_shutdown(sigrx)
+                .map_err(|e| error!("server error: {}", e)),
+        ),
+        Some(acceptor) => {
+            let listener = TcpListener::bind(&addr)
+                .map_err(|e| FlyCliError::from(format!("error binding {}: {}", addr, e).as_str()))?;
+            // Handshakes run concurrently, a slow client doesn't hold up the others.
+            let incoming = listener
+                .incoming()
+                .map(move |stream| {
+                    acceptor.accept_async(stream).then(|r| match r {
+                        Ok(stream) => Ok::<_, std::io::Error>(Some(stream)),
+                        Err(e) => {
+                            debug!("error accepting TLS connection: {}", e);
+                            Ok(None)
+                        }
+                    })
+                })
+                .buffer_unordered(TLS_HANDSHAKES)
+                .filter_map(|stream| stream);
+            Box::new(
+                Server::builder(incoming)
+                    .serve(make_service_fn(move |conn: &SslStream<TcpStream>| {
+                        let remote_addr = conn
+                            .get_ref()
+                            .get_ref()
+                            .peer_addr()
+                            .unwrap_or_else(|_| "0.0.0.0:0".parse().unwrap());
+                        let rt_manager_clone = rt_manager.clone();
+                        service_fn(move |req| {
+                            serve_http(true, req, rt_manager_clone.clone(), remote_addr)
+                        })
+                    }))
+                    .with_graceful_shutdown(sigrx)
+                    .map_err(|e| error!("server error: {}", e)),
+            )
+        }
+    };
+    let server = server.and_then(|_| {
+        info!("HTTP server closed.");
+        Ok(())
+    });
 
     tokio::run(future::lazy(move || {
-        let rt_lock = runtime.read().unwrap();
-        tokio::spawn(
-            rt_lock
-                .ptr.to_runtime()
-                .run()
-                .map_err(|e| error!("error running runtime event loop: {}", e)),
-        );
-
         tokio::spawn(server);
 
-        println!("Listening on http://{}", addr);
+        println!("Listening on {}://{}", scheme, addr);
 
         sigfut
     }));
 
     Ok(())
 }
+
+// Builds the TLS acceptor when a certificate was given. The certificate from --tls-cert is
+// served to clients that don't ask for a hostname in --tls-certs-dir.
+fn tls_acceptor(args: &ArgMatches<'_>) -> FlyCliResult<Option<SslAcceptor>> {
+    let (cert, key) = match (args.value_of("tls-cert"), args.value_of("tls-key")) {
+        (Some(cert), Some(key)) => (cert, key),
+        _ => return Ok(None),
+    };
+    let identity = tls::Identity::from_files(cert, key)
+        .map_err(|e| FlyCliError::from(format!("{}", e).as_str()))?;
+    let store = match args.value_of("tls-certs-dir") {
+        Some(dir) => {
+            let store = tls::CertStore::from_dir(dir)
+                .map_err(|e| FlyCliError::from(format!("{}", e).as_str()))?;
+            info!("loaded {} certificates from {}", store.len(), dir);
+            store
+        }
+        None => tls::CertStore::new(),
+    };
+    let acceptor = tls::acceptor(&identity, Arc::new(store))
+        .map_err(|e| FlyCliError::from(format!("{}", e).as_str()))?;
+    Ok(Some(acceptor))
+}
+
+// Evaluates the app in a new pool of isolates. Returns the pool's uuid and whether
+// every isolate evaluated it without throwing. When `inspect` is set every isolate
+// is exposed to the inspector, and `Some(true)` pauses the first one on start. Isolates
+// started from a snapshot already have the app evaluated.
+fn start_app_pool(
+    rt_manager: &StandardRuntimeManager,
+    pool_size: usize,
+    lib_paths: Vec<String>,
+    app_path: String,
+    resolved_paths: Arc<Mutex<HashSet<PathBuf>>>,
+    inspect: Option<bool>,
+    snapshot: Option<AppSnapshot>,
+) -> (Uuid, bool) {
+    let failed = Arc::new(AtomicBool::new(false));
+    let factory_failed = failed.clone();
+    let brk = AtomicBool::new(inspect.unwrap_or(false));
+    let members = rt_manager.new_runtime_pool(
+        pool_size,
+        Box::new(move || {
+            let mut rt = Runtime::new(RuntimeConfig {
+                name: None,
+                version: None,
+                settings: &SETTINGS.read().unwrap(),
+                module_resolvers: Some(vec![Box::new(
+                    LocalDiskModuleResolver::new(None).with_resolved_paths(resolved_paths.clone()),
+                ) as Box<ModuleResolver>]),
+                app_logger: &slog_scope::logger(),
+                msg_handler: None,
+                ops: None,
+                permissions: None,
+                cpu_budget: None,
+                heap_limits: None,
+                dev_tools: snapshot.is_none(),
+                snapshot: snapshot.clone(),
+            });
+            if inspect.is_some() {
+                fly::inspector::inspect(&rt, brk.swap(false, Ordering::SeqCst));
+            }
+            let mut ok = true;
+            if snapshot.is_none() {
+                for lib_path in lib_paths.iter() {
+                    ok &= rt.eval_file(lib_path);
+                }
+                ok &= rt.eval_file_with_dev_tools(&app_path);
+            }
+            if !ok {
+                factory_failed.store(true, Ordering::SeqCst);
+            }
+            // keeps running until the pool is removed
+            let _ = rt.run();
+            rt
+        }),
+    );
+    let uuid = Uuid::parse_str(&members[0].read().unwrap().get_uuid()).unwrap();
+    (uuid, !failed.load(Ordering::SeqCst))
+}
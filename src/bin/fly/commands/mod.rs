@@ -1,7 +1,7 @@
 use crate::util::*;
 
 pub fn commands() -> Vec<App> {
-  vec![http::cli(), test::cli(), dns::cli(), eval::cli()]
+  vec![http::cli(), test::cli(), dns::cli(), eval::cli(), snapshot::cli()]
 }
 
 pub fn command_exec(name: &str) -> Option<ExecFn> {
@@ -9,6 +9,7 @@ pub fn command_exec(name: &str) -> Option<ExecFn> {
     "dns" => dns::exec,
     "eval" => eval::exec,
     "http" => http::exec,
+    "snapshot" => snapshot::exec,
     "test" => test::exec,
     _ => return None,
   };
@@ -19,4 +20,5 @@ pub fn command_exec(name: &str) -> Option<ExecFn> {
 pub mod dns;
 pub mod eval;
 pub mod http;
+pub mod snapshot;
 pub mod test;
@@ -0,0 +1,62 @@
+use crate::errors::*;
+use crate::util::*;
+use clap::{Arg, ArgMatches};
+use fly::settings::SETTINGS;
+use fly::snapshot::AppSnapshot;
+
+pub fn cli() -> App {
+    subcommand("snapshot")
+        .about("Create a startup snapshot with the app already evaluated")
+        .arg(
+            Arg::with_name("path")
+                .help("The app to snapshot, its imports are snapshotted with it")
+                .default_value("./index.js")
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("lib")
+                .short("l")
+                .long("lib")
+                .help("Libraries or shims to load before app code")
+                .takes_value(true)
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .help("Where to write the snapshot")
+                .default_value("app.snapshot")
+                .takes_value(true),
+        )
+}
+
+pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
+    let lib_paths = if args.is_present("lib") {
+        glob(args.values_of("lib").unwrap().collect(), None)?
+    } else {
+        vec![]
+    };
+    let app_path = match glob(vec![args.value_of("path").unwrap()], Some(1))?.first() {
+        Some(path) => path.to_owned(),
+        None => return Err(FlyCliError::from("No source code found")),
+    };
+
+    let mut libs = vec![];
+    for path in lib_paths {
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| FlyCliError::from(format!("error reading {}: {}", path, e).as_str()))?;
+        libs.push((path, source));
+    }
+
+    let snapshot = AppSnapshot::create(&SETTINGS.read().unwrap(), &libs, &app_path)
+        .map_err(|e| FlyCliError::from(format!("{}", e).as_str()))?;
+
+    let output = args.value_of("output").unwrap();
+    snapshot
+        .write(output)
+        .map_err(|e| FlyCliError::from(format!("error writing snapshot: {}", e).as_str()))?;
+    println!("Wrote {} ({} bytes)", output, snapshot.size());
+
+    Ok(())
+}
@@ -9,7 +9,7 @@ use futures::Future;
 const PATTERN_DEFAULT: &str = "**/*.{test,spec}.{js,ts}";
 
 pub fn cli() -> App {
-    subcommand("test")
+    inspect_args(subcommand("test")
         .about("Run unit tests")
         .arg(
             Arg::with_name("paths")
@@ -25,10 +25,12 @@ pub fn cli() -> App {
                 .help("Libraries or shims to load before app code")
                 .takes_value(true)
                 .multiple(true),
-        )
+        ))
 }
 
 pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
+    let inspect = inspect_options(args)?;
+
     let mut rt = Runtime::new(RuntimeConfig {
         name: None,
         version: None,
@@ -36,10 +38,18 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
         module_resolvers: None,
         app_logger: &slog_scope::logger(),
         msg_handler: None,
+        ops: None,
         permissions: Some(RuntimePermissions::new(true)),
+        cpu_budget: None,
+        heap_limits: None,
         dev_tools: true,
+        snapshot: None,
     });
 
+    if let Some((_, brk)) = inspect {
+        fly::inspector::inspect(&rt, brk);
+    }
+
     if args.is_present("lib") {
         for lib_path in glob(args.values_of("lib").unwrap().collect(), None)? {
             rt.eval_file(&lib_path);
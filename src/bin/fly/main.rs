@@ -1,6 +1,7 @@
 mod commands;
 mod errors;
 mod util;
+mod watch;
 
 #[macro_use]
 extern crate log;
@@ -1,6 +1,11 @@
 use crate::errors::{FlyCliError, FlyCliResult};
-use clap::{AppSettings, ArgMatches, SubCommand};
+use clap::{AppSettings, Arg, ArgMatches, SubCommand};
 use std::error::Error;
+use fly::standard_runtime_manager::StandardRuntimeManager;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+const INSPECT_ADDR_DEFAULT: &str = "127.0.0.1:9229";
 
 pub type App = clap::App<'static, 'static>;
 
@@ -14,6 +19,84 @@ pub fn subcommand(name: &'static str) -> App {
     ])
 }
 
+pub fn inspect_args(app: App) -> App {
+    app.arg(
+        Arg::with_name("inspect")
+            .long("inspect")
+            .help("Start the DevTools inspector")
+            .value_name("host:port")
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true),
+    )
+    .arg(
+        Arg::with_name("inspect-brk")
+            .long("inspect-brk")
+            .help("Start the DevTools inspector and pause on the first statement")
+            .value_name("host:port")
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true)
+            .conflicts_with("inspect"),
+    )
+}
+
+/// Returns the inspector address and whether to break on start, starting the
+/// inspector server if either flag is present.
+pub fn inspect_options(args: &ArgMatches<'_>) -> FlyCliResult<Option<(SocketAddr, bool)>> {
+    let (name, brk) = if args.is_present("inspect-brk") {
+        ("inspect-brk", true)
+    } else if args.is_present("inspect") {
+        ("inspect", false)
+    } else {
+        return Ok(None);
+    };
+    let addr: SocketAddr = args
+        .value_of(name)
+        .unwrap_or(INSPECT_ADDR_DEFAULT)
+        .parse()
+        .map_err(|e: std::net::AddrParseError| FlyCliError::from(format!("invalid inspector address: {}", e).as_str()))?;
+    fly::inspector::listen(addr);
+    Ok(Some((addr, brk)))
+}
+
+pub fn admin_arg() -> Arg<'static, 'static> {
+    Arg::with_name("admin")
+        .long("admin")
+        .help("Serve heap snapshots and CPU profiles on this address")
+        .value_name("host:port")
+        .takes_value(true)
+}
+
+/// Starts the admin listener if an address was given on the command line or in settings.
+pub fn start_admin(
+    args: &ArgMatches<'_>,
+    rt_manager: Arc<RwLock<StandardRuntimeManager>>,
+) -> FlyCliResult<()> {
+    let raw = match args.value_of("admin") {
+        Some(v) => v.to_string(),
+        None => match fly::settings::SETTINGS.read().unwrap().admin_bind_addr {
+            Some(ref v) => v.clone(),
+            None => return Ok(()),
+        },
+    };
+    let addr: SocketAddr = raw
+        .parse()
+        .map_err(|e: std::net::AddrParseError| FlyCliError::from(format!("invalid admin address: {}", e).as_str()))?;
+    let token = fly::settings::SETTINGS.read().unwrap().admin_token.clone();
+    fly::admin::listen(addr, token, rt_manager)
+        .map_err(|e| FlyCliError::from(format!("{}", e).as_str()))
+}
+
+/// Loads settings and checks the fetch client can be created from them, so a bad CA
+/// bundle or egress range stops the server instead of every app that fetches.
+pub fn check_settings() -> FlyCliResult<()> {
+    let settings = fly::settings::Settings::new()
+        .map_err(|e| FlyCliError::from(format!("invalid settings: {}", e).as_str()))?;
+    fly::http_client::check_settings(&settings)
+        .map_err(|e| FlyCliError::from(format!("invalid fetch settings: {}", e).as_str()))
+}
+
 pub fn glob(patterns: Vec<&str>, max_depth: Option<usize>) -> FlyCliResult<Vec<String>> {
     let patterns: Vec<&str> = patterns.into_iter().map(clean_pattern).collect();
 
@@ -0,0 +1,40 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Polls the modification time of every path in `paths` and calls `on_change` when
+/// any of them changes. Paths added to the set later on are picked up on the next poll.
+pub fn watch<F>(paths: Arc<Mutex<HashSet<PathBuf>>>, on_change: F)
+where
+    F: Fn() + Send + 'static,
+{
+    thread::Builder::new()
+        .name("file-watcher".to_string())
+        .spawn(move || {
+            let mut mtimes: HashMap<PathBuf, Option<SystemTime>> = HashMap::new();
+            loop {
+                let current: Vec<PathBuf> = paths.lock().unwrap().iter().cloned().collect();
+                let mut changed = false;
+                for path in current {
+                    let mtime = fs::metadata(&path).and_then(|m| m.modified()).ok();
+                    match mtimes.insert(path.clone(), mtime) {
+                        Some(prev) if prev != mtime => {
+                            info!("{} changed", path.display());
+                            changed = true;
+                        }
+                        _ => {}
+                    }
+                }
+                if changed {
+                    on_change();
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        })
+        .unwrap();
+}
@@ -0,0 +1,127 @@
+//! V8 code cache for compiled app code. Compiling an evaluated source or a module produces
+//! cached data, stored under a key made of the v8 version, its file name or origin url and a
+//! hash of its source, and handed back to v8 the next time it's compiled, by any runtime.
+
+use sha2::Digest; // puts trait in scope
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref PRUNED_DIRS: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+}
+
+pub trait CodeCacheStore: Send + Sync {
+    fn get(&self, key: &str) -> Option<Vec<u8>>;
+    fn set(&self, key: &str, data: &[u8]);
+}
+
+/// Key for a module's cached data, changes whenever v8 does.
+pub fn cache_key(origin_url: &str, source: &str) -> String {
+    cache_key_for(&libfly::version(), origin_url, source)
+}
+
+fn cache_key_for(v8_version: &str, origin_url: &str, source: &str) -> String {
+    let mut hasher = Sha256::default();
+    for part in &[v8_version, origin_url, source] {
+        hasher.input(part.as_bytes());
+        hasher.input(&[0]);
+    }
+    hasher
+        .result()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Keeps cached data in a directory per v8 version. Directories left by other v8
+/// versions are removed, v8 would reject their data anyway.
+pub struct DiskCodeCacheStore {
+    dir: PathBuf,
+}
+
+impl DiskCodeCacheStore {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self::with_version(root.into(), &libfly::version())
+    }
+
+    fn with_version(root: PathBuf, v8_version: &str) -> Self {
+        let dir = root.join(v8_version);
+        if PRUNED_DIRS.lock().unwrap().insert(root.clone()) {
+            if let Err(e) = prune(&root, v8_version) {
+                warn!("error pruning code cache in {}: {}", root.display(), e);
+            }
+        }
+        DiskCodeCacheStore { dir }
+    }
+}
+
+fn prune(root: &Path, v8_version: &str) -> io::Result<()> {
+    if !root.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        if entry.file_name() != v8_version && entry.path().is_dir() {
+            debug!("removing stale code cache {}", entry.path().display());
+            fs::remove_dir_all(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+impl CodeCacheStore for DiskCodeCacheStore {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        fs::read(self.dir.join(key)).ok()
+    }
+
+    fn set(&self, key: &str, data: &[u8]) {
+        // written aside and renamed so concurrent readers never see a partial entry
+        let tmp = self.dir.join(format!(".{}.{}", key, rand::random::<u32>()));
+        let res = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&tmp, data))
+            .and_then(|_| fs::rename(&tmp, self.dir.join(key)));
+        if let Err(e) = res {
+            warn!("error writing code cache entry {}: {}", key, e);
+            let _ = fs::remove_file(&tmp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        let key = cache_key_for("7.0", "file:///app.js", "export default 1");
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key_for("7.0", "file:///app.js", "export default 1"));
+        assert_ne!(key, cache_key_for("7.1", "file:///app.js", "export default 1"));
+        assert_ne!(key, cache_key_for("7.0", "file:///lib.js", "export default 1"));
+        assert_ne!(key, cache_key_for("7.0", "file:///app.js", "export default 2"));
+    }
+
+    #[test]
+    fn test_disk_store() {
+        let root = tempfile::tempdir().unwrap();
+        let store = DiskCodeCacheStore::with_version(root.path().to_path_buf(), "7.0");
+        assert_eq!(store.get("abc"), None);
+        store.set("abc", b"cached");
+        assert_eq!(store.get("abc"), Some(b"cached".to_vec()));
+    }
+
+    #[test]
+    fn test_disk_store_prunes_other_versions() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("6.9")).unwrap();
+        fs::write(root.path().join("6.9").join("abc"), b"old").unwrap();
+        let store = DiskCodeCacheStore::with_version(root.path().to_path_buf(), "7.0");
+        store.set("abc", b"new");
+        assert!(!root.path().join("6.9").exists());
+        assert_eq!(store.get("abc"), Some(b"new".to_vec()));
+    }
+}
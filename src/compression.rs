@@ -0,0 +1,500 @@
+//! Compression of responses served to clients, negotiated from `Accept-Encoding`.
+
+use brotli::CompressorWriter;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::{Async, Poll, Stream};
+use hyper::header::{self, HeaderMap, HeaderValue};
+use hyper::StatusCode;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use crate::settings::CompressionConfig;
+
+// Compressed output is sent once there's this much of it, or when the app's stream stalls.
+const CHUNK_SIZE: usize = 16 * 1024;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const BROTLI_WINDOW: u32 = 22;
+
+// Compressible types outside text/* and the json and xml families.
+const COMPRESSIBLE_TYPES: &[&str] = &[
+    "application/javascript",
+    "application/x-javascript",
+    "application/ecmascript",
+    "application/json",
+    "application/xml",
+    "application/wasm",
+    "application/vnd.ms-fontobject",
+    "application/x-font-opentype",
+    "application/x-font-truetype",
+    "application/x-font-ttf",
+    "font/eot",
+    "font/opentype",
+    "font/otf",
+    "font/ttf",
+    "image/svg+xml",
+    "image/vnd.microsoft.icon",
+    "image/x-icon",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// The encoding a client prefers from its `Accept-Encoding`, brotli on ties.
+pub fn preferred_encoding(headers: &HeaderMap) -> Option<Encoding> {
+    let mut br = None;
+    let mut gzip = None;
+    let mut any = None;
+    for value in headers.get_all(header::ACCEPT_ENCODING) {
+        let value = match value.to_str() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        for item in value.split(',') {
+            let mut params = item.split(';');
+            let coding = params.next().unwrap_or("").trim().to_lowercase();
+            let q = params
+                .filter_map(|p| {
+                    let p = p.trim();
+                    if p.starts_with("q=") || p.starts_with("Q=") {
+                        p[2..].trim().parse::<f32>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .next()
+                .unwrap_or(1.0);
+            match coding.as_str() {
+                "br" => br = Some(q),
+                "gzip" | "x-gzip" => gzip = Some(q),
+                "*" => any = Some(q),
+                _ => {}
+            }
+        }
+    }
+    let br = br.or(any).unwrap_or(0.0);
+    let gzip = gzip.or(any).unwrap_or(0.0);
+    if br <= 0.0 && gzip <= 0.0 {
+        None
+    } else if br >= gzip {
+        Some(Encoding::Brotli)
+    } else {
+        Some(Encoding::Gzip)
+    }
+}
+
+/// Types worth compressing: text, scripts, json, xml, svg and uncompressed fonts.
+pub fn is_compressible(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+    essence.starts_with("text/")
+        || (essence.starts_with("application/")
+            && (essence.ends_with("+json") || essence.ends_with("+xml")))
+        || COMPRESSIBLE_TYPES.contains(&essence.as_str())
+}
+
+/// How to encode a response for a client accepting `accepted`, `None` to send it as is.
+/// Responses the app already encoded, ranges and `no-transform` responses are left alone.
+/// `len` is the size of the body, when it's known.
+pub fn response_encoding(
+    config: &CompressionConfig,
+    accepted: Option<Encoding>,
+    status: StatusCode,
+    headers: &HeaderMap,
+    len: Option<usize>,
+) -> Option<Encoding> {
+    let encoding = accepted?;
+    if !config.enabled
+        || status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+        || status == StatusCode::PARTIAL_CONTENT
+        || headers.contains_key(header::CONTENT_ENCODING)
+        || headers.contains_key(header::CONTENT_RANGE)
+        || has_no_transform(headers)
+    {
+        return None;
+    }
+    match headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(ct) if is_compressible(ct) => {}
+        _ => return None,
+    }
+    let len = len.or_else(|| {
+        headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+    });
+    match len {
+        Some(len) if len < config.min_size => None,
+        _ => Some(encoding),
+    }
+}
+
+fn has_no_transform(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|d| d.trim().eq_ignore_ascii_case("no-transform"))
+}
+
+/// Describes a body encoded with `encoding`: its length changes, it varies on
+/// `Accept-Encoding`, and its validator can only be weak.
+pub fn set_headers(headers: &mut HeaderMap, encoding: Encoding) {
+    headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    headers.remove(header::CONTENT_LENGTH);
+
+    let varies = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|v| {
+            let v = v.trim();
+            v == "*" || v.eq_ignore_ascii_case("accept-encoding")
+        });
+    if !varies {
+        headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+
+    let weak = match headers.get(header::ETAG).and_then(|v| v.to_str().ok()) {
+        Some(etag) if etag.starts_with('"') => HeaderValue::from_str(&format!("W/{}", etag)).ok(),
+        _ => None,
+    };
+    if let Some(weak) = weak {
+        headers.insert(header::ETAG, weak);
+    }
+}
+
+/// Encodes a whole body at once.
+pub fn encode(config: &CompressionConfig, encoding: Encoding, body: &[u8]) -> io::Result<Vec<u8>> {
+    let output = Output::default();
+    let mut writer = Writer::new(config, encoding, output.clone());
+    writer.write_all(body)?;
+    writer.finish()?;
+    Ok(output.take())
+}
+
+/// Encodes a body as it streams, without buffering it. Whatever was compressed so far is
+/// flushed when the body stalls, so slow streams still reach the client promptly.
+pub struct Encoder<S> {
+    stream: S,
+    writer: Option<Writer>,
+    output: Output,
+    unflushed: bool,
+}
+
+impl<S> Encoder<S> {
+    pub fn new(config: &CompressionConfig, encoding: Encoding, stream: S) -> Self {
+        let output = Output::default();
+        Encoder {
+            stream,
+            writer: Some(Writer::new(config, encoding, output.clone())),
+            output,
+            unflushed: false,
+        }
+    }
+}
+
+impl<S> Stream for Encoder<S>
+where
+    S: Stream<Item = Vec<u8>, Error = io::Error>,
+{
+    type Item = Vec<u8>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Vec<u8>>, io::Error> {
+        loop {
+            let writer = match self.writer {
+                Some(ref mut w) => w,
+                None => return Ok(Async::Ready(None)),
+            };
+            match self.stream.poll()? {
+                Async::Ready(Some(chunk)) => {
+                    writer.write_all(&chunk)?;
+                    self.unflushed = true;
+                    if self.output.len() >= CHUNK_SIZE {
+                        return Ok(Async::Ready(Some(self.output.take())));
+                    }
+                }
+                Async::Ready(None) => {
+                    self.writer.take().unwrap().finish()?;
+                    let out = self.output.take();
+                    return Ok(Async::Ready(if out.is_empty() { None } else { Some(out) }));
+                }
+                Async::NotReady => {
+                    if self.unflushed {
+                        writer.flush()?;
+                        self.unflushed = false;
+                    }
+                    let out = self.output.take();
+                    return Ok(if out.is_empty() {
+                        Async::NotReady
+                    } else {
+                        Async::Ready(Some(out))
+                    });
+                }
+            }
+        }
+    }
+}
+
+enum Writer {
+    Gzip(GzEncoder<Output>),
+    Brotli(Box<CompressorWriter<Output>>),
+}
+
+impl Writer {
+    fn new(config: &CompressionConfig, encoding: Encoding, output: Output) -> Self {
+        match encoding {
+            Encoding::Gzip => Writer::Gzip(GzEncoder::new(
+                output,
+                Compression::new(config.gzip_level.min(9)),
+            )),
+            Encoding::Brotli => Writer::Brotli(Box::new(CompressorWriter::new(
+                output,
+                BROTLI_BUFFER_SIZE,
+                config.brotli_level.min(11),
+                BROTLI_WINDOW,
+            ))),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Writer::Gzip(w) => w.finish().map(|_| ()),
+            Writer::Brotli(w) => {
+                w.into_inner();
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Writer::Gzip(w) => w.write(buf),
+            Writer::Brotli(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Writer::Gzip(w) => w.flush(),
+            Writer::Brotli(w) => w.flush(),
+        }
+    }
+}
+
+// Where encoders write. The brotli encoder doesn't hand out its writer until it's done, so the
+// output is shared with it instead.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn take(&self) -> Vec<u8> {
+        mem::replace(&mut *self.0.lock().unwrap(), Vec::new())
+    }
+
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use futures::{stream, Future};
+    use std::io::Read;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (k, v) in pairs {
+            map.append(*k, HeaderValue::from_static(*v));
+        }
+        map
+    }
+
+    fn decode(encoding: Encoding, body: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        match encoding {
+            Encoding::Gzip => GzDecoder::new(body).read_to_end(&mut out).unwrap(),
+            Encoding::Brotli => brotli::Decompressor::new(body, 4096)
+                .read_to_end(&mut out)
+                .unwrap(),
+        };
+        out
+    }
+
+    #[test]
+    fn test_preferred_encoding() {
+        let cases: &[(&str, Option<Encoding>)] = &[
+            ("gzip, deflate, br", Some(Encoding::Brotli)),
+            ("gzip", Some(Encoding::Gzip)),
+            ("br;q=0.5, gzip", Some(Encoding::Gzip)),
+            ("br;q=0, gzip;q=0", None),
+            ("*", Some(Encoding::Brotli)),
+            ("*;q=0.1, gzip", Some(Encoding::Gzip)),
+            ("identity", None),
+            ("deflate", None),
+        ];
+        for (accept, expected) in cases {
+            let h = headers(&[("accept-encoding", *accept)]);
+            assert_eq!(preferred_encoding(&h), *expected, "{}", accept);
+        }
+        assert_eq!(preferred_encoding(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_is_compressible() {
+        assert!(is_compressible("text/html; charset=utf-8"));
+        assert!(is_compressible("application/json"));
+        assert!(is_compressible("application/ld+json"));
+        assert!(is_compressible("Image/SVG+XML"));
+        assert!(!is_compressible("image/png"));
+        assert!(!is_compressible("application/octet-stream"));
+        assert!(!is_compressible("font/woff2"));
+    }
+
+    #[test]
+    fn test_response_encoding() {
+        let config = CompressionConfig::default();
+        let gzip = Some(Encoding::Gzip);
+        let html = headers(&[("content-type", "text/html")]);
+        assert_eq!(
+            response_encoding(&config, gzip, StatusCode::OK, &html, None),
+            gzip
+        );
+        assert_eq!(
+            response_encoding(&config, None, StatusCode::OK, &html, None),
+            None
+        );
+        assert_eq!(
+            response_encoding(&config, gzip, StatusCode::OK, &html, Some(10)),
+            None
+        );
+        assert_eq!(
+            response_encoding(&config, gzip, StatusCode::NOT_MODIFIED, &html, None),
+            None
+        );
+
+        let small = headers(&[("content-type", "text/html"), ("content-length", "10")]);
+        assert_eq!(
+            response_encoding(&config, gzip, StatusCode::OK, &small, None),
+            None
+        );
+
+        for h in &[
+            headers(&[("content-type", "image/png")]),
+            headers(&[("content-type", "text/html"), ("content-encoding", "br")]),
+            headers(&[
+                ("content-type", "text/html"),
+                ("cache-control", "public, no-transform"),
+            ]),
+            headers(&[
+                ("content-type", "text/html"),
+                ("content-range", "bytes 0-1/2"),
+            ]),
+        ] {
+            assert_eq!(
+                response_encoding(&config, gzip, StatusCode::OK, h, None),
+                None
+            );
+        }
+
+        let disabled = CompressionConfig {
+            enabled: false,
+            ..config
+        };
+        assert_eq!(
+            response_encoding(&disabled, gzip, StatusCode::OK, &html, None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_set_headers() {
+        let mut h = headers(&[
+            ("content-length", "2048"),
+            ("vary", "origin"),
+            ("etag", "\"abc\""),
+        ]);
+        set_headers(&mut h, Encoding::Brotli);
+        assert_eq!(h.get("content-encoding").unwrap(), "br");
+        assert!(!h.contains_key("content-length"));
+        assert_eq!(h.get_all("vary").iter().count(), 2);
+        assert_eq!(h.get("etag").unwrap(), "W/\"abc\"");
+
+        let mut h = headers(&[("vary", "Accept-Encoding"), ("etag", "W/\"abc\"")]);
+        set_headers(&mut h, Encoding::Gzip);
+        assert_eq!(h.get_all("vary").iter().count(), 1);
+        assert_eq!(h.get("etag").unwrap(), "W/\"abc\"");
+    }
+
+    #[test]
+    fn test_encode() {
+        let config = CompressionConfig::default();
+        let body = "hello world ".repeat(1000).into_bytes();
+        for encoding in &[Encoding::Gzip, Encoding::Brotli] {
+            let encoded = encode(&config, *encoding, &body).unwrap();
+            assert!(encoded.len() < body.len());
+            assert_eq!(decode(*encoding, &encoded), body);
+        }
+    }
+
+    #[test]
+    fn test_encoder_stream() {
+        let config = CompressionConfig::default();
+        let chunks: Vec<Vec<u8>> = (0..100)
+            .map(|i| format!("chunk {} ", i).repeat(100).into_bytes())
+            .collect();
+        let body = chunks.concat();
+        for encoding in &[Encoding::Gzip, Encoding::Brotli] {
+            let encoder = Encoder::new(
+                &config,
+                *encoding,
+                stream::iter_ok::<_, io::Error>(chunks.clone()),
+            );
+            let encoded = encoder.concat2().wait().unwrap();
+            assert_eq!(decode(*encoding, &encoded), body);
+        }
+    }
+}
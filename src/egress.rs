@@ -0,0 +1,542 @@
+//! Where `fetch` can connect to. Addresses are checked once hosts are resolved, so
+//! an app can't reach the node's own network by pointing a hostname at it.
+
+use futures::{future, Future};
+use hyper::client::connect::dns::{GaiResolver, Name, Resolve};
+use hyper::client::connect::{Connect, Connected, Destination};
+use hyper::client::HttpConnector;
+use tokio::net::TcpStream;
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::time::Duration;
+use std::vec;
+
+use crate::errors::{self, ErrorKind, FlyError, FlyResult};
+use crate::metrics::FETCH_EGRESS_DENIED_TOTAL;
+use crate::settings::EgressConfig;
+
+lazy_static! {
+    pub(crate) static ref GAI_RESOLVER: GaiResolver = GaiResolver::new(4);
+}
+
+/// An IP range, like `10.0.0.0/8` or `fc00::/7`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn parse(s: &str) -> FlyResult<Cidr> {
+        let invalid = || errors::new(ErrorKind::InvalidInput, format!("invalid CIDR: {}", s));
+        let (addr, prefix) = match s.find('/') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Cidr { addr, prefix })
+    }
+
+    /// IPv6 addresses embedding an IPv4 address are in IPv4 ranges holding that address,
+    /// as well as in the IPv6 ranges they're in.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.contains_addr(ip) || (canonical(ip) != ip && self.contains_addr(canonical(ip)))
+    }
+
+    fn contains_addr(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::max_value()
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::max_value()
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Why a connection was denied.
+#[derive(Debug, Clone)]
+pub struct EgressError {
+    pub host: String,
+    pub ip: IpAddr,
+    // loopback, link_local, private, reserved or denied (by the deny list)
+    pub reason: &'static str,
+}
+
+impl fmt::Display for EgressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.host == self.ip.to_string() {
+            write!(f, "egress denied to {} ({})", self.ip, self.reason)
+        } else {
+            write!(
+                f,
+                "egress denied to {} at {} ({})",
+                self.host, self.ip, self.reason
+            )
+        }
+    }
+}
+
+impl Error for EgressError {}
+
+impl From<EgressError> for FlyError {
+    fn from(err: EgressError) -> FlyError {
+        errors::new(ErrorKind::EgressDenied, err.to_string())
+    }
+}
+
+impl EgressError {
+    /// Finds an `EgressError` in what a hyper client request failed with.
+    pub fn find(err: &(Error + 'static)) -> Option<&EgressError> {
+        let mut cur = Some(err);
+        while let Some(err) = cur {
+            if let Some(egress) = err.downcast_ref::<EgressError>() {
+                return Some(egress);
+            }
+            if let Some(io_err) = err.downcast_ref::<io::Error>() {
+                if let Some(inner) = io_err.get_ref() {
+                    if let Some(egress) = inner.downcast_ref::<EgressError>() {
+                        return Some(egress);
+                    }
+                }
+            }
+            cur = err.source();
+        }
+        None
+    }
+}
+
+/// Loopback, link-local and private ranges are denied unless allowed. The deny list
+/// takes precedence over the allow list.
+#[derive(Debug, Clone, Default)]
+pub struct EgressPolicy {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+}
+
+impl EgressPolicy {
+    pub fn new(config: &EgressConfig) -> FlyResult<EgressPolicy> {
+        Ok(EgressPolicy {
+            allow: parse_all("allow", &config.allow)?,
+            deny: parse_all("deny", &config.deny)?,
+        })
+    }
+
+    pub fn check(&self, host: &str, ip: IpAddr) -> Result<(), EgressError> {
+        let reason = if self.deny.iter().any(|c| c.contains(ip)) {
+            Some("denied")
+        } else if self.allow.iter().any(|c| c.contains(ip)) {
+            None
+        } else {
+            default_denial(canonical(ip))
+        };
+        match reason {
+            None => Ok(()),
+            Some(reason) => Err(EgressError {
+                host: host.to_string(),
+                ip,
+                reason,
+            }),
+        }
+    }
+}
+
+// Every bad entry is reported at once, `list` is the setting they're from.
+fn parse_all(list: &str, cidrs: &[String]) -> FlyResult<Vec<Cidr>> {
+    let mut parsed = vec![];
+    let mut invalid = vec![];
+    for c in cidrs {
+        match Cidr::parse(c) {
+            Ok(cidr) => parsed.push(cidr),
+            Err(_) => invalid.push(c.as_str()),
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(errors::new(
+            ErrorKind::InvalidInput,
+            format!("invalid CIDR in egress.{}: {}", list, invalid.join(", ")),
+        ));
+    }
+    Ok(parsed)
+}
+
+// IPv6 addresses embedding an IPv4 address are checked as that address, they reach it
+// through NAT64 or a 6to4 relay. Those are IPv4-mapped (::ffff:0:0/96), NAT64
+// (64:ff9b::/96), 6to4 (2002::/16) and IPv4-compatible (::/96, less :: and ::1).
+fn canonical(ip: IpAddr) -> IpAddr {
+    let v6 = match ip {
+        IpAddr::V6(v6) => v6,
+        _ => return ip,
+    };
+    let (hi, lo) = match v6.segments() {
+        [0, 0, 0, 0, 0, 0xffff, hi, lo] => (hi, lo),
+        [0x64, 0xff9b, 0, 0, 0, 0, hi, lo] => (hi, lo),
+        [0x2002, hi, lo, _, _, _, _, _] => (hi, lo),
+        [0, 0, 0, 0, 0, 0, hi, lo] if hi != 0 => (hi, lo),
+        _ => return ip,
+    };
+    IpAddr::V4(Ipv4Addr::new(
+        (hi >> 8) as u8,
+        hi as u8,
+        (lo >> 8) as u8,
+        lo as u8,
+    ))
+}
+
+fn default_denial(ip: IpAddr) -> Option<&'static str> {
+    match ip {
+        IpAddr::V4(v4) => {
+            let octets = v4.octets();
+            if v4.is_loopback() {
+                Some("loopback")
+            } else if v4.is_link_local() {
+                Some("link_local")
+            } else if v4.is_private() || (octets[0] == 100 && octets[1] & 0xc0 == 64) {
+                // 100.64.0.0/10 is carrier-grade NAT
+                Some("private")
+            } else if v4.is_unspecified()
+                || octets[0] == 0
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || octets[0] >= 240
+            {
+                Some("reserved")
+            } else {
+                None
+            }
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            if v6.is_loopback() {
+                Some("loopback")
+            } else if first & 0xffc0 == 0xfe80 {
+                Some("link_local")
+            } else if first & 0xfe00 == 0xfc00 {
+                Some("private")
+            } else if v6.is_unspecified() || v6.is_multicast() || v6.segments()[..7] == [0; 7] {
+                // what's left of ::/96 once IPv4-compatible addresses are checked as IPv4
+                Some("reserved")
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// Labels of the runtime connections are made for, for the metrics.
+#[derive(Debug, Clone)]
+struct EgressLabels {
+    runtime: String,
+    version: String,
+}
+
+impl EgressLabels {
+    fn denied(&self, err: EgressError) -> io::Error {
+        warn!("{}", err);
+        FETCH_EGRESS_DENIED_TOTAL
+            .with_label_values(&[self.runtime.as_str(), self.version.as_str(), err.reason])
+            .inc();
+        io::Error::new(io::ErrorKind::PermissionDenied, err)
+    }
+}
+
+/// Resolves hostnames, leaving out addresses the policy denies.
+#[derive(Clone)]
+pub struct EgressResolver {
+    policy: Arc<EgressPolicy>,
+    labels: Arc<EgressLabels>,
+}
+
+impl Resolve for EgressResolver {
+    type Addrs = vec::IntoIter<IpAddr>;
+    type Future = Box<Future<Item = Self::Addrs, Error = io::Error> + Send>;
+
+    fn resolve(&self, name: Name) -> Self::Future {
+        let policy = self.policy.clone();
+        let labels = self.labels.clone();
+        let host = name.as_str().to_string();
+        Box::new(GAI_RESOLVER.resolve(name).and_then(move |addrs| {
+            let mut allowed = vec![];
+            let mut denied = None;
+            for ip in addrs {
+                match policy.check(&host, ip) {
+                    Ok(_) => allowed.push(ip),
+                    Err(e) => denied = Some(e),
+                }
+            }
+            match denied {
+                Some(e) if allowed.is_empty() => Err(labels.denied(e)),
+                _ => Ok(allowed.into_iter()),
+            }
+        }))
+    }
+}
+
+/// An `HttpConnector` enforcing an `EgressPolicy`. Hosts that are IP addresses
+/// aren't resolved, so they're checked before connecting.
+#[derive(Clone)]
+pub struct EgressConnector {
+    http: HttpConnector<EgressResolver>,
+    policy: Arc<EgressPolicy>,
+    labels: Arc<EgressLabels>,
+}
+
+impl EgressConnector {
+    pub fn new(policy: EgressPolicy, runtime: &str, version: &str) -> EgressConnector {
+        let policy = Arc::new(policy);
+        let labels = Arc::new(EgressLabels {
+            runtime: runtime.to_string(),
+            version: version.to_string(),
+        });
+        let mut http = HttpConnector::new_with_resolver(EgressResolver {
+            policy: policy.clone(),
+            labels: labels.clone(),
+        });
+        http.enforce_http(false);
+        EgressConnector {
+            http,
+            policy,
+            labels,
+        }
+    }
+
+    pub fn set_connect_timeout(&mut self, dur: Option<Duration>) {
+        self.http.set_connect_timeout(dur);
+    }
+
+    /// Checks a host that's an IP address, names are checked once resolved.
+    pub fn check_literal(&self, host: &str) -> io::Result<()> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        match host.parse::<IpAddr>() {
+            Ok(ip) => self
+                .policy
+                .check(host, ip)
+                .map_err(|e| self.labels.denied(e)),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Resolves `host` to an address the policy allows. For connections through a proxy,
+    /// which would otherwise resolve names without checking them.
+    pub fn resolve(&self, host: &str) -> Box<Future<Item = IpAddr, Error = io::Error> + Send> {
+        let literal = host.trim_start_matches('[').trim_end_matches(']');
+        if let Ok(ip) = literal.parse::<IpAddr>() {
+            return Box::new(future::result(self.check_literal(host).map(|_| ip)));
+        }
+        let name = match host.parse::<Name>() {
+            Ok(name) => name,
+            Err(e) => return Box::new(future::err(io::Error::new(io::ErrorKind::InvalidInput, e))),
+        };
+        let resolver = EgressResolver {
+            policy: self.policy.clone(),
+            labels: self.labels.clone(),
+        };
+        let host = host.to_string();
+        Box::new(resolver.resolve(name).and_then(move |mut addrs| {
+            addrs.next().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::AddrNotAvailable,
+                    format!("no addresses for {}", host),
+                )
+            })
+        }))
+    }
+}
+
+impl Connect for EgressConnector {
+    type Transport = TcpStream;
+    type Error = io::Error;
+    type Future = Box<Future<Item = (TcpStream, Connected), Error = io::Error> + Send>;
+
+    fn connect(&self, dst: Destination) -> Self::Future {
+        if let Err(e) = self.check_literal(dst.host()) {
+            return Box::new(future::err(e));
+        }
+        Box::new(self.http.connect(dst))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr() {
+        let cidr = Cidr::parse("10.1.0.0/16").unwrap();
+        assert!(cidr.contains(ip("10.1.2.3")));
+        assert!(!cidr.contains(ip("10.2.0.1")));
+        assert!(cidr.contains(ip("::ffff:10.1.0.1")));
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains(ip("8.8.8.8")));
+        assert!(Cidr::parse("2001:db8::/32")
+            .unwrap()
+            .contains(ip("2001:db8::1")));
+        assert!(Cidr::parse("1.2.3.4").unwrap().contains(ip("1.2.3.4")));
+        // NAT64, 6to4 and IPv4-compatible addresses are in the ranges of what they embed
+        assert!(cidr.contains(ip("64:ff9b::a01:203")));
+        assert!(cidr.contains(ip("2002:a01:203::1")));
+        assert!(cidr.contains(ip("::10.1.2.3")));
+        assert!(!cidr.contains(ip("2002:a02:1::1")));
+        // and still in their own
+        assert!(Cidr::parse("2002::/16")
+            .unwrap()
+            .contains(ip("2002:a01:203::1")));
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(Cidr::parse("nope/8").is_err());
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = EgressPolicy::default();
+        for denied in &[
+            "127.0.0.1",
+            "169.254.169.254",
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.1.1",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+            "2002:7f00:1::",
+            "2002:a00:1::1",
+            "::127.0.0.1",
+            "::10.0.0.1",
+            "::2",
+        ] {
+            assert!(policy.check("host", ip(denied)).is_err(), "{}", denied);
+        }
+        for allowed in &[
+            "8.8.8.8",
+            "172.32.0.1",
+            "2606:4700::1111",
+            "64:ff9b::808:808",
+            "2002:808:808::1",
+            "::8.8.8.8",
+        ] {
+            assert!(policy.check("host", ip(allowed)).is_ok(), "{}", allowed);
+        }
+        assert_eq!(
+            policy
+                .check("host", ip("169.254.169.254"))
+                .unwrap_err()
+                .reason,
+            "link_local"
+        );
+        assert_eq!(
+            policy
+                .check("host", ip("64:ff9b::a9fe:a9fe"))
+                .unwrap_err()
+                .reason,
+            "link_local"
+        );
+        assert_eq!(
+            policy
+                .check("host", ip("2002:a00:1::1"))
+                .unwrap_err()
+                .reason,
+            "private"
+        );
+        assert_eq!(
+            policy.check("host", ip("::127.0.0.1")).unwrap_err().reason,
+            "loopback"
+        );
+    }
+
+    #[test]
+    fn test_configured_policy() {
+        let policy = EgressPolicy::new(&EgressConfig {
+            allow: vec!["10.1.0.0/16".to_string()],
+            deny: vec!["10.1.2.0/24".to_string(), "203.0.113.0/24".to_string()],
+        })
+        .unwrap();
+        assert!(policy.check("host", ip("10.1.0.5")).is_ok());
+        assert!(policy.check("host", ip("10.2.0.5")).is_err());
+        assert_eq!(
+            policy.check("host", ip("10.1.2.5")).unwrap_err().reason,
+            "denied"
+        );
+        assert!(policy.check("host", ip("203.0.113.9")).is_err());
+        assert!(policy.check("host", ip("8.8.8.8")).is_ok());
+
+        let policy = EgressPolicy::new(&EgressConfig {
+            allow: vec![],
+            deny: vec!["64:ff9b::/96".to_string()],
+        })
+        .unwrap();
+        assert!(policy.check("host", ip("64:ff9b::808:808")).is_err());
+        assert!(policy.check("host", ip("8.8.8.8")).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let err = EgressPolicy::new(&EgressConfig {
+            allow: vec![],
+            deny: vec![
+                "nope".to_string(),
+                "10.0.0.0/8".to_string(),
+                "10.0.0.0/33".to_string(),
+            ],
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            format!("{}", err),
+            "invalid CIDR in egress.deny: nope, 10.0.0.0/33"
+        );
+    }
+
+    #[test]
+    fn test_find_in_io_error() {
+        let err = EgressError {
+            host: "metadata".to_string(),
+            ip: ip("169.254.169.254"),
+            reason: "link_local",
+        };
+        let io_err = io::Error::new(io::ErrorKind::PermissionDenied, err);
+        let found = EgressError::find(&io_err).unwrap();
+        assert_eq!(found.host, "metadata");
+        let fly_err: FlyError = found.clone().into();
+        assert_eq!(fly_err.kind(), ErrorKind::EgressDenied);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let egress = EgressConnector::new(EgressPolicy::default(), "test", "1");
+        assert_eq!(egress.resolve("8.8.8.8").wait().unwrap(), ip("8.8.8.8"));
+        for denied in &["127.0.0.1", "[::1]", "localhost"] {
+            let err = egress.resolve(denied).wait().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied, "{}", denied);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! How `fetch` follows redirects, per the Fetch spec, and when it retries a request.
+
+use hyper::header::{self, HeaderMap};
+use hyper::{Method, StatusCode, Uri};
+use rand::Rng;
+use std::time::Duration;
+
+use crate::errors::{self, ErrorKind, FlyError, FlyResult};
+
+/// Redirects followed before giving up.
+pub const MAX_REDIRECTS: usize = 20;
+/// Highest number of retries a request can ask for.
+pub const MAX_RETRIES: u32 = 10;
+
+const BACKOFF_BASE: Duration = Duration::from_millis(100);
+const BACKOFF_MAX: Duration = Duration::from_secs(10);
+
+// Dropped when a redirect turns the request into a GET.
+const BODY_HEADERS: &[header::HeaderName] = &[
+    header::CONTENT_ENCODING,
+    header::CONTENT_LANGUAGE,
+    header::CONTENT_LOCATION,
+    header::CONTENT_TYPE,
+    header::CONTENT_LENGTH,
+    header::TRANSFER_ENCODING,
+];
+
+// Dropped when a redirect leaves the request's origin.
+const CREDENTIAL_HEADERS: &[header::HeaderName] = &[
+    header::AUTHORIZATION,
+    header::PROXY_AUTHORIZATION,
+    header::COOKIE,
+    header::HOST,
+];
+
+/// The request to send after a redirect.
+#[derive(Debug)]
+pub struct Redirect {
+    pub method: Method,
+    pub uri: Uri,
+    pub headers: HeaderMap,
+    /// The body isn't sent again, the method was rewritten to GET.
+    pub drop_body: bool,
+}
+
+/// What to send after a response, `None` unless it's a redirect with a `Location`.
+pub fn redirect(
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    status: StatusCode,
+    res_headers: &HeaderMap,
+) -> FlyResult<Option<Redirect>> {
+    match status.as_u16() {
+        301 | 302 | 303 | 307 | 308 => {}
+        _ => return Ok(None),
+    }
+    let location = match res_headers.get(header::LOCATION) {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+    let location = location
+        .to_str()
+        .map_err(|_| redirect_error("invalid redirect location".to_string()))?;
+    let base = url::Url::parse(&uri.to_string())
+        .map_err(|e| redirect_error(format!("invalid url {}: {}", uri, e)))?;
+    let mut next = base
+        .join(location)
+        .map_err(|e| redirect_error(format!("invalid redirect location {}: {}", location, e)))?;
+    if next.scheme() != "http" && next.scheme() != "https" {
+        return Err(redirect_error(format!(
+            "unsupported redirect scheme: {}",
+            next.scheme()
+        )));
+    }
+    next.set_fragment(None);
+    let next_uri: Uri = next
+        .as_str()
+        .parse()
+        .map_err(|e| redirect_error(format!("invalid redirect location {}: {}", next, e)))?;
+
+    let mut headers = headers.clone();
+    let to_get = match status.as_u16() {
+        301 | 302 => *method == Method::POST,
+        303 => *method != Method::GET && *method != Method::HEAD,
+        _ => false,
+    };
+    if to_get {
+        for name in BODY_HEADERS {
+            headers.remove(name);
+        }
+    }
+    if base.origin() != next.origin() {
+        for name in CREDENTIAL_HEADERS {
+            headers.remove(name);
+        }
+    }
+
+    Ok(Some(Redirect {
+        method: if to_get { Method::GET } else { method.clone() },
+        uri: next_uri,
+        headers,
+        drop_body: to_get,
+    }))
+}
+
+fn redirect_error(msg: String) -> FlyError {
+    errors::new(ErrorKind::InvalidData, msg)
+}
+
+/// Methods safe to send more than once.
+pub fn is_idempotent(method: &Method) -> bool {
+    match *method {
+        Method::GET
+        | Method::HEAD
+        | Method::OPTIONS
+        | Method::TRACE
+        | Method::PUT
+        | Method::DELETE => true,
+        _ => false,
+    }
+}
+
+/// Responses worth another try: the origin, or something in front of it, is having trouble.
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    match status {
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT => {
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Errors that could go away on their own. Denials and aborts won't.
+pub fn is_retryable_error(err: &FlyError) -> bool {
+    match err.kind() {
+        ErrorKind::EgressDenied
+        | ErrorKind::PermissionDenied
+        | ErrorKind::HttpCanceled
+        | ErrorKind::HttpUser
+        | ErrorKind::InvalidInput
+        | ErrorKind::InvalidData => false,
+        _ => true,
+    }
+}
+
+/// How long to wait before retry number `attempt` (from 0): exponential, with full jitter.
+pub fn backoff(attempt: u32) -> Duration {
+    let cap = backoff_cap(attempt);
+    let cap_ms = cap.as_secs() * 1000 + u64::from(cap.subsec_millis());
+    let ms = rand::thread_rng().gen_range(0, cap_ms + 1);
+    Duration::from_millis(ms)
+}
+
+fn backoff_cap(attempt: u32) -> Duration {
+    BACKOFF_BASE
+        .checked_mul(1u32 << attempt.min(16))
+        .map_or(BACKOFF_MAX, |d| d.min(BACKOFF_MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    fn follow(method: Method, uri: &str, status: u16, location: &str) -> Redirect {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer x"));
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        let mut res_headers = HeaderMap::new();
+        res_headers.insert(header::LOCATION, HeaderValue::from_str(location).unwrap());
+        redirect(
+            &method,
+            &uri.parse().unwrap(),
+            &headers,
+            StatusCode::from_u16(status).unwrap(),
+            &res_headers,
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_not_a_redirect() {
+        let mut res_headers = HeaderMap::new();
+        let uri = "http://example.com/".parse().unwrap();
+        let res = redirect(
+            &Method::GET,
+            &uri,
+            &HeaderMap::new(),
+            StatusCode::FOUND,
+            &res_headers,
+        );
+        assert!(res.unwrap().is_none());
+
+        res_headers.insert(header::LOCATION, HeaderValue::from_static("/next"));
+        let res = redirect(
+            &Method::GET,
+            &uri,
+            &HeaderMap::new(),
+            StatusCode::OK,
+            &res_headers,
+        );
+        assert!(res.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_relative_location() {
+        let r = follow(Method::GET, "http://example.com/a/b?q#frag", 301, "c#top");
+        assert_eq!(r.uri, "http://example.com/a/c");
+        assert_eq!(r.method, Method::GET);
+        assert!(r.headers.contains_key(header::AUTHORIZATION));
+    }
+
+    #[test]
+    fn test_method_rewrites() {
+        let r = follow(Method::POST, "http://example.com/", 303, "/done");
+        assert_eq!(r.method, Method::GET);
+        assert!(r.drop_body);
+        assert!(!r.headers.contains_key(header::CONTENT_TYPE));
+
+        let r = follow(Method::PUT, "http://example.com/", 303, "/done");
+        assert_eq!(r.method, Method::GET);
+
+        let r = follow(Method::HEAD, "http://example.com/", 303, "/done");
+        assert_eq!(r.method, Method::HEAD);
+
+        let r = follow(Method::POST, "http://example.com/", 302, "/done");
+        assert_eq!(r.method, Method::GET);
+
+        let r = follow(Method::PUT, "http://example.com/", 302, "/done");
+        assert_eq!(r.method, Method::PUT);
+        assert!(!r.drop_body);
+
+        for status in &[307, 308] {
+            let r = follow(Method::POST, "http://example.com/", *status, "/again");
+            assert_eq!(r.method, Method::POST);
+            assert!(!r.drop_body);
+            assert!(r.headers.contains_key(header::CONTENT_TYPE));
+        }
+    }
+
+    #[test]
+    fn test_cross_origin_strips_credentials() {
+        let r = follow(
+            Method::GET,
+            "https://example.com/",
+            302,
+            "https://other.com/",
+        );
+        assert!(!r.headers.contains_key(header::AUTHORIZATION));
+
+        let r = follow(
+            Method::GET,
+            "https://example.com/",
+            302,
+            "http://example.com/",
+        );
+        assert!(!r.headers.contains_key(header::AUTHORIZATION));
+
+        let r = follow(
+            Method::GET,
+            "https://example.com/",
+            302,
+            "https://example.com:443/x",
+        );
+        assert!(r.headers.contains_key(header::AUTHORIZATION));
+    }
+
+    #[test]
+    fn test_bad_locations() {
+        let uri = "http://example.com/".parse().unwrap();
+        for location in &["ftp://example.com/", "http://exa mple.com/"] {
+            let mut res_headers = HeaderMap::new();
+            res_headers.insert(header::LOCATION, HeaderValue::from_str(location).unwrap());
+            let err = redirect(
+                &Method::GET,
+                &uri,
+                &HeaderMap::new(),
+                StatusCode::FOUND,
+                &res_headers,
+            )
+            .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_retryable() {
+        assert!(is_idempotent(&Method::GET));
+        assert!(is_idempotent(&Method::PUT));
+        assert!(!is_idempotent(&Method::POST));
+        assert!(!is_idempotent(&Method::PATCH));
+
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+
+        assert!(is_retryable_error(&errors::new(
+            ErrorKind::ConnectionRefused,
+            "refused".to_string()
+        )));
+        assert!(!is_retryable_error(&errors::new(
+            ErrorKind::EgressDenied,
+            "denied".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff_cap(0), Duration::from_millis(100));
+        assert_eq!(backoff_cap(3), Duration::from_millis(800));
+        assert_eq!(backoff_cap(10), BACKOFF_MAX);
+        assert_eq!(backoff_cap(u32::max_value()), BACKOFF_MAX);
+        for attempt in 0..5 {
+            assert!(backoff(attempt) <= backoff_cap(attempt));
+        }
+    }
+}
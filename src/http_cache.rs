@@ -0,0 +1,637 @@
+//! Caching `fetch` responses after RFC 7234. Every request an app serves goes through the
+//! same cache, so it acts as a shared cache: `private` responses aren't stored and
+//! `s-maxage` wins over `max-age`.
+
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use hyper::{Method, StatusCode};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Response header telling how the cache handled a fetch.
+pub const CACHE_STATUS_HEADER: &str = "x-cache";
+
+/// Biggest body stored, larger ones are only passed along.
+pub const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+// How long responses that can be revalidated are kept past their freshness.
+const REVALIDATE_WINDOW: u64 = 24 * 60 * 60;
+// Cap on the freshness guessed from `Last-Modified`.
+const MAX_HEURISTIC_LIFETIME: u64 = 24 * 60 * 60;
+
+const CONDITIONAL_HEADERS: &[HeaderName] = &[
+    header::IF_NONE_MATCH,
+    header::IF_MODIFIED_SINCE,
+    header::IF_MATCH,
+    header::IF_UNMODIFIED_SINCE,
+    header::IF_RANGE,
+    header::RANGE,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheStatus {
+    /// Served fresh from the cache.
+    Hit,
+    /// Fetched from the origin.
+    Miss,
+    /// Served stale from the cache, while it's revalidated.
+    Stale,
+    /// Served from the cache after the origin said it hadn't changed.
+    Revalidated,
+    /// Not cacheable, went straight to the origin.
+    Bypass,
+}
+
+impl CacheStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "HIT",
+            CacheStatus::Miss => "MISS",
+            CacheStatus::Stale => "STALE",
+            CacheStatus::Revalidated => "REVALIDATED",
+            CacheStatus::Bypass => "BYPASS",
+        }
+    }
+}
+
+/// The `Cache-Control` directives this cache follows.
+#[derive(Debug, Default, PartialEq)]
+pub struct CacheControl {
+    pub no_store: bool,
+    pub no_cache: bool,
+    pub private: bool,
+    pub public: bool,
+    pub must_revalidate: bool,
+    pub max_age: Option<u64>,
+    pub s_maxage: Option<u64>,
+    pub stale_while_revalidate: Option<u64>,
+}
+
+impl CacheControl {
+    /// `Pragma: no-cache` counts when there's no `Cache-Control`.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut cc = CacheControl::default();
+        if !headers.contains_key(header::CACHE_CONTROL) {
+            cc.no_cache = headers
+                .get_all(header::PRAGMA)
+                .iter()
+                .any(|v| v.to_str().map(|v| v.contains("no-cache")).unwrap_or(false));
+            return cc;
+        }
+        for value in headers.get_all(header::CACHE_CONTROL) {
+            let value = match value.to_str() {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+            for directive in value.split(',') {
+                let mut parts = directive.splitn(2, '=');
+                let name = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+                // an invalid delta means the response is already stale
+                let secs = parts
+                    .next()
+                    .map(|v| v.trim().trim_matches('"').parse::<u64>().unwrap_or(0));
+                match name.as_str() {
+                    "no-store" => cc.no_store = true,
+                    "no-cache" => cc.no_cache = true,
+                    "private" => cc.private = true,
+                    "public" => cc.public = true,
+                    "must-revalidate" | "proxy-revalidate" => cc.must_revalidate = true,
+                    "max-age" => cc.max_age = Some(secs.unwrap_or(0)),
+                    "s-maxage" => cc.s_maxage = Some(secs.unwrap_or(0)),
+                    "stale-while-revalidate" => cc.stale_while_revalidate = secs,
+                    _ => {}
+                }
+            }
+        }
+        cc
+    }
+}
+
+/// Whether the cache can answer a request at all. Conditional and range requests go
+/// straight to the origin.
+pub fn is_cacheable_request(method: &Method, headers: &HeaderMap) -> bool {
+    *method == Method::GET && !CONDITIONAL_HEADERS.iter().any(|h| headers.contains_key(h))
+}
+
+/// Whether a response can be stored, given the request it answers.
+pub fn is_storable(
+    method: &Method,
+    req_headers: &HeaderMap,
+    status: StatusCode,
+    res_headers: &HeaderMap,
+) -> bool {
+    if *method != Method::GET {
+        return false;
+    }
+    match status.as_u16() {
+        200 | 203 | 204 | 300 | 301 | 404 | 405 | 410 | 414 | 501 => {}
+        _ => return false,
+    }
+    let req_cc = CacheControl::from_headers(req_headers);
+    let res_cc = CacheControl::from_headers(res_headers);
+    if req_cc.no_store || res_cc.no_store || res_cc.private {
+        return false;
+    }
+    if req_headers.contains_key(header::AUTHORIZATION)
+        && !(res_cc.public || res_cc.must_revalidate || res_cc.s_maxage.is_some())
+    {
+        return false;
+    }
+    // never matches another request
+    !vary_names(res_headers).iter().any(|n| n == "*")
+}
+
+/// Where a request's response is stored in the `CacheStore`.
+pub fn cache_key(url: &str) -> String {
+    format!("fetch-cache:{}", url)
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Freshness {
+    Fresh,
+    /// Stale, but it can be served while it's revalidated.
+    StaleWhileRevalidate,
+    Stale,
+}
+
+/// A stored response's status and headers, kept as its entry's meta.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub status: u16,
+    // values are latin-1 strings, they can be any bytes
+    headers: Vec<(String, String)>,
+    // the request's values for each header named by `Vary`
+    vary: Vec<(String, Option<String>)>,
+    // the response's age when it was received, in seconds
+    initial_age: u64,
+    response_time: u64,
+}
+
+impl StoredResponse {
+    /// `request_time` and `response_time` are when the request was sent and its
+    /// response received, in seconds since the epoch.
+    pub fn new(
+        req_headers: &HeaderMap,
+        status: StatusCode,
+        res_headers: &HeaderMap,
+        request_time: u64,
+        response_time: u64,
+    ) -> Self {
+        let vary = vary_names(res_headers)
+            .into_iter()
+            .map(|name| {
+                let value = joined_value(req_headers, &name);
+                (name, value)
+            })
+            .collect();
+        StoredResponse {
+            status: status.as_u16(),
+            headers: headers_to_vec(res_headers),
+            vary,
+            initial_age: initial_age(res_headers, request_time, response_time),
+            response_time,
+        }
+    }
+
+    pub fn from_meta(meta: &str) -> Option<Self> {
+        serde_json::from_str(meta).ok()
+    }
+
+    pub fn to_meta(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = match HeaderName::from_bytes(name.as_bytes()) {
+                Ok(n) => n,
+                Err(_) => continue,
+            };
+            if let Ok(value) = HeaderValue::from_bytes(&from_latin1(value)) {
+                headers.append(name, value);
+            }
+        }
+        headers
+    }
+
+    /// Whether the response can answer a request, going by `Vary`.
+    pub fn matches(&self, req_headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| joined_value(req_headers, name) == *value)
+    }
+
+    pub fn age(&self, now: u64) -> u64 {
+        self.initial_age + now.saturating_sub(self.response_time)
+    }
+
+    pub fn freshness_lifetime(&self) -> u64 {
+        freshness_lifetime(&self.headers(), self.response_time)
+    }
+
+    pub fn freshness(&self, req_headers: &HeaderMap, now: u64) -> Freshness {
+        let res_cc = CacheControl::from_headers(&self.headers());
+        let req_cc = CacheControl::from_headers(req_headers);
+        if res_cc.no_cache || req_cc.no_cache {
+            return Freshness::Stale;
+        }
+        let age = self.age(now);
+        let mut lifetime = self.freshness_lifetime();
+        if let Some(max_age) = req_cc.max_age {
+            lifetime = lifetime.min(max_age);
+        }
+        if age < lifetime {
+            return Freshness::Fresh;
+        }
+        match res_cc.stale_while_revalidate {
+            Some(swr) if !res_cc.must_revalidate && age < lifetime + swr => {
+                Freshness::StaleWhileRevalidate
+            }
+            _ => Freshness::Stale,
+        }
+    }
+
+    /// Adds the response's validators to a request, false if it has none.
+    pub fn add_validators(&self, req_headers: &mut HeaderMap) -> bool {
+        let headers = self.headers();
+        let mut added = false;
+        if let Some(etag) = headers.get(header::ETAG) {
+            req_headers.insert(header::IF_NONE_MATCH, etag.clone());
+            added = true;
+        }
+        if let Some(last_modified) = headers.get(header::LAST_MODIFIED) {
+            req_headers.insert(header::IF_MODIFIED_SINCE, last_modified.clone());
+            added = true;
+        }
+        added
+    }
+
+    /// Updates the response from a `304 Not Modified` revalidating it.
+    pub fn freshen(&mut self, res_headers: &HeaderMap, request_time: u64, response_time: u64) {
+        let mut headers = self.headers();
+        for name in res_headers.keys() {
+            // these describe the 304's own (empty) body
+            if *name == header::CONTENT_LENGTH || *name == header::TRANSFER_ENCODING {
+                continue;
+            }
+            headers.remove(name);
+            for value in res_headers.get_all(name) {
+                headers.append(name.clone(), value.clone());
+            }
+        }
+        self.headers = headers_to_vec(&headers);
+        self.initial_age = initial_age(res_headers, request_time, response_time);
+        self.response_time = response_time;
+    }
+
+    /// How many seconds the store should keep the response: while it can be served, and
+    /// a while longer when it can be revalidated. `None` when it's not worth storing.
+    pub fn ttl(&self, now: u64) -> Option<u32> {
+        let headers = self.headers();
+        let cc = CacheControl::from_headers(&headers);
+        let mut ttl = self.freshness_lifetime().saturating_sub(self.age(now));
+        if ttl > 0 {
+            ttl += cc.stale_while_revalidate.unwrap_or(0);
+        }
+        if headers.contains_key(header::ETAG) || headers.contains_key(header::LAST_MODIFIED) {
+            ttl += REVALIDATE_WINDOW;
+        }
+        if ttl == 0 {
+            return None;
+        }
+        Some(ttl.min(u64::from(u32::max_value())) as u32)
+    }
+}
+
+// RFC 7234 4.2.1
+fn freshness_lifetime(headers: &HeaderMap, response_time: u64) -> u64 {
+    let cc = CacheControl::from_headers(headers);
+    if let Some(secs) = cc.s_maxage.or(cc.max_age) {
+        return secs;
+    }
+    let date = date_value(headers, header::DATE).unwrap_or(response_time);
+    if headers.contains_key(header::EXPIRES) {
+        // an invalid date means it's already expired
+        return date_value(headers, header::EXPIRES)
+            .map(|expires| expires.saturating_sub(date))
+            .unwrap_or(0);
+    }
+    match date_value(headers, header::LAST_MODIFIED) {
+        Some(last_modified) => {
+            (date.saturating_sub(last_modified) / 10).min(MAX_HEURISTIC_LIFETIME)
+        }
+        None => 0,
+    }
+}
+
+// RFC 7234 4.2.3
+fn initial_age(headers: &HeaderMap, request_time: u64, response_time: u64) -> u64 {
+    let date = date_value(headers, header::DATE).unwrap_or(response_time);
+    let apparent_age = response_time.saturating_sub(date);
+    let age_value = headers
+        .get(header::AGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(0);
+    let response_delay = response_time.saturating_sub(request_time);
+    apparent_age.max(age_value + response_delay)
+}
+
+fn date_value(headers: &HeaderMap, name: HeaderName) -> Option<u64> {
+    let value = headers.get(name)?.to_str().ok()?;
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some(date.timestamp().max(0) as u64)
+}
+
+fn vary_names(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|n| n.trim().to_ascii_lowercase())
+        .filter(|n| !n.is_empty())
+        .collect()
+}
+
+// All of a header's values, the way they'd be combined on one line.
+fn joined_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    let values: Vec<String> = headers
+        .get_all(name)
+        .iter()
+        .map(|v| to_latin1(v.as_bytes()))
+        .collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.join(", "))
+    }
+}
+
+fn headers_to_vec(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| (name.as_str().to_string(), to_latin1(value.as_bytes())))
+        .collect()
+}
+
+fn to_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| char::from(b)).collect()
+}
+
+fn from_latin1(s: &str) -> Vec<u8> {
+    s.chars().map(|c| c as u32 as u8).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_550_000_000;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn stored(res: &[(&'static str, &str)]) -> StoredResponse {
+        StoredResponse::new(&HeaderMap::new(), StatusCode::OK, &headers(res), NOW, NOW)
+    }
+
+    fn http_date(secs: u64) -> String {
+        chrono::NaiveDateTime::from_timestamp(secs as i64, 0)
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
+    }
+
+    #[test]
+    fn test_cache_control() {
+        let cc = CacheControl::from_headers(&headers(&[
+            ("cache-control", "public, max-age=60"),
+            (
+                "cache-control",
+                "stale-while-revalidate=\"30\", S-MAXAGE=120",
+            ),
+        ]));
+        assert_eq!(
+            cc,
+            CacheControl {
+                public: true,
+                max_age: Some(60),
+                s_maxage: Some(120),
+                stale_while_revalidate: Some(30),
+                ..Default::default()
+            }
+        );
+
+        let cc = CacheControl::from_headers(&headers(&[("cache-control", "max-age=soon")]));
+        assert_eq!(cc.max_age, Some(0));
+
+        let cc = CacheControl::from_headers(&headers(&[("pragma", "no-cache")]));
+        assert!(cc.no_cache);
+    }
+
+    #[test]
+    fn test_storable() {
+        let req = HeaderMap::new();
+        let ok = |res: &[(&'static str, &str)]| {
+            is_storable(&Method::GET, &req, StatusCode::OK, &headers(res))
+        };
+        assert!(ok(&[("cache-control", "max-age=60")]));
+        assert!(ok(&[]));
+        assert!(!ok(&[("cache-control", "no-store")]));
+        assert!(!ok(&[("cache-control", "private, max-age=60")]));
+        assert!(!ok(&[("vary", "*")]));
+
+        assert!(!is_storable(
+            &Method::POST,
+            &req,
+            StatusCode::OK,
+            &HeaderMap::new()
+        ));
+        assert!(!is_storable(
+            &Method::GET,
+            &req,
+            StatusCode::PARTIAL_CONTENT,
+            &HeaderMap::new()
+        ));
+
+        let authed = headers(&[("authorization", "Bearer x")]);
+        assert!(!is_storable(
+            &Method::GET,
+            &authed,
+            StatusCode::OK,
+            &headers(&[("cache-control", "max-age=60")])
+        ));
+        assert!(is_storable(
+            &Method::GET,
+            &authed,
+            StatusCode::OK,
+            &headers(&[("cache-control", "s-maxage=60")])
+        ));
+    }
+
+    #[test]
+    fn test_cacheable_request() {
+        assert!(is_cacheable_request(&Method::GET, &HeaderMap::new()));
+        assert!(!is_cacheable_request(&Method::POST, &HeaderMap::new()));
+        assert!(!is_cacheable_request(
+            &Method::GET,
+            &headers(&[("range", "bytes=0-10")])
+        ));
+        assert!(!is_cacheable_request(
+            &Method::GET,
+            &headers(&[("if-none-match", "\"abc\"")])
+        ));
+    }
+
+    #[test]
+    fn test_freshness_lifetime() {
+        assert_eq!(
+            stored(&[("cache-control", "max-age=60")]).freshness_lifetime(),
+            60
+        );
+        assert_eq!(
+            stored(&[("cache-control", "max-age=60, s-maxage=10")]).freshness_lifetime(),
+            10
+        );
+        let date = http_date(NOW);
+        assert_eq!(
+            stored(&[
+                ("date", date.as_str()),
+                ("expires", http_date(NOW + 300).as_str())
+            ])
+            .freshness_lifetime(),
+            300
+        );
+        assert_eq!(stored(&[("expires", "0")]).freshness_lifetime(), 0);
+        assert_eq!(
+            stored(&[
+                ("date", date.as_str()),
+                ("last-modified", http_date(NOW - 1000).as_str())
+            ])
+            .freshness_lifetime(),
+            100
+        );
+        assert_eq!(stored(&[]).freshness_lifetime(), 0);
+    }
+
+    #[test]
+    fn test_age() {
+        let res = headers(&[("date", http_date(NOW - 10).as_str()), ("age", "5")]);
+        let stored = StoredResponse::new(&HeaderMap::new(), StatusCode::OK, &res, NOW - 2, NOW);
+        // the response was 10 seconds old according to its date
+        assert_eq!(stored.age(NOW), 10);
+        assert_eq!(stored.age(NOW + 20), 30);
+
+        let res = headers(&[("age", "30")]);
+        let stored = StoredResponse::new(&HeaderMap::new(), StatusCode::OK, &res, NOW - 2, NOW);
+        assert_eq!(stored.age(NOW), 32);
+    }
+
+    #[test]
+    fn test_freshness() {
+        let req = HeaderMap::new();
+        let res = stored(&[("cache-control", "max-age=60, stale-while-revalidate=30")]);
+        assert_eq!(res.freshness(&req, NOW + 59), Freshness::Fresh);
+        assert_eq!(
+            res.freshness(&req, NOW + 60),
+            Freshness::StaleWhileRevalidate
+        );
+        assert_eq!(res.freshness(&req, NOW + 90), Freshness::Stale);
+
+        let no_cache = headers(&[("cache-control", "no-cache")]);
+        assert_eq!(res.freshness(&no_cache, NOW), Freshness::Stale);
+        let max_age = headers(&[("cache-control", "max-age=10")]);
+        assert_eq!(
+            res.freshness(&max_age, NOW + 20),
+            Freshness::StaleWhileRevalidate
+        );
+
+        let res = stored(&[(
+            "cache-control",
+            "max-age=60, stale-while-revalidate=30, must-revalidate",
+        )]);
+        assert_eq!(res.freshness(&req, NOW + 60), Freshness::Stale);
+
+        let res = stored(&[("cache-control", "no-cache, max-age=60")]);
+        assert_eq!(res.freshness(&req, NOW), Freshness::Stale);
+    }
+
+    #[test]
+    fn test_vary() {
+        let req = headers(&[("accept-encoding", "gzip"), ("accept-language", "en")]);
+        let res = headers(&[("vary", "Accept-Encoding"), ("cache-control", "max-age=60")]);
+        let stored = StoredResponse::new(&req, StatusCode::OK, &res, NOW, NOW);
+
+        assert!(stored.matches(&req));
+        assert!(stored.matches(&headers(&[("accept-encoding", "gzip")])));
+        assert!(!stored.matches(&headers(&[("accept-encoding", "br")])));
+        assert!(!stored.matches(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_revalidation() {
+        let mut res = stored(&[
+            ("cache-control", "max-age=60"),
+            ("etag", "\"v1\""),
+            ("last-modified", http_date(NOW - 1000).as_str()),
+            ("content-length", "5"),
+        ]);
+        let mut req = HeaderMap::new();
+        assert!(res.add_validators(&mut req));
+        assert_eq!(req.get("if-none-match").unwrap(), "\"v1\"");
+        assert_eq!(
+            req.get("if-modified-since").unwrap(),
+            &http_date(NOW - 1000)
+        );
+        assert!(!stored(&[]).add_validators(&mut HeaderMap::new()));
+
+        let not_modified = headers(&[("cache-control", "max-age=120"), ("content-length", "0")]);
+        res.freshen(&not_modified, NOW + 100, NOW + 100);
+        assert_eq!(res.freshness_lifetime(), 120);
+        assert_eq!(res.age(NOW + 100), 0);
+        let headers = res.headers();
+        assert_eq!(headers.get("etag").unwrap(), "\"v1\"");
+        assert_eq!(headers.get("content-length").unwrap(), "5");
+    }
+
+    #[test]
+    fn test_ttl() {
+        assert_eq!(
+            stored(&[("cache-control", "max-age=60")]).ttl(NOW),
+            Some(60)
+        );
+        assert_eq!(
+            stored(&[("cache-control", "max-age=60, stale-while-revalidate=30")]).ttl(NOW),
+            Some(90)
+        );
+        assert_eq!(
+            stored(&[("cache-control", "max-age=0"), ("etag", "\"v1\"")]).ttl(NOW),
+            Some(REVALIDATE_WINDOW as u32)
+        );
+        assert_eq!(stored(&[("cache-control", "max-age=0")]).ttl(NOW), None);
+    }
+
+    #[test]
+    fn test_meta_round_trip() {
+        let mut res = headers(&[("cache-control", "max-age=60"), ("set-cookie", "a=1")]);
+        res.append("set-cookie", HeaderValue::from_static("b=2"));
+        res.insert("x-latin1", HeaderValue::from_bytes(b"caf\xe9").unwrap());
+        let stored = StoredResponse::new(&HeaderMap::new(), StatusCode::NOT_FOUND, &res, NOW, NOW);
+
+        let loaded = StoredResponse::from_meta(&stored.to_meta()).unwrap();
+        assert_eq!(loaded.status, 404);
+        assert_eq!(loaded.headers(), res);
+        assert!(StoredResponse::from_meta("{}").is_none());
+    }
+}
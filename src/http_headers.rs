@@ -0,0 +1,142 @@
+//! Headers as sent to and from JS. Every value of a header is its own `HttpHeader`, in
+//! order, and values that aren't visible ASCII go as bytes.
+
+use flatbuffers::{FlatBufferBuilder, ForwardsUOffset, Vector, WIPOffset};
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::HeaderMap;
+
+use crate::errors::{self, ErrorKind, FlyResult};
+use crate::msg;
+
+pub type HeadersVector<'a> = WIPOffset<Vector<'a, ForwardsUOffset<msg::HttpHeader<'a>>>>;
+
+pub fn create_headers<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    headers: &HeaderMap,
+) -> HeadersVector<'a> {
+    let headers: Vec<_> = headers
+        .iter()
+        .map(|(key, value)| {
+            let key = builder.create_string(key.as_str());
+            let (value, value_bytes) = match value.to_str() {
+                Ok(s) => (Some(builder.create_string(s)), None),
+                Err(_) => (None, Some(builder.create_vector(value.as_bytes()))),
+            };
+            msg::HttpHeader::create(
+                builder,
+                &msg::HttpHeaderArgs {
+                    key: Some(key),
+                    value,
+                    value_bytes,
+                },
+            )
+        })
+        .collect();
+    builder.create_vector(&headers)
+}
+
+/// Fails on the first invalid name or value.
+pub fn headers_from_msg(
+    headers: Option<Vector<ForwardsUOffset<msg::HttpHeader>>>,
+) -> FlyResult<HeaderMap> {
+    let mut map = HeaderMap::new();
+    let headers = match headers {
+        Some(h) => h,
+        None => return Ok(map),
+    };
+    for i in 0..headers.len() {
+        let h = headers.get(i);
+        let key = h.key().unwrap_or("");
+        let name = HeaderName::from_bytes(key.as_bytes()).map_err(|_| {
+            errors::new(
+                ErrorKind::InvalidInput,
+                format!("invalid header name: {:?}", key),
+            )
+        })?;
+        let value = match h.value_bytes() {
+            Some(bytes) => HeaderValue::from_bytes(bytes),
+            None => HeaderValue::from_bytes(h.value().unwrap_or("").as_bytes()),
+        }
+        .map_err(|_| {
+            errors::new(
+                ErrorKind::InvalidInput,
+                format!("invalid value for header {}", name),
+            )
+        })?;
+        map.append(name, value);
+    }
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(headers: &HeaderMap) -> FlyResult<HeaderMap> {
+        let builder = &mut FlatBufferBuilder::new();
+        let vector = create_headers(builder, headers);
+        let req = msg::HttpRequest::create(
+            builder,
+            &msg::HttpRequestArgs {
+                headers: Some(vector),
+                ..Default::default()
+            },
+        );
+        builder.finish(req, None);
+        let req = flatbuffers::get_root::<msg::HttpRequest>(builder.finished_data());
+        headers_from_msg(req.headers())
+    }
+
+    #[test]
+    fn test_keeps_every_value_in_order() {
+        let mut headers = HeaderMap::new();
+        headers.append("set-cookie", HeaderValue::from_static("a=1; Path=/"));
+        headers.append("via", HeaderValue::from_static("1.1 one"));
+        headers.append("set-cookie", HeaderValue::from_static("b=2"));
+        headers.append("via", HeaderValue::from_static("1.1 two"));
+
+        let res = round_trip(&headers).unwrap();
+        let cookies: Vec<_> = res.get_all("set-cookie").iter().collect();
+        assert_eq!(cookies, vec!["a=1; Path=/", "b=2"]);
+        let via: Vec<_> = res.get_all("via").iter().collect();
+        assert_eq!(via, vec!["1.1 one", "1.1 two"]);
+    }
+
+    #[test]
+    fn test_opaque_values() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-latin1", HeaderValue::from_bytes(b"caf\xe9").unwrap());
+
+        let res = round_trip(&headers).unwrap();
+        assert_eq!(res.get("x-latin1").unwrap().as_bytes(), b"caf\xe9");
+    }
+
+    #[test]
+    fn test_invalid_headers() {
+        for (key, value) in &[("bad name", "ok"), ("x-ok", "bad\nvalue")] {
+            let builder = &mut FlatBufferBuilder::new();
+            let key = builder.create_string(key);
+            let value = builder.create_string(value);
+            let header = msg::HttpHeader::create(
+                builder,
+                &msg::HttpHeaderArgs {
+                    key: Some(key),
+                    value: Some(value),
+                    ..Default::default()
+                },
+            );
+            let vector = builder.create_vector(&[header]);
+            let req = msg::HttpRequest::create(
+                builder,
+                &msg::HttpRequestArgs {
+                    headers: Some(vector),
+                    ..Default::default()
+                },
+            );
+            builder.finish(req, None);
+            let req = flatbuffers::get_root::<msg::HttpRequest>(builder.finished_data());
+            let err = headers_from_msg(req.headers()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+    }
+}
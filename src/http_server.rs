@@ -1,13 +1,20 @@
 use futures::{future, Future, Stream};
 use std::net::{IpAddr, SocketAddr};
 
+use crate::compression::{self, Encoding};
+use crate::errors::FlyError;
 use crate::js::*;
 use crate::metrics::*;
+use crate::ops::websocket;
+use crate::runtime::JsRuntime;
+use crate::settings::{CompressionConfig, SETTINGS};
 use crate::utils::*;
+use crate::ws;
 use crate::{get_next_stream_id, RuntimeManager};
 
 use hyper::body::Payload;
-use hyper::{header, Body, Request, Response, StatusCode};
+use hyper::upgrade::Upgraded;
+use hyper::{header, Body, HeaderMap, Method, Request, Response, StatusCode};
 
 use floating_duration::TimeAsFloat;
 use std::io;
@@ -25,6 +32,8 @@ lazy_static! {
         let s = format!("Fly ({})", crate::BUILD_VERSION);
         header::HeaderValue::from_str(s.as_str()).unwrap()
     };
+    static ref COMPRESSION: CompressionConfig =
+        SETTINGS.read().unwrap().compression.unwrap_or_default();
 }
 
 struct RequestInfo {
@@ -58,6 +67,13 @@ pub fn serve_http(
 
     slog_debug!(logger, "begin request");
 
+    // answered for JS if it accepts the socket
+    let ws_accept = if ws::is_upgrade_request(&req) {
+        Some(ws::upgrade_response(&req))
+    } else {
+        None
+    };
+
     let (parts, body) = req.into_parts();
     let host = if parts.version == hyper::Version::HTTP_2 {
         match parts.uri.host() {
@@ -147,18 +163,31 @@ pub fn serve_http(
     let outbound_data =
         DATA_OUT_TOTAL.with_label_values(&[rt_name.as_str(), rt_version.as_str(), "http_response"]);
 
-    let body = if body.is_end_stream() {
-        None
+    // the upgraded connection is taken from the request body, JS doesn't get one
+    let (body, upgrade) = if ws_accept.is_some() {
+        (None, Some(body))
+    } else if body.is_end_stream() {
+        (None, None)
     } else {
-        Some(JsBody::BoxedStream(Box::new({
-            body.map_err(|e| format!("{}", e).into())
-                .map(move |chunk| chunk.into_bytes().to_vec())
-                .inspect(move |bytes| inbound_data.inc_by(bytes.len() as i64))
-        })))
+        (
+            Some(JsBody::BoxedStream(Box::new({
+                body.map_err(|e| format!("{}", e).into())
+                    .map(move |chunk| chunk.into_bytes().to_vec())
+                    .inspect(move |bytes| inbound_data.inc_by(bytes.len() as i64))
+            }))),
+            None,
+        )
     };
 
     let rt_name = rt_lock.name.clone();
     let rt_version = rt_lock.version.clone();
+    let ptr = rt_lock.ptr;
+
+    let accepted: Option<Encoding> = if parts.method == Method::HEAD {
+        None
+    } else {
+        compression::preferred_encoding(&parts.headers)
+    };
 
     match rt_lock.dispatch_event(
         stream_id,
@@ -188,21 +217,70 @@ pub fn serve_http(
         }
         Some(Ok(EventResponseChannel::Http(rx))) => wrap_future(
             rx.and_then(move |res: JsHttpResponse| {
+                if let Some(socket) = res.websocket {
+                    return Ok(accept_websocket(
+                        ptr,
+                        socket,
+                        ws_accept,
+                        upgrade,
+                        res.headers,
+                    ));
+                }
+
                 let (mut parts, mut body) = Response::<Body>::default().into_parts();
                 parts.headers = res.headers;
                 parts.status = res.status;
 
                 if let Some(js_body) = res.body {
+                    let len = match js_body {
+                        JsBody::Static(ref b) => Some(b.len()),
+                        _ => None,
+                    };
+                    let encoding = compression::response_encoding(
+                        &COMPRESSION,
+                        accepted,
+                        parts.status,
+                        &parts.headers,
+                        len,
+                    );
                     body = match js_body {
-                        JsBody::Stream(s) => Body::wrap_stream(
-                            s.map_err(|_| {
+                        JsBody::Stream(s) => {
+                            let s = s.map_err(|_| {
                                 io::Error::new(io::ErrorKind::Interrupted, "interrupted stream")
-                            })
-                            .inspect(move |v| {
+                            });
+                            let s: Box<Stream<Item = Vec<u8>, Error = io::Error> + Send> =
+                                match encoding {
+                                    Some(encoding) => {
+                                        compression::set_headers(&mut parts.headers, encoding);
+                                        Box::new(compression::Encoder::new(
+                                            &COMPRESSION,
+                                            encoding,
+                                            s,
+                                        ))
+                                    }
+                                    None => Box::new(s),
+                                };
+                            Body::wrap_stream(s.inspect(move |v| {
                                 outbound_data.inc_by(v.len() as i64);
-                            }),
-                        ),
+                            }))
+                        }
                         JsBody::Static(b) => {
+                            let encoded = encoding.and_then(|encoding| {
+                                match compression::encode(&COMPRESSION, encoding, &b) {
+                                    Ok(encoded) => Some((encoding, encoded)),
+                                    Err(e) => {
+                                        error!("error compressing response: {}", e);
+                                        None
+                                    }
+                                }
+                            });
+                            let b = match encoded {
+                                Some((encoding, encoded)) => {
+                                    compression::set_headers(&mut parts.headers, encoding);
+                                    encoded
+                                }
+                                None => b,
+                            };
                             outbound_data.inc_by(b.len() as i64);
                             Body::from(b)
                         }
@@ -220,6 +298,55 @@ pub fn serve_http(
     }
 }
 
+// Completes the handshake for a socket JS accepted, and serves it once the connection is
+// upgraded. A socket accepted for a request that wasn't an upgrade is closed right away.
+fn accept_websocket(
+    ptr: JsRuntime,
+    socket: JsWebSocket,
+    accept: Option<Response<Body>>,
+    upgrade: Option<Body>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    match (accept, upgrade) {
+        (Some(mut res), Some(body)) if res.status() == StatusCode::SWITCHING_PROTOCOLS => {
+            // JS may pick a subprotocol or set cookies, the handshake itself is ours
+            for (name, value) in headers.iter() {
+                if !is_handshake_header(name) {
+                    res.headers_mut().append(name, value.clone());
+                }
+            }
+            websocket::serve(
+                ptr,
+                socket,
+                body.on_upgrade()
+                    .map_err(|e| FlyError::from(format!("error upgrading connection: {}", e))),
+            );
+            res
+        }
+        (accept, _) => {
+            websocket::serve(
+                ptr,
+                socket,
+                future::err::<Upgraded, _>(FlyError::from("not a websocket upgrade".to_string())),
+            );
+            accept.unwrap_or_else(|| {
+                simple_response(
+                    StatusCode::BAD_REQUEST,
+                    Some("expected a websocket upgrade"),
+                )
+            })
+        }
+    }
+}
+
+fn is_handshake_header(name: &header::HeaderName) -> bool {
+    *name == header::UPGRADE
+        || *name == header::CONNECTION
+        || *name == header::SEC_WEBSOCKET_ACCEPT
+        || *name == header::CONTENT_LENGTH
+        || *name == header::TRANSFER_ENCODING
+}
+
 fn future_response(
     res: Response<Body>,
     req: RequestInfo,
@@ -296,90 +423,3 @@ where
         Ok(res)
     }))
 }
-
-// static APPLICATION_X_JAVASCRIPT: &str = "application/x-javascript";
-// static APPLICATION_VND_MS_FONTOBJECT: &str = "application/vnd.ms-fontobject";
-// static APPLICATION_X_FONT_OPENTYPE: &str = "application/x-font-opentype";
-// static APPLICATION_X_FONT_TRUETYPE: &str = "application/x-font-truetype";
-// static APPLICATION_X_FONT_TTF: &str = "application/x-font-ttf";
-// static FONT_EOT: &str = "font/eot";
-// static FONT_OPENTYPE: &str = "font/opentype";
-// static FONT_OTF: &str = "font/otf";
-// static IMAGE_VND_MICROSOFT_ICON: &str = "image/vnd.microsoft.icon";
-
-// static OTHER_ALLOWED_MIME_TYPES: [&str; 9] = [
-//     APPLICATION_X_JAVASCRIPT,
-//     APPLICATION_VND_MS_FONTOBJECT,
-//     APPLICATION_X_FONT_OPENTYPE,
-//     APPLICATION_X_FONT_TRUETYPE,
-//     APPLICATION_X_FONT_TTF,
-//     FONT_EOT,
-//     FONT_OPENTYPE,
-//     FONT_OTF,
-//     IMAGE_VND_MICROSOFT_ICON,
-// ];
-
-// fn gunzip(chunk: hyper::Chunk) -> Result<Vec<u8>, FlyError> {
-//     let bytes = chunk.into_bytes();
-//     let mut v = vec![];
-//     let mut gz = GzDecoder::new(&bytes[..]);
-//     match gz.read_to_end(&mut v) {
-//         Ok(_) => Ok(v),
-//         Err(e) => Err(format!("gzip decode error: {}", e).into()),
-//     }
-// }
-
-// fn gzip<B>(bytes: B) -> Result<Vec<u8>, io::Error>
-// where
-//     B: AsRef<[u8]>,
-// {
-//     let mut v = vec![];
-//     let mut gz = GzEncoder::new(bytes.as_ref(), Compression::default());
-//     gz.read_to_end(&mut v)?;
-//     Ok(v)
-// }
-
-// fn contains_gzip(header_value: Option<&header::HeaderValue>) -> bool {
-//     if let Some(enc) = header_value {
-//         if let Ok(encstr) = enc.to_str() {
-//             if encstr.contains("gzip") {
-//                 true
-//             } else {
-//                 false
-//             }
-//         } else {
-//             false
-//         }
-//     } else {
-//         false
-//     }
-// }
-
-// fn gzippable_content_type(header_value: Option<&header::HeaderValue>) -> bool {
-//     if let Some(maybe_content_type) = header_value {
-//         if let Ok(content_type) = maybe_content_type.to_str() {
-//             if let Ok(m) = mime::Mime::from_str(content_type) {
-//                 if m.type_() == mime::TEXT {
-//                     true
-//                 } else if m.type_() == mime::APPLICATION {
-//                     match m.subtype() {
-//                         mime::JAVASCRIPT | mime::JSON | mime::XML => true,
-//                         _ => false,
-//                     }
-//                 } else if m.type_() == mime::IMAGE && m.subtype() == mime::SVG {
-//                     true
-//                 } else {
-//                     false
-//                 }
-//             } else if OTHER_ALLOWED_MIME_TYPES.contains(&content_type) {
-//                 true
-//             } else {
-//                 false
-//             }
-//         } else {
-//             false
-//         }
-//     } else {
-//         false
-//     }
-// }
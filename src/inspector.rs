@@ -0,0 +1,348 @@
+//! Chrome DevTools Protocol server. Every inspected runtime is exposed as a target,
+//! frontends connect over WebSocket and are bridged to a V8 inspector session in libfly.
+
+use futures::{future, sync::mpsc, Future, Sink, Stream};
+use hyper::service::service_fn;
+use hyper::{header, Body, Request, Response, Server, StatusCode};
+use libc::{c_char, c_int};
+use libfly::*;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc as std_mpsc, Arc, Mutex};
+use std::thread;
+use tokio::codec::Framed;
+
+use crate::runtime::{JsRuntime, Runtime};
+use crate::ws;
+
+type BoxedResponseFuture = Box<Future<Item = Response<Body>, Error = hyper::Error> + Send>;
+
+lazy_static! {
+    static ref TARGETS: Mutex<HashMap<String, Arc<Target>>> = Mutex::new(HashMap::new());
+    static ref LISTEN_ADDR: Mutex<Option<SocketAddr>> = Mutex::new(None);
+}
+
+enum Incoming {
+    Message(String),
+    Disconnected,
+}
+
+struct Target {
+    ptr: JsRuntime,
+    title: String,
+    alive: AtomicBool,
+    incoming_tx: Mutex<std_mpsc::Sender<Incoming>>,
+    incoming_rx: Mutex<std_mpsc::Receiver<Incoming>>,
+    outgoing: Mutex<Option<mpsc::UnboundedSender<String>>>,
+}
+
+/// Starts the DevTools server on its own thread.
+pub fn listen(addr: SocketAddr) {
+    *LISTEN_ADDR.lock().unwrap() = Some(addr);
+    thread::Builder::new()
+        .name("inspector-server".to_string())
+        .spawn(move || {
+            let server = match Server::try_bind(&addr) {
+                Ok(b) => b.serve(|| service_fn(serve)),
+                Err(e) => {
+                    error!("error binding inspector to {}: {}", addr, e);
+                    return;
+                }
+            };
+            tokio::run(server.map_err(|e| error!("inspector server error: {}", e)));
+        })
+        .unwrap();
+}
+
+/// Exposes the runtime to DevTools. When `wait_for_debugger` is set, blocks until a
+/// frontend attaches and pauses on the next statement evaluated.
+pub fn inspect(rt: &Runtime, wait_for_debugger: bool) {
+    let uuid = rt.get_uuid();
+    let (tx, rx) = std_mpsc::channel::<Incoming>();
+    TARGETS.lock().unwrap().insert(
+        uuid.clone(),
+        Arc::new(Target {
+            ptr: rt.ptr,
+            title: format!("{} v{}", rt.name, rt.version),
+            alive: AtomicBool::new(true),
+            incoming_tx: Mutex::new(tx),
+            incoming_rx: Mutex::new(rx),
+            outgoing: Mutex::new(None),
+        }),
+    );
+
+    if let Some(addr) = *LISTEN_ADDR.lock().unwrap() {
+        println!("Debugger listening on ws://{}/{}", addr, uuid);
+        println!("Open {}", frontend_url(&addr.to_string(), &uuid));
+    }
+    if wait_for_debugger {
+        println!("Waiting for the debugger to attach...");
+    }
+
+    unsafe { js_inspector_start(rt.ptr.0, inspector_send, inspector_wait, wait_for_debugger) };
+}
+
+/// Called when a runtime is disposed.
+pub fn forget(uuid: &str) {
+    if let Some(target) = TARGETS.lock().unwrap().remove(uuid) {
+        target.alive.store(false, Ordering::SeqCst);
+        target.outgoing.lock().unwrap().take();
+    }
+}
+
+fn target_for(uuid: &str) -> Option<Arc<Target>> {
+    TARGETS.lock().unwrap().get(uuid).cloned()
+}
+
+fn frontend_url(host: &str, uuid: &str) -> String {
+    format!(
+        "chrome-devtools://devtools/bundled/js_app.html?experiments=true&v8only=true&ws={}/{}",
+        host, uuid
+    )
+}
+
+fn dispatch_message(ptr: JsRuntime, msg: &str) {
+    unsafe {
+        js_inspector_dispatch(
+            ptr.0,
+            fly_simple_buf {
+                ptr: msg.as_ptr() as *const c_char,
+                len: msg.len() as c_int,
+            },
+        )
+    };
+}
+
+// Queues a message from the frontend. If the isolate is paused it's picked up by
+// the pause loop, otherwise it's dispatched from the runtime's event loop.
+fn receive(target: Arc<Target>, msg: Incoming) {
+    if !target.alive.load(Ordering::SeqCst) {
+        return;
+    }
+    if target.incoming_tx.lock().unwrap().send(msg).is_err() {
+        return;
+    }
+    let ptr = target.ptr;
+    ptr.to_runtime().spawn(future::lazy(move || {
+        unsafe { js_inspector_dispatch_pending(ptr.0) };
+        Ok(())
+    }));
+}
+
+extern "C" fn inspector_send(raw: *const js_runtime, message: fly_simple_buf) {
+    let rt = unsafe { Runtime::from_raw(raw) };
+    let target = match target_for(&rt.get_uuid()) {
+        Some(t) => t,
+        None => return,
+    };
+    let bytes = unsafe { slice::from_raw_parts(message.ptr as *const u8, message.len as usize) };
+    if let Some(tx) = target.outgoing.lock().unwrap().as_ref() {
+        if tx
+            .unbounded_send(String::from_utf8_lossy(bytes).into_owned())
+            .is_err()
+        {
+            debug!("inspector frontend went away");
+        }
+    }
+}
+
+// Called with the isolate locked. When blocking, returns false once the frontend
+// disconnects, otherwise once the queue is empty.
+extern "C" fn inspector_wait(raw: *const js_runtime, block: bool) -> bool {
+    let rt = unsafe { Runtime::from_raw(raw) };
+    let target = match target_for(&rt.get_uuid()) {
+        Some(t) => t,
+        None => return false,
+    };
+    let next = {
+        let rx = target.incoming_rx.lock().unwrap();
+        if block {
+            rx.recv().ok()
+        } else {
+            rx.try_recv().ok()
+        }
+    };
+    match next {
+        Some(Incoming::Message(msg)) => {
+            dispatch_message(target.ptr, &msg);
+            true
+        }
+        Some(Incoming::Disconnected) => !block,
+        None => false,
+    }
+}
+
+fn serve(req: Request<Body>) -> BoxedResponseFuture {
+    let path = req.uri().path().trim_matches('/').to_string();
+    let listen_addr = *LISTEN_ADDR.lock().unwrap();
+    let host = match req
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+    {
+        Some(h) => h.to_string(),
+        None => match listen_addr {
+            Some(addr) => addr.to_string(),
+            None => "127.0.0.1:9229".to_string(),
+        },
+    };
+    // a page whose name was rebound to our address would otherwise drive the debugger
+    if !allowed_host(&host, listen_addr) {
+        warn!("inspector request with unexpected host {}", host);
+        return Box::new(future::ok(
+            Response::builder()
+                .status(StatusCode::FORBIDDEN)
+                .body(Body::empty())
+                .unwrap(),
+        ));
+    }
+
+    match path.as_str() {
+        "json" | "json/list" => {
+            let targets: Vec<serde_json::Value> = TARGETS
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(uuid, target)| {
+                    json!({
+                        "description": "fly runtime",
+                        "devtoolsFrontendUrl": frontend_url(&host, uuid),
+                        "id": uuid,
+                        "title": target.title,
+                        "type": "node",
+                        "url": "file://",
+                        "webSocketDebuggerUrl": format!("ws://{}/{}", host, uuid),
+                    })
+                })
+                .collect();
+            json_response(serde_json::Value::Array(targets))
+        }
+        "json/version" => json_response(json!({
+            "Browser": format!("fly/{}", crate::BUILD_VERSION),
+            "Protocol-Version": "1.3",
+            "V8-Version": libfly::version(),
+        })),
+        uuid => match target_for(uuid) {
+            Some(target) if ws::is_upgrade_request(&req) => {
+                let res = ws::upgrade_response(&req);
+                hyper::rt::spawn(
+                    req.into_body()
+                        .on_upgrade()
+                        .map_err(|e| error!("inspector upgrade error: {}", e))
+                        .and_then(move |upgraded| session(target, upgraded)),
+                );
+                Box::new(future::ok(res))
+            }
+            _ => Box::new(future::ok(
+                Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::empty())
+                    .unwrap(),
+            )),
+        },
+    }
+}
+
+// Whether the Host header names the inspector itself: localhost, a loopback address or
+// the address it's bound to.
+fn allowed_host(host: &str, listen_addr: Option<SocketAddr>) -> bool {
+    let name = if host.starts_with('[') {
+        match host.find(']') {
+            Some(end) => &host[1..end],
+            None => return false,
+        }
+    } else {
+        host.rsplitn(2, ':').last().unwrap_or(host)
+    };
+    if name.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    match name.parse::<IpAddr>() {
+        Ok(ip) => ip.is_loopback() || listen_addr.map_or(false, |addr| addr.ip() == ip),
+        Err(_) => false,
+    }
+}
+
+fn json_response(value: serde_json::Value) -> BoxedResponseFuture {
+    Box::new(future::ok(
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/json; charset=UTF-8")
+            .body(Body::from(value.to_string()))
+            .unwrap(),
+    ))
+}
+
+fn session(
+    target: Arc<Target>,
+    upgraded: hyper::upgrade::Upgraded,
+) -> impl Future<Item = (), Error = ()> {
+    let (sink, stream) = Framed::new(upgraded, ws::Codec::new(ws::Role::Server)).split();
+    let (out_tx, out_rx) = mpsc::unbounded::<String>();
+
+    {
+        // anything left over from a previous session is stale
+        let rx = target.incoming_rx.lock().unwrap();
+        while rx.try_recv().is_ok() {}
+    }
+    *target.outgoing.lock().unwrap() = Some(out_tx);
+
+    let reader_target = target.clone();
+    let reader = stream
+        .take_while(|msg| {
+            Ok(match msg {
+                ws::Message::Close(_) => false,
+                _ => true,
+            })
+        })
+        .for_each(move |msg| {
+            if let ws::Message::Text(s) = msg {
+                receive(reader_target.clone(), Incoming::Message(s));
+            }
+            Ok(())
+        });
+    let writer = sink.send_all(
+        out_rx
+            .map(ws::Message::Text)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "inspector channel closed")),
+    );
+
+    reader.select2(writer).then(move |_| {
+        debug!("inspector frontend disconnected");
+        target.outgoing.lock().unwrap().take();
+        receive(target, Incoming::Disconnected);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_host() {
+        let listen_addr = Some("10.0.0.5:9229".parse().unwrap());
+        for host in &[
+            "localhost",
+            "localhost:9229",
+            "LOCALHOST:9229",
+            "127.0.0.1:9229",
+            "[::1]:9229",
+            "10.0.0.5:9229",
+        ] {
+            assert!(allowed_host(host, listen_addr), "{}", host);
+        }
+        for host in &[
+            "evil.com",
+            "evil.com:9229",
+            "localhost.evil.com:9229",
+            "10.0.0.6:9229",
+            "[::1",
+        ] {
+            assert!(!allowed_host(host, listen_addr), "{}", host);
+        }
+    }
+}
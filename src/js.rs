@@ -1,5 +1,8 @@
 use crate::errors::FlyError;
-use futures::{sync::mpsc, Stream};
+use crate::stream_channel::StreamReceiver;
+use crate::ws;
+use futures::sync::mpsc;
+use futures::Stream;
 use hyper::HeaderMap;
 use hyper::StatusCode;
 use std::net::SocketAddr;
@@ -7,7 +10,7 @@ use trust_dns as dns;
 
 pub enum JsBody {
     BoxedStream(Box<Stream<Item = Vec<u8>, Error = FlyError> + Send>),
-    Stream(mpsc::UnboundedReceiver<Vec<u8>>),
+    Stream(StreamReceiver),
     Static(Vec<u8>),
 }
 
@@ -15,6 +18,14 @@ pub struct JsHttpResponse {
     pub headers: HeaderMap,
     pub status: StatusCode,
     pub body: Option<JsBody>,
+    // set when JS accepted a websocket upgrade
+    pub websocket: Option<JsWebSocket>,
+}
+
+/// The server end of an upgraded request, frames JS sends come out of `outgoing`.
+pub struct JsWebSocket {
+    pub id: u32,
+    pub outgoing: mpsc::Receiver<ws::Message>,
 }
 
 pub struct JsHttpRequest {
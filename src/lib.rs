@@ -36,11 +36,16 @@ pub mod msg;
 pub mod ops;
 pub mod runtime;
 pub mod runtime_permissions;
+pub mod scheduler;
+pub mod snapshot;
+pub mod stream_channel;
 pub mod utils;
+pub mod watchdog;
 
 pub mod acme_store;
 pub mod cache_store;
 pub mod cache_store_notifier;
+pub mod code_cache;
 pub mod data_store;
 pub mod fs_store;
 
@@ -49,9 +54,19 @@ pub mod settings;
 pub mod runtime_manager;
 pub use crate::runtime_manager::{RuntimeManager, RuntimeManagerError};
 
+pub mod admin;
 pub mod dns_server;
 pub mod standard_runtime_manager;
 pub mod http_server;
+pub mod tls;
+pub mod compression;
+pub mod http_client;
+pub mod http_cache;
+pub mod http_headers;
+pub mod fetch_policy;
+pub mod egress;
+pub mod inspector;
+pub mod ws;
 
 pub mod metrics;
 
@@ -59,6 +74,7 @@ pub mod module_resolver;
 pub use crate::module_resolver::{JsonSecretsResolver, LocalDiskModuleResolver, ModuleResolver};
 
 pub mod msg_handler;
+pub mod op_registry;
 
 mod disk_fs;
 mod postgres_data;
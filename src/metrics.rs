@@ -2,7 +2,7 @@ use futures::{future, Canceled, Future};
 use http::header;
 use hyper::{Body, Request, Response, StatusCode};
 
-use prometheus::{Encoder, HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder};
+use prometheus::{Encoder, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder};
 
 pub fn serve_metrics_http(
     _req: Request<Body>,
@@ -71,6 +71,69 @@ lazy_static! {
         &["runtime", "version"]
     )
     .unwrap();
+    pub static ref RUNTIME_TERMINATIONS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "fly_runtime_terminations_total",
+        "Total number of events terminated for exceeding their CPU budget.",
+        &["runtime", "version", "event"]
+    )
+    .unwrap();
+    pub static ref CODE_CACHE_LOOKUPS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "fly_code_cache_lookups_total",
+        "Total number of code cache lookups when compiling app sources and modules, by result (hit, miss or rejected).",
+        &["runtime", "version", "result"]
+    )
+    .unwrap();
+    pub static ref OP_DURATION: HistogramVec = register_histogram_vec!(
+        "fly_op_duration_seconds",
+        "Time taken by ops called from JS, by op, in seconds. Native ops are labeled OpCall:<name>.",
+        &["runtime", "version", "op"],
+        vec![0.0001, 0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0, 10.0]
+    )
+    .unwrap();
+    pub static ref OP_ERRORS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "fly_op_errors_total",
+        "Total number of ops called from JS that returned an error, by op. Native ops are labeled OpCall:<name>.",
+        &["runtime", "version", "op"]
+    )
+    .unwrap();
+    pub static ref RUNTIME_HEAP_LIMIT_RESETS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "fly_runtime_heap_limit_resets_total",
+        "Total number of isolates recreated after reaching their heap limit.",
+        &["runtime", "version"]
+    )
+    .unwrap();
+    pub static ref RUNTIME_REMOVALS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "fly_runtime_removals_total",
+        "Total number of runtimes removed, by drain outcome.",
+        &["runtime", "version", "outcome"]
+    )
+    .unwrap();
+    pub static ref RUNTIME_DRAIN_DURATION: HistogramVec = register_histogram_vec!(
+        "fly_runtime_drain_duration_seconds",
+        "Time taken to drain and dispose of a runtime, in seconds.",
+        &["runtime", "version"],
+        vec![0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0]
+    )
+    .unwrap();
+    pub static ref RUNTIMES_LIVE_GAUGE: IntGauge =
+        register_int_gauge!("fly_runtimes_live", "Number of runtimes currently loaded.").unwrap();
+    pub static ref RUNTIMES_EVICTED_GAUGE: IntGauge = register_int_gauge!(
+        "fly_runtimes_evicted",
+        "Number of idle runtimes evicted and waiting to be recreated."
+    )
+    .unwrap();
+    pub static ref RUNTIME_POOL_QUEUE_DEPTH: IntGaugeVec = register_int_gauge_vec!(
+        "fly_runtime_pool_queue_depth",
+        "Events pending on each isolate of a runtime pool.",
+        &["runtime", "version", "isolate"]
+    )
+    .unwrap();
+    pub static ref SCHEDULER_WORKER_RUNTIMES: IntGaugeVec = register_int_gauge_vec!(
+        "fly_scheduler_worker_runtimes",
+        "Runtimes assigned to each event loop worker thread.",
+        &["worker"]
+    )
+    .unwrap();
     pub static ref CACHE_GET_DURATION: HistogramVec = register_histogram_vec!(
         "fly_cache_get_duration_seconds",
         "Cache get duration in seconds.",
@@ -160,6 +223,24 @@ lazy_static! {
         &["runtime", "version", "hostname"]
     )
     .unwrap();
+    pub static ref FETCH_REDIRECTS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "fly_fetch_redirects_total",
+        "Total number of redirects followed by fetch, by the redirecting host.",
+        &["runtime", "version", "hostname"]
+    )
+    .unwrap();
+    pub static ref FETCH_RETRIES_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "fly_fetch_retries_total",
+        "Total number of fetch retries, by host and reason.",
+        &["runtime", "version", "hostname", "reason"]
+    )
+    .unwrap();
+    pub static ref FETCH_CACHE_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "fly_fetch_cache_total",
+        "Total number of fetches through the HTTP cache, by cache status.",
+        &["runtime", "version", "status"]
+    )
+    .unwrap();
     pub static ref FETCH_HEADERS_DURATION: HistogramVec = register_histogram_vec!(
         "fly_fetch_read_headers_duration_histogram_seconds",
         "Time to get headers for a fetch, by runtime, in seconds.",
@@ -174,4 +255,10 @@ lazy_static! {
         vec![0.01, 0.025, 0.05, 0.075, 0.1, 0.25, 0.5, 1.0, 5.0, 10.0]
     )
     .unwrap();
+    pub static ref FETCH_EGRESS_DENIED_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "fly_fetch_egress_denied_total",
+        "Total number of fetch connections denied by the egress policy, by reason.",
+        &["runtime", "version", "reason"]
+    )
+    .unwrap();
 }
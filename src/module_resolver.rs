@@ -6,7 +6,11 @@ use std::marker::Send;
 
 use std::clone::Clone;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+use std::fmt;
+
+use std::sync::{Arc, Mutex};
 
 use serde_json;
 
@@ -55,15 +59,102 @@ pub trait ModuleResolver: Send + Sync {
     fn get_protocol(&self) -> String;
 }
 
+/**
+ * Why a module couldn't be imported. Thrown into JS as the error of the failed import.
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleError {
+    // ERR_MODULE_NOT_FOUND, ERR_MODULE_COMPILE or ERR_PERMISSION_DENIED
+    pub code: &'static str,
+    pub message: String,
+    pub specifier: String,
+    pub referer: Option<String>,
+    // what each resolver tried said, in order
+    pub attempts: Vec<String>,
+    // the compiler's message and location, for compile errors
+    pub diagnostics: Option<String>,
+}
+
+impl ModuleError {
+    pub fn not_found(
+        specifier: &str,
+        referer: &str,
+        message: String,
+        attempts: Vec<String>,
+    ) -> Self {
+        ModuleError {
+            code: "ERR_MODULE_NOT_FOUND",
+            message,
+            specifier: specifier.to_string(),
+            referer: Some(referer.to_string()),
+            attempts,
+            diagnostics: None,
+        }
+    }
+
+    pub fn compile(specifier: &str, referer: Option<String>, diagnostics: String) -> Self {
+        ModuleError {
+            code: "ERR_MODULE_COMPILE",
+            message: format!("Could not compile {}", specifier),
+            specifier: specifier.to_string(),
+            referer,
+            attempts: vec![],
+            diagnostics: Some(diagnostics),
+        }
+    }
+
+    pub fn permission_denied(specifier: &str, referer: Option<String>, err: FlyError) -> Self {
+        ModuleError {
+            code: "ERR_PERMISSION_DENIED",
+            message: format!("Could not import {}: {}", specifier, err),
+            specifier: specifier.to_string(),
+            referer,
+            attempts: vec![],
+            diagnostics: None,
+        }
+    }
+}
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        for attempt in self.attempts.iter() {
+            write!(f, "\n  {}", attempt)?;
+        }
+        if let Some(ref diagnostics) = self.diagnostics {
+            write!(f, "\n{}", diagnostics)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<ModuleError> for FlyError {
+    fn from(err: ModuleError) -> FlyError {
+        match err.code {
+            "ERR_PERMISSION_DENIED" => new(ErrorKind::PermissionDenied, err.to_string()),
+            _ => FlyError::from(err.to_string()),
+        }
+    }
+}
+
 /**
  * This trait is a used as the "front door" of the dynamic module resolution system.
  */
 pub trait ModuleResolverManager: Send + Sync {
+    /**
+     * The url a specifier points to, without running any resolver. Lets callers check it
+     * before resolvers like the secrets one read anything.
+     */
+    fn resolve_url(
+        &self,
+        specifier: &str,
+        referer_info: Option<&RefererInfo>,
+    ) -> Result<url::Url, ModuleError>;
     fn resolve_module(
         &self,
         specifier: String,
         referer_info: Option<RefererInfo>,
-    ) -> FlyResult<LoadedModule>;
+    ) -> Result<LoadedModule, ModuleError>;
 }
 
 /**
@@ -131,6 +222,7 @@ impl SourceLoader for LocalDiskRawLoader {
 
 pub struct LocalDiskModuleResolver {
     pub default_working_url: String,
+    resolved_paths: Option<Arc<Mutex<HashSet<PathBuf>>>>,
 }
 
 impl LocalDiskModuleResolver {
@@ -146,6 +238,28 @@ impl LocalDiskModuleResolver {
         };
         Self {
             default_working_url,
+            resolved_paths: None,
+        }
+    }
+
+    /**
+     * Records the path of every module resolved from disk in `paths`, used to watch them for changes.
+     */
+    pub fn with_resolved_paths(mut self, paths: Arc<Mutex<HashSet<PathBuf>>>) -> Self {
+        self.resolved_paths = Some(paths);
+        self
+    }
+
+    fn module_source_data(&self, module_file_path: PathBuf) -> ModuleSourceData {
+        if let Some(paths) = &self.resolved_paths {
+            paths.lock().unwrap().insert(module_file_path.clone());
+        }
+        ModuleSourceData {
+            origin_url: url::Url::from_file_path(module_file_path.clone())
+                .unwrap()
+                .as_str()
+                .to_string(),
+            source_loader: Box::new(LocalDiskRawLoader::new(module_file_path, None)),
         }
     }
 }
@@ -171,35 +285,17 @@ impl ModuleResolver for LocalDiskModuleResolver {
         let mut module_file_path = module_specifier_url.to_file_path()?;
 
         if module_file_path.is_file() {
-            return Ok(ModuleSourceData {
-                origin_url: url::Url::from_file_path(module_file_path.clone())
-                    .unwrap()
-                    .as_str()
-                    .to_string(),
-                source_loader: Box::new(LocalDiskRawLoader::new(module_file_path, None)),
-            });
+            return Ok(self.module_source_data(module_file_path));
         }
         let did_set = module_file_path.set_extension("ts");
         trace!("trying module {} ({})", module_file_path.display(), did_set);
         if module_file_path.is_file() {
-            return Ok(ModuleSourceData {
-                origin_url: url::Url::from_file_path(module_file_path.clone())
-                    .unwrap()
-                    .as_str()
-                    .to_string(),
-                source_loader: Box::new(LocalDiskRawLoader::new(module_file_path, None)),
-            });
+            return Ok(self.module_source_data(module_file_path));
         }
         let did_set = module_file_path.set_extension("js");
         trace!("trying module {} ({})", module_file_path.display(), did_set);
         if module_file_path.is_file() {
-            return Ok(ModuleSourceData {
-                origin_url: url::Url::from_file_path(module_file_path.clone())
-                    .unwrap()
-                    .as_str()
-                    .to_string(),
-                source_loader: Box::new(LocalDiskRawLoader::new(module_file_path, None)),
-            });
+            return Ok(self.module_source_data(module_file_path));
         }
         // TODO: Add code here for json files and other media types.
         error!("NOPE");
@@ -355,11 +451,33 @@ impl StandardModuleResolverManager {
 }
 
 impl ModuleResolverManager for StandardModuleResolverManager {
+    fn resolve_url(
+        &self,
+        specifier: &str,
+        referer_info: Option<&RefererInfo>,
+    ) -> Result<url::Url, ModuleError> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url.as_str(),
+            None => self.default_working_url.as_str(),
+        };
+        parse_url(specifier, referer_origin_url).map_err(|e| {
+            ModuleError::not_found(
+                specifier,
+                referer_origin_url,
+                format!(
+                    "Could not resolve {} from {}: {}",
+                    specifier, referer_origin_url, e
+                ),
+                vec![],
+            )
+        })
+    }
+
     fn resolve_module(
         &self,
         specifier: String,
         referer_info: Option<RefererInfo>,
-    ) -> FlyResult<LoadedModule> {
+    ) -> Result<LoadedModule, ModuleError> {
         let referer_origin_url = match referer_info.clone() {
             Some(v) => v.origin_url,
             None => self.default_working_url.clone(),
@@ -367,38 +485,55 @@ impl ModuleResolverManager for StandardModuleResolverManager {
         // Parse the specifier with the referer origin_url as the working path/url.
         info!("resolve_module {} from {}", &specifier, &referer_origin_url);
 
-        let specifier_url = parse_url(specifier.as_str(), referer_origin_url.as_str())?;
+        let specifier_url = self.resolve_url(&specifier, referer_info.as_ref())?;
 
         // Try to get a vector of the resolvers for the protocol we are tring to resolve.
         let resolvers = match self.protocol_resolver_map.get(specifier_url.scheme()) {
             Some(v) => v,
             None => {
-                return Err(FlyError::from(format!(
-                    "Could not resolve {} from {}: no resolvers for protocol {} setup.",
-                    specifier,
+                return Err(ModuleError::not_found(
+                    &specifier,
                     &referer_origin_url,
-                    specifier_url.scheme()
-                )));
+                    format!(
+                        "Could not resolve {} from {}: no resolvers for protocol {} setup.",
+                        specifier,
+                        &referer_origin_url,
+                        specifier_url.scheme()
+                    ),
+                    vec![],
+                ));
             }
         };
 
+        let mut attempts = vec![];
         for resolver in resolvers {
             let resolver_result = resolver.resolve_module(specifier.as_str(), referer_info.clone());
-            if let Err(e) = resolver_result {
-                info!("Resolver failed trying the next one: {}", e);
-            } else {
-                let module_loader = resolver_result.unwrap();
-                let loaded_source = module_loader.source_loader.load_source()?;
-                return Ok(LoadedModule {
-                    loaded_source,
-                    origin_url: module_loader.origin_url,
-                });
-            }
+            let attempt = match resolver_result {
+                Err(e) => e,
+                Ok(module_loader) => match module_loader.source_loader.load_source() {
+                    Ok(loaded_source) => {
+                        return Ok(LoadedModule {
+                            loaded_source,
+                            origin_url: module_loader.origin_url,
+                        });
+                    }
+                    Err(e) => {
+                        FlyError::from(format!("error loading {}: {}", module_loader.origin_url, e))
+                    }
+                },
+            };
+            info!("Resolver failed trying the next one: {}", attempt);
+            attempts.push(format!("{}: {}", resolver.get_protocol(), attempt));
         }
 
-        Err(FlyError::from(format!(
-            "Could not resolve {} from {}: exausted all resolvers.",
-            specifier, referer_origin_url
-        )))
+        Err(ModuleError::not_found(
+            &specifier,
+            &referer_origin_url,
+            format!(
+                "Could not resolve {} from {}: exausted all resolvers.",
+                specifier, referer_origin_url
+            ),
+            attempts,
+        ))
     }
 }
@@ -745,11 +745,18 @@ pub enum Any {
   RequestServiceRequest = 44,
   RequestServiceResponse = 45,
   OsExit = 46,
+  OpCall = 47,
+  OpCallReady = 48,
+  StreamCredit = 49,
+  FetchAbort = 50,
+  WebSocketOpen = 51,
+  WebSocketMessage = 52,
+  WebSocketClose = 53,
 
 }
 
 const ENUM_MIN_ANY: u8 = 0;
-const ENUM_MAX_ANY: u8 = 46;
+const ENUM_MAX_ANY: u8 = 53;
 
 impl<'a> flatbuffers::Follow<'a> for Any {
   type Inner = Self;
@@ -783,7 +790,7 @@ impl flatbuffers::Push for Any {
 }
 
 #[allow(non_camel_case_types)]
-const ENUM_VALUES_ANY:[Any; 47] = [
+const ENUM_VALUES_ANY:[Any; 54] = [
   Any::NONE,
   Any::TimerStart,
   Any::TimerReady,
@@ -830,11 +837,18 @@ const ENUM_VALUES_ANY:[Any; 47] = [
   Any::ServiceResponse,
   Any::RequestServiceRequest,
   Any::RequestServiceResponse,
-  Any::OsExit
+  Any::OsExit,
+  Any::OpCall,
+  Any::OpCallReady,
+  Any::StreamCredit,
+  Any::FetchAbort,
+  Any::WebSocketOpen,
+  Any::WebSocketMessage,
+  Any::WebSocketClose
 ];
 
 #[allow(non_camel_case_types)]
-const ENUM_NAMES_ANY:[&'static str; 47] = [
+const ENUM_NAMES_ANY:[&'static str; 54] = [
     "NONE",
     "TimerStart",
     "TimerReady",
@@ -881,7 +895,14 @@ const ENUM_NAMES_ANY:[&'static str; 47] = [
     "ServiceResponse",
     "RequestServiceRequest",
     "RequestServiceResponse",
-    "OsExit"
+    "OsExit",
+    "OpCall",
+    "OpCallReady",
+    "StreamCredit",
+    "FetchAbort",
+    "WebSocketOpen",
+    "WebSocketMessage",
+    "WebSocketClose"
 ];
 
 pub fn enum_name_any(e: Any) -> &'static str {
@@ -929,11 +950,12 @@ pub enum ErrorKind {
   HttpCanceled = 32,
   HttpParse = 33,
   HttpOther = 34,
+  EgressDenied = 35,
 
 }
 
 const ENUM_MIN_ERROR_KIND: i8 = 0;
-const ENUM_MAX_ERROR_KIND: i8 = 34;
+const ENUM_MAX_ERROR_KIND: i8 = 35;
 
 impl<'a> flatbuffers::Follow<'a> for ErrorKind {
   type Inner = Self;
@@ -967,7 +989,7 @@ impl flatbuffers::Push for ErrorKind {
 }
 
 #[allow(non_camel_case_types)]
-const ENUM_VALUES_ERROR_KIND:[ErrorKind; 35] = [
+const ENUM_VALUES_ERROR_KIND:[ErrorKind; 36] = [
   ErrorKind::NoError,
   ErrorKind::String,
   ErrorKind::NotFound,
@@ -1002,11 +1024,12 @@ const ENUM_VALUES_ERROR_KIND:[ErrorKind; 35] = [
   ErrorKind::HttpClosed,
   ErrorKind::HttpCanceled,
   ErrorKind::HttpParse,
-  ErrorKind::HttpOther
+  ErrorKind::HttpOther,
+  ErrorKind::EgressDenied
 ];
 
 #[allow(non_camel_case_types)]
-const ENUM_NAMES_ERROR_KIND:[&'static str; 35] = [
+const ENUM_NAMES_ERROR_KIND:[&'static str; 36] = [
     "NoError",
     "String",
     "NotFound",
@@ -1041,7 +1064,8 @@ const ENUM_NAMES_ERROR_KIND:[&'static str; 35] = [
     "HttpClosed",
     "HttpCanceled",
     "HttpParse",
-    "HttpOther"
+    "HttpOther",
+    "EgressDenied"
 ];
 
 pub fn enum_name_error_kind(e: ErrorKind) -> &'static str {
@@ -1130,6 +1154,144 @@ pub fn enum_name_http_method(e: HttpMethod) -> &'static str {
   ENUM_NAMES_HTTP_METHOD[index]
 }
 
+#[allow(non_camel_case_types)]
+#[repr(i8)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RedirectMode {
+  Follow = 0,
+  Manual = 1,
+  Error = 2,
+
+}
+
+const ENUM_MIN_REDIRECT_MODE: i8 = 0;
+const ENUM_MAX_REDIRECT_MODE: i8 = 2;
+
+impl<'a> flatbuffers::Follow<'a> for RedirectMode {
+  type Inner = Self;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    flatbuffers::read_scalar_at::<Self>(buf, loc)
+  }
+}
+
+impl flatbuffers::EndianScalar for RedirectMode {
+  #[inline]
+  fn to_little_endian(self) -> Self {
+    let n = i8::to_le(self as i8);
+    let p = &n as *const i8 as *const RedirectMode;
+    unsafe { *p }
+  }
+  #[inline]
+  fn from_little_endian(self) -> Self {
+    let n = i8::from_le(self as i8);
+    let p = &n as *const i8 as *const RedirectMode;
+    unsafe { *p }
+  }
+}
+
+impl flatbuffers::Push for RedirectMode {
+    type Output = RedirectMode;
+    #[inline]
+    fn push(&self, dst: &mut [u8], _rest: &[u8]) {
+        flatbuffers::emplace_scalar::<RedirectMode>(dst, *self);
+    }
+}
+
+#[allow(non_camel_case_types)]
+const ENUM_VALUES_REDIRECT_MODE:[RedirectMode; 3] = [
+  RedirectMode::Follow,
+  RedirectMode::Manual,
+  RedirectMode::Error
+];
+
+#[allow(non_camel_case_types)]
+const ENUM_NAMES_REDIRECT_MODE:[&'static str; 3] = [
+    "Follow",
+    "Manual",
+    "Error"
+];
+
+pub fn enum_name_redirect_mode(e: RedirectMode) -> &'static str {
+  let index: usize = e as usize;
+  ENUM_NAMES_REDIRECT_MODE[index]
+}
+
+#[allow(non_camel_case_types)]
+#[repr(i8)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HttpCacheMode {
+  Off = 0,
+  Default = 1,
+  NoStore = 2,
+  Reload = 3,
+  NoCache = 4,
+  ForceCache = 5,
+  OnlyIfCached = 6,
+
+}
+
+const ENUM_MIN_HTTP_CACHE_MODE: i8 = 0;
+const ENUM_MAX_HTTP_CACHE_MODE: i8 = 6;
+
+impl<'a> flatbuffers::Follow<'a> for HttpCacheMode {
+  type Inner = Self;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    flatbuffers::read_scalar_at::<Self>(buf, loc)
+  }
+}
+
+impl flatbuffers::EndianScalar for HttpCacheMode {
+  #[inline]
+  fn to_little_endian(self) -> Self {
+    let n = i8::to_le(self as i8);
+    let p = &n as *const i8 as *const HttpCacheMode;
+    unsafe { *p }
+  }
+  #[inline]
+  fn from_little_endian(self) -> Self {
+    let n = i8::from_le(self as i8);
+    let p = &n as *const i8 as *const HttpCacheMode;
+    unsafe { *p }
+  }
+}
+
+impl flatbuffers::Push for HttpCacheMode {
+    type Output = HttpCacheMode;
+    #[inline]
+    fn push(&self, dst: &mut [u8], _rest: &[u8]) {
+        flatbuffers::emplace_scalar::<HttpCacheMode>(dst, *self);
+    }
+}
+
+#[allow(non_camel_case_types)]
+const ENUM_VALUES_HTTP_CACHE_MODE:[HttpCacheMode; 7] = [
+  HttpCacheMode::Off,
+  HttpCacheMode::Default,
+  HttpCacheMode::NoStore,
+  HttpCacheMode::Reload,
+  HttpCacheMode::NoCache,
+  HttpCacheMode::ForceCache,
+  HttpCacheMode::OnlyIfCached
+];
+
+#[allow(non_camel_case_types)]
+const ENUM_NAMES_HTTP_CACHE_MODE:[&'static str; 7] = [
+    "Off",
+    "Default",
+    "NoStore",
+    "Reload",
+    "NoCache",
+    "ForceCache",
+    "OnlyIfCached"
+];
+
+pub fn enum_name_http_cache_mode(e: HttpCacheMode) -> &'static str {
+  let index: usize = e as usize;
+  ENUM_NAMES_HTTP_CACHE_MODE[index]
+}
+
 #[allow(non_camel_case_types)]
 #[repr(i8)]
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -4979,6 +5141,182 @@ impl<'a: 'b, 'b> OsExitBuilder<'a, 'b> {
   }
 }
 
+pub enum OpCallOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct OpCall<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for OpCall<'a> {
+    type Inner = OpCall<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> OpCall<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        OpCall {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args OpCallArgs<'args>) -> flatbuffers::WIPOffset<OpCall<'bldr>> {
+      let mut builder = OpCallBuilder::new(_fbb);
+      if let Some(x) = args.name { builder.add_name(x); }
+      builder.add_json(args.json);
+      builder.finish()
+    }
+
+    pub const VT_NAME: flatbuffers::VOffsetT = 4;
+    pub const VT_JSON: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn name(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(OpCall::VT_NAME, None)
+  }
+  #[inline]
+  pub fn json(&self) -> bool {
+    self._tab.get::<bool>(OpCall::VT_JSON, Some(false)).unwrap()
+  }
+}
+
+pub struct OpCallArgs<'a> {
+    pub name: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub json: bool,
+}
+impl<'a> Default for OpCallArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        OpCallArgs {
+            name: None,
+            json: false,
+        }
+    }
+}
+pub struct OpCallBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> OpCallBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_name(&mut self, name: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(OpCall::VT_NAME, name);
+  }
+  #[inline]
+  pub fn add_json(&mut self, json: bool) {
+    self.fbb_.push_slot::<bool>(OpCall::VT_JSON, json, false);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> OpCallBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    OpCallBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<OpCall<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum OpCallReadyOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct OpCallReady<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for OpCallReady<'a> {
+    type Inner = OpCallReady<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> OpCallReady<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        OpCallReady {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args OpCallReadyArgs<'args>) -> flatbuffers::WIPOffset<OpCallReady<'bldr>> {
+      let mut builder = OpCallReadyBuilder::new(_fbb);
+      if let Some(x) = args.payload { builder.add_payload(x); }
+      builder.add_json(args.json);
+      builder.finish()
+    }
+
+    pub const VT_JSON: flatbuffers::VOffsetT = 4;
+    pub const VT_PAYLOAD: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn json(&self) -> bool {
+    self._tab.get::<bool>(OpCallReady::VT_JSON, Some(false)).unwrap()
+  }
+  #[inline]
+  pub fn payload(&self) -> Option<&'a [u8]> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u8>>>(OpCallReady::VT_PAYLOAD, None).map(|v| v.safe_slice())
+  }
+}
+
+pub struct OpCallReadyArgs<'a> {
+    pub json: bool,
+    pub payload: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a ,  u8>>>,
+}
+impl<'a> Default for OpCallReadyArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        OpCallReadyArgs {
+            json: false,
+            payload: None,
+        }
+    }
+}
+pub struct OpCallReadyBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> OpCallReadyBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_json(&mut self, json: bool) {
+    self.fbb_.push_slot::<bool>(OpCallReady::VT_JSON, json, false);
+  }
+  #[inline]
+  pub fn add_payload(&mut self, payload: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u8>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(OpCallReady::VT_PAYLOAD, payload);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> OpCallReadyBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    OpCallReadyBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<OpCallReady<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
 pub enum BaseOffset {}
 #[derive(Copy, Clone, Debug, PartialEq)]
 
@@ -5508,6 +5846,76 @@ impl<'a> Base<'a> {
     }
   }
 
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_op_call(&'a self) -> Option<OpCall> {
+    if self.msg_type() == Any::OpCall {
+      self.msg().map(|u| OpCall::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_op_call_ready(&'a self) -> Option<OpCallReady> {
+    if self.msg_type() == Any::OpCallReady {
+      self.msg().map(|u| OpCallReady::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_stream_credit(&'a self) -> Option<StreamCredit> {
+    if self.msg_type() == Any::StreamCredit {
+      self.msg().map(|u| StreamCredit::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_fetch_abort(&'a self) -> Option<FetchAbort> {
+    if self.msg_type() == Any::FetchAbort {
+      self.msg().map(|u| FetchAbort::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_web_socket_open(&'a self) -> Option<WebSocketOpen> {
+    if self.msg_type() == Any::WebSocketOpen {
+      self.msg().map(|u| WebSocketOpen::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_web_socket_message(&'a self) -> Option<WebSocketMessage> {
+    if self.msg_type() == Any::WebSocketMessage {
+      self.msg().map(|u| WebSocketMessage::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_web_socket_close(&'a self) -> Option<WebSocketClose> {
+    if self.msg_type() == Any::WebSocketClose {
+      self.msg().map(|u| WebSocketClose::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
 }
 
 pub struct BaseArgs<'a> {
@@ -5856,6 +6264,7 @@ impl<'a> HttpHeader<'a> {
         _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
         args: &'args HttpHeaderArgs<'args>) -> flatbuffers::WIPOffset<HttpHeader<'bldr>> {
       let mut builder = HttpHeaderBuilder::new(_fbb);
+      if let Some(x) = args.value_bytes { builder.add_value_bytes(x); }
       if let Some(x) = args.value { builder.add_value(x); }
       if let Some(x) = args.key { builder.add_key(x); }
       builder.finish()
@@ -5863,6 +6272,7 @@ impl<'a> HttpHeader<'a> {
 
     pub const VT_KEY: flatbuffers::VOffsetT = 4;
     pub const VT_VALUE: flatbuffers::VOffsetT = 6;
+    pub const VT_VALUE_BYTES: flatbuffers::VOffsetT = 8;
 
   #[inline]
   pub fn key(&self) -> Option<&'a str> {
@@ -5872,11 +6282,16 @@ impl<'a> HttpHeader<'a> {
   pub fn value(&self) -> Option<&'a str> {
     self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(HttpHeader::VT_VALUE, None)
   }
+  #[inline]
+  pub fn value_bytes(&self) -> Option<&'a [u8]> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u8>>>(HttpHeader::VT_VALUE_BYTES, None).map(|v| v.safe_slice())
+  }
 }
 
 pub struct HttpHeaderArgs<'a> {
     pub key: Option<flatbuffers::WIPOffset<&'a  str>>,
     pub value: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub value_bytes: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a ,  u8>>>,
 }
 impl<'a> Default for HttpHeaderArgs<'a> {
     #[inline]
@@ -5884,6 +6299,7 @@ impl<'a> Default for HttpHeaderArgs<'a> {
         HttpHeaderArgs {
             key: None,
             value: None,
+            value_bytes: None,
         }
     }
 }
@@ -5901,6 +6317,10 @@ impl<'a: 'b, 'b> HttpHeaderBuilder<'a, 'b> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(HttpHeader::VT_VALUE, value);
   }
   #[inline]
+  pub fn add_value_bytes(&mut self, value_bytes: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u8>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(HttpHeader::VT_VALUE_BYTES, value_bytes);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> HttpHeaderBuilder<'a, 'b> {
     let start = _fbb.start_table();
     HttpHeaderBuilder {
@@ -5942,15 +6362,19 @@ impl<'a> StreamChunk<'a> {
     #[allow(unused_mut)]
     pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
         _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
-        args: &'args StreamChunkArgs) -> flatbuffers::WIPOffset<StreamChunk<'bldr>> {
+        args: &'args StreamChunkArgs<'args>) -> flatbuffers::WIPOffset<StreamChunk<'bldr>> {
       let mut builder = StreamChunkBuilder::new(_fbb);
+      if let Some(x) = args.error { builder.add_error(x); }
       builder.add_id(args.id);
+      builder.add_error_kind(args.error_kind);
       builder.add_done(args.done);
       builder.finish()
     }
 
     pub const VT_ID: flatbuffers::VOffsetT = 4;
     pub const VT_DONE: flatbuffers::VOffsetT = 6;
+    pub const VT_ERROR_KIND: flatbuffers::VOffsetT = 8;
+    pub const VT_ERROR: flatbuffers::VOffsetT = 10;
 
   #[inline]
   pub fn id(&self) -> u32 {
@@ -5960,18 +6384,30 @@ impl<'a> StreamChunk<'a> {
   pub fn done(&self) -> bool {
     self._tab.get::<bool>(StreamChunk::VT_DONE, Some(false)).unwrap()
   }
+  #[inline]
+  pub fn error_kind(&self) -> ErrorKind {
+    self._tab.get::<ErrorKind>(StreamChunk::VT_ERROR_KIND, Some(ErrorKind::NoError)).unwrap()
+  }
+  #[inline]
+  pub fn error(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(StreamChunk::VT_ERROR, None)
+  }
 }
 
-pub struct StreamChunkArgs {
+pub struct StreamChunkArgs<'a> {
     pub id: u32,
     pub done: bool,
+    pub error_kind: ErrorKind,
+    pub error: Option<flatbuffers::WIPOffset<&'a  str>>,
 }
-impl<'a> Default for StreamChunkArgs {
+impl<'a> Default for StreamChunkArgs<'a> {
     #[inline]
     fn default() -> Self {
         StreamChunkArgs {
             id: 0,
             done: false,
+            error_kind: ErrorKind::NoError,
+            error: None,
         }
     }
 }
@@ -5989,6 +6425,14 @@ impl<'a: 'b, 'b> StreamChunkBuilder<'a, 'b> {
     self.fbb_.push_slot::<bool>(StreamChunk::VT_DONE, done, false);
   }
   #[inline]
+  pub fn add_error_kind(&mut self, error_kind: ErrorKind) {
+    self.fbb_.push_slot::<ErrorKind>(StreamChunk::VT_ERROR_KIND, error_kind, ErrorKind::NoError);
+  }
+  #[inline]
+  pub fn add_error(&mut self, error: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(StreamChunk::VT_ERROR, error);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> StreamChunkBuilder<'a, 'b> {
     let start = _fbb.start_table();
     StreamChunkBuilder {
@@ -6003,6 +6447,106 @@ impl<'a: 'b, 'b> StreamChunkBuilder<'a, 'b> {
   }
 }
 
+pub enum StreamCreditOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct StreamCredit<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for StreamCredit<'a> {
+    type Inner = StreamCredit<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> StreamCredit<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        StreamCredit {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args StreamCreditArgs) -> flatbuffers::WIPOffset<StreamCredit<'bldr>> {
+      let mut builder = StreamCreditBuilder::new(_fbb);
+      builder.add_bytes(args.bytes);
+      builder.add_id(args.id);
+      builder.add_cancel(args.cancel);
+      builder.finish()
+    }
+
+    pub const VT_ID: flatbuffers::VOffsetT = 4;
+    pub const VT_BYTES: flatbuffers::VOffsetT = 6;
+    pub const VT_CANCEL: flatbuffers::VOffsetT = 8;
+
+  #[inline]
+  pub fn id(&self) -> u32 {
+    self._tab.get::<u32>(StreamCredit::VT_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn bytes(&self) -> u32 {
+    self._tab.get::<u32>(StreamCredit::VT_BYTES, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn cancel(&self) -> bool {
+    self._tab.get::<bool>(StreamCredit::VT_CANCEL, Some(false)).unwrap()
+  }
+}
+
+pub struct StreamCreditArgs {
+    pub id: u32,
+    pub bytes: u32,
+    pub cancel: bool,
+}
+impl<'a> Default for StreamCreditArgs {
+    #[inline]
+    fn default() -> Self {
+        StreamCreditArgs {
+            id: 0,
+            bytes: 0,
+            cancel: false,
+        }
+    }
+}
+pub struct StreamCreditBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> StreamCreditBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_id(&mut self, id: u32) {
+    self.fbb_.push_slot::<u32>(StreamCredit::VT_ID, id, 0);
+  }
+  #[inline]
+  pub fn add_bytes(&mut self, bytes: u32) {
+    self.fbb_.push_slot::<u32>(StreamCredit::VT_BYTES, bytes, 0);
+  }
+  #[inline]
+  pub fn add_cancel(&mut self, cancel: bool) {
+    self.fbb_.push_slot::<bool>(StreamCredit::VT_CANCEL, cancel, false);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> StreamCreditBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    StreamCreditBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<StreamCredit<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
 pub enum HttpRequestOffset {}
 #[derive(Copy, Clone, Debug, PartialEq)]
 
@@ -6032,10 +6576,15 @@ impl<'a> HttpRequest<'a> {
         _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
         args: &'args HttpRequestArgs<'args>) -> flatbuffers::WIPOffset<HttpRequest<'bldr>> {
       let mut builder = HttpRequestBuilder::new(_fbb);
+      builder.add_body_timeout(args.body_timeout);
+      builder.add_headers_timeout(args.headers_timeout);
       if let Some(x) = args.remote_addr { builder.add_remote_addr(x); }
       if let Some(x) = args.headers { builder.add_headers(x); }
       if let Some(x) = args.url { builder.add_url(x); }
       builder.add_id(args.id);
+      builder.add_cache(args.cache);
+      builder.add_retries(args.retries);
+      builder.add_redirect(args.redirect);
       builder.add_has_body(args.has_body);
       builder.add_method(args.method);
       builder.finish()
@@ -6047,6 +6596,11 @@ impl<'a> HttpRequest<'a> {
     pub const VT_HEADERS: flatbuffers::VOffsetT = 10;
     pub const VT_REMOTE_ADDR: flatbuffers::VOffsetT = 12;
     pub const VT_HAS_BODY: flatbuffers::VOffsetT = 14;
+    pub const VT_HEADERS_TIMEOUT: flatbuffers::VOffsetT = 16;
+    pub const VT_BODY_TIMEOUT: flatbuffers::VOffsetT = 18;
+    pub const VT_REDIRECT: flatbuffers::VOffsetT = 20;
+    pub const VT_RETRIES: flatbuffers::VOffsetT = 22;
+    pub const VT_CACHE: flatbuffers::VOffsetT = 24;
 
   #[inline]
   pub fn id(&self) -> u32 {
@@ -6072,6 +6626,26 @@ impl<'a> HttpRequest<'a> {
   pub fn has_body(&self) -> bool {
     self._tab.get::<bool>(HttpRequest::VT_HAS_BODY, Some(false)).unwrap()
   }
+  #[inline]
+  pub fn headers_timeout(&self) -> u32 {
+    self._tab.get::<u32>(HttpRequest::VT_HEADERS_TIMEOUT, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn body_timeout(&self) -> u32 {
+    self._tab.get::<u32>(HttpRequest::VT_BODY_TIMEOUT, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn redirect(&self) -> RedirectMode {
+    self._tab.get::<RedirectMode>(HttpRequest::VT_REDIRECT, Some(RedirectMode::Follow)).unwrap()
+  }
+  #[inline]
+  pub fn retries(&self) -> u8 {
+    self._tab.get::<u8>(HttpRequest::VT_RETRIES, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn cache(&self) -> HttpCacheMode {
+    self._tab.get::<HttpCacheMode>(HttpRequest::VT_CACHE, Some(HttpCacheMode::Off)).unwrap()
+  }
 }
 
 pub struct HttpRequestArgs<'a> {
@@ -6081,6 +6655,11 @@ pub struct HttpRequestArgs<'a> {
     pub headers: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a , flatbuffers::ForwardsUOffset<HttpHeader<'a >>>>>,
     pub remote_addr: Option<flatbuffers::WIPOffset<&'a  str>>,
     pub has_body: bool,
+    pub headers_timeout: u32,
+    pub body_timeout: u32,
+    pub redirect: RedirectMode,
+    pub retries: u8,
+    pub cache: HttpCacheMode,
 }
 impl<'a> Default for HttpRequestArgs<'a> {
     #[inline]
@@ -6092,6 +6671,11 @@ impl<'a> Default for HttpRequestArgs<'a> {
             headers: None,
             remote_addr: None,
             has_body: false,
+            headers_timeout: 0,
+            body_timeout: 0,
+            redirect: RedirectMode::Follow,
+            retries: 0,
+            cache: HttpCacheMode::Off,
         }
     }
 }
@@ -6125,6 +6709,26 @@ impl<'a: 'b, 'b> HttpRequestBuilder<'a, 'b> {
     self.fbb_.push_slot::<bool>(HttpRequest::VT_HAS_BODY, has_body, false);
   }
   #[inline]
+  pub fn add_headers_timeout(&mut self, headers_timeout: u32) {
+    self.fbb_.push_slot::<u32>(HttpRequest::VT_HEADERS_TIMEOUT, headers_timeout, 0);
+  }
+  #[inline]
+  pub fn add_body_timeout(&mut self, body_timeout: u32) {
+    self.fbb_.push_slot::<u32>(HttpRequest::VT_BODY_TIMEOUT, body_timeout, 0);
+  }
+  #[inline]
+  pub fn add_redirect(&mut self, redirect: RedirectMode) {
+    self.fbb_.push_slot::<RedirectMode>(HttpRequest::VT_REDIRECT, redirect, RedirectMode::Follow);
+  }
+  #[inline]
+  pub fn add_retries(&mut self, retries: u8) {
+    self.fbb_.push_slot::<u8>(HttpRequest::VT_RETRIES, retries, 0);
+  }
+  #[inline]
+  pub fn add_cache(&mut self, cache: HttpCacheMode) {
+    self.fbb_.push_slot::<HttpCacheMode>(HttpRequest::VT_CACHE, cache, HttpCacheMode::Off);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> HttpRequestBuilder<'a, 'b> {
     let start = _fbb.start_table();
     HttpRequestBuilder {
@@ -6171,6 +6775,7 @@ impl<'a> HttpResponse<'a> {
       if let Some(x) = args.headers { builder.add_headers(x); }
       builder.add_id(args.id);
       builder.add_status(args.status);
+      builder.add_websocket(args.websocket);
       builder.add_has_body(args.has_body);
       builder.finish()
     }
@@ -6179,6 +6784,7 @@ impl<'a> HttpResponse<'a> {
     pub const VT_HEADERS: flatbuffers::VOffsetT = 6;
     pub const VT_STATUS: flatbuffers::VOffsetT = 8;
     pub const VT_HAS_BODY: flatbuffers::VOffsetT = 10;
+    pub const VT_WEBSOCKET: flatbuffers::VOffsetT = 12;
 
   #[inline]
   pub fn id(&self) -> u32 {
@@ -6196,6 +6802,10 @@ impl<'a> HttpResponse<'a> {
   pub fn has_body(&self) -> bool {
     self._tab.get::<bool>(HttpResponse::VT_HAS_BODY, Some(false)).unwrap()
   }
+  #[inline]
+  pub fn websocket(&self) -> bool {
+    self._tab.get::<bool>(HttpResponse::VT_WEBSOCKET, Some(false)).unwrap()
+  }
 }
 
 pub struct HttpResponseArgs<'a> {
@@ -6203,6 +6813,7 @@ pub struct HttpResponseArgs<'a> {
     pub headers: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a , flatbuffers::ForwardsUOffset<HttpHeader<'a >>>>>,
     pub status: u16,
     pub has_body: bool,
+    pub websocket: bool,
 }
 impl<'a> Default for HttpResponseArgs<'a> {
     #[inline]
@@ -6212,6 +6823,7 @@ impl<'a> Default for HttpResponseArgs<'a> {
             headers: None,
             status: 0,
             has_body: false,
+            websocket: false,
         }
     }
 }
@@ -6237,6 +6849,10 @@ impl<'a: 'b, 'b> HttpResponseBuilder<'a, 'b> {
     self.fbb_.push_slot::<bool>(HttpResponse::VT_HAS_BODY, has_body, false);
   }
   #[inline]
+  pub fn add_websocket(&mut self, websocket: bool) {
+    self.fbb_.push_slot::<bool>(HttpResponse::VT_WEBSOCKET, websocket, false);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> HttpResponseBuilder<'a, 'b> {
     let start = _fbb.start_table();
     HttpResponseBuilder {
@@ -6356,9 +6972,11 @@ impl<'a> FetchHttpResponse<'a> {
         _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
         args: &'args FetchHttpResponseArgs<'args>) -> flatbuffers::WIPOffset<FetchHttpResponse<'bldr>> {
       let mut builder = FetchHttpResponseBuilder::new(_fbb);
+      if let Some(x) = args.url { builder.add_url(x); }
       if let Some(x) = args.headers { builder.add_headers(x); }
       builder.add_id(args.id);
       builder.add_status(args.status);
+      builder.add_redirected(args.redirected);
       builder.add_has_body(args.has_body);
       builder.finish()
     }
@@ -6367,6 +6985,8 @@ impl<'a> FetchHttpResponse<'a> {
     pub const VT_HEADERS: flatbuffers::VOffsetT = 6;
     pub const VT_STATUS: flatbuffers::VOffsetT = 8;
     pub const VT_HAS_BODY: flatbuffers::VOffsetT = 10;
+    pub const VT_URL: flatbuffers::VOffsetT = 12;
+    pub const VT_REDIRECTED: flatbuffers::VOffsetT = 14;
 
   #[inline]
   pub fn id(&self) -> u32 {
@@ -6384,6 +7004,14 @@ impl<'a> FetchHttpResponse<'a> {
   pub fn has_body(&self) -> bool {
     self._tab.get::<bool>(FetchHttpResponse::VT_HAS_BODY, Some(false)).unwrap()
   }
+  #[inline]
+  pub fn url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(FetchHttpResponse::VT_URL, None)
+  }
+  #[inline]
+  pub fn redirected(&self) -> bool {
+    self._tab.get::<bool>(FetchHttpResponse::VT_REDIRECTED, Some(false)).unwrap()
+  }
 }
 
 pub struct FetchHttpResponseArgs<'a> {
@@ -6391,6 +7019,8 @@ pub struct FetchHttpResponseArgs<'a> {
     pub headers: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a , flatbuffers::ForwardsUOffset<HttpHeader<'a >>>>>,
     pub status: u16,
     pub has_body: bool,
+    pub url: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub redirected: bool,
 }
 impl<'a> Default for FetchHttpResponseArgs<'a> {
     #[inline]
@@ -6400,6 +7030,8 @@ impl<'a> Default for FetchHttpResponseArgs<'a> {
             headers: None,
             status: 0,
             has_body: false,
+            url: None,
+            redirected: false,
         }
     }
 }
@@ -6425,6 +7057,14 @@ impl<'a: 'b, 'b> FetchHttpResponseBuilder<'a, 'b> {
     self.fbb_.push_slot::<bool>(FetchHttpResponse::VT_HAS_BODY, has_body, false);
   }
   #[inline]
+  pub fn add_url(&mut self, url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(FetchHttpResponse::VT_URL, url);
+  }
+  #[inline]
+  pub fn add_redirected(&mut self, redirected: bool) {
+    self.fbb_.push_slot::<bool>(FetchHttpResponse::VT_REDIRECTED, redirected, false);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> FetchHttpResponseBuilder<'a, 'b> {
     let start = _fbb.start_table();
     FetchHttpResponseBuilder {
@@ -6439,6 +7079,346 @@ impl<'a: 'b, 'b> FetchHttpResponseBuilder<'a, 'b> {
   }
 }
 
+pub enum FetchAbortOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct FetchAbort<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for FetchAbort<'a> {
+    type Inner = FetchAbort<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> FetchAbort<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        FetchAbort {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args FetchAbortArgs) -> flatbuffers::WIPOffset<FetchAbort<'bldr>> {
+      let mut builder = FetchAbortBuilder::new(_fbb);
+      builder.add_id(args.id);
+      builder.finish()
+    }
+
+    pub const VT_ID: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn id(&self) -> u32 {
+    self._tab.get::<u32>(FetchAbort::VT_ID, Some(0)).unwrap()
+  }
+}
+
+pub struct FetchAbortArgs {
+    pub id: u32,
+}
+impl<'a> Default for FetchAbortArgs {
+    #[inline]
+    fn default() -> Self {
+        FetchAbortArgs {
+            id: 0,
+        }
+    }
+}
+pub struct FetchAbortBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> FetchAbortBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_id(&mut self, id: u32) {
+    self.fbb_.push_slot::<u32>(FetchAbort::VT_ID, id, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> FetchAbortBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    FetchAbortBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<FetchAbort<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum WebSocketOpenOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct WebSocketOpen<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for WebSocketOpen<'a> {
+    type Inner = WebSocketOpen<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> WebSocketOpen<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        WebSocketOpen {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args WebSocketOpenArgs) -> flatbuffers::WIPOffset<WebSocketOpen<'bldr>> {
+      let mut builder = WebSocketOpenBuilder::new(_fbb);
+      builder.add_id(args.id);
+      builder.finish()
+    }
+
+    pub const VT_ID: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn id(&self) -> u32 {
+    self._tab.get::<u32>(WebSocketOpen::VT_ID, Some(0)).unwrap()
+  }
+}
+
+pub struct WebSocketOpenArgs {
+    pub id: u32,
+}
+impl<'a> Default for WebSocketOpenArgs {
+    #[inline]
+    fn default() -> Self {
+        WebSocketOpenArgs {
+            id: 0,
+        }
+    }
+}
+pub struct WebSocketOpenBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> WebSocketOpenBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_id(&mut self, id: u32) {
+    self.fbb_.push_slot::<u32>(WebSocketOpen::VT_ID, id, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> WebSocketOpenBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    WebSocketOpenBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<WebSocketOpen<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum WebSocketMessageOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct WebSocketMessage<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for WebSocketMessage<'a> {
+    type Inner = WebSocketMessage<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> WebSocketMessage<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        WebSocketMessage {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args WebSocketMessageArgs) -> flatbuffers::WIPOffset<WebSocketMessage<'bldr>> {
+      let mut builder = WebSocketMessageBuilder::new(_fbb);
+      builder.add_id(args.id);
+      builder.add_binary(args.binary);
+      builder.finish()
+    }
+
+    pub const VT_ID: flatbuffers::VOffsetT = 4;
+    pub const VT_BINARY: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn id(&self) -> u32 {
+    self._tab.get::<u32>(WebSocketMessage::VT_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn binary(&self) -> bool {
+    self._tab.get::<bool>(WebSocketMessage::VT_BINARY, Some(false)).unwrap()
+  }
+}
+
+pub struct WebSocketMessageArgs {
+    pub id: u32,
+    pub binary: bool,
+}
+impl<'a> Default for WebSocketMessageArgs {
+    #[inline]
+    fn default() -> Self {
+        WebSocketMessageArgs {
+            id: 0,
+            binary: false,
+        }
+    }
+}
+pub struct WebSocketMessageBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> WebSocketMessageBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_id(&mut self, id: u32) {
+    self.fbb_.push_slot::<u32>(WebSocketMessage::VT_ID, id, 0);
+  }
+  #[inline]
+  pub fn add_binary(&mut self, binary: bool) {
+    self.fbb_.push_slot::<bool>(WebSocketMessage::VT_BINARY, binary, false);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> WebSocketMessageBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    WebSocketMessageBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<WebSocketMessage<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum WebSocketCloseOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct WebSocketClose<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for WebSocketClose<'a> {
+    type Inner = WebSocketClose<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> WebSocketClose<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        WebSocketClose {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args WebSocketCloseArgs<'args>) -> flatbuffers::WIPOffset<WebSocketClose<'bldr>> {
+      let mut builder = WebSocketCloseBuilder::new(_fbb);
+      if let Some(x) = args.reason { builder.add_reason(x); }
+      builder.add_id(args.id);
+      builder.add_code(args.code);
+      builder.finish()
+    }
+
+    pub const VT_ID: flatbuffers::VOffsetT = 4;
+    pub const VT_CODE: flatbuffers::VOffsetT = 6;
+    pub const VT_REASON: flatbuffers::VOffsetT = 8;
+
+  #[inline]
+  pub fn id(&self) -> u32 {
+    self._tab.get::<u32>(WebSocketClose::VT_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn code(&self) -> u16 {
+    self._tab.get::<u16>(WebSocketClose::VT_CODE, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn reason(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(WebSocketClose::VT_REASON, None)
+  }
+}
+
+pub struct WebSocketCloseArgs<'a> {
+    pub id: u32,
+    pub code: u16,
+    pub reason: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for WebSocketCloseArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        WebSocketCloseArgs {
+            id: 0,
+            code: 0,
+            reason: None,
+        }
+    }
+}
+pub struct WebSocketCloseBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> WebSocketCloseBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_id(&mut self, id: u32) {
+    self.fbb_.push_slot::<u32>(WebSocketClose::VT_ID, id, 0);
+  }
+  #[inline]
+  pub fn add_code(&mut self, code: u16) {
+    self.fbb_.push_slot::<u16>(WebSocketClose::VT_CODE, code, 0);
+  }
+  #[inline]
+  pub fn add_reason(&mut self, reason: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(WebSocketClose::VT_REASON, reason);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> WebSocketCloseBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    WebSocketCloseBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<WebSocketClose<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
 pub enum CryptoDigestOffset {}
 #[derive(Copy, Clone, Debug, PartialEq)]
 
@@ -7200,6 +8180,7 @@ impl<'a> LoadModuleResp<'a> {
         _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
         args: &'args LoadModuleRespArgs<'args>) -> flatbuffers::WIPOffset<LoadModuleResp<'bldr>> {
       let mut builder = LoadModuleRespBuilder::new(_fbb);
+      if let Some(x) = args.error { builder.add_error(x); }
       if let Some(x) = args.source_code { builder.add_source_code(x); }
       if let Some(x) = args.origin_url { builder.add_origin_url(x); }
       builder.finish()
@@ -7207,6 +8188,7 @@ impl<'a> LoadModuleResp<'a> {
 
     pub const VT_ORIGIN_URL: flatbuffers::VOffsetT = 4;
     pub const VT_SOURCE_CODE: flatbuffers::VOffsetT = 6;
+    pub const VT_ERROR: flatbuffers::VOffsetT = 8;
 
   #[inline]
   pub fn origin_url(&self) -> Option<&'a str> {
@@ -7216,11 +8198,16 @@ impl<'a> LoadModuleResp<'a> {
   pub fn source_code(&self) -> Option<&'a str> {
     self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadModuleResp::VT_SOURCE_CODE, None)
   }
+  #[inline]
+  pub fn error(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadModuleResp::VT_ERROR, None)
+  }
 }
 
 pub struct LoadModuleRespArgs<'a> {
     pub origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
     pub source_code: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub error: Option<flatbuffers::WIPOffset<&'a  str>>,
 }
 impl<'a> Default for LoadModuleRespArgs<'a> {
     #[inline]
@@ -7228,6 +8215,7 @@ impl<'a> Default for LoadModuleRespArgs<'a> {
         LoadModuleRespArgs {
             origin_url: None,
             source_code: None,
+            error: None,
         }
     }
 }
@@ -7245,6 +8233,10 @@ impl<'a: 'b, 'b> LoadModuleRespBuilder<'a, 'b> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModuleResp::VT_SOURCE_CODE, source_code);
   }
   #[inline]
+  pub fn add_error(&mut self, error: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModuleResp::VT_ERROR, error);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> LoadModuleRespBuilder<'a, 'b> {
     let start = _fbb.start_table();
     LoadModuleRespBuilder {
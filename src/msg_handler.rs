@@ -1,3 +1,4 @@
+use crate::errors::{self, ErrorKind};
 use crate::msg;
 use crate::runtime::Runtime;
 use libfly::*;
@@ -7,6 +8,44 @@ use crate::utils::*;
 
 pub trait MessageHandler: Send + Sync {
     fn handle_msg(&self, rt: &mut Runtime, base: &msg::Base, raw_buf: fly_buf) -> Box<Op>;
+
+    /// Wraps every op handled, see `OpHook`.
+    fn op_hook(&self) -> Option<&OpHook> {
+        None
+    }
+}
+
+/// Wraps ops, e.g. to run each in its own tracing span. `op_name` is the message type,
+/// as in `TimerStart` or `CacheGet`. JS gets what the returned op resolves to.
+pub trait OpHook: Send + Sync {
+    fn wrap(&self, rt: &Runtime, op_name: &'static str, op: Box<Op>) -> Box<Op>;
+}
+
+/// Ops that wait on I/O or on another runtime. They're refused when sent synchronously:
+/// waiting on one would block the worker thread, and every runtime sharing it with this one.
+pub fn is_io_op(msg_type: msg::Any) -> bool {
+    match msg_type {
+        msg::Any::HttpRequest
+        | msg::Any::CacheGet
+        | msg::Any::CacheSet
+        | msg::Any::CacheDel
+        | msg::Any::CacheNotifyDel
+        | msg::Any::CacheNotifyPurgeTag
+        | msg::Any::CacheExpire
+        | msg::Any::CacheSetMeta
+        | msg::Any::CachePurgeTag
+        | msg::Any::DataPut
+        | msg::Any::DataGet
+        | msg::Any::DataDel
+        | msg::Any::DataIncr
+        | msg::Any::DataDropCollection
+        | msg::Any::DnsQuery
+        | msg::Any::ImageApplyTransforms
+        | msg::Any::AcmeGetChallenge
+        | msg::Any::RequestServiceRequest
+        | msg::Any::OpCall => true,
+        _ => false,
+    }
 }
 
 pub struct DefaultMessageHandler {}
@@ -19,8 +58,12 @@ impl MessageHandler for DefaultMessageHandler {
             msg::Any::TimerStart => ops::timers::op_timer_start,
             msg::Any::TimerClear => ops::timers::op_timer_clear,
             msg::Any::HttpRequest => ops::fetch::op_fetch,
+            msg::Any::FetchAbort => ops::fetch::op_fetch_abort,
             msg::Any::HttpResponse => ops::fetch::op_http_response,
             msg::Any::StreamChunk => ops::streams::op_stream_chunk,
+            msg::Any::StreamCredit => ops::streams::op_stream_credit,
+            msg::Any::WebSocketMessage => ops::websocket::op_ws_message,
+            msg::Any::WebSocketClose => ops::websocket::op_ws_close,
             msg::Any::CacheGet => ops::cache::op_cache_get,
             msg::Any::CacheSet => ops::cache::op_cache_set,
             msg::Any::CacheDel => ops::cache::op_cache_del,
@@ -46,7 +89,13 @@ impl MessageHandler for DefaultMessageHandler {
             msg::Any::RequestServiceRequest => ops::service::op_request_service_request,
             msg::Any::ServiceResponse => ops::service::op_service_response,
             msg::Any::OsExit => ops::os::op_exit,
-            _ => unimplemented!(),
+            msg::Any::OpCall => ops::native::op_call,
+            _ => {
+                return odd_future(errors::new(
+                    ErrorKind::InvalidInput,
+                    format!("unhandled message type {:?}", msg_type),
+                ));
+            }
         };
 
         handler(rt, base, raw_buf)
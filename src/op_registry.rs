@@ -0,0 +1,78 @@
+//! Native ops registered by embedders and called from JS with `fly.ops.call(name, payload)`.
+
+use futures::Future;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::errors::FlyError;
+use crate::runtime::Runtime;
+
+/// What an op is called with and resolves to. `ArrayBuffer`s and typed arrays
+/// cross as `Binary`, anything else is serialized as `Json`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpPayload {
+    Binary(Vec<u8>),
+    Json(serde_json::Value),
+}
+
+pub type OpFuture = Box<Future<Item = OpPayload, Error = FlyError> + Send>;
+pub type NativeOp = Fn(&Runtime, OpPayload) -> OpFuture + Send + Sync;
+
+#[derive(Clone, Default)]
+pub struct OpRegistry {
+    ops: HashMap<String, Arc<NativeOp>>,
+}
+
+impl OpRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `op` as `name`, replacing any op already registered under it.
+    pub fn register<F>(&mut self, name: &str, op: F)
+    where
+        F: Fn(&Runtime, OpPayload) -> OpFuture + Send + Sync + 'static,
+    {
+        self.ops.insert(name.to_string(), Arc::new(op));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<NativeOp>> {
+        self.ops.get(name).cloned()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.ops.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        names
+    }
+}
+
+impl fmt::Debug for OpRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OpRegistry")
+            .field("ops", &self.names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+
+    #[test]
+    fn test_register() {
+        let mut registry = OpRegistry::new();
+        assert!(registry.get("echo").is_none());
+        registry.register("echo", |_rt, payload| Box::new(future::ok(payload)));
+        registry.register("auth.lookup", |_rt, _payload| {
+            Box::new(future::ok(OpPayload::Json(serde_json::Value::Null)))
+        });
+        assert!(registry.get("echo").is_some());
+        assert_eq!(registry.names(), vec!["auth.lookup", "echo"]);
+
+        let cloned = registry.clone();
+        assert!(cloned.get("auth.lookup").is_some());
+    }
+}
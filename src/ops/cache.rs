@@ -1,5 +1,3 @@
-use futures::sync::mpsc;
-
 use crate::msg;
 use flatbuffers::FlatBufferBuilder;
 
@@ -9,6 +7,7 @@ use crate::utils::*;
 use libfly::*;
 
 use crate::get_next_stream_id;
+use crate::stream_channel;
 
 use futures::{Future, Stream};
 
@@ -16,6 +15,10 @@ use crate::cache_store::*;
 use crate::cache_store_notifier::*;
 
 pub fn op_cache_del(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+  if let Err(e) = rt.permissions.check_cache_write() {
+    return odd_future(e);
+  }
+
   let msg = base.msg_as_cache_del().unwrap();
   let key = msg.key().unwrap().to_string();
 
@@ -29,6 +32,10 @@ pub fn op_cache_del(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op
 }
 
 pub fn op_cache_expire(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+  if let Err(e) = rt.permissions.check_cache_write() {
+    return odd_future(e);
+  }
+
   let msg = base.msg_as_cache_expire().unwrap();
   let key = msg.key().unwrap().to_string();
   let ttl = msg.ttl();
@@ -43,13 +50,17 @@ pub fn op_cache_expire(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box
 }
 
 pub fn op_cache_set(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+  if let Err(e) = rt.permissions.check_cache_write() {
+    return odd_future(e);
+  }
+
   let cmd_id = base.cmd_id();
   let msg = base.msg_as_cache_set().unwrap();
   let key = msg.key().unwrap().to_string();
 
   let stream_id = get_next_stream_id();
 
-  let (sender, recver) = mpsc::unbounded::<Vec<u8>>();
+  let (sender, recver) = stream_channel::channel(&rt.stream_buffer);
   {
     rt.streams.lock().unwrap().insert(stream_id, sender);
   }
@@ -106,6 +117,10 @@ pub fn op_cache_set(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op
 }
 
 pub fn op_cache_get(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+  if let Err(e) = rt.permissions.check_cache_read() {
+    return odd_future(e);
+  }
+
   let cmd_id = base.cmd_id();
   let msg = base.msg_as_cache_get().unwrap();
 
@@ -160,6 +175,10 @@ pub fn op_cache_get(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op
 }
 
 pub fn op_cache_notify_del(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+  if let Err(e) = rt.permissions.check_cache_write() {
+    return odd_future(e);
+  }
+
   let msg = base.msg_as_cache_notify_del().unwrap();
 
   let key = msg.key().unwrap().to_string();
@@ -179,6 +198,10 @@ pub fn op_cache_notify_del(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) ->
 }
 
 pub fn op_cache_notify_purge_tag(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+  if let Err(e) = rt.permissions.check_cache_write() {
+    return odd_future(e);
+  }
+
   let msg = base.msg_as_cache_notify_purge_tag().unwrap();
 
   let tag = msg.tag().unwrap().to_string();
@@ -198,6 +221,10 @@ pub fn op_cache_notify_purge_tag(rt: &mut Runtime, base: &msg::Base, _raw: fly_b
 }
 
 pub fn op_cache_set_meta(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+  if let Err(e) = rt.permissions.check_cache_write() {
+    return odd_future(e);
+  }
+
   let msg = base.msg_as_cache_set_meta().unwrap();
   let key = msg.key().unwrap().to_string();
   let meta = msg.meta().unwrap().to_string();
@@ -212,6 +239,10 @@ pub fn op_cache_set_meta(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> B
 }
 
 pub fn op_cache_purge_tag(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+  if let Err(e) = rt.permissions.check_cache_write() {
+    return odd_future(e);
+  }
+
   let msg = base.msg_as_cache_purge_tag().unwrap();
   let tag = msg.tag().unwrap().to_string();
 
@@ -8,6 +8,10 @@ use libfly::*;
 use futures::Future;
 
 pub fn op_data_put(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    if let Err(e) = rt.permissions.check_data_write() {
+        return odd_future(e);
+    }
+
     let msg = base.msg_as_data_put().unwrap();
     let coll = msg.collection().unwrap().to_string();
     let key = msg.key().unwrap().to_string();
@@ -22,6 +26,10 @@ pub fn op_data_put(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op>
 }
 
 pub fn op_data_get(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    if let Err(e) = rt.permissions.check_data_read() {
+        return odd_future(e);
+    }
+
     let cmd_id = base.cmd_id();
     let msg = base.msg_as_data_get().unwrap();
     let coll = msg.collection().unwrap().to_string();
@@ -58,6 +66,10 @@ pub fn op_data_get(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op>
 }
 
 pub fn op_data_del(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    if let Err(e) = rt.permissions.check_data_write() {
+        return odd_future(e);
+    }
+
     let msg = base.msg_as_data_del().unwrap();
     let coll = msg.collection().unwrap().to_string();
     let key = msg.key().unwrap().to_string();
@@ -71,6 +83,10 @@ pub fn op_data_del(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op>
 }
 
 pub fn op_data_drop_coll(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    if let Err(e) = rt.permissions.check_data_write() {
+        return odd_future(e);
+    }
+
     let msg = base.msg_as_data_drop_collection().unwrap();
     let coll = msg.collection().unwrap().to_string();
 
@@ -83,6 +99,10 @@ pub fn op_data_drop_coll(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> B
 }
 
 pub fn op_data_incr(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    if let Err(e) = rt.permissions.check_data_write() {
+        return odd_future(e);
+    }
+
     let msg = base.msg_as_data_incr().unwrap();
     let coll = msg.collection().unwrap().to_string();
     let key = msg.key().unwrap().to_string();
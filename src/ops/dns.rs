@@ -272,7 +272,7 @@ fn dns_query(
   )
 }
 
-pub fn op_dns_query(_rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+pub fn op_dns_query(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
   debug!("handle dns");
   let cmd_id = base.cmd_id();
   let msg = base.msg_as_dns_query().unwrap();
@@ -307,6 +307,10 @@ pub fn op_dns_query(_rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<O
         format!("{}:53", ns)
       }
     };
+    // querying a name server of the app's choosing is reaching out to it
+    if let Err(e) = rt.permissions.check_net(&format!("dns://{}", ns)) {
+      return odd_future(e);
+    }
     let sockaddr = ns.to_socket_addrs().unwrap().next().unwrap();
     {
       if let Some(client) = DNS_RESOLVERS.lock().unwrap().get_mut(&sockaddr) {
@@ -489,8 +493,9 @@ pub fn op_dns_response(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box
     vec![]
   };
 
-  let mut responses = rt.dns_responses.lock().unwrap();
-  match responses.remove(&req_id) {
+  let sender = rt.dns_responses.lock().unwrap().remove(&req_id);
+  rt.event_answered();
+  match sender {
     Some(sender) => {
       if let Err(_) = sender.send(JsDnsResponse {
         op_code: op_code,
@@ -1,11 +1,13 @@
 use crate::msg;
 use flatbuffers::FlatBufferBuilder;
 
+use crate::http_headers::create_headers;
 use crate::runtime::Runtime;
 use libfly::*;
 
 use crate::js::*;
 use crate::utils::*;
+use crate::watchdog::WatchedEvent;
 
 use hyper::Method;
 
@@ -41,24 +43,7 @@ pub fn op_add_event_ln(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box
                             _ => unimplemented!(),
                         };
 
-                        let headers: Vec<_> = req
-                            .headers
-                            .iter()
-                            .map(|(key, value)| {
-                                let key = builder.create_string(key.as_str());
-                                let value = builder.create_string(value.to_str().unwrap());
-                                msg::HttpHeader::create(
-                                    builder,
-                                    &msg::HttpHeaderArgs {
-                                        key: Some(key),
-                                        value: Some(value),
-                                        ..Default::default()
-                                    },
-                                )
-                            })
-                            .collect();
-
-                        let req_headers = builder.create_vector(&headers);
+                        let req_headers = create_headers(builder, &req.headers);
                         let req_remote_addr =
                             builder.create_string(req.remote_addr.ip().to_string().as_str());
 
@@ -88,7 +73,10 @@ pub fn op_add_event_ln(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box
                             .unwrap(),
                         );
 
-                        ptr.send(to_send, None);
+                        if !ptr.send_event(WatchedEvent::Fetch, to_send, None) {
+                            ptr.to_runtime().event_terminated(WatchedEvent::Fetch, req.id);
+                            return Ok(());
+                        }
 
                         if let Some(stream) = req.body {
                             send_body_stream(ptr, req.id, stream);
@@ -183,7 +171,9 @@ pub fn op_add_event_ln(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box
                             .unwrap(),
                         );
 
-                        ptr.send(to_send, None);
+                        if !ptr.send_event(WatchedEvent::Resolv, to_send, None) {
+                            ptr.to_runtime().event_terminated(WatchedEvent::Resolv, req.id);
+                        }
                         Ok(())
                     }),
             );
@@ -224,7 +214,9 @@ pub fn op_add_event_ln(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box
                             .unwrap(),
                         );
 
-                        ptr.send(to_send, None);
+                        if !ptr.send_event(WatchedEvent::Serve, to_send, None) {
+                            ptr.to_runtime().event_terminated(WatchedEvent::Serve, req.id);
+                        }
 
                         Ok(())
                     })
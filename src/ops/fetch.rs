@@ -1,28 +1,35 @@
+use futures::future::{Either, Loop, Shared};
 use futures::{
-    future,
+    future, stream,
     sync::{mpsc, oneshot},
+    Async, Poll,
 };
 
 use crate::msg;
 use flatbuffers::FlatBufferBuilder;
 
+use crate::cache_store::{CacheEntry, CacheSetOptions, CacheStream};
+use crate::egress::EgressError;
+use crate::fetch_policy;
+use crate::http_cache::{self, CacheStatus, Freshness, StoredResponse};
+use crate::http_client::HttpClient;
+use crate::http_headers::{create_headers, headers_from_msg};
 use crate::js::*;
-use crate::runtime::{Runtime, EVENT_LOOP};
+use crate::runtime::{JsRuntime, Runtime};
+use crate::runtime_permissions::RuntimePermissions;
 use crate::utils::*;
 use libfly::*;
 
-use crate::errors::{FlyError, FlyResult};
+use crate::errors::{self, ErrorKind, FlyError, FlyResult};
 
 use crate::get_next_stream_id;
+use crate::stream_channel;
 
+use bytes::Bytes;
 use hyper::body::Payload;
-use hyper::client::HttpConnector;
-use hyper::header::HeaderName;
+use hyper::header::{self, HeaderValue};
 use hyper::rt::{Future, Stream};
-use hyper::HeaderMap;
-use hyper::{Body, Client, Method, Request, StatusCode};
-
-use hyper_tls::HttpsConnector;
+use hyper::{Body, HeaderMap, Method, Request, Response, StatusCode};
 
 use std::io;
 
@@ -31,15 +38,8 @@ use std::slice;
 use crate::metrics::*;
 use floating_duration::TimeAsFloat;
 use http::uri::Scheme;
-use std::time;
-
-lazy_static! {
-    static ref HTTP_CLIENT: Client<HttpsConnector<HttpConnector>, Body> = {
-        Client::builder()
-            .executor(EVENT_LOOP.0.clone())
-            .build(HttpsConnector::new(4).unwrap())
-    };
-}
+use std::time::{self, Duration, Instant};
+use tokio::timer::{Delay, Timeout};
 
 pub fn op_fetch(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
     let cmd_id = base.cmd_id();
@@ -49,32 +49,36 @@ pub fn op_fetch(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
     if url.starts_with("file://") {
         return file_request(rt, cmd_id, url);
     }
+    if let Err(e) = rt.permissions.check_net(url) {
+        return odd_future(e);
+    }
+    let client = match rt.http_client {
+        Ok(ref client) => client.clone(),
+        Err(ref e) => return odd_future(format!("fetch is unavailable: {}", e).into()),
+    };
 
     let ptr = rt.ptr;
 
     let req_id = msg.id();
 
+    let cache_mode = msg.cache();
+    if cache_mode != msg::HttpCacheMode::Off {
+        if let Err(e) = rt
+            .permissions
+            .check_cache_read()
+            .and_then(|_| rt.permissions.check_cache_write())
+        {
+            return odd_future(e);
+        }
+    }
+
     let http_uri: hyper::Uri = match url.parse() {
         Ok(u) => u,
         Err(e) => return odd_future(format!("{}", e).into()),
     };
 
     // for the metrics
-    let host_str = http_uri.host().unwrap_or("unknown");
-    let host = if let Some(port) = http_uri.port_part() {
-        format!("{}:{}", host_str, port.as_str())
-    } else {
-        let port = if let Some(scheme) = http_uri.scheme_part() {
-            if scheme == &Scheme::HTTPS {
-                "443"
-            } else {
-                "80"
-            }
-        } else {
-            "80"
-        };
-        format!("{}:{}", host_str, port)
-    };
+    let host = host_label(&http_uri);
 
     FETCH_HTTP_REQUESTS_TOTAL
         .with_label_values(&[rt.name.as_str(), rt.version.as_str(), host.as_str()])
@@ -92,61 +96,86 @@ pub fn op_fetch(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
         msg::HttpMethod::Trace => Method::TRACE,
     };
 
-    let msg_headers = msg.headers().unwrap();
-    let mut headers = HeaderMap::new();
-    for i in 0..msg_headers.len() {
-        let h = msg_headers.get(i);
-        trace!("header: {} => {}", h.key().unwrap(), h.value().unwrap());
-        headers.insert(
-            HeaderName::from_bytes(h.key().unwrap().as_bytes()).unwrap(),
-            h.value().unwrap().parse().unwrap(),
-        );
-    }
+    let headers = match headers_from_msg(msg.headers()) {
+        Ok(h) => h,
+        Err(e) => return odd_future(e),
+    };
 
     let has_body = msg.has_body();
     trace!("HAS BODY? {}", has_body);
     let req_body = if has_body {
         if raw.data_len > 0 {
             trace!("STATIC BODY!");
-            Body::from(unsafe { slice::from_raw_parts(raw.data_ptr, raw.data_len) }.to_vec())
+            RequestBody::Static(Bytes::from(
+                unsafe { slice::from_raw_parts(raw.data_ptr, raw.data_len) }.to_vec(),
+            ))
         } else {
             trace!("STREAMING BODY");
-            let (sender, recver) = mpsc::unbounded::<Vec<u8>>();
+            let (sender, recver) = stream_channel::channel(&rt.stream_buffer);
             {
                 rt.streams.lock().unwrap().insert(req_id, sender);
             }
-            Body::wrap_stream(recver.map_err(|_| std::sync::mpsc::RecvError {}))
+            RequestBody::Stream(Some(Body::wrap_stream(
+                recver.map_err(|_| std::sync::mpsc::RecvError {}),
+            )))
         }
     } else {
-        Body::empty()
+        RequestBody::Empty
     };
-    // let req_body = Body::empty();
 
-    let mut req = Request::new(req_body);
-    {
-        *req.uri_mut() = http_uri.clone();
-        *req.method_mut() = method;
-        *req.headers_mut() = headers;
-    }
+    let (p, c) = oneshot::channel::<FlyResult<FetchResponse>>();
+
+    let (abort_tx, abort_rx) = oneshot::channel::<()>();
+    rt.fetch_aborts.lock().unwrap().insert(req_id, abort_tx);
+    let abort = Aborted(abort_rx.shared());
 
-    let (p, c) = oneshot::channel::<FlyResult<JsHttpResponse>>();
+    let headers_timeout = match msg.headers_timeout() {
+        0 => Duration::from_millis(rt.fetch_timeouts.headers_ms),
+        ms => Duration::from_millis(u64::from(ms)),
+    };
+    let body_timeout = match msg.body_timeout() {
+        0 => Duration::from_millis(rt.fetch_timeouts.body_ms),
+        ms => Duration::from_millis(u64::from(ms)),
+    };
 
     let rt_name = rt.name.clone();
     let rt_version = rt.version.clone();
-    let method = req.method().clone();
+    let state = FetchState {
+        client,
+        permissions: rt.permissions.clone(),
+        method: method.clone(),
+        uri: http_uri,
+        headers,
+        body: req_body,
+        redirect: msg.redirect(),
+        redirects: 0,
+        retries: u32::from(msg.retries()).min(fetch_policy::MAX_RETRIES),
+        attempt: 0,
+        headers_timeout,
+        rt_name: rt_name.clone(),
+        rt_version: rt_version.clone(),
+    };
 
     rt.spawn(future::lazy(move || {
         let timer = time::Instant::now();
-        HTTP_CLIENT.request(req).then(move |reserr| {
+        let res = send_cached(ptr, state, cache_mode);
+        res.select2(abort.clone()).then(move |reserr| {
             debug!("got http response (or error)");
+            let reserr = match reserr {
+                Ok(Either::A((res, _))) => Ok(res),
+                // aborting only ever fails
+                Ok(Either::B(_)) => unreachable!(),
+                Err(Either::A((err, _))) | Err(Either::B((err, _))) => Err(err),
+            };
             if let Err(err) = reserr {
-                if p.send(Err(err.into())).is_err() {
+                forget_abort(ptr, req_id);
+                if p.send(Err(err)).is_err() {
                     error!("error sending error for http response :/");
                 }
                 return Ok(());
             }
 
-            let res = reserr.unwrap(); // should be safe.
+            let (res, state) = reserr.unwrap(); // should be safe.
 
             FETCH_HEADERS_DURATION
                 .with_label_values(&[
@@ -163,21 +192,33 @@ pub fn op_fetch(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
             let mut stream_rx: Option<JsBody> = None;
             let has_body = !body.is_end_stream();
             if has_body {
-                stream_rx = Some(JsBody::BoxedStream(Box::new(
-                    body.map_err(|e| format!("{}", e).into()).map(move |chunk| {
-                        let bytes = chunk.into_bytes();
-                        DATA_IN_TOTAL
-                            .with_label_values(&[rt_name.as_str(), rt_version.as_str(), "fetch"])
-                            .inc_by(bytes.len() as i64);
-                        bytes.to_vec()
-                    }),
-                )));
+                let body = FetchBody {
+                    ptr,
+                    req_id,
+                    body,
+                    deadline: Delay::new(Instant::now() + body_timeout),
+                    abort,
+                };
+                stream_rx = Some(JsBody::BoxedStream(Box::new(body.map(move |chunk| {
+                    let bytes = chunk.into_bytes();
+                    DATA_IN_TOTAL
+                        .with_label_values(&[rt_name.as_str(), rt_version.as_str(), "fetch"])
+                        .inc_by(bytes.len() as i64);
+                    bytes.to_vec()
+                }))));
+            } else {
+                forget_abort(ptr, req_id);
             }
 
-            if p.send(Ok(JsHttpResponse {
-                headers: parts.headers,
-                status: parts.status,
-                body: stream_rx,
+            if p.send(Ok(FetchResponse {
+                res: JsHttpResponse {
+                    headers: parts.headers,
+                    status: parts.status,
+                    body: stream_rx,
+                    websocket: None,
+                },
+                url: state.uri.to_string(),
+                redirected: state.redirects > 0,
             }))
             .is_err()
             {
@@ -196,32 +237,20 @@ pub fn op_fetch(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
                 format!("err getting response from oneshot: {}", e).as_str(),
             ))
         })
-        .and_then(move |reserr: FlyResult<JsHttpResponse>| {
+        .and_then(move |reserr: FlyResult<FetchResponse>| {
             if let Err(err) = reserr {
                 return Err(err);
             }
 
-            let res = reserr.unwrap();
+            let FetchResponse {
+                res,
+                url,
+                redirected,
+            } = reserr.unwrap();
 
             let builder = &mut FlatBufferBuilder::new();
-            let headers: Vec<_> = res
-                .headers
-                .iter()
-                .map(|(key, value)| {
-                    let key = builder.create_string(key.as_str());
-                    let value = builder.create_string(value.to_str().unwrap());
-                    msg::HttpHeader::create(
-                        builder,
-                        &msg::HttpHeaderArgs {
-                            key: Some(key),
-                            value: Some(value),
-                            ..Default::default()
-                        },
-                    )
-                })
-                .collect();
-
-            let res_headers = builder.create_vector(&headers);
+            let res_headers = create_headers(builder, &res.headers);
+            let url = builder.create_string(&url);
 
             let msg = msg::FetchHttpResponse::create(
                 builder,
@@ -230,6 +259,8 @@ pub fn op_fetch(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
                     headers: Some(res_headers),
                     status: res.status.as_u16(),
                     has_body: res.body.is_some(),
+                    url: Some(url),
+                    redirected,
                     ..Default::default()
                 },
             );
@@ -250,6 +281,567 @@ pub fn op_fetch(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
     Box::new(fut)
 }
 
+struct FetchResponse {
+    res: JsHttpResponse,
+    url: String,
+    redirected: bool,
+}
+
+// A request body, static ones can be sent more than once.
+enum RequestBody {
+    Empty,
+    Static(Bytes),
+    Stream(Option<Body>),
+}
+
+impl RequestBody {
+    fn take(&mut self) -> Body {
+        match *self {
+            RequestBody::Empty => Body::empty(),
+            RequestBody::Static(ref bytes) => Body::from(bytes.clone()),
+            RequestBody::Stream(ref mut body) => body.take().unwrap_or_else(Body::empty),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match *self {
+            RequestBody::Empty => true,
+            _ => false,
+        }
+    }
+
+    fn is_replayable(&self) -> bool {
+        match *self {
+            RequestBody::Stream(_) => false,
+            _ => true,
+        }
+    }
+}
+
+type FetchStep =
+    Box<Future<Item = Loop<(Response<Body>, FetchState), FetchState>, Error = FlyError> + Send>;
+
+// A request as it gets retried and redirected.
+struct FetchState {
+    client: HttpClient,
+    permissions: RuntimePermissions,
+    method: Method,
+    uri: hyper::Uri,
+    headers: HeaderMap,
+    body: RequestBody,
+    redirect: msg::RedirectMode,
+    redirects: usize,
+    retries: u32,
+    attempt: u32,
+    headers_timeout: Duration,
+    rt_name: String,
+    rt_version: String,
+}
+
+impl FetchState {
+    // Resolves to the last response, and the request that got it.
+    fn send(self) -> impl Future<Item = (Response<Body>, FetchState), Error = FlyError> {
+        future::loop_fn(self, |mut state| {
+            let mut req = Request::new(state.body.take());
+            *req.method_mut() = state.method.clone();
+            *req.uri_mut() = state.uri.clone();
+            *req.headers_mut() = state.headers.clone();
+            Timeout::new(state.client.request(req), state.headers_timeout)
+                .map_err(|e| {
+                    if e.is_elapsed() {
+                        return errors::new(ErrorKind::TimedOut, "fetch timed out".to_string());
+                    }
+                    match e.into_inner() {
+                        Some(err) => match EgressError::find(&err) {
+                            Some(egress) => egress.clone().into(),
+                            None => err.into(),
+                        },
+                        None => FlyError::from("fetch timer error".to_string()),
+                    }
+                })
+                .then(move |res| state.next(res))
+        })
+    }
+
+    fn next(mut self, res: FlyResult<Response<Body>>) -> FetchStep {
+        let res = match res {
+            Ok(res) => res,
+            Err(e) => {
+                if fetch_policy::is_retryable_error(&e) && self.can_retry() {
+                    return self.retry("error");
+                }
+                return Box::new(future::err(e));
+            }
+        };
+        if fetch_policy::is_retryable_status(res.status()) && self.can_retry() {
+            return self.retry("status");
+        }
+        if self.redirect == msg::RedirectMode::Manual {
+            return Box::new(future::ok(Loop::Break((res, self))));
+        }
+        match self.follow(&res) {
+            Ok(true) => Box::new(future::ok(Loop::Continue(self))),
+            Ok(false) => Box::new(future::ok(Loop::Break((res, self)))),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+
+    // Points the request at where `res` redirects to, if it does.
+    fn follow(&mut self, res: &Response<Body>) -> FlyResult<bool> {
+        let next = match fetch_policy::redirect(
+            &self.method,
+            &self.uri,
+            &self.headers,
+            res.status(),
+            res.headers(),
+        )? {
+            Some(next) => next,
+            None => return Ok(false),
+        };
+        if self.redirect == msg::RedirectMode::Error {
+            return Err(errors::new(
+                ErrorKind::InvalidData,
+                format!("fetch to {} was redirected", self.uri),
+            ));
+        }
+        if self.redirects >= fetch_policy::MAX_REDIRECTS {
+            return Err(errors::new(
+                ErrorKind::InvalidData,
+                format!("fetch to {} redirected too many times", self.uri),
+            ));
+        }
+        if !next.drop_body && !self.body.is_replayable() {
+            return Err(errors::new(
+                ErrorKind::InvalidData,
+                "can't follow a redirect with a streaming body".to_string(),
+            ));
+        }
+        self.permissions.check_net(&next.uri.to_string())?;
+
+        FETCH_REDIRECTS_TOTAL
+            .with_label_values(&[
+                self.rt_name.as_str(),
+                self.rt_version.as_str(),
+                host_label(&self.uri).as_str(),
+            ])
+            .inc();
+        self.redirects += 1;
+        self.method = next.method;
+        self.uri = next.uri;
+        self.headers = next.headers;
+        if next.drop_body {
+            self.body = RequestBody::Empty;
+        }
+        Ok(true)
+    }
+
+    // The same request, to revalidate a cached response in the background.
+    fn revalidation(&self) -> FetchState {
+        FetchState {
+            client: self.client.clone(),
+            permissions: self.permissions.clone(),
+            method: self.method.clone(),
+            uri: self.uri.clone(),
+            headers: self.headers.clone(),
+            body: RequestBody::Empty,
+            redirect: self.redirect,
+            redirects: 0,
+            retries: self.retries,
+            attempt: 0,
+            headers_timeout: self.headers_timeout,
+            rt_name: self.rt_name.clone(),
+            rt_version: self.rt_version.clone(),
+        }
+    }
+
+    fn can_retry(&self) -> bool {
+        self.retries > 0 && fetch_policy::is_idempotent(&self.method) && self.body.is_replayable()
+    }
+
+    fn retry(mut self, reason: &str) -> FetchStep {
+        FETCH_RETRIES_TOTAL
+            .with_label_values(&[
+                self.rt_name.as_str(),
+                self.rt_version.as_str(),
+                host_label(&self.uri).as_str(),
+                reason,
+            ])
+            .inc();
+        let delay = fetch_policy::backoff(self.attempt);
+        self.retries -= 1;
+        self.attempt += 1;
+        Box::new(
+            Delay::new(Instant::now() + delay)
+                .map_err(|e| FlyError::from(format!("fetch timer error: {}", e)))
+                .map(move |_| Loop::Continue(self)),
+        )
+    }
+}
+
+type FetchFuture = Box<Future<Item = (Response<Body>, FetchState), Error = FlyError> + Send>;
+
+// Sends a request through the runtime's HTTP cache, as its `cache` mode says.
+fn send_cached(ptr: JsRuntime, state: FetchState, mode: msg::HttpCacheMode) -> FetchFuture {
+    if mode == msg::HttpCacheMode::Off {
+        return Box::new(state.send());
+    }
+    if mode == msg::HttpCacheMode::NoStore
+        || !http_cache::is_cacheable_request(&state.method, &state.headers)
+        || !state.body.is_empty()
+    {
+        return Box::new(
+            state
+                .send()
+                .map(|(res, state)| (with_cache_status(res, CacheStatus::Bypass, &state), state)),
+        );
+    }
+
+    let key = http_cache::cache_key(&state.uri.to_string());
+    let lookup: Box<Future<Item = Option<CacheEntry>, Error = FlyError> + Send> =
+        if mode == msg::HttpCacheMode::Reload {
+            Box::new(future::ok(None))
+        } else {
+            // a broken cache shouldn't break fetches
+            Box::new(ptr.to_runtime().cache_store.get(key.clone()).then(|res| {
+                Ok::<_, FlyError>(res.unwrap_or_else(|e| {
+                    warn!("error reading fetch cache: {:?}", e);
+                    None
+                }))
+            }))
+        };
+
+    Box::new(lookup.and_then(move |entry| -> FetchFuture {
+        let cached = entry.and_then(|entry| {
+            let stored = StoredResponse::from_meta(entry.meta.as_ref()?)?;
+            if stored.matches(&state.headers) {
+                Some((stored, entry.stream))
+            } else {
+                None
+            }
+        });
+        let (stored, body) = match cached {
+            Some(c) => c,
+            None if mode == msg::HttpCacheMode::OnlyIfCached => {
+                let mut res = Response::new(Body::empty());
+                *res.status_mut() = StatusCode::GATEWAY_TIMEOUT;
+                let res = with_cache_status(res, CacheStatus::Miss, &state);
+                return Box::new(future::ok((res, state)));
+            }
+            None => return fetch_and_store(ptr, state, key, CacheStatus::Miss),
+        };
+
+        let freshness = match mode {
+            msg::HttpCacheMode::ForceCache | msg::HttpCacheMode::OnlyIfCached => Freshness::Fresh,
+            msg::HttpCacheMode::NoCache => Freshness::Stale,
+            _ => stored.freshness(&state.headers, http_cache::unix_now()),
+        };
+        match freshness {
+            Freshness::Fresh => {
+                let res = cached_response(&stored, body, CacheStatus::Hit, &state);
+                Box::new(future::ok((res, state)))
+            }
+            Freshness::StaleWhileRevalidate => {
+                let revalidation = revalidate(ptr, state.revalidation(), key, stored.clone(), None)
+                    .and_then(|(res, _)| {
+                        res.into_body().map_err(FlyError::from).for_each(|_| Ok(()))
+                    })
+                    .map_err(|e| debug!("error revalidating cached fetch: {}", e));
+                ptr.to_runtime().spawn(revalidation);
+                let res = cached_response(&stored, body, CacheStatus::Stale, &state);
+                Box::new(future::ok((res, state)))
+            }
+            Freshness::Stale => revalidate(ptr, state, key, stored, Some(body)),
+        }
+    }))
+}
+
+// Asks the origin whether a stored response changed, serving `cached` if it didn't.
+fn revalidate(
+    ptr: JsRuntime,
+    mut state: FetchState,
+    key: String,
+    mut stored: StoredResponse,
+    cached: Option<CacheStream>,
+) -> FetchFuture {
+    if !stored.add_validators(&mut state.headers) {
+        return fetch_and_store(ptr, state, key, CacheStatus::Miss);
+    }
+    let request_time = http_cache::unix_now();
+    Box::new(state.send().map(move |(res, state)| {
+        if res.status() != StatusCode::NOT_MODIFIED {
+            let res = store_response(ptr, &state, key, res, request_time);
+            return (with_cache_status(res, CacheStatus::Miss, &state), state);
+        }
+        stored.freshen(res.headers(), request_time, http_cache::unix_now());
+        if let Some(ttl) = stored.ttl(http_cache::unix_now()) {
+            let rt = ptr.to_runtime();
+            rt.spawn(
+                rt.cache_store
+                    .set_meta(key.clone(), stored.to_meta())
+                    .join(rt.cache_store.expire(key, ttl))
+                    .map(|_| ())
+                    .map_err(|e| error!("error updating fetch cache: {:?}", e)),
+            );
+        }
+        match cached {
+            Some(body) => {
+                let res = cached_response(&stored, body, CacheStatus::Revalidated, &state);
+                (res, state)
+            }
+            None => (res, state),
+        }
+    }))
+}
+
+fn fetch_and_store(
+    ptr: JsRuntime,
+    state: FetchState,
+    key: String,
+    status: CacheStatus,
+) -> FetchFuture {
+    let request_time = http_cache::unix_now();
+    Box::new(state.send().map(move |(res, state)| {
+        let res = store_response(ptr, &state, key, res, request_time);
+        (with_cache_status(res, status, &state), state)
+    }))
+}
+
+// Stores a response once its body has been read, if it can be.
+fn store_response(
+    ptr: JsRuntime,
+    state: &FetchState,
+    key: String,
+    res: Response<Body>,
+    request_time: u64,
+) -> Response<Body> {
+    // stored under the URL that was asked for, not the one it redirected to
+    if state.redirects > 0
+        || !http_cache::is_storable(&state.method, &state.headers, res.status(), res.headers())
+    {
+        return res;
+    }
+    let now = http_cache::unix_now();
+    let stored = StoredResponse::new(
+        &state.headers,
+        res.status(),
+        res.headers(),
+        request_time,
+        now,
+    );
+    let ttl = match stored.ttl(now) {
+        Some(ttl) => ttl,
+        None => return res,
+    };
+    let (parts, body) = res.into_parts();
+    if body.is_end_stream() {
+        store(ptr, key, vec![], stored.to_meta(), ttl);
+        return Response::from_parts(parts, body);
+    }
+    let body = StoreBody {
+        ptr,
+        key,
+        meta: stored.to_meta(),
+        ttl,
+        body,
+        buf: Some(vec![]),
+    };
+    Response::from_parts(parts, Body::wrap_stream(body))
+}
+
+fn store(ptr: JsRuntime, key: String, body: Vec<u8>, meta: String, ttl: u32) {
+    let rt = ptr.to_runtime();
+    rt.spawn(
+        rt.cache_store
+            .set(
+                key,
+                Box::new(stream::once(Ok::<_, ()>(body))),
+                CacheSetOptions {
+                    ttl: Some(ttl),
+                    tags: None,
+                    meta: Some(meta),
+                },
+            )
+            .map_err(|e| error!("error storing fetch response: {:?}", e)),
+    );
+}
+
+// A response body that gets stored once it's been read whole.
+struct StoreBody {
+    ptr: JsRuntime,
+    key: String,
+    meta: String,
+    ttl: u32,
+    body: Body,
+    // dropped when too big to store, or when the body fails
+    buf: Option<Vec<u8>>,
+}
+
+impl Stream for StoreBody {
+    type Item = hyper::Chunk;
+    type Error = hyper::Error;
+
+    fn poll(&mut self) -> Poll<Option<hyper::Chunk>, hyper::Error> {
+        let res = self.body.poll();
+        match res {
+            Ok(Async::Ready(Some(ref chunk))) => {
+                if let Some(mut buf) = self.buf.take() {
+                    buf.extend_from_slice(chunk);
+                    if buf.len() <= http_cache::MAX_BODY_SIZE {
+                        self.buf = Some(buf);
+                    }
+                }
+            }
+            Ok(Async::Ready(None)) => {
+                if let Some(buf) = self.buf.take() {
+                    store(self.ptr, self.key.clone(), buf, self.meta.clone(), self.ttl);
+                }
+            }
+            Ok(Async::NotReady) => {}
+            Err(_) => self.buf = None,
+        }
+        res
+    }
+}
+
+fn cached_response(
+    stored: &StoredResponse,
+    body: CacheStream,
+    status: CacheStatus,
+    state: &FetchState,
+) -> Response<Body> {
+    let body = body.map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{:?}", e)));
+    let mut res = Response::new(Body::wrap_stream(body));
+    *res.status_mut() = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    *res.headers_mut() = stored.headers();
+    res.headers_mut().insert(
+        header::AGE,
+        HeaderValue::from(stored.age(http_cache::unix_now())),
+    );
+    with_cache_status(res, status, state)
+}
+
+fn with_cache_status(
+    mut res: Response<Body>,
+    status: CacheStatus,
+    state: &FetchState,
+) -> Response<Body> {
+    FETCH_CACHE_TOTAL
+        .with_label_values(&[
+            state.rt_name.as_str(),
+            state.rt_version.as_str(),
+            status.as_str(),
+        ])
+        .inc();
+    res.headers_mut().insert(
+        http_cache::CACHE_STATUS_HEADER,
+        HeaderValue::from_static(status.as_str()),
+    );
+    res
+}
+
+fn host_label(uri: &hyper::Uri) -> String {
+    let host = uri.host().unwrap_or("unknown");
+    if let Some(port) = uri.port_part() {
+        format!("{}:{}", host, port.as_str())
+    } else {
+        let port = if let Some(scheme) = uri.scheme_part() {
+            if scheme == &Scheme::HTTPS {
+                "443"
+            } else {
+                "80"
+            }
+        } else {
+            "80"
+        };
+        format!("{}:{}", host, port)
+    }
+}
+
+pub fn op_fetch_abort(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let msg = base.msg_as_fetch_abort().unwrap();
+    let req_id = msg.id();
+
+    if let Some(tx) = rt.fetch_aborts.lock().unwrap().remove(&req_id) {
+        let _ = tx.send(());
+    }
+    // stop waiting on the rest of a streaming request body
+    rt.streams.lock().unwrap().remove(&req_id);
+
+    ok_future(None)
+}
+
+// Once a fetch is done there's nothing left to abort.
+fn forget_abort(ptr: JsRuntime, req_id: u32) {
+    ptr.to_runtime()
+        .fetch_aborts
+        .lock()
+        .unwrap()
+        .remove(&req_id);
+}
+
+fn aborted_error() -> FlyError {
+    errors::new(ErrorKind::HttpCanceled, "fetch aborted".to_string())
+}
+
+// Fails once the fetch is aborted, never resolves otherwise.
+#[derive(Clone)]
+struct Aborted(Shared<oneshot::Receiver<()>>);
+
+impl Future for Aborted {
+    type Item = ();
+    type Error = FlyError;
+
+    fn poll(&mut self) -> Poll<(), FlyError> {
+        match self.0.poll() {
+            Ok(Async::Ready(_)) => Err(aborted_error()),
+            // dropped once the fetch is done
+            _ => Ok(Async::NotReady),
+        }
+    }
+}
+
+// A response body that fails once aborted or past its deadline.
+struct FetchBody {
+    ptr: JsRuntime,
+    req_id: u32,
+    body: Body,
+    deadline: Delay,
+    abort: Aborted,
+}
+
+impl FetchBody {
+    fn poll_body(&mut self) -> Poll<Option<hyper::Chunk>, FlyError> {
+        self.abort.poll()?;
+        match self.deadline.poll() {
+            Ok(Async::NotReady) => {}
+            Ok(Async::Ready(_)) => {
+                return Err(errors::new(
+                    ErrorKind::TimedOut,
+                    "fetch body timed out".to_string(),
+                ));
+            }
+            Err(e) => return Err(FlyError::from(format!("fetch timer error: {}", e))),
+        }
+        self.body.poll().map_err(FlyError::from)
+    }
+}
+
+impl Stream for FetchBody {
+    type Item = hyper::Chunk;
+    type Error = FlyError;
+
+    fn poll(&mut self) -> Poll<Option<hyper::Chunk>, FlyError> {
+        let res = self.poll_body();
+        match res {
+            Ok(Async::NotReady) | Ok(Async::Ready(Some(_))) => {}
+            _ => {
+                forget_abort(self.ptr, self.req_id);
+            }
+        }
+        res
+    }
+}
+
 pub fn op_http_response(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
     debug!("handling http response");
     let msg = base.msg_as_http_response().unwrap();
@@ -260,24 +852,17 @@ pub fn op_http_response(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box
         Err(e) => return odd_future(format!("{}", e).into()),
     };
 
-    let mut headers = HeaderMap::new();
-
-    if let Some(msg_headers) = msg.headers() {
-        for i in 0..msg_headers.len() {
-            let h = msg_headers.get(i);
-            headers.insert(
-                HeaderName::from_bytes(h.key().unwrap().as_bytes()).unwrap(),
-                h.value().unwrap().parse().unwrap(),
-            );
-        }
-    }
+    let headers = match headers_from_msg(msg.headers()) {
+        Ok(h) => h,
+        Err(e) => return odd_future(e),
+    };
 
     let mut body: Option<JsBody> = None;
     let has_body = msg.has_body();
     if has_body {
         if raw.data_len == 0 {
             debug!("http response will have a streaming body");
-            let (sender, recver) = mpsc::unbounded::<Vec<u8>>();
+            let (sender, recver) = stream_channel::channel(&rt.stream_buffer);
             {
                 let mut streams = rt.streams.lock().unwrap();
                 streams.insert(req_id, sender);
@@ -291,14 +876,27 @@ pub fn op_http_response(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box
         }
     }
 
-    let mut responses = rt.responses.lock().unwrap();
-    match responses.remove(&req_id) {
+    let mut websocket: Option<JsWebSocket> = None;
+    if msg.websocket() {
+        debug!("http response accepts a websocket");
+        let (sender, outgoing) = mpsc::channel(stream_channel::CHANNEL_CHUNKS);
+        rt.websockets.lock().unwrap().insert(req_id, sender);
+        websocket = Some(JsWebSocket {
+            id: req_id,
+            outgoing,
+        });
+    }
+
+    let sender = rt.responses.lock().unwrap().remove(&req_id);
+    rt.event_answered();
+    match sender {
         Some(sender) => {
             if sender
                 .send(JsHttpResponse {
                     headers: headers,
                     status: status,
                     body: body,
+                    websocket: websocket,
                 })
                 .is_err()
             {
@@ -314,6 +912,9 @@ pub fn op_http_response(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box
 fn file_request(rt: &mut Runtime, cmd_id: u32, url: &str) -> Box<Op> {
     let req_id = get_next_stream_id();
     let path: String = url.chars().skip(7).collect();
+    if let Err(e) = rt.permissions.check_file(&path) {
+        return odd_future(e);
+    }
 
     let ptr = rt.ptr;
 
@@ -12,8 +12,9 @@ use crate::utils::*;
 use libfly::*;
 
 use crate::get_next_stream_id;
+use crate::stream_channel;
 
-use futures::{sync::mpsc, Future, Stream};
+use futures::{Future, Stream};
 use std::{fmt, fmt::Display};
 
 #[derive(Debug)]
@@ -104,7 +105,7 @@ pub fn op_image_transform(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) ->
     let in_id = get_next_stream_id();
     let out_id = get_next_stream_id();
 
-    let (sender, recver) = mpsc::unbounded::<Vec<u8>>();
+    let (sender, recver) = stream_channel::channel(&rt.stream_buffer);
     {
         rt.streams.lock().unwrap().insert(in_id, sender);
     }
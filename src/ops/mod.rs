@@ -7,8 +7,10 @@ pub mod events;
 pub mod fetch;
 pub mod image;
 pub mod modules;
+pub mod native;
 pub mod os;
 pub mod service;
 pub mod source_map;
 pub mod streams;
 pub mod timers;
+pub mod websocket;
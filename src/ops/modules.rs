@@ -1,12 +1,12 @@
 use crate::msg;
 use flatbuffers::FlatBufferBuilder;
 
-use crate::runtime::Runtime;
+use crate::runtime::{log_module_error, Runtime};
 use libfly::*;
 
 use crate::utils::*;
 
-use crate::module_resolver::RefererInfo;
+use crate::module_resolver::{LoadedModule, ModuleError, RefererInfo};
 
 use futures::future;
 
@@ -25,34 +25,84 @@ pub fn op_load_module(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<
         None => None,
     };
 
+    // checked before resolving too, resolvers read files and secrets right away
+    let referer = referer_info.as_ref().map(|r| r.origin_url.clone());
+    let url = match rt
+        .module_resolver_manager
+        .resolve_url(&specifier_url, referer_info.as_ref())
+    {
+        Ok(url) => url,
+        Err(e) => return module_error(rt, cmd_id, e),
+    };
+    if let Err(e) = rt.permissions.check_loaded_module(url.as_str()) {
+        let err = ModuleError::permission_denied(&specifier_url, referer, e);
+        return module_error(rt, cmd_id, err);
+    }
     let module = match rt
         .module_resolver_manager
-        .resolve_module(specifier_url, referer_info)
+        .resolve_module(specifier_url.clone(), referer_info)
     {
         Ok(m) => m,
-        Err(e) => return odd_future(e.into()),
+        Err(e) => return module_error(rt, cmd_id, e),
     };
+    if let Err(e) = rt.permissions.check_loaded_module(&module.origin_url) {
+        let err = ModuleError::permission_denied(&specifier_url, referer, e);
+        return module_error(rt, cmd_id, err);
+    }
 
+    Box::new(future::lazy(move || Ok(load_module_resp(cmd_id, &module))))
+}
+
+// Answered rather than failed, so the loader throws an error carrying `err`'s details
+// like a failed import does.
+fn module_error(rt: &Runtime, cmd_id: u32, err: ModuleError) -> Box<Op> {
+    log_module_error(rt, &err);
     Box::new(future::lazy(move || {
-        let builder = &mut FlatBufferBuilder::new();
-        let origin_url = builder.create_string(&module.origin_url);
-        let source_code = builder.create_string(&module.loaded_source.source);
-
-        let msg = msg::LoadModuleResp::create(
-            builder,
-            &msg::LoadModuleRespArgs {
-                origin_url: Some(origin_url),
-                source_code: Some(source_code),
-            },
-        );
-        Ok(serialize_response(
-            cmd_id,
-            builder,
-            msg::BaseArgs {
-                msg: Some(msg.as_union_value()),
-                msg_type: msg::Any::LoadModuleResp,
-                ..Default::default()
-            },
-        ))
+        Ok(load_module_error_resp(cmd_id, &err))
     }))
 }
+
+pub(crate) fn load_module_resp(cmd_id: u32, module: &LoadedModule) -> Buf {
+    let builder = &mut FlatBufferBuilder::new();
+    let origin_url = builder.create_string(&module.origin_url);
+    let source_code = builder.create_string(&module.loaded_source.source);
+
+    let msg = msg::LoadModuleResp::create(
+        builder,
+        &msg::LoadModuleRespArgs {
+            origin_url: Some(origin_url),
+            source_code: Some(source_code),
+        },
+    );
+    serialize_response(
+        cmd_id,
+        builder,
+        msg::BaseArgs {
+            msg: Some(msg.as_union_value()),
+            msg_type: msg::Any::LoadModuleResp,
+            ..Default::default()
+        },
+    )
+}
+
+pub(crate) fn load_module_error_resp(cmd_id: u32, err: &ModuleError) -> Buf {
+    let builder = &mut FlatBufferBuilder::new();
+    let error = builder.create_string(&serde_json::to_string(err).unwrap());
+
+    let msg = msg::LoadModuleResp::create(
+        builder,
+        &msg::LoadModuleRespArgs {
+            error: Some(error),
+            ..Default::default()
+        },
+    );
+    serialize_response(
+        cmd_id,
+        builder,
+        msg::BaseArgs {
+            msg: Some(msg.as_union_value()),
+            msg_type: msg::Any::LoadModuleResp,
+            ..Default::default()
+        },
+    )
+}
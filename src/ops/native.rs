@@ -0,0 +1,72 @@
+use crate::msg;
+use flatbuffers::FlatBufferBuilder;
+
+use crate::errors::{self, ErrorKind};
+use crate::op_registry::OpPayload;
+use crate::runtime::Runtime;
+use crate::utils::*;
+use libfly::*;
+
+use futures::Future;
+use std::slice;
+
+pub fn op_call(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
+    let cmd_id = base.cmd_id();
+    let msg = base.msg_as_op_call().unwrap();
+
+    let name = msg.name().unwrap_or("");
+    let op = match rt.ops.get(name) {
+        Some(op) => op,
+        None => {
+            return odd_future(errors::new(
+                ErrorKind::NotFound,
+                format!("no op registered as {}", name),
+            ));
+        }
+    };
+
+    let bytes = if raw.data_len > 0 {
+        unsafe { slice::from_raw_parts(raw.data_ptr, raw.data_len) }.to_vec()
+    } else {
+        vec![]
+    };
+    let payload = if msg.json() {
+        match serde_json::from_slice(&bytes) {
+            Ok(v) => OpPayload::Json(v),
+            Err(e) => {
+                return odd_future(errors::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid payload for op {}: {}", name, e),
+                ));
+            }
+        }
+    } else {
+        OpPayload::Binary(bytes)
+    };
+
+    Box::new((*op)(rt, payload).map(move |res| {
+        let (json, bytes) = match res {
+            OpPayload::Binary(b) => (false, b),
+            OpPayload::Json(v) => (true, serde_json::to_vec(&v).unwrap()),
+        };
+
+        let builder = &mut FlatBufferBuilder::new();
+        let payload = builder.create_vector(&bytes);
+        let ready = msg::OpCallReady::create(
+            builder,
+            &msg::OpCallReadyArgs {
+                json,
+                payload: Some(payload),
+            },
+        );
+        serialize_response(
+            cmd_id,
+            builder,
+            msg::BaseArgs {
+                msg: Some(ready.as_union_value()),
+                msg_type: msg::Any::OpCallReady,
+                ..Default::default()
+            },
+        )
+    }))
+}
@@ -70,8 +70,9 @@ pub fn op_service_response(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) ->
     let msg = base.msg_as_service_response().unwrap();
     let req_id = msg.id();
 
-    let mut responses = rt.service_responses.lock().unwrap();
-    match responses.remove(&req_id) {
+    let sender = rt.service_responses.lock().unwrap().remove(&req_id);
+    rt.event_answered();
+    match sender {
         Some(sender) => {
             if let Err(_) = sender.send(JsServiceResponse {
                 success: msg.success(),
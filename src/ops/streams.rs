@@ -1,10 +1,13 @@
 use crate::msg;
 
+use crate::errors::{self, ErrorKind};
 use crate::runtime::Runtime;
+use crate::stream_channel;
 use libfly::*;
 
 use crate::utils::*;
 
+use futures::Future;
 use std::slice;
 
 pub fn op_stream_chunk(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
@@ -13,20 +16,52 @@ pub fn op_stream_chunk(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<
     let stream_id = msg.id();
 
     let mut streams = rt.streams.lock().unwrap();
-    if raw.data_len > 0 {
-        match streams.get_mut(&stream_id) {
-            Some(sender) => {
-                let bytes = unsafe { slice::from_raw_parts(raw.data_ptr, raw.data_len) }.to_vec();
-                match sender.unbounded_send(bytes.to_vec()) {
-                    Err(e) => error!("error sending chunk: {}", e),
-                    _ => debug!("chunk streamed"),
-                }
+    let mut sender = if msg.done() {
+        match streams.remove(&stream_id) {
+            Some(sender) => sender,
+            None => return ok_future(None),
+        }
+    } else {
+        match streams.get(&stream_id) {
+            Some(sender) => sender.clone(),
+            None => {
+                return odd_future(errors::new(
+                    ErrorKind::NotFound,
+                    format!("no stream with id {}", stream_id),
+                ));
             }
-            None => unimplemented!(),
+        }
+    };
+
+    if raw.data_len == 0 {
+        return ok_future(None);
+    }
+    let bytes = unsafe { slice::from_raw_parts(raw.data_ptr, raw.data_len) }.to_vec();
+
+    // Sync chunks can't wait for room without blocking the event loop their
+    // consumer runs on.
+    if base.sync() {
+        return match sender.try_send(bytes) {
+            Ok(_) => ok_future(None),
+            Err(e) => odd_future(e),
         };
     }
-    if msg.done() {
-        streams.remove(&stream_id);
+    Box::new(sender.send(bytes).map(|_| None))
+}
+
+pub fn op_stream_credit(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let msg = base.msg_as_stream_credit().unwrap();
+    let stream_id = msg.id();
+
+    let mut credits = rt.stream_credits.lock().unwrap();
+    if msg.cancel() {
+        if let Some(window) = credits.remove(&stream_id) {
+            window.close();
+        }
+        // a fetch's body is its last part that could be aborted
+        rt.fetch_aborts.lock().unwrap().remove(&stream_id);
+    } else if let Some(window) = credits.get(&stream_id) {
+        stream_channel::credit(window, &rt.stream_buffer, msg.bytes() as usize);
     }
 
     ok_future(None)
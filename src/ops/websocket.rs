@@ -0,0 +1,405 @@
+//! WebSockets JS accepted from upgrade requests. Frames from the client are sent to JS
+//! like response body chunks, no faster than JS credits the socket for what it has read.
+//! Frames JS sends go through the socket's sender in `Runtime::websockets`.
+
+use crate::msg;
+use flatbuffers::{FlatBufferBuilder, UnionWIPOffset, WIPOffset};
+
+use crate::errors::{self, ErrorKind, FlyError};
+use crate::js::JsWebSocket;
+use crate::metrics::*;
+use crate::runtime::{JsRuntime, Runtime};
+use crate::stream_channel::{BufferBudget, CREDIT_WINDOW};
+use crate::utils::*;
+use crate::ws::{self, Message, Role};
+use libfly::*;
+
+use futures::future::Either;
+use futures::sync::mpsc;
+use futures::{future, stream, try_ready, Async, Future, Poll, Sink, Stream};
+use hyper::upgrade::Upgraded;
+use tokio::codec::Framed;
+use tokio::timer::{Interval, Timeout};
+
+use std::io;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const GOING_AWAY: u16 = 1001;
+const PROTOCOL_ERROR: u16 = 1002;
+const NO_STATUS: u16 = 1005;
+const ABNORMAL: u16 = 1006;
+
+// Idle clients are pinged this often, and dropped if they haven't answered by the next ping.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+// How long either end gets to finish the closing handshake.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Close = (u16, String);
+
+pub fn op_ws_message(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
+    let msg = base.msg_as_web_socket_message().unwrap();
+    let id = msg.id();
+
+    let sender = match rt.websockets.lock().unwrap().get(&id) {
+        Some(sender) => sender.clone(),
+        None => {
+            return odd_future(errors::new(
+                ErrorKind::NotFound,
+                format!("no websocket with id {}", id),
+            ));
+        }
+    };
+
+    let data = if raw.data_len == 0 {
+        vec![]
+    } else {
+        unsafe { slice::from_raw_parts(raw.data_ptr, raw.data_len) }.to_vec()
+    };
+    let frame = if msg.binary() {
+        Message::Binary(data)
+    } else {
+        match String::from_utf8(data) {
+            Ok(s) => Message::Text(s),
+            Err(_) => {
+                return odd_future(errors::new(
+                    ErrorKind::InvalidData,
+                    "invalid utf-8 in text message".to_string(),
+                ));
+            }
+        }
+    };
+
+    Box::new(sender.send(frame).map(|_| None).map_err(move |_| {
+        errors::new(
+            ErrorKind::NotConnected,
+            format!("websocket {} is closed", id),
+        )
+    }))
+}
+
+pub fn op_ws_close(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let msg = base.msg_as_web_socket_close().unwrap();
+    let id = msg.id();
+
+    // closing twice, or after the client did, is a no-op
+    let sender = match rt.websockets.lock().unwrap().remove(&id) {
+        Some(sender) => sender,
+        None => return ok_future(None),
+    };
+    let frame = match msg.code() {
+        0 => Message::Close(None),
+        code => Message::Close(Some((code, msg.reason().unwrap_or("").to_string()))),
+    };
+
+    Box::new(sender.send(frame).then(|_| Ok(None)))
+}
+
+/// Runs `socket` over the connection `upgrade` resolves to, until either end closes it. JS
+/// gets an open event once the connection is upgraded, and a close event when it's done.
+pub fn serve<U>(ptr: JsRuntime, socket: JsWebSocket, upgrade: U)
+where
+    U: Future<Item = Upgraded, Error = FlyError> + Send + 'static,
+{
+    let id = socket.id;
+    ptr.to_runtime().spawn(
+        upgrade
+            .then(move |res| match res {
+                Ok(upgraded) => {
+                    send_open(ptr, id);
+                    Either::A(session(ptr, socket, upgraded))
+                }
+                Err(e) => {
+                    debug!("websocket {} not upgraded: {}", id, e);
+                    Either::B(future::ok((ABNORMAL, String::new())))
+                }
+            })
+            .then(move |res| {
+                let (code, reason) = res.unwrap_or_else(|_| (ABNORMAL, String::new()));
+                ptr.to_runtime().websockets.lock().unwrap().remove(&id);
+                send_close(ptr, id, code, &reason);
+                Ok(())
+            }),
+    );
+}
+
+// Resolves to the close code and reason JS is told the socket closed with.
+fn session(
+    ptr: JsRuntime,
+    socket: JsWebSocket,
+    upgraded: Upgraded,
+) -> impl Future<Item = Close, Error = ()> + Send {
+    let rt = ptr.to_runtime();
+    let id = socket.id;
+    let (sink, frames) = Framed::new(upgraded, ws::Codec::new(Role::Server)).split();
+
+    let inbound_data =
+        DATA_IN_TOTAL.with_label_values(&[rt.name.as_str(), rt.version.as_str(), "websocket"]);
+    let outbound_data =
+        DATA_OUT_TOTAL.with_label_values(&[rt.name.as_str(), rt.version.as_str(), "websocket"]);
+
+    let window = BufferBudget::new(CREDIT_WINDOW);
+    rt.stream_credits.lock().unwrap().insert(id, window.clone());
+    let budget = rt.stream_buffer.clone();
+
+    // answers to the client's pings and closes, sent ahead of anything JS queued
+    let (replies, replies_rx) = mpsc::unbounded();
+    let alive = Arc::new(AtomicBool::new(true));
+
+    let reader = {
+        let alive = alive.clone();
+        let window = window.clone();
+        let budget = budget.clone();
+        let error_replies = replies.clone();
+        frames
+            .map_err(move |e| {
+                debug!("websocket {} protocol error: {}", id, e);
+                let _ = error_replies
+                    .unbounded_send(Message::Close(Some((PROTOCOL_ERROR, String::new()))));
+                (PROTOCOL_ERROR, String::new())
+            })
+            .for_each(move |frame| {
+                alive.store(true, Ordering::SeqCst);
+                match frame {
+                    Message::Text(_) | Message::Binary(_) => {
+                        let len = payload_len(&frame);
+                        inbound_data.inc_by(len as i64);
+                        let budget = budget.clone();
+                        // only fails when the runtime is going away
+                        Either::A(
+                            window
+                                .acquire(len)
+                                .and_then(move |_| budget.acquire(len))
+                                .map_err(|_| (GOING_AWAY, String::new()))
+                                .map(move |_| send_message(ptr, id, &frame)),
+                        )
+                    }
+                    Message::Ping(data) => {
+                        let _ = replies.unbounded_send(Message::Pong(data));
+                        Either::B(future::ok(()))
+                    }
+                    Message::Pong(_) => Either::B(future::ok(())),
+                    Message::Close(close) => {
+                        let close = match close {
+                            Some((code, _)) if !ws::is_valid_close_code(code) => {
+                                (PROTOCOL_ERROR, String::new())
+                            }
+                            Some(close) => close,
+                            None => (NO_STATUS, String::new()),
+                        };
+                        let echo = if close.0 == NO_STATUS {
+                            None
+                        } else {
+                            Some((close.0, String::new()))
+                        };
+                        let _ = replies.unbounded_send(Message::Close(echo));
+                        Either::B(future::err(close))
+                    }
+                }
+            })
+            // the client went away without closing
+            .then(|res| match res {
+                Ok(_) => Ok::<_, ()>((ABNORMAL, String::new())),
+                Err(close) => Ok(close),
+            })
+    };
+
+    let pings = Interval::new(Instant::now() + PING_INTERVAL, PING_INTERVAL)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("ping timer: {}", e)))
+        .and_then(move |_| {
+            if alive.swap(false, Ordering::SeqCst) {
+                Ok(Message::Ping(vec![]))
+            } else {
+                Err(io::Error::new(io::ErrorKind::TimedOut, "no pong"))
+            }
+        });
+    // JS dropping its end of the socket closes it
+    let outgoing = socket
+        .outgoing
+        .chain(stream::once(Ok(Message::Close(Some((
+            GOING_AWAY,
+            String::new(),
+        ))))))
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "websocket channel failed"));
+    let replies_rx =
+        replies_rx.map_err(|_| io::Error::new(io::ErrorKind::Other, "websocket channel failed"));
+    let frames_out = UntilClose::new(replies_rx.select(outgoing).select(pings))
+        .inspect(move |frame| outbound_data.inc_by(payload_len(frame) as i64));
+    let writer = sink
+        .send_all(frames_out)
+        .map(|(_, frames)| frames.into_inner().sent.unwrap_or(None));
+
+    reader
+        .select2(writer)
+        .then(move |res| -> Box<Future<Item = Close, Error = ()> + Send> {
+            match res {
+                // the client closed or went away, give the writer time to echo the close
+                Ok(Either::A((close, writer))) => {
+                    Box::new(Timeout::new(writer, CLOSE_TIMEOUT).then(move |_| Ok::<_, ()>(close)))
+                }
+                // we closed, wait for the client's close
+                Ok(Either::B((sent, reader))) => {
+                    let sent = sent.unwrap_or((NO_STATUS, String::new()));
+                    Box::new(
+                        Timeout::new(reader, CLOSE_TIMEOUT)
+                            .then(move |res| Ok::<_, ()>(res.unwrap_or(sent))),
+                    )
+                }
+                Err(Either::A((_, _))) => Box::new(future::ok((ABNORMAL, String::new()))),
+                Err(Either::B((e, _))) => {
+                    debug!("websocket {} failed: {}", id, e);
+                    Box::new(future::ok((ABNORMAL, String::new())))
+                }
+            }
+        })
+        .then(move |res| {
+            ptr.to_runtime().stream_credits.lock().unwrap().remove(&id);
+            // JS stops crediting once the socket is closed
+            budget.release(window.used());
+            res
+        })
+}
+
+// Bytes of a data frame, control frames don't count as data.
+fn payload_len(frame: &Message) -> usize {
+    match *frame {
+        Message::Text(ref s) => s.len(),
+        Message::Binary(ref b) => b.len(),
+        _ => 0,
+    }
+}
+
+// Ends a stream of outgoing frames with the first close frame, nothing may follow it.
+struct UntilClose<S> {
+    inner: S,
+    sent: Option<Option<Close>>,
+}
+
+impl<S> UntilClose<S> {
+    fn new(inner: S) -> Self {
+        UntilClose { inner, sent: None }
+    }
+}
+
+impl<S: Stream<Item = Message>> Stream for UntilClose<S> {
+    type Item = Message;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Message>, S::Error> {
+        if self.sent.is_some() {
+            return Ok(Async::Ready(None));
+        }
+        match try_ready!(self.inner.poll()) {
+            Some(Message::Close(close)) => {
+                self.sent = Some(close.clone());
+                Ok(Async::Ready(Some(Message::Close(close))))
+            }
+            frame => Ok(Async::Ready(frame)),
+        }
+    }
+}
+
+fn send_open(ptr: JsRuntime, id: u32) {
+    let builder = &mut FlatBufferBuilder::new();
+    let msg = msg::WebSocketOpen::create(builder, &msg::WebSocketOpenArgs { id });
+    send_event(
+        ptr,
+        builder,
+        msg::Any::WebSocketOpen,
+        msg.as_union_value(),
+        None,
+    );
+}
+
+fn send_message(ptr: JsRuntime, id: u32, frame: &Message) {
+    let (binary, data) = match *frame {
+        Message::Text(ref s) => (false, s.as_bytes()),
+        Message::Binary(ref b) => (true, b.as_slice()),
+        _ => return,
+    };
+    let builder = &mut FlatBufferBuilder::new();
+    let msg = msg::WebSocketMessage::create(builder, &msg::WebSocketMessageArgs { id, binary });
+    send_event(
+        ptr,
+        builder,
+        msg::Any::WebSocketMessage,
+        msg.as_union_value(),
+        Some(fly_buf {
+            alloc_ptr: ptr::null_mut() as *mut u8,
+            alloc_len: 0,
+            data_ptr: data.as_ptr() as *mut u8,
+            data_len: data.len(),
+        }),
+    );
+}
+
+fn send_close(ptr: JsRuntime, id: u32, code: u16, reason: &str) {
+    let builder = &mut FlatBufferBuilder::new();
+    let reason = builder.create_string(reason);
+    let msg = msg::WebSocketClose::create(
+        builder,
+        &msg::WebSocketCloseArgs {
+            id,
+            code,
+            reason: Some(reason),
+        },
+    );
+    send_event(
+        ptr,
+        builder,
+        msg::Any::WebSocketClose,
+        msg.as_union_value(),
+        None,
+    );
+}
+
+fn send_event(
+    ptr: JsRuntime,
+    builder: &mut FlatBufferBuilder,
+    msg_type: msg::Any,
+    msg: WIPOffset<UnionWIPOffset>,
+    raw: Option<fly_buf>,
+) {
+    ptr.send(
+        fly_buf_from(
+            serialize_response(
+                0,
+                builder,
+                msg::BaseArgs {
+                    msg: Some(msg),
+                    msg_type,
+                    ..Default::default()
+                },
+            )
+            .unwrap(),
+        ),
+        raw,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_until_close() {
+        let frames = stream::iter_ok::<_, ()>(vec![
+            Message::Text("a".to_string()),
+            Message::Close(Some((1000, "bye".to_string()))),
+            Message::Text("b".to_string()),
+        ]);
+        let mut until = UntilClose::new(frames);
+        let sent: Vec<Message> = until.by_ref().collect().wait().unwrap();
+        assert_eq!(
+            sent,
+            vec![
+                Message::Text("a".to_string()),
+                Message::Close(Some((1000, "bye".to_string()))),
+            ]
+        );
+        assert_eq!(until.sent, Some(Some((1000, "bye".to_string()))));
+    }
+}
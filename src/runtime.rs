@@ -1,9 +1,7 @@
 use tokio;
 
-use tokio::runtime::current_thread;
-
 use std::ffi::{CStr, CString};
-use std::sync::{Mutex, Once};
+use std::sync::{Arc, Condvar, Mutex, Once};
 
 use self::fs::File;
 use std::fs;
@@ -12,6 +10,7 @@ use std::io::Read;
 use libfly::*;
 
 use futures::{
+  future::Shared,
   sync::{mpsc, oneshot},
   Future,
 };
@@ -19,9 +18,9 @@ use std::collections::HashMap;
 
 use std::thread;
 
-use std::sync::{Arc, RwLock};
+use std::sync::RwLock;
 
-use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 
 use std::ptr;
 use std::slice;
@@ -37,8 +36,10 @@ use slog::{slog_debug, slog_error, slog_info, slog_o, slog_trace, slog_warn, Log
 
 use crate::acme_store;
 use crate::cache_store;
+use crate::code_cache::{self, CodeCacheStore, DiskCodeCacheStore};
 use crate::data_store;
 use crate::fs_store;
+use crate::http_client::{self, HttpClient};
 use crate::utils::*;
 
 use crate::postgres_data;
@@ -51,14 +52,21 @@ use crate::{disk_fs, redis_fs};
 
 use crate::v8env::{DEV_TOOLS_SOURCE, FLY_SNAPSHOT};
 
+use crate::inspector;
 use crate::runtime_permissions::RuntimePermissions;
+use crate::scheduler::{self, EventLoop};
+use crate::snapshot::AppSnapshot;
+use crate::stream_channel::{self, BufferBudget, StreamSender};
+use crate::watchdog::{self, CpuBudget, WatchedEvent};
+use crate::ws;
 use crate::settings::{
-  AcmeStoreConfig, CacheStore, CacheStoreNotifier, DataStore, FsStore, Settings,
+  AcmeStoreConfig, CacheStore, CacheStoreNotifier, CodeCacheStoreConfig, DataStore, FsStore,
+  FetchTimeouts, HeapLimits, Settings,
 };
 
 use crate::module_resolver::{
-  LoadedModule, LocalDiskModuleResolver, ModuleResolver, ModuleResolverManager, RefererInfo,
-  StandardModuleResolverManager,
+  LoadedModule, LocalDiskModuleResolver, ModuleError, ModuleResolver, ModuleResolverManager,
+  RefererInfo, StandardModuleResolverManager,
 };
 
 use crate::runtime_manager::{RuntimeManagerCallbacks};
@@ -68,7 +76,16 @@ use std::str;
 
 use std::time;
 
-use crate::msg_handler::{DefaultMessageHandler, MessageHandler};
+use crate::msg_handler::{is_io_op, DefaultMessageHandler, MessageHandler};
+use crate::op_registry::OpRegistry;
+
+use crate::metrics::{
+  CODE_CACHE_LOOKUPS_TOTAL, OP_DURATION, OP_ERRORS_TOTAL, RUNTIME_DRAIN_DURATION,
+  RUNTIME_HEAP_LIMIT_RESETS_TOTAL, RUNTIME_REMOVALS_TOTAL, RUNTIME_TERMINATIONS_TOTAL,
+};
+use floating_duration::TimeAsFloat;
+use hyper::{HeaderMap, StatusCode};
+use trust_dns as dns;
 
 #[derive(Debug, Copy, Clone)]
 pub struct JsRuntime(pub *const js_runtime);
@@ -76,7 +93,23 @@ unsafe impl Send for JsRuntime {}
 unsafe impl Sync for JsRuntime {}
 
 impl JsRuntime {
+  // Timers, resolved promises, stream chunks, anything but events.
   pub fn send(&self, buf: fly_buf, raw: Option<fly_buf>) {
+    if !self.send_event(WatchedEvent::Callback, buf, raw) {
+      self.to_runtime().terminated(WatchedEvent::Callback);
+    }
+  }
+
+  // Returns false if javascript was terminated for going over the event's cpu budget.
+  pub fn send_event(&self, event: WatchedEvent, buf: fly_buf, raw: Option<fly_buf>) -> bool {
+    let rt = self.to_runtime();
+    // out of heap, nothing runs in the isolate until the runtime manager replaces it
+    if rt.is_poisoned() {
+      free_fly_buf(buf);
+      rt.fail_pending_events();
+      return true;
+    }
+    let watch = watchdog::arm(*self, self.to_runtime().cpu_budget.for_event(event));
     unsafe {
       js_send(
         self.0,
@@ -87,6 +120,11 @@ impl JsRuntime {
         },
       )
     };
+    let terminated = watch.disarm();
+    if rt.is_poisoned() {
+      rt.heap_limit_reached();
+    }
+    !terminated
   }
 
   pub fn send_error(&self, cmd_id: u32, err: FlyError) {
@@ -104,72 +142,98 @@ pub struct Runtime {
   pub name: String,
   pub version: String,
   pub app_logger: Logger,
-  pub event_loop: Mutex<current_thread::Handle>,
   pub timers: Mutex<HashMap<u32, oneshot::Sender<()>>>,
   pub responses: Mutex<HashMap<u32, oneshot::Sender<JsHttpResponse>>>,
   pub dns_responses: Mutex<HashMap<u32, oneshot::Sender<JsDnsResponse>>>,
   pub service_responses: Mutex<HashMap<u32, oneshot::Sender<JsServiceResponse>>>,
-  pub streams: Mutex<HashMap<u32, mpsc::UnboundedSender<Vec<u8>>>>,
+  // chunks sent from JS, by stream id
+  pub streams: Mutex<HashMap<u32, StreamSender>>,
+  // credit windows of streams sent to JS, by stream id
+  pub stream_credits: Mutex<HashMap<u32, BufferBudget>>,
+  // body bytes buffered across all streams
+  pub stream_buffer: BufferBudget,
+  // frames sent from JS to websockets, by request id
+  pub websockets: Mutex<HashMap<u32, mpsc::Sender<ws::Message>>>,
+  // in-flight fetches JS can abort, by request id
+  pub fetch_aborts: Mutex<HashMap<u32, oneshot::Sender<()>>>,
+  pub fetch_timeouts: FetchTimeouts,
   pub cache_store: Box<cache_store::CacheStore + 'static + Send + Sync>,
   pub data_store: Box<data_store::DataStore + 'static + Send + Sync>,
   pub fs_store: Box<fs_store::FsStore + 'static + Send + Sync>,
   pub acme_store: Option<Box<acme_store::AcmeStore + 'static + Send + Sync>>,
+  // why fetch is unavailable, when settings checked at startup can't be loaded anymore
+  pub http_client: Result<HttpClient, String>,
+  pub code_cache: Option<Box<CodeCacheStore>>,
   pub fetch_events: Option<mpsc::UnboundedSender<JsHttpRequest>>,
   pub resolv_events: Option<mpsc::UnboundedSender<JsDnsRequest>>,
   pub serve_events: Option<mpsc::UnboundedSender<JsServiceRequest>>,
   pub last_event_at: AtomicUsize,
   pub module_resolver_manager: Box<ModuleResolverManager>,
   pub msg_handler: Box<MessageHandler>,
+  pub ops: OpRegistry,
   pub permissions: RuntimePermissions,
+  pub cpu_budget: CpuBudget,
+  pub heap_limits: HeapLimits,
+  // kept alive for as long as the isolate, which may be recreated from it
+  snapshot: Option<AppSnapshot>,
+  // everything evaluated in the isolate, replayed when it has to be recreated
+  sources: Mutex<Vec<(String, String)>>,
+  // set once running out of heap was dealt with, until the isolate is reset
+  heap_limit_handled: AtomicBool,
+  // set by drain, events aren't dispatched anymore
+  draining: AtomicBool,
+  // whether drain had to fail events
+  drain_timed_out: AtomicBool,
+  // notified whenever a pending event is answered, drain waits on it
+  answered_lock: Mutex<()>,
+  answered: Condvar,
+  // pending events, for pools to read without locking the runtime
+  queue_depth: Arc<AtomicUsize>,
   metadata_cache: RwLock<HashMap<i32, Box<LoadedModule>>>,
   pub manager_callbacks: Option<RuntimeManagerCallbacks>,
   uuid: String,
   ready_ch: Option<oneshot::Sender<()>>,
   quit_ch: Option<oneshot::Receiver<()>>,
+  shutdown_ch: Option<oneshot::Sender<()>>,
+  // every future spawned on the event loop is raced against this
+  shutdown_signal: Shared<oneshot::Receiver<()>>,
+  // the event loop shares its thread with other runtimes
+  worker: EventLoop,
 }
 
 static JSINIT: Once = Once::new();
 
-fn init_event_loop(
-  name: String,
-) -> (
-  current_thread::Handle,
-  oneshot::Sender<()>,
-  oneshot::Receiver<()>,
-) {
-  let (c, p) = oneshot::channel::<(
-    current_thread::Handle,
-    oneshot::Sender<()>,
-    oneshot::Receiver<()>,
-  )>();
-  thread::Builder::new()
-    .name(format!("runtime-loop-{}", name))
-    .spawn(move || {
-      let mut el = current_thread::Runtime::new().unwrap();
-      let (txready, rxready) = oneshot::channel::<()>();
-      let (txquit, rxquit) = oneshot::channel::<()>();
-
-      c.send((el.handle(), txready, rxquit)).unwrap();
-
-      // keep it alive at least until all scripts are evaled
-      el.spawn(
-        rxready
-          .map_err(|_| error!("error recving ready signal for runtime"))
-          .and_then(|_| Ok(trace!("ready ch received!"))),
-      );
+/// Initializes v8, once per process.
+pub(crate) fn init_v8(settings: &Settings) {
+  JSINIT.call_once(|| {
+    unsafe { js_init() };
+    if let Some(threads) = settings.event_loop_threads {
+      if let Err(e) = scheduler::set_worker_count(threads) {
+        error!("{}", e);
+      }
+    }
+  });
+}
 
-      match el.run() {
-        Ok(_) => trace!("runtime event loop ran fine"),
-        Err(e) => error!("error running runtime event loop: {}", e),
-      };
-      trace!("Event loop has run its course.");
-      match txquit.send(()) {
-        Ok(_) => trace!("Sent quit () in channel successfully."),
-        Err(_) => error!("error sending quit signal for runtime"),
-      };
-    })
-    .unwrap();
-  p.wait().unwrap()
+// How long shutdown waits for the event loop to wind down once events are drained.
+const EVENT_LOOP_STOP_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+
+fn init_event_loop(
+  shutdown_signal: Shared<oneshot::Receiver<()>>,
+) -> (EventLoop, oneshot::Sender<()>, oneshot::Receiver<()>) {
+  let (worker, rxquit) = scheduler::assign();
+  let (txready, rxready) = oneshot::channel::<()>();
+
+  // keep it alive at least until all scripts are evaled
+  worker.spawn(
+    rxready
+      .map_err(|_| error!("error recving ready signal for runtime"))
+      .and_then(|_| Ok(trace!("ready ch received!")))
+      .select(shutdown_signal.then(|_| Ok::<(), ()>(())))
+      .then(|_| Ok(())),
+  );
+
+  (worker, txready, rxquit)
 }
 
 pub struct RuntimeConfig<'a> {
@@ -179,20 +243,37 @@ pub struct RuntimeConfig<'a> {
   pub module_resolvers: Option<Vec<Box<ModuleResolver>>>,
   pub app_logger: &'a Logger,
   pub msg_handler: Option<Box<MessageHandler>>,
+  // native ops callable from JS with fly.ops.call
+  pub ops: Option<OpRegistry>,
   pub permissions: Option<RuntimePermissions>,
+  pub cpu_budget: Option<CpuBudget>,
+  pub heap_limits: Option<HeapLimits>,
   pub dev_tools: bool,
+  // start from an app snapshot instead of the bare v8env one
+  pub snapshot: Option<AppSnapshot>,
 }
 
 impl Runtime {
   pub fn new(config: RuntimeConfig) -> Box<Runtime> {
-    JSINIT.call_once(|| unsafe { js_init() });
+    init_v8(config.settings);
 
     let rt_name = config.name.unwrap_or("v8".to_string());
     let rt_version = config.version.unwrap_or("0".to_string());
     let app_logger = config
       .app_logger
       .new(slog_o!("app_name" => rt_name.to_owned(), "app_version" => rt_version.to_owned()));
-    let (rthandle, txready, rxquit) = init_event_loop(format!("{}-{}", rt_name, rt_version));
+    let (txshutdown, rxshutdown) = oneshot::channel::<()>();
+    let shutdown_signal = rxshutdown.shared();
+    let (worker, txready, rxquit) = init_event_loop(shutdown_signal.clone());
+    let heap_limits = config
+      .heap_limits
+      .or(config.settings.heap_limits)
+      .unwrap_or_default();
+    let http_client =
+      http_client::new_client(config.settings, &rt_name, &rt_version).map_err(|e| {
+        error!("error creating fetch client for {}: {}", rt_name, e);
+        format!("{}", e)
+      });
     let rt_module_resolvers =
       config.module_resolvers.unwrap_or(vec![
         Box::new(LocalDiskModuleResolver::new(None)) as Box<ModuleResolver>
@@ -203,14 +284,28 @@ impl Runtime {
       name: rt_name,
       version: rt_version,
       app_logger: app_logger,
-      event_loop: Mutex::new(rthandle.clone()),
       ready_ch: Some(txready),
       quit_ch: Some(rxquit),
+      shutdown_ch: Some(txshutdown),
+      shutdown_signal,
+      worker,
       timers: Mutex::new(HashMap::new()),
       responses: Mutex::new(HashMap::new()),
       dns_responses: Mutex::new(HashMap::new()),
       service_responses: Mutex::new(HashMap::new()),
       streams: Mutex::new(HashMap::new()),
+      stream_credits: Mutex::new(HashMap::new()),
+      stream_buffer: BufferBudget::new(
+        config
+          .settings
+          .stream_buffer_limit_mb
+          .unwrap_or(stream_channel::DEFAULT_BUFFER_LIMIT_MB)
+          * 1024
+          * 1024,
+      ),
+      websockets: Mutex::new(HashMap::new()),
+      fetch_aborts: Mutex::new(HashMap::new()),
+      fetch_timeouts: config.settings.fetch_timeouts.unwrap_or_default(),
       // stream_recv: Mutex::new(HashMap::new()),
       fetch_events: None,
       resolv_events: None,
@@ -254,6 +349,13 @@ impl Runtime {
         },
         None => None,
       },
+      http_client,
+      code_cache: match config.settings.code_cache {
+        Some(CodeCacheStoreConfig::Disk(ref conf)) => {
+          Some(Box::new(DiskCodeCacheStore::new(conf.dir.as_str())) as Box<CodeCacheStore>)
+        }
+        None => None,
+      },
       last_event_at: ATOMIC_USIZE_INIT,
       module_resolver_manager: Box::new(StandardModuleResolverManager::new(
         rt_module_resolvers,
@@ -265,24 +367,39 @@ impl Runtime {
       msg_handler: config
         .msg_handler
         .unwrap_or(Box::new(DefaultMessageHandler {})),
+      ops: config.ops.unwrap_or_default(),
       permissions: config.permissions.unwrap_or_default(),
+      cpu_budget: config
+        .cpu_budget
+        .or(config.settings.cpu_budget)
+        .unwrap_or_default(),
+      heap_limits,
+      snapshot: config.snapshot,
+      sources: Mutex::new(vec![]),
+      heap_limit_handled: AtomicBool::new(false),
+      draining: AtomicBool::new(false),
+      drain_timed_out: AtomicBool::new(false),
+      answered_lock: Mutex::new(()),
+      answered: Condvar::new(),
+      queue_depth: Arc::new(AtomicUsize::new(0)),
     });
 
     (*rt).ptr.0 = unsafe {
-      let ptr = js_runtime_new(js_runtime_options {
-        snapshot: *FLY_SNAPSHOT,
+      js_runtime_new(js_runtime_options {
+        snapshot: match rt.snapshot {
+          Some(ref snapshot) => snapshot.as_simple_buf(),
+          None => *FLY_SNAPSHOT,
+        },
         data: rt.as_ref() as *const _ as *mut libc::c_void,
         recv_cb: msg_from_js,
         print_cb: print_from_js,
         resolve_cb: resolve_callback,
-        soft_memory_limit: 128,
-        hard_memory_limit: 256,
-      });
-      let cfilename = CString::new("fly_main.js").unwrap();
-      let cscript = CString::new("flyMain()").unwrap();
-      js_eval(ptr, cfilename.as_ptr(), cscript.as_ptr());
-      ptr
+        soft_memory_limit: heap_limits.soft_mb,
+        hard_memory_limit: heap_limits.hard_mb,
+      })
     };
+    rt.boot();
+    rt.touch();
 
     if config.dev_tools {
       debug!("Loading dev tools");
@@ -294,18 +411,133 @@ impl Runtime {
     rt
   }
 
-  pub fn eval(&self, filename: &str, code: &str) {
+  fn boot(&self) {
+    let cfilename = CString::new("fly_main.js").unwrap();
+    let cscript = CString::new("flyMain()").unwrap();
+    unsafe {
+      js_eval(self.ptr.0, cfilename.as_ptr(), cscript.as_ptr());
+    }
+  }
+
+  // Returns false if evaluation threw.
+  pub fn eval(&self, filename: &str, code: &str) -> bool {
+    self
+      .sources
+      .lock()
+      .unwrap()
+      .push((filename.to_string(), code.to_string()));
+    self.eval_source(filename, code)
+  }
+
+  fn eval_source(&self, filename: &str, code: &str) -> bool {
     debug!("evaluating '{}'", filename);
     let cfilename = CString::new(filename).unwrap();
     let ccode = CString::new(code).unwrap();
     let ptr = self.ptr;
-    unsafe {
-      js_eval(ptr.0, cfilename.as_ptr(), ccode.as_ptr());
-    }
+    // every runtime of an app evaluates the same sources, compiled once per v8 version
+    let ok = match self.code_cache {
+      Some(ref store) => with_code_cache(self, store.as_ref(), filename, code, |cached| {
+        let res = unsafe { js_eval_cached(ptr.0, cfilename.as_ptr(), ccode.as_ptr(), cached) };
+        (res.success, res.cache_rejected, res.code_cache)
+      }),
+      None => unsafe { js_eval(ptr.0, cfilename.as_ptr(), ccode.as_ptr()) },
+    };
     debug!("finished evaluating '{}'", cfilename.to_string_lossy());
+    ok
+  }
+
+  /// Whether the isolate ran out of heap. Nothing runs in it anymore, the runtime manager
+  /// replaces the runtime, or resets it, the next time it's looked up.
+  pub fn is_poisoned(&self) -> bool {
+    unsafe { js_runtime_heap_limit_reached(self.ptr.0) }
   }
 
-  pub fn eval_file(&self, filename: &str) {
+  // The isolate ran out of heap and was terminated. Everything in flight is lost, fail
+  // it rather than have it wait for a replacement.
+  fn heap_limit_reached(&self) {
+    if self.heap_limit_handled.swap(true, Ordering::SeqCst) {
+      return;
+    }
+    slog_error!(
+      self.app_logger,
+      #"runtime",
+      "old space limit of {}MB reached, execution terminated",
+      self.heap_limits.soft_mb;
+      "source" => "v8env"
+    );
+    RUNTIME_HEAP_LIMIT_RESETS_TOTAL
+      .with_label_values(&[self.name.as_str(), self.version.as_str()])
+      .inc();
+
+    self.fail_pending_events();
+    self.close_stream_credits();
+    self.abort_fetches();
+  }
+
+  /// Starts over with a fresh isolate loaded with the same sources, for a poisoned
+  /// runtime the manager has no factory to replace with.
+  pub fn reset(&mut self) {
+    self.heap_limit_reached();
+
+    self.fetch_events.take();
+    self.resolv_events.take();
+    self.serve_events.take();
+
+    self.timers.lock().unwrap().clear();
+    self.streams.lock().unwrap().clear();
+    self.websockets.lock().unwrap().clear();
+    self.metadata_cache.write().unwrap().clear();
+
+    unsafe { js_runtime_reset(self.ptr.0) };
+    self.heap_limit_handled.store(false, Ordering::SeqCst);
+    self.boot();
+
+    let sources = self.sources.lock().unwrap().clone();
+    for (filename, code) in sources.iter() {
+      self.eval_source(filename, code);
+      if self.is_poisoned() {
+        slog_error!(
+          self.app_logger,
+          #"runtime",
+          "heap limit reached while reloading '{}', giving up",
+          filename;
+          "source" => "v8env"
+        );
+        return;
+      }
+    }
+  }
+
+  fn fail_pending_events(&self) {
+    for (_, tx) in self.responses.lock().unwrap().drain() {
+      if tx.send(unavailable_http_response()).is_err() {
+        error!("error sending unavailable http response");
+      }
+    }
+    for (_, tx) in self.dns_responses.lock().unwrap().drain() {
+      if tx.send(servfail_dns_response()).is_err() {
+        error!("error sending servfail dns response");
+      }
+    }
+    for (_, tx) in self.service_responses.lock().unwrap().drain() {
+      if tx.send(failed_service_response()).is_err() {
+        error!("error sending failed service response");
+      }
+    }
+    self.event_answered();
+  }
+
+  // Wakes drain up, called once a pending event was removed. The response maps must not
+  // be locked by the caller.
+  pub(crate) fn event_answered(&self) {
+    self
+      .queue_depth
+      .store(self.pending_events(), Ordering::SeqCst);
+    let _guard = self.answered_lock.lock().unwrap();
+    self.answered.notify_all();
+  }
+
+  pub fn eval_file(&self, filename: &str) -> bool {
     let mut file = File::open(filename).unwrap();
     let mut contents = String::new();
     file.read_to_string(&mut contents).unwrap();
@@ -313,14 +545,37 @@ impl Runtime {
     self.eval(filename, contents.as_str())
   }
 
-  pub fn eval_file_with_dev_tools(&self, filename: &str) {
-    self.eval(filename, &format!("dev.run('{}')", filename));
+  pub fn eval_file_with_dev_tools(&self, filename: &str) -> bool {
+    self.eval(filename, &format!("dev.run('{}')", filename))
   }
 
+  // Collects garbage first, so used_heap_size is what's actually retained.
   pub fn heap_statistics(&self) -> js_heap_stats {
     unsafe { js_runtime_heap_statistics(self.ptr.0) }
   }
 
+  // As they are now, without a garbage collection.
+  pub fn current_heap_statistics(&self) -> js_heap_stats {
+    unsafe { js_runtime_current_heap_statistics(self.ptr.0) }
+  }
+
+  /// Writes a .heapsnapshot of the isolate to `path`.
+  pub fn dump_heap_snapshot(&self, path: &str) -> bool {
+    let cpath = CString::new(path).unwrap();
+    unsafe { js_dump_heap_snapshot(self.ptr.0, cpath.as_ptr()) }
+  }
+
+  pub fn start_cpu_profile(&self, sampling_interval: time::Duration) {
+    let micros = sampling_interval.as_secs() * 1_000_000 + u64::from(sampling_interval.subsec_micros());
+    unsafe { js_cpu_profile_start(self.ptr.0, micros as i32) };
+  }
+
+  /// Stops the profile started with start_cpu_profile and writes it to `path` as a .cpuprofile.
+  pub fn stop_cpu_profile(&self, path: &str) -> bool {
+    let cpath = CString::new(path).unwrap();
+    unsafe { js_cpu_profile_stop(self.ptr.0, cpath.as_ptr()) }
+  }
+
   pub unsafe fn from_raw<'a>(raw: *const js_runtime) -> &'a mut Self {
     let ptr = js_get_data(raw) as *mut _;
     &mut *ptr
@@ -331,6 +586,7 @@ impl Runtime {
       // stop listening to events
       self.fetch_events.take();
       self.resolv_events.take();
+      self.serve_events.take();
     };
 
     match self.timers.lock() {
@@ -342,12 +598,125 @@ impl Runtime {
       Ok(mut streams) => streams.clear(),
       Err(_) => error!("error acquiring lock to clear streams"),
     };
+    match self.websockets.lock() {
+      Ok(mut sockets) => sockets.clear(),
+      Err(_) => error!("error acquiring lock to clear websockets"),
+    };
+    self.close_stream_credits();
+    self.abort_fetches();
+
+    inspector::forget(&self.uuid);
 
     unsafe {
       js_runtime_dispose(self.ptr.0);
     };
   }
 
+  // stops everything waiting on JS to read a stream
+  fn close_stream_credits(&self) {
+    match self.stream_credits.lock() {
+      Ok(mut credits) => {
+        for (_, window) in credits.drain() {
+          window.close();
+        }
+      }
+      Err(_) => error!("error acquiring lock to clear stream credits"),
+    };
+  }
+
+  fn abort_fetches(&self) {
+    match self.fetch_aborts.lock() {
+      Ok(mut aborts) => {
+        for (_, tx) in aborts.drain() {
+          let _ = tx.send(());
+        }
+      }
+      Err(_) => error!("error acquiring lock to abort fetches"),
+    };
+  }
+
+  // Kept up to date with pending_events, readable without any lock. Approximate while
+  // events come and go.
+  pub fn queue_depth(&self) -> Arc<AtomicUsize> {
+    self.queue_depth.clone()
+  }
+
+  pub fn pending_events(&self) -> usize {
+    self.responses.lock().unwrap().len()
+      + self.dns_responses.lock().unwrap().len()
+      + self.service_responses.lock().unwrap().len()
+  }
+
+  // Stops accepting events and waits for the ones in flight to be answered. Anything
+  // still pending when the timeout is reached gets failed. Returns whether it drained.
+  // Only needs shared access, callers shouldn't hold the runtime's write lock meanwhile.
+  pub fn drain(&self, timeout: time::Duration) -> bool {
+    self.draining.store(true, Ordering::SeqCst);
+
+    let deadline = time::Instant::now() + timeout;
+    let mut guard = self.answered_lock.lock().unwrap();
+    while self.pending_events() > 0 {
+      let now = time::Instant::now();
+      if now >= deadline {
+        drop(guard);
+        slog_warn!(
+          self.app_logger,
+          #"runtime",
+          "{} events still pending after {:?}, failing them",
+          self.pending_events(),
+          timeout;
+          "source" => "runtime"
+        );
+        self.drain_timed_out.store(true, Ordering::SeqCst);
+        self.fail_pending_events();
+        return false;
+      }
+      guard = self.answered.wait_timeout(guard, deadline - now).unwrap().0;
+    }
+    !self.drain_timed_out.load(Ordering::SeqCst)
+  }
+
+  // Drains events, stops the event loop thread and frees the isolate. The runtime
+  // can't be used afterwards. Returns whether events drained, here or in an earlier drain.
+  pub fn shutdown(&mut self, timeout: time::Duration) -> bool {
+    let timer = time::Instant::now();
+    let drained = self.drain(timeout);
+    self.fetch_events.take();
+    self.resolv_events.take();
+    self.serve_events.take();
+
+    if let Some(tx) = self.shutdown_ch.take() {
+      if tx.send(()).is_err() {
+        error!("error sending shutdown signal for runtime");
+      }
+    }
+    if self.worker.wait_done(EVENT_LOOP_STOP_TIMEOUT) {
+      self.dispose();
+    } else {
+      // freeing the isolate from under a running future would be worse than leaking it
+      slog_error!(
+        self.app_logger,
+        #"runtime",
+        "event loop still running {:?} after shutdown, leaking the isolate",
+        EVENT_LOOP_STOP_TIMEOUT;
+        "source" => "runtime"
+      );
+    }
+
+    RUNTIME_DRAIN_DURATION
+      .with_label_values(&[self.name.as_str(), self.version.as_str()])
+      .observe(timer.elapsed().as_fractional_secs());
+    RUNTIME_REMOVALS_TOTAL
+      .with_label_values(&[
+        self.name.as_str(),
+        self.version.as_str(),
+        if drained { "drained" } else { "timed_out" },
+      ])
+      .inc();
+
+    drained
+  }
+
   pub fn run(&mut self) -> oneshot::Receiver<()> {
     self.ready_ch.take().unwrap().send(()).unwrap(); //TODO: no unwrap
     self.quit_ch.take().unwrap()
@@ -359,12 +728,11 @@ impl Runtime {
   {
     let n = NEXT_FUTURE_ID.fetch_add(1, Ordering::SeqCst);
     trace!("SPAWNING A FUTURE! id: {}", n);
-    self
-      .event_loop
-      .lock()
-      .unwrap()
-      .spawn(fut.then(move |_| Ok(trace!("SPAWNED FUTURE IS DONE id: {}", n))))
-      .unwrap();
+    self.worker.spawn(
+      fut
+        .select(self.shutdown_signal.clone().then(|_| Ok::<(), ()>(())))
+        .then(move |_| Ok(trace!("SPAWNED FUTURE IS DONE id: {}", n))),
+    );
   }
 
   pub fn dispatch_event(
@@ -372,6 +740,9 @@ impl Runtime {
     id: u32,
     event: JsEvent,
   ) -> Option<Result<EventResponseChannel, EventDispatchError>> {
+    if self.draining.load(Ordering::SeqCst) {
+      return None;
+    }
     let res = match event {
       JsEvent::Fetch(req) => match self.fetch_events {
         None => return None,
@@ -418,13 +789,73 @@ impl Runtime {
       },
     };
 
+    self.touch();
+    self
+      .queue_depth
+      .store(self.pending_events(), Ordering::SeqCst);
+
+    Some(Ok(res))
+  }
+
+  // Marks the runtime as used. Lookups do it too, so it isn't evicted between being looked
+  // up and getting its event.
+  pub(crate) fn touch(&self) {
     if let Ok(epoch) = time::SystemTime::now().duration_since(time::UNIX_EPOCH) {
       self
         .last_event_at
         .store(epoch.as_secs() as usize, Ordering::SeqCst);
     }
+  }
 
-    Some(Ok(res))
+  // Time since the last dispatched event, or since creation if there hasn't been any.
+  pub fn idle_for(&self) -> time::Duration {
+    let last = self.last_event_at.load(Ordering::SeqCst) as u64;
+    match time::SystemTime::now().duration_since(time::UNIX_EPOCH) {
+      Ok(epoch) => time::Duration::from_secs(epoch.as_secs().saturating_sub(last)),
+      Err(_) => time::Duration::from_secs(0),
+    }
+  }
+
+  // Called once javascript was terminated by the watchdog.
+  pub fn terminated(&self, event: WatchedEvent) {
+    slog_error!(
+      self.app_logger,
+      #"runtime",
+      "javascript exceeded its {} cpu budget of {:?}, execution terminated",
+      event.as_str(),
+      self.cpu_budget.for_event(event);
+      "source" => "watchdog"
+    );
+    RUNTIME_TERMINATIONS_TOTAL
+      .with_label_values(&[self.name.as_str(), self.version.as_str(), event.as_str()])
+      .inc();
+  }
+
+  // Called once javascript was terminated by the watchdog while handling an event,
+  // the app will never respond to it so we answer on its behalf.
+  pub fn event_terminated(&self, event: WatchedEvent, id: u32) {
+    self.terminated(event);
+
+    let sent = match event {
+      WatchedEvent::Fetch => match self.responses.lock().unwrap().remove(&id) {
+        Some(tx) => tx.send(unavailable_http_response()).is_ok(),
+        None => true,
+      },
+      WatchedEvent::Resolv => match self.dns_responses.lock().unwrap().remove(&id) {
+        Some(tx) => tx.send(servfail_dns_response()).is_ok(),
+        None => true,
+      },
+      WatchedEvent::Serve => match self.service_responses.lock().unwrap().remove(&id) {
+        Some(tx) => tx.send(failed_service_response()).is_ok(),
+        None => true,
+      },
+      // nothing waits on callbacks
+      WatchedEvent::Callback => true,
+    };
+    self.event_answered();
+    if !sent {
+      error!("error sending response for terminated {} event", event.as_str());
+    }
   }
 
   pub fn get_module_metadata(&self, hash: &i32) -> Option<Box<LoadedModule>> {
@@ -450,6 +881,38 @@ impl Runtime {
   pub fn get_uuid(&self) -> String {
     return self.uuid.clone();
   }
+
+  pub(crate) fn set_uuid(&mut self, uuid: String) {
+    self.uuid = uuid;
+  }
+}
+
+fn unavailable_http_response() -> JsHttpResponse {
+  JsHttpResponse {
+    headers: HeaderMap::new(),
+    status: StatusCode::SERVICE_UNAVAILABLE,
+    body: None,
+    websocket: None,
+  }
+}
+
+fn servfail_dns_response() -> JsDnsResponse {
+  JsDnsResponse {
+    op_code: dns::op::OpCode::Query,
+    message_type: dns::op::MessageType::Response,
+    response_code: dns::op::ResponseCode::ServFail,
+    answers: vec![],
+    queries: vec![],
+    authoritative: false,
+    truncated: false,
+  }
+}
+
+fn failed_service_response() -> JsServiceResponse {
+  JsServiceResponse {
+    success: false,
+    data: Some("execution terminated".to_string()),
+  }
 }
 
 lazy_static! {
@@ -471,6 +934,15 @@ lazy_static! {
   };
 }
 
+// Native ops are told apart by their registered name, as in `OpCall:resize`. Names that
+// aren't registered share `OpCall`, JS can't grow the label set.
+fn op_metric_label(rt: &Runtime, base: &msg::Base, op_name: &'static str) -> String {
+  match base.msg_as_op_call().and_then(|msg| msg.name()) {
+    Some(name) if rt.ops.get(name).is_some() => format!("{}:{}", op_name, name),
+    _ => op_name.to_string(),
+  }
+}
+
 pub extern "C" fn msg_from_js(raw: *const js_runtime, buf: fly_buf, raw_buf: fly_buf) {
   let bytes = unsafe { slice::from_raw_parts(buf.data_ptr, buf.data_len) };
   let base = msg::get_root_as_base(bytes);
@@ -479,17 +951,42 @@ pub extern "C" fn msg_from_js(raw: *const js_runtime, buf: fly_buf, raw_buf: fly
 
   let msg_type = base.msg_type();
   let cmd_id = base.cmd_id();
+  let op_name = msg::enum_name_any(msg_type);
+
+  let timer = time::Instant::now();
+  if base.sync() && is_io_op(msg_type) {
+    let err = FlyError::from(format!("{} can only be sent asynchronously", op_name));
+    error!("error in {:?}: {}", msg_type, err);
+    if let Some(box_u8) = build_error(cmd_id, err) {
+      unsafe { js_set_response(ptr.0, fly_buf_from(box_u8)) }
+    }
+    return;
+  }
+  let mut op = rt.msg_handler.handle_msg(ptr.to_runtime(), &base, raw_buf);
+  if let Some(hook) = rt.msg_handler.op_hook() {
+    op = hook.wrap(rt, op_name, op);
+  }
 
-  let fut = rt
-    .msg_handler
-    .handle_msg(ptr.to_runtime(), &base, raw_buf)
-    .or_else(move |err| {
-      error!("error in {:?}: {:?}", msg_type, err);
-      Ok(build_error(cmd_id, err))
-    });
+  let rt_name = rt.name.clone();
+  let rt_version = rt.version.clone();
+  let op_label = op_metric_label(rt, &base, op_name);
+  let fut = op.then(move |res| {
+    let labels = [rt_name.as_str(), rt_version.as_str(), op_label.as_str()];
+    OP_DURATION
+      .with_label_values(&labels)
+      .observe(timer.elapsed().as_fractional_secs());
+    match res {
+      Ok(buf) => Ok(buf),
+      Err(err) => {
+        OP_ERRORS_TOTAL.with_label_values(&labels).inc();
+        error!("error in {:?}: {:?}", msg_type, err);
+        Ok(build_error(cmd_id, err))
+      }
+    }
+  });
 
   if base.sync() {
-    // Execute future synchronously.
+    // Execute future synchronously, it doesn't wait on anything but this runtime.
     let maybe_box_u8 = fut.wait().unwrap();
     match maybe_box_u8 {
       None => {}
@@ -565,45 +1062,182 @@ pub unsafe extern "C" fn resolve_callback(
   let referer_loaded_module = match rt.get_module_metadata(&referer_identity_hash) {
     Some(v) => v,
     None => {
-      error!("Failed to find module hash in metadata cache! Exiting.");
-      std::process::exit(1);
+      return throw_module_error(
+        rt,
+        ModuleError {
+          code: "ERR_MODULE_NOT_FOUND",
+          message: format!("Could not resolve {}: unknown referer module", specifier_str),
+          specifier: specifier_str,
+          referer: None,
+          attempts: vec![],
+          diagnostics: None,
+        },
+      );
     }
   };
+  let referer_origin_url = referer_loaded_module.origin_url.clone();
+  let referer_info = RefererInfo {
+    origin_url: referer_loaded_module.origin_url,
+    is_wasm: Some(referer_loaded_module.loaded_source.is_wasm),
+    source_code: Some(referer_loaded_module.loaded_source.source),
+    indentifier_hash: Some(referer_identity_hash),
+  };
 
-  let loaded_module = match rt.module_resolver_manager.resolve_module(
-    specifier_str,
-    Some(RefererInfo {
-      origin_url: referer_loaded_module.origin_url,
-      is_wasm: Some(referer_loaded_module.loaded_source.is_wasm),
-      source_code: Some(referer_loaded_module.loaded_source.source),
-      indentifier_hash: Some(referer_identity_hash),
-    }),
-  ) {
+  // checked before any resolver runs, the secrets one reads them just resolving
+  let url = match rt
+    .module_resolver_manager
+    .resolve_url(&specifier_str, Some(&referer_info))
+  {
     Ok(v) => v,
-    Err(e) => {
-      error!("Failed to resolve and load module! Exiting. {}", e);
-      std::process::exit(1);
-    }
+    Err(e) => return throw_module_error(rt, e),
+  };
+  if let Err(e) = rt.permissions.check_module(url.as_str()) {
+    return throw_module_error(
+      rt,
+      ModuleError::permission_denied(&specifier_str, Some(referer_origin_url), e),
+    );
+  }
+
+  let loaded_module = match rt
+    .module_resolver_manager
+    .resolve_module(specifier_str.clone(), Some(referer_info))
+  {
+    Ok(v) => v,
+    Err(e) => return throw_module_error(rt, e),
   };
+  if let Err(e) = rt.permissions.check_module(&loaded_module.origin_url) {
+    return throw_module_error(
+      rt,
+      ModuleError::permission_denied(&specifier_str, Some(referer_origin_url), e),
+    );
+  }
 
+  // the module data keeps pointers into these, they have to outlive the compilation
+  let origin_url = CString::new(loaded_module.origin_url.as_str()).unwrap();
+  let source_map_url = CString::new("").unwrap();
+  let source_code = CString::new(loaded_module.loaded_source.source.as_str()).unwrap();
   let module_data = js_module_data {
-    origin_url: CString::new(loaded_module.origin_url).unwrap().as_ptr(),
-    source_map_url: CString::new("").unwrap().as_ptr(),
+    origin_url: origin_url.as_ptr(),
+    source_map_url: source_map_url.as_ptr(),
     is_wasm: loaded_module.loaded_source.is_wasm,
     source_code: fly_simple_buf {
-      ptr: CString::new(loaded_module.loaded_source.source.as_str())
-        .unwrap()
-        .as_ptr(),
+      ptr: source_code.as_ptr(),
       len: loaded_module.loaded_source.source.len() as i32,
     },
   };
 
-  let compile_result = js_compile_module(raw, module_data);
+  let compile = |cached| {
+    let res = js_compile_module(raw, module_data, cached);
+    let (rejected, produced) = (res.cache_rejected, res.code_cache);
+    (res, rejected, produced)
+  };
+  let compile_result = match rt.code_cache {
+    Some(ref store) => with_code_cache(
+      rt,
+      store.as_ref(),
+      &loaded_module.origin_url,
+      &loaded_module.loaded_source.source,
+      compile,
+    ),
+    None => {
+      let (res, _, produced) = compile(fly_simple_buf {
+        ptr: ptr::null(),
+        len: 0,
+      });
+      if !produced.ptr.is_null() {
+        js_code_cache_free(produced);
+      }
+      res
+    }
+  };
 
   if compile_result.success {
-    return compile_result.compiled_module;
+    compile_result.compiled_module
   } else {
-    error!("Module compile failed! Exiting.");
-    std::process::exit(1);
+    let diagnostics = CStr::from_ptr(js_runtime_last_exception(raw))
+      .to_string_lossy()
+      .into_owned();
+    throw_module_error(
+      rt,
+      ModuleError::compile(&specifier_str, Some(referer_origin_url), diagnostics),
+    )
+  }
+}
+
+// Compiles with the data cached for `origin_url` and `source`, and stores what v8 produced
+// when there was none or it was rejected. `compile` returns what it compiled, whether v8
+// rejected the cached data, and the data v8 produced.
+fn with_code_cache<T, F>(
+  rt: &Runtime,
+  store: &CodeCacheStore,
+  origin_url: &str,
+  source: &str,
+  compile: F,
+) -> T
+where
+  F: FnOnce(fly_simple_buf) -> (T, bool, fly_simple_buf),
+{
+  let key = code_cache::cache_key(origin_url, source);
+  let cached = store.get(&key);
+  let cached_buf = match cached {
+    Some(ref data) => fly_simple_buf {
+      ptr: data.as_ptr() as *const libc::c_char,
+      len: data.len() as i32,
+    },
+    None => fly_simple_buf {
+      ptr: ptr::null(),
+      len: 0,
+    },
+  };
+
+  let (compiled, rejected, produced) = compile(cached_buf);
+
+  let result = if cached.is_none() {
+    "miss"
+  } else if rejected {
+    "rejected"
+  } else {
+    "hit"
+  };
+  CODE_CACHE_LOOKUPS_TOTAL
+    .with_label_values(&[rt.name.as_str(), rt.version.as_str(), result])
+    .inc();
+  if !produced.ptr.is_null() {
+    let data = unsafe { slice::from_raw_parts(produced.ptr as *const u8, produced.len as usize) };
+    store.set(&key, data);
+    unsafe { js_code_cache_free(produced) };
+  }
+  compiled
+}
+
+// Logs `err` and throws it into the import that failed, returns the empty module v8
+// expects back alongside the exception.
+/// Logs why a module couldn't be imported to the app's log.
+pub(crate) fn log_module_error(rt: &Runtime, err: &ModuleError) {
+  slog_error!(rt.app_logger, #"runtime", "{}", err;
+    "source" => "module",
+    "code" => err.code,
+    "specifier" => &err.specifier
+  );
+}
+
+unsafe fn throw_module_error(rt: &Runtime, err: ModuleError) -> js_compiled_module {
+  log_module_error(rt, &err);
+  let message = CString::new(err.message.replace('\0', "")).unwrap();
+  // json escapes any NUL in there
+  let details = CString::new(serde_json::to_string(&err).unwrap()).unwrap();
+  js_throw_module_error(rt.ptr.0, message.as_ptr(), details.as_ptr());
+  js_compiled_module {
+    hash: 0,
+    data: js_module_data {
+      origin_url: ptr::null(),
+      source_map_url: ptr::null(),
+      is_wasm: false,
+      source_code: fly_simple_buf {
+        ptr: ptr::null(),
+        len: 0,
+      },
+    },
+    ptr: ptr::null_mut(),
   }
 }
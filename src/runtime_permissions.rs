@@ -1,26 +1,509 @@
-use crate::errors::{permission_denied, FlyResult};
+use crate::errors::{self, ErrorKind, FlyError, FlyResult};
+use std::path::{Component, Path};
 
-#[derive(Debug)]
+/// What a runtime's code is allowed to do. The operator sets these, apps can only narrow
+/// them from their config. The default allows everything but `os`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct RuntimePermissions {
   pub allow_os: bool,
+  pub net: NetPermissions,
+  pub files: FilePermissions,
+  /// Importing from the `secrets:` resolver.
+  pub allow_secrets: bool,
+  pub cache: StorePermissions,
+  pub data: StorePermissions,
+}
+
+/// The `permissions` an app's config can set. There's nothing here that widens what the
+/// operator allows, an app asking for `allow_os` is ignored.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct AppPermissions {
+  net: NetPermissions,
+  files: FilePermissions,
+  allow_secrets: bool,
+  cache: StorePermissions,
+  data: StorePermissions,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NetPermissions {
+  /// Hosts `fetch` can reach, as `host`, `host:port`, `*.example.com` or `*`.
+  /// Any host when unset.
+  pub allow_hosts: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FilePermissions {
+  /// `file://` fetches from the `FsStore`, and local files loaded with `loadModule`.
+  pub allow: bool,
+  /// Only paths under this prefix can be fetched or loaded.
+  pub prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StorePermissions {
+  pub read: bool,
+  pub write: bool,
 }
 
 impl RuntimePermissions {
   pub fn new(allow_os: bool) -> Self {
-    Self { allow_os }
+    Self {
+      allow_os,
+      ..Default::default()
+    }
+  }
+
+  /// `base` narrowed by the `permissions` key of an app's config, if it has one.
+  pub fn from_app_config(config: &serde_json::Value, base: &RuntimePermissions) -> FlyResult<Self> {
+    match config.get("permissions") {
+      Some(v) => serde_json::from_value(v.clone())
+        .map(|app| base.narrowed(app))
+        .map_err(|e| {
+          errors::new(
+            ErrorKind::InvalidInput,
+            format!("invalid permissions in app config: {}", e),
+          )
+        }),
+      None => Ok(base.clone()),
+    }
+  }
+
+  // Only what both the operator and the app allow.
+  fn narrowed(&self, app: AppPermissions) -> Self {
+    let (allow_files, prefix) = match (&self.files.prefix, app.files.prefix) {
+      (None, prefix) => (true, prefix),
+      (Some(base), None) => (true, Some(base.clone())),
+      (Some(base), Some(prefix)) => {
+        if path_within(&prefix, base) {
+          (true, Some(prefix))
+        } else if path_within(base, &prefix) {
+          (true, Some(base.clone()))
+        } else {
+          // disjoint, nothing is in both
+          (false, Some(base.clone()))
+        }
+      }
+    };
+    let allow_hosts = match (&self.net.allow_hosts, app.net.allow_hosts) {
+      (None, hosts) => hosts,
+      (Some(base), None) => Some(base.clone()),
+      (Some(base), Some(hosts)) => Some(
+        hosts
+          .into_iter()
+          .filter(|h| base.iter().any(|b| pattern_covers(b, h)))
+          .collect(),
+      ),
+    };
+    RuntimePermissions {
+      allow_os: self.allow_os,
+      net: NetPermissions { allow_hosts },
+      files: FilePermissions {
+        allow: self.files.allow && app.files.allow && allow_files,
+        prefix,
+      },
+      allow_secrets: self.allow_secrets && app.allow_secrets,
+      cache: self.cache.narrowed(&app.cache),
+      data: self.data.narrowed(&app.data),
+    }
   }
 
   pub fn check_os(&self) -> FlyResult<()> {
     if self.allow_os {
       Ok(())
     } else {
-      Err(permission_denied())
+      Err(denied("os access".to_string()))
+    }
+  }
+
+  pub fn check_net(&self, url: &str) -> FlyResult<()> {
+    let patterns = match self.net.allow_hosts {
+      Some(ref p) => p,
+      None => return Ok(()),
+    };
+    let url = url::Url::parse(url).map_err(|e| {
+      errors::new(
+        ErrorKind::InvalidInput,
+        format!("invalid url {}: {}", url, e),
+      )
+    })?;
+    let host = url.host_str().unwrap_or("");
+    let port = url.port_or_known_default();
+    if patterns.iter().any(|p| host_matches(p, host, port)) {
+      Ok(())
+    } else {
+      Err(denied(match port {
+        Some(port) => format!("network access to {}:{}", host, port),
+        None => format!("network access to {}", host),
+      }))
+    }
+  }
+
+  pub fn check_file(&self, path: &str) -> FlyResult<()> {
+    if !self.files.allow {
+      return Err(denied("file access".to_string()));
+    }
+    if let Some(ref prefix) = self.files.prefix {
+      if !path_within(path, prefix) {
+        return Err(denied(format!("file access to {}", path)));
+      }
+    }
+    Ok(())
+  }
+
+  pub fn check_secrets(&self) -> FlyResult<()> {
+    if self.allow_secrets {
+      Ok(())
+    } else {
+      Err(denied("secrets access".to_string()))
+    }
+  }
+
+  /// Checks a module can be imported from `url`. Done with the url the specifier resolves
+  /// to before any resolver runs, and again with where the module was loaded from.
+  pub fn check_module(&self, url: &str) -> FlyResult<()> {
+    if url.starts_with("secrets:") {
+      self.check_secrets()
+    } else {
+      Ok(())
     }
   }
+
+  /// Checks a module the app loads itself with `loadModule`, local files need file access
+  /// like `file://` fetches do.
+  pub fn check_loaded_module(&self, url: &str) -> FlyResult<()> {
+    self.check_module(url)?;
+    match url::Url::parse(url) {
+      Ok(ref parsed) if parsed.scheme() == "file" => match parsed.to_file_path() {
+        Ok(path) => self.check_file(&path.to_string_lossy()),
+        Err(_) => Err(denied(format!("file access to {}", url))),
+      },
+      _ => Ok(()),
+    }
+  }
+
+  pub fn check_cache_read(&self) -> FlyResult<()> {
+    check_store("cache read", self.cache.read)
+  }
+
+  pub fn check_cache_write(&self) -> FlyResult<()> {
+    check_store("cache write", self.cache.write)
+  }
+
+  pub fn check_data_read(&self) -> FlyResult<()> {
+    check_store("data read", self.data.read)
+  }
+
+  pub fn check_data_write(&self) -> FlyResult<()> {
+    check_store("data write", self.data.write)
+  }
 }
 
 impl Default for RuntimePermissions {
   fn default() -> Self {
-    RuntimePermissions { allow_os: false }
+    RuntimePermissions {
+      allow_os: false,
+      net: NetPermissions::default(),
+      files: FilePermissions::default(),
+      allow_secrets: true,
+      cache: StorePermissions::default(),
+      data: StorePermissions::default(),
+    }
+  }
+}
+
+impl Default for AppPermissions {
+  fn default() -> Self {
+    let allowed = RuntimePermissions::default();
+    AppPermissions {
+      net: allowed.net,
+      files: allowed.files,
+      allow_secrets: allowed.allow_secrets,
+      cache: allowed.cache,
+      data: allowed.data,
+    }
+  }
+}
+
+impl StorePermissions {
+  fn narrowed(&self, app: &StorePermissions) -> Self {
+    StorePermissions {
+      read: self.read && app.read,
+      write: self.write && app.write,
+    }
+  }
+}
+
+impl Default for NetPermissions {
+  fn default() -> Self {
+    NetPermissions { allow_hosts: None }
+  }
+}
+
+impl Default for FilePermissions {
+  fn default() -> Self {
+    FilePermissions {
+      allow: true,
+      prefix: None,
+    }
+  }
+}
+
+impl Default for StorePermissions {
+  fn default() -> Self {
+    StorePermissions {
+      read: true,
+      write: true,
+    }
+  }
+}
+
+fn denied(what: String) -> FlyError {
+  errors::new(
+    ErrorKind::PermissionDenied,
+    format!("permission denied: {}", what),
+  )
+}
+
+fn check_store(what: &str, allowed: bool) -> FlyResult<()> {
+  if allowed {
+    Ok(())
+  } else {
+    Err(denied(what.to_string()))
+  }
+}
+
+// Whether `path` is `prefix` or under it, comparing whole components so `/public` doesn't
+// cover `/publicfoo`. Paths climbing out with `..` are never within anything.
+fn path_within(path: &str, prefix: &str) -> bool {
+  let path = Path::new(path);
+  !path.components().any(|c| c == Component::ParentDir) && path.starts_with(prefix)
+}
+
+// A host pattern's host and port, None if the port isn't a number.
+fn split_pattern(pattern: &str) -> Option<(&str, Option<u16>)> {
+  match pattern.rfind(':') {
+    Some(i) => match pattern[i + 1..].parse::<u16>() {
+      Ok(p) => Some((&pattern[..i], Some(p))),
+      Err(_) => None,
+    },
+    None => Some((pattern, None)),
+  }
+}
+
+// Whether every host `inner` matches is also matched by `outer`.
+fn pattern_covers(outer: &str, inner: &str) -> bool {
+  if outer == "*" {
+    return true;
+  }
+  let ((outer_host, outer_port), (inner_host, inner_port)) =
+    match (split_pattern(outer), split_pattern(inner)) {
+      (Some(o), Some(i)) => (o, i),
+      _ => return false,
+    };
+  if outer_port.is_some() && outer_port != inner_port {
+    return false;
+  }
+  let outer_host = outer_host.to_lowercase();
+  let inner_host = inner_host.to_lowercase();
+  if outer_host.starts_with("*.") {
+    inner_host.ends_with(&outer_host[1..])
+  } else {
+    inner_host == outer_host
+  }
+}
+
+fn host_matches(pattern: &str, host: &str, port: Option<u16>) -> bool {
+  if pattern == "*" {
+    return true;
+  }
+  let (pattern_host, pattern_port) = match split_pattern(pattern) {
+    Some(p) => p,
+    None => return false,
+  };
+  if pattern_port.is_some() && pattern_port != port {
+    return false;
+  }
+  let host = host.to_lowercase();
+  let pattern_host = pattern_host.to_lowercase();
+  if pattern_host.starts_with("*.") {
+    host.ends_with(&pattern_host[1..])
+  } else {
+    host == pattern_host
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_host_matches() {
+    assert!(host_matches("*", "example.com", Some(443)));
+    assert!(host_matches("example.com", "example.com", Some(443)));
+    assert!(host_matches("Example.com", "example.com", Some(80)));
+    assert!(host_matches("example.com:443", "example.com", Some(443)));
+    assert!(!host_matches("example.com:8080", "example.com", Some(443)));
+    assert!(host_matches("*.example.com", "api.example.com", Some(443)));
+    assert!(!host_matches("*.example.com", "example.com", Some(443)));
+    assert!(!host_matches("*.example.com", "badexample.com", Some(443)));
+    assert!(!host_matches(
+      "example.com",
+      "example.com.evil.net",
+      Some(443)
+    ));
+  }
+
+  #[test]
+  fn test_check_net() {
+    let mut perms = RuntimePermissions::default();
+    assert!(perms.check_net("https://anywhere.net/").is_ok());
+
+    perms.net.allow_hosts = Some(vec![
+      "api.example.com".to_string(),
+      "localhost:8080".to_string(),
+    ]);
+    assert!(perms.check_net("https://api.example.com/v1").is_ok());
+    assert!(perms.check_net("http://localhost:8080/").is_ok());
+    assert!(perms.check_net("http://localhost:9000/").is_err());
+    assert!(perms.check_net("https://anywhere.net/").is_err());
+    // name servers of dns queries
+    assert!(perms.check_net("dns://8.8.8.8:53").is_err());
+  }
+
+  #[test]
+  fn test_check_loaded_module() {
+    let mut perms = RuntimePermissions::default();
+    assert!(perms.check_loaded_module("file:///app/lib.js").is_ok());
+
+    perms.files.prefix = Some("/app".to_string());
+    assert!(perms.check_loaded_module("file:///app/lib.js").is_ok());
+    assert!(perms.check_loaded_module("file:///etc/passwd").is_err());
+    assert!(perms
+      .check_loaded_module("file:///app/%2e%2e/etc/passwd")
+      .is_err());
+    assert!(perms
+      .check_loaded_module("https://example.com/lib.js")
+      .is_ok());
+
+    perms.allow_secrets = false;
+    assert!(perms.check_loaded_module("secrets:///key").is_err());
+    perms.files.allow = false;
+    assert!(perms.check_loaded_module("file:///app/lib.js").is_err());
+  }
+
+  #[test]
+  fn test_check_file() {
+    let mut perms = RuntimePermissions::default();
+    assert!(perms.check_file("/etc/passwd").is_ok());
+
+    perms.files.prefix = Some("/public/".to_string());
+    assert!(perms.check_file("/public/index.html").is_ok());
+    assert!(perms.check_file("/public/../etc/passwd").is_err());
+    assert!(perms.check_file("/etc/passwd").is_err());
+
+    perms.files.prefix = Some("/public".to_string());
+    assert!(perms.check_file("/public/index.html").is_ok());
+    assert!(perms.check_file("/publicfoo/secret").is_err());
+    assert!(perms.check_file("/public/a/../../etc/passwd").is_err());
+
+    perms.files.allow = false;
+    assert!(perms.check_file("/public/index.html").is_err());
+  }
+
+  #[test]
+  fn test_from_app_config() {
+    let config = json!({
+      "permissions": {
+        "net": { "allow_hosts": ["*.example.com"] },
+        "cache": { "write": false },
+        "allow_secrets": false
+      }
+    });
+    let perms =
+      RuntimePermissions::from_app_config(&config, &RuntimePermissions::default()).unwrap();
+    assert!(!perms.allow_os);
+    assert!(perms.check_cache_read().is_ok());
+    assert!(perms.check_cache_write().is_err());
+    assert!(perms.check_data_write().is_ok());
+    assert!(perms.check_secrets().is_err());
+    assert!(perms.check_net("https://www.example.com/").is_ok());
+
+    let perms =
+      RuntimePermissions::from_app_config(&json!({}), &RuntimePermissions::default()).unwrap();
+    assert!(perms.check_secrets().is_ok());
+    assert!(perms.check_os().is_err());
+
+    assert!(RuntimePermissions::from_app_config(
+      &json!({ "permissions": { "cache": true } }),
+      &RuntimePermissions::default()
+    )
+    .is_err());
+  }
+
+  #[test]
+  fn test_app_config_cannot_allow_os() {
+    let config = json!({ "permissions": { "allow_os": true } });
+    let perms =
+      RuntimePermissions::from_app_config(&config, &RuntimePermissions::default()).unwrap();
+    assert!(perms.check_os().is_err());
+
+    // it only keeps what the operator allowed
+    let base = RuntimePermissions::new(true);
+    let perms = RuntimePermissions::from_app_config(&config, &base).unwrap();
+    assert!(perms.check_os().is_ok());
+  }
+
+  #[test]
+  fn test_app_config_only_narrows() {
+    let mut base = RuntimePermissions::default();
+    base.allow_secrets = false;
+    base.cache.write = false;
+    base.net.allow_hosts = Some(vec!["*.example.com".to_string()]);
+    base.files.prefix = Some("/public".to_string());
+
+    let config = json!({
+      "permissions": {
+        "allow_secrets": true,
+        "cache": { "write": true },
+        "net": { "allow_hosts": ["api.example.com", "evil.net", "*"] },
+        "files": { "prefix": "/" }
+      }
+    });
+    let perms = RuntimePermissions::from_app_config(&config, &base).unwrap();
+    assert!(perms.check_secrets().is_err());
+    assert!(perms.check_cache_write().is_err());
+    assert!(perms.check_net("https://api.example.com/").is_ok());
+    assert!(perms.check_net("https://www.example.com/").is_err());
+    assert!(perms.check_net("https://evil.net/").is_err());
+    assert!(perms.check_file("/public/index.html").is_ok());
+    assert!(perms.check_file("/etc/passwd").is_err());
+
+    let config = json!({ "permissions": { "files": { "prefix": "/public/img" } } });
+    let perms = RuntimePermissions::from_app_config(&config, &base).unwrap();
+    assert!(perms.check_file("/public/img/a.png").is_ok());
+    assert!(perms.check_file("/public/index.html").is_err());
+
+    let config = json!({ "permissions": { "files": { "prefix": "/private" } } });
+    let perms = RuntimePermissions::from_app_config(&config, &base).unwrap();
+    assert!(perms.check_file("/public/index.html").is_err());
+    assert!(perms.check_file("/private/key").is_err());
+  }
+
+  #[test]
+  fn test_pattern_covers() {
+    assert!(pattern_covers("*", "anything.net:8080"));
+    assert!(pattern_covers("*.example.com", "api.example.com"));
+    assert!(pattern_covers("*.example.com", "*.api.example.com"));
+    assert!(!pattern_covers("*.example.com", "*"));
+    assert!(!pattern_covers("example.com", "*.example.com"));
+    assert!(pattern_covers("example.com", "example.com:443"));
+    assert!(!pattern_covers("example.com:443", "example.com"));
   }
 }
@@ -0,0 +1,203 @@
+//! Runtime event loops share a fixed set of worker threads. A runtime is pinned to the
+//! worker with the fewest runtimes when it's created and stays there for its whole life,
+//! its futures are tied to that worker's reactor. Runtimes on the same worker take turns:
+//! one busy running javascript holds the others up until it yields, or the watchdog
+//! terminates it once it's over its cpu budget. Ops javascript waits on synchronously
+//! can't wait on I/O for the same reason, see `msg_handler::is_io_op`.
+
+use tokio::runtime::current_thread;
+
+use futures::{future, sync::oneshot, Future};
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_BOOL_INIT, ATOMIC_USIZE_INIT};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::errors::{FlyError, FlyResult};
+use crate::metrics::SCHEDULER_WORKER_RUNTIMES;
+
+// 0 means one worker per online CPU
+static WORKER_COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+// set once the workers are started, there's no changing how many there are afterwards
+static STARTED: AtomicBool = ATOMIC_BOOL_INIT;
+
+lazy_static! {
+  static ref WORKERS: Vec<Worker> = {
+    STARTED.store(true, Ordering::SeqCst);
+    let count = match WORKER_COUNT.load(Ordering::SeqCst) {
+      0 => online_cpus(),
+      n => n,
+    };
+    debug!("starting {} runtime workers", count);
+    (0..count).map(Worker::start).collect()
+  };
+}
+
+/// Number of threads runtime event loops are multiplexed on. Fails once the first
+/// runtime was created, the workers are started by then.
+pub fn set_worker_count(count: usize) -> FlyResult<()> {
+  if STARTED.load(Ordering::SeqCst) {
+    return Err(FlyError::from(format!(
+      "runtime workers already started, can't change their count to {}",
+      count
+    )));
+  }
+  WORKER_COUNT.store(count, Ordering::SeqCst);
+  Ok(())
+}
+
+fn online_cpus() -> usize {
+  let n = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) };
+  if n < 1 {
+    1
+  } else {
+    n as usize
+  }
+}
+
+struct Worker {
+  handle: current_thread::Handle,
+  runtimes: AtomicUsize,
+}
+
+impl Worker {
+  fn start(index: usize) -> Self {
+    let (c, p) = oneshot::channel::<current_thread::Handle>();
+    thread::Builder::new()
+      .name(format!("runtime-worker-{}", index))
+      .spawn(move || {
+        let mut el = current_thread::Runtime::new().unwrap();
+        c.send(el.handle()).unwrap();
+        // runtimes come and go, the worker lives as long as the process
+        if let Err(e) = el.block_on(future::empty::<(), ()>()) {
+          error!("error running runtime worker {}: {:?}", index, e);
+        }
+      })
+      .unwrap();
+    Worker {
+      handle: p.wait().unwrap(),
+      runtimes: AtomicUsize::new(0),
+    }
+  }
+}
+
+/// A runtime's share of a worker thread. Behaves like a dedicated event loop: it's
+/// done once every future spawned through it has completed.
+pub struct EventLoop {
+  worker: usize,
+  handle: current_thread::Handle,
+  tasks: Arc<AtomicUsize>,
+  done_ch: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+  // notified once every spawned future has completed
+  done: Arc<(Mutex<()>, Condvar)>,
+}
+
+/// Assigns a runtime to the worker with the fewest runtimes. The receiver
+/// resolves when the event loop is done.
+pub fn assign() -> (EventLoop, oneshot::Receiver<()>) {
+  let (index, worker) = WORKERS
+    .iter()
+    .enumerate()
+    .min_by_key(|(_, w)| w.runtimes.load(Ordering::SeqCst))
+    .unwrap();
+  let count = worker.runtimes.fetch_add(1, Ordering::SeqCst) + 1;
+  SCHEDULER_WORKER_RUNTIMES
+    .with_label_values(&[&index.to_string()])
+    .set(count as i64);
+
+  let (tx, rx) = oneshot::channel::<()>();
+  (
+    EventLoop {
+      worker: index,
+      handle: worker.handle.clone(),
+      tasks: Arc::new(AtomicUsize::new(0)),
+      done_ch: Arc::new(Mutex::new(Some(tx))),
+      done: Arc::new((Mutex::new(()), Condvar::new())),
+    },
+    rx,
+  )
+}
+
+impl EventLoop {
+  pub fn spawn<F>(&self, fut: F)
+  where
+    F: Future<Item = (), Error = ()> + Send + 'static,
+  {
+    self.tasks.fetch_add(1, Ordering::SeqCst);
+    let tasks = self.tasks.clone();
+    let done_ch = self.done_ch.clone();
+    let done = self.done.clone();
+    let res = self.handle.spawn(fut.then(move |res| {
+      if tasks.fetch_sub(1, Ordering::SeqCst) == 1 {
+        if let Some(tx) = done_ch.lock().unwrap().take() {
+          if tx.send(()).is_err() {
+            trace!("nobody waiting on event loop completion");
+          }
+        }
+        let (ref lock, ref cvar) = *done;
+        let _guard = lock.lock().unwrap();
+        cvar.notify_all();
+      }
+      res
+    }));
+    if let Err(e) = res {
+      self.tasks.fetch_sub(1, Ordering::SeqCst);
+      error!("error spawning future on runtime worker: {:?}", e);
+    }
+  }
+
+  /// Whether every future spawned so far has completed.
+  pub fn is_done(&self) -> bool {
+    self.tasks.load(Ordering::SeqCst) == 0
+  }
+
+  /// Waits up to `timeout` for every future spawned so far to complete. Returns whether
+  /// they did.
+  pub fn wait_done(&self, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let (ref lock, ref cvar) = *self.done;
+    let mut guard = lock.lock().unwrap();
+    while !self.is_done() {
+      let now = Instant::now();
+      if now >= deadline {
+        return false;
+      }
+      guard = cvar.wait_timeout(guard, deadline - now).unwrap().0;
+    }
+    true
+  }
+}
+
+impl Drop for EventLoop {
+  fn drop(&mut self) {
+    let count = WORKERS[self.worker].runtimes.fetch_sub(1, Ordering::SeqCst) - 1;
+    SCHEDULER_WORKER_RUNTIMES
+      .with_label_values(&[&self.worker.to_string()])
+      .set(count as i64);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_event_loop_done() {
+    let (event_loop, done) = assign();
+    let (tx, rx) = oneshot::channel::<()>();
+    event_loop.spawn(rx.map_err(|_| ()));
+    assert!(!event_loop.is_done());
+    assert!(!event_loop.wait_done(Duration::from_millis(50)));
+
+    tx.send(()).unwrap();
+    assert!(event_loop.wait_done(Duration::from_secs(5)));
+    assert!(done.wait().is_ok());
+  }
+
+  #[test]
+  fn test_worker_count_is_fixed_once_started() {
+    let _ = assign();
+    assert!(set_worker_count(2).is_err());
+  }
+}
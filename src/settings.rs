@@ -2,6 +2,9 @@ extern crate config;
 use self::config::{Config, ConfigError, Environment, File};
 use std::sync::RwLock;
 
+use crate::egress::EgressPolicy;
+use crate::watchdog::CpuBudget;
+
 lazy_static! {
   pub static ref SETTINGS: RwLock<Settings> = RwLock::new(Settings::new().unwrap());
 }
@@ -65,6 +68,128 @@ pub enum CacheStoreNotifier {
   Redis(RedisCacheNotifierConfig),
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct DiskCodeCacheConfig {
+  pub dir: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CodeCacheStoreConfig {
+  Disk(DiskCodeCacheConfig),
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct HeapLimits {
+  // v8 old space size, in megabytes
+  pub soft_mb: usize,
+  // v8 heap plus array buffers, in megabytes
+  pub hard_mb: usize,
+}
+
+impl Default for HeapLimits {
+  fn default() -> Self {
+    HeapLimits {
+      soft_mb: 128,
+      hard_mb: 256,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct FetchTimeouts {
+  // establishing the TCP connection. Only set here, connections are pooled and shared by
+  // every request of a runtime, so requests can't override it
+  pub connect_ms: u64,
+  // waiting for response headers once connected
+  pub headers_ms: u64,
+  // reading the whole response body
+  pub body_ms: u64,
+}
+
+impl Default for FetchTimeouts {
+  fn default() -> Self {
+    FetchTimeouts {
+      connect_ms: 10_000,
+      headers_ms: 60_000,
+      body_ms: 300_000,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct CompressionConfig {
+  // gzip or brotli, negotiated with clients, for compressible responses
+  pub enabled: bool,
+  // 0 (fastest) to 9 (smallest)
+  pub gzip_level: u32,
+  // 0 (fastest) to 11 (smallest)
+  pub brotli_level: u32,
+  // responses known to be smaller than this many bytes are sent as is
+  pub min_size: usize,
+}
+
+impl Default for CompressionConfig {
+  fn default() -> Self {
+    CompressionConfig {
+      enabled: true,
+      gzip_level: 6,
+      brotli_level: 4,
+      min_size: 1024,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProxyConfig {
+  // http://host:port of a proxy fetch tunnels every connection through with CONNECT. Hosts
+  // are resolved and checked against egress first, the proxy is given the address.
+  // Connections to the proxy itself are plain TCP, https:// proxy urls are refused when the
+  // client is created. Use a local forwarder to reach a proxy that only accepts TLS.
+  pub url: String,
+  pub username: Option<String>,
+  pub password: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClientCertConfig {
+  // PEM certificate, followed by its chain if any
+  pub cert: String,
+  // PEM private key
+  pub key: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct HttpClientConfig {
+  pub proxy: Option<ProxyConfig>,
+  // PEM bundles trusted on top of the system's roots
+  #[serde(default)]
+  pub ca_bundles: Vec<String>,
+  // presented to origins asking for a client certificate
+  pub client_cert: Option<ClientCertConfig>,
+  // idle connections kept per host, unlimited when unset
+  pub max_idle_per_host: Option<usize>,
+  // seconds before idle connections are closed, 90 when unset
+  pub idle_timeout_secs: Option<u64>,
+  // speak HTTP/2 to every origin. The TLS connector doesn't offer ALPN, so HTTP/2 isn't
+  // negotiated per connection: fetch speaks HTTP/1.1 everywhere unless this is set, and
+  // with it origins only speaking HTTP/1.1 fail to connect.
+  #[serde(default)]
+  pub http2_only: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct EgressConfig {
+  // CIDRs fetch can connect to even though they're in a range denied by default
+  #[serde(default)]
+  pub allow: Vec<String>,
+  // CIDRs fetch can't connect to, on top of loopback, link-local and private ranges
+  #[serde(default)]
+  pub deny: Vec<String>,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
   pub data_store: Option<DataStore>,
@@ -72,6 +197,31 @@ pub struct Settings {
   pub cache_store_notifier: Option<CacheStoreNotifier>,
   pub fs_store: Option<FsStore>,
   pub acme_store: Option<AcmeStoreConfig>,
+  pub heap_limits: Option<HeapLimits>,
+  // number of isolates serving each app
+  pub isolate_pool_size: Option<usize>,
+  // threads runtime event loops are multiplexed on, defaults to one per CPU
+  pub event_loop_threads: Option<usize>,
+  // address of the admin listener (heap snapshots, cpu profiles), disabled when unset
+  pub admin_bind_addr: Option<String>,
+  // bearer token the admin listener requires, it can only bind loopback addresses without one
+  pub admin_token: Option<String>,
+  // where compiled app code is cached, disabled when unset
+  pub code_cache: Option<CodeCacheStoreConfig>,
+  // body bytes buffered across a runtime's streams before senders wait, defaults to 64
+  pub stream_buffer_limit_mb: Option<usize>,
+  // addresses fetch can connect to once hosts are resolved
+  pub egress: Option<EgressConfig>,
+  // default fetch timeouts, requests can set their own headers and body timeouts
+  pub fetch_timeouts: Option<FetchTimeouts>,
+  // how fetch connects to origins
+  pub http_client: Option<HttpClientConfig>,
+  // compression of responses served to clients
+  pub compression: Option<CompressionConfig>,
+  // cpu time javascript may take per event or callback before it's terminated
+  pub cpu_budget: Option<CpuBudget>,
+  // seconds an evictable runtime may go without events before it's evicted, never when unset
+  pub runtime_idle_ttl_secs: Option<u64>,
 }
 
 impl Settings {
@@ -80,7 +230,12 @@ impl Settings {
 
     s.merge(File::with_name(".fly").required(false))?;
     s.merge(Environment::with_prefix("FLY"))?;
-    s.try_into()
+    let settings: Settings = s.try_into()?;
+    // a bad range is reported here, not when the first app fetches
+    if let Some(ref egress) = settings.egress {
+      EgressPolicy::new(egress).map_err(|e| ConfigError::Message(format!("{}", e)))?;
+    }
+    Ok(settings)
   }
 }
 
@@ -92,6 +247,19 @@ impl Default for Settings {
       cache_store_notifier: None,
       fs_store: None,
       acme_store: None,
+      heap_limits: None,
+      isolate_pool_size: None,
+      event_loop_threads: None,
+      admin_bind_addr: None,
+      admin_token: None,
+      code_cache: None,
+      stream_buffer_limit_mb: None,
+      egress: None,
+      fetch_timeouts: None,
+      http_client: None,
+      compression: None,
+      cpu_budget: None,
+      runtime_idle_ttl_secs: None,
     }
   }
 }
@@ -0,0 +1,239 @@
+//! App startup snapshots. `fly snapshot` evaluates an app and the modules it imports on
+//! top of the v8env snapshot and saves the heap, runtimes created from it start with the
+//! app initialised. There's no runtime while the app is evaluated, so it can only load
+//! modules and add event listeners. v8env adds the listeners again when a runtime boots.
+//!
+//! Snapshots only load in the fly build that made them, so files start with a header
+//! holding the build version.
+
+use libc::c_void;
+use libfly::*;
+use std::ffi::CString;
+use std::fs;
+use std::os::raw::c_char;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+
+use crate::errors::{FlyError, FlyResult};
+use crate::module_resolver::{
+    LocalDiskModuleResolver, ModuleResolver, ModuleResolverManager, RefererInfo,
+    StandardModuleResolverManager,
+};
+use crate::msg;
+use crate::ops::modules::{load_module_error_resp, load_module_resp};
+use crate::runtime;
+use crate::settings::Settings;
+use crate::utils::{build_error, Buf};
+use crate::v8env::{DEV_TOOLS_SOURCE, FLY_SNAPSHOT};
+
+const MAGIC: &[u8] = b"FLYSNAP\0";
+
+#[derive(Clone)]
+pub struct AppSnapshot {
+    blob: Arc<Vec<u8>>,
+}
+
+impl AppSnapshot {
+    /// Evaluates `libs`, as (filename, source) pairs, in order, then runs the app at
+    /// `app_path` with the module loader `fly http` uses. Fails if one throws.
+    pub fn create(
+        settings: &Settings,
+        libs: &[(String, String)],
+        app_path: &str,
+    ) -> FlyResult<AppSnapshot> {
+        runtime::init_v8(settings);
+
+        let mut scripts = vec![
+            ("dev-tools.js".to_string(), DEV_TOOLS_SOURCE.to_string()),
+            (
+                "<installDevTools>".to_string(),
+                "installDevTools();".to_string(),
+            ),
+        ];
+        scripts.extend_from_slice(libs);
+        scripts.push((app_path.to_string(), format!("dev.run('{}')", app_path)));
+
+        let mut cstrings = vec![];
+        for (filename, code) in scripts.iter() {
+            let code = CString::new(code.as_str())
+                .map_err(|e| FlyError::from(format!("invalid source in {}: {}", filename, e)))?;
+            cstrings.push((CString::new(filename.as_str()).unwrap(), code));
+        }
+        let js_scripts: Vec<js_script> = cstrings
+            .iter()
+            .map(|(filename, code)| js_script {
+                filename: filename.as_ptr(),
+                code: code.as_ptr(),
+            })
+            .collect();
+
+        let mut sender = SnapshotSender {
+            module_resolver: StandardModuleResolverManager::new(
+                vec![Box::new(LocalDiskModuleResolver::new(None)) as Box<ModuleResolver>],
+                None,
+            ),
+            response: None,
+        };
+        let snap = unsafe {
+            js_create_app_snapshot(
+                *FLY_SNAPSHOT,
+                js_scripts.as_ptr(),
+                js_scripts.len() as i32,
+                send_from_snapshot,
+                &mut sender as *mut SnapshotSender as *mut c_void,
+            )
+        };
+        if snap.ptr.is_null() {
+            return Err(FlyError::from(
+                "error evaluating app while creating snapshot".to_string(),
+            ));
+        }
+        let blob =
+            unsafe { slice::from_raw_parts(snap.ptr as *const u8, snap.len as usize) }.to_vec();
+        unsafe { js_snapshot_free(snap) };
+
+        Ok(AppSnapshot {
+            blob: Arc::new(blob),
+        })
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> FlyResult<AppSnapshot> {
+        let data = fs::read(path)?;
+        let blob = decode(&data, crate::BUILD_VERSION)?;
+        Ok(AppSnapshot {
+            blob: Arc::new(blob.to_vec()),
+        })
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> FlyResult<()> {
+        fs::write(path, encode(&self.blob, crate::BUILD_VERSION))?;
+        Ok(())
+    }
+
+    /// Size of the snapshot blob, in bytes.
+    pub fn size(&self) -> usize {
+        self.blob.len()
+    }
+
+    // Only valid as long as self is alive.
+    pub(crate) fn as_simple_buf(&self) -> fly_simple_buf {
+        fly_simple_buf {
+            ptr: self.blob.as_ptr() as *const c_char,
+            len: self.blob.len() as i32,
+        }
+    }
+}
+
+// Answers what the app sends while it's evaluated for a snapshot.
+struct SnapshotSender {
+    module_resolver: StandardModuleResolverManager,
+    // libfly copies the response before the next message
+    response: Buf,
+}
+
+impl SnapshotSender {
+    fn handle(&self, base: &msg::Base) -> Buf {
+        let cmd_id = base.cmd_id();
+        match base.msg_type() {
+            // added to the runtime when it boots from the snapshot
+            msg::Any::AddEventListener => None,
+            msg::Any::LoadModule => {
+                let msg = base.msg_as_load_module().unwrap();
+                let referer_info = msg.referer_origin_url().map(|url| RefererInfo {
+                    origin_url: url.to_string(),
+                    is_wasm: Some(false),
+                    source_code: None,
+                    indentifier_hash: None,
+                });
+                match self
+                    .module_resolver
+                    .resolve_module(msg.specifier_url().unwrap().to_string(), referer_info)
+                {
+                    Ok(module) => load_module_resp(cmd_id, &module),
+                    Err(e) => load_module_error_resp(cmd_id, &e),
+                }
+            }
+            msg_type => build_error(
+                cmd_id,
+                FlyError::from(format!(
+                    "{} is not available while creating a snapshot",
+                    msg::enum_name_any(msg_type)
+                )),
+            ),
+        }
+    }
+}
+
+extern "C" fn send_from_snapshot(data: *mut c_void, buf: fly_simple_buf) -> fly_simple_buf {
+    let sender = unsafe { &mut *(data as *mut SnapshotSender) };
+    let bytes = unsafe { slice::from_raw_parts(buf.ptr as *const u8, buf.len as usize) };
+    sender.response = sender.handle(&msg::get_root_as_base(bytes));
+    match sender.response {
+        Some(ref res) => fly_simple_buf {
+            ptr: res.as_ptr() as *const c_char,
+            len: res.len() as i32,
+        },
+        None => fly_simple_buf {
+            ptr: ptr::null(),
+            len: 0,
+        },
+    }
+}
+
+fn encode(blob: &[u8], version: &str) -> Vec<u8> {
+    let mut data = Vec::with_capacity(MAGIC.len() + 4 + version.len() + blob.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&(version.len() as u32).to_le_bytes());
+    data.extend_from_slice(version.as_bytes());
+    data.extend_from_slice(blob);
+    data
+}
+
+fn decode<'a>(data: &'a [u8], version: &str) -> Result<&'a [u8], String> {
+    if data.len() < MAGIC.len() + 4 || &data[..MAGIC.len()] != MAGIC {
+        return Err("not a fly snapshot".to_string());
+    }
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&data[MAGIC.len()..MAGIC.len() + 4]);
+    let start = MAGIC.len() + 4;
+    let end = start + u32::from_le_bytes(len) as usize;
+    if data.len() < end {
+        return Err("truncated fly snapshot".to_string());
+    }
+    let built_by = String::from_utf8_lossy(&data[start..end]);
+    if built_by != version {
+        return Err(format!(
+            "snapshot was created by fly {}, this is fly {}, it has to be recreated",
+            built_by, version
+        ));
+    }
+    Ok(&data[end..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let data = encode(b"heap", "1.2.3");
+        assert_eq!(decode(&data, "1.2.3"), Ok(&b"heap"[..]));
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let data = encode(b"heap", "1.2.3");
+        assert!(decode(&data, "1.2.4").is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(decode(b"", "1.2.3").is_err());
+        assert!(decode(b"not a snapshot at all", "1.2.3").is_err());
+        let mut data = encode(b"", "1.2.3");
+        data.truncate(data.len() - 2);
+        assert!(decode(&data, "1.2.3").is_err());
+    }
+}
@@ -7,10 +7,16 @@ use crate::{get_next_stream_id};
 
 use crate::js::*;
 use crate::utils::*;
+use crate::metrics::{ RUNTIMES_EVICTED_GAUGE, RUNTIMES_LIVE_GAUGE, RUNTIME_POOL_QUEUE_DEPTH };
 
 use std::collections::HashMap;
 
 use std::sync::{ Mutex, Arc, RwLock };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+use std::mem;
+use std::thread;
+use std::time::Duration;
 
 use uuid::Uuid;
 
@@ -18,11 +24,47 @@ use futures::future::Future;
 
 use futures::sync::oneshot;
 
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+const REAPER_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Hostname that matches any host without a binding of its own.
+pub const CATCH_ALL_HOSTNAME: &str = "*";
+
+/// Builds a runtime with its sources loaded and its event loop running (`run` called).
+pub type RuntimeFactory = Box<Fn() -> Box<Runtime> + Send + Sync>;
+
+// Filled in by whoever recreates an evicted runtime, others looking it up meanwhile wait on it.
+type RevivalSlot = Arc<Mutex<Option<Arc<RwLock<Box<Runtime>>>>>>;
+
+// An isolate of a pool, with its queue depth readable without locking it.
+struct PoolMember {
+    rt: Arc<RwLock<Box<Runtime>>>,
+    queue_depth: Arc<AtomicUsize>,
+}
+
+impl PoolMember {
+    fn new(rt: &Arc<RwLock<Box<Runtime>>>) -> Self {
+        let queue_depth = rt.read().unwrap().queue_depth();
+        PoolMember { rt: rt.clone(), queue_depth }
+    }
+}
+
 pub struct StandardRuntimeManager {
     uuid_to_runtime: RwLock<HashMap<String, Arc<RwLock<Box<Runtime>>>>>,
     hostname_to_uuid: RwLock<HashMap<String, String>>,
     servicename_to_uuid: RwLock<HashMap<String, String>>,
     self_ref: Option<Arc<RwLock<StandardRuntimeManager>>>,
+    drain_timeout: Duration,
+    // runtimes that can be evicted when idle, and recreated when looked up again
+    factories: RwLock<HashMap<String, Arc<RuntimeFactory>>>,
+    // evicted runtimes and pools, with how many isolates to recreate
+    evicted: RwLock<HashMap<String, usize>>,
+    reviving: Mutex<HashMap<String, RevivalSlot>>,
+    // identical isolates serving the same app, keyed by the uuid of the first one
+    pools: RwLock<HashMap<String, Vec<PoolMember>>>,
+    // what builds a pool's members, by pool uuid. Pools are evicted as a whole.
+    pool_factories: RwLock<HashMap<String, Arc<RuntimeFactory>>>,
 }
 
 impl StandardRuntimeManager {
@@ -32,22 +74,309 @@ impl StandardRuntimeManager {
             hostname_to_uuid: RwLock::new(HashMap::new()),
             servicename_to_uuid: RwLock::new(HashMap::new()),
             self_ref: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            factories: RwLock::new(HashMap::new()),
+            evicted: RwLock::new(HashMap::new()),
+            reviving: Mutex::new(HashMap::new()),
+            pools: RwLock::new(HashMap::new()),
+            pool_factories: RwLock::new(HashMap::new()),
         }));
         new_self_ref.write().unwrap().self_ref = Some(new_self_ref.clone());
         new_self_ref
     }
-}
 
-impl RuntimeManager for StandardRuntimeManager {
-    fn new_runtime(
-        &mut self,
-        config: RuntimeConfig,
-    ) -> Arc<RwLock<Box<Runtime>>> {
-        let runtime = Runtime::new(config);
-        let uuid_map_lock = self.uuid_to_runtime.get_mut().unwrap();
+    /// How long remove_runtime waits for in flight events before failing them.
+    pub fn set_drain_timeout(&mut self, timeout: Duration) {
+        self.drain_timeout = timeout;
+    }
+
+    /// Creates a runtime that may be evicted once idle. The factory is kept around to
+    /// recreate it, under the same uuid, the next time it's looked up.
+    pub fn new_evictable_runtime(&self, factory: RuntimeFactory) -> Arc<RwLock<Box<Runtime>>> {
+        let rt_arc = self.register_runtime(factory());
+        let uuid = rt_arc.read().unwrap().get_uuid();
+        self.factories.write().unwrap().insert(uuid, Arc::new(factory));
+        rt_arc
+    }
+
+    /// Creates `size` isolates from the same factory. Hostnames and service names should be
+    /// bound to the uuid of the first one, lookups then return the least busy isolate.
+    pub fn new_runtime_pool(&self, size: usize, factory: RuntimeFactory) -> Vec<Arc<RwLock<Box<Runtime>>>> {
+        let members: Vec<Arc<RwLock<Box<Runtime>>>> = (0..size.max(1))
+            .map(|_| self.register_runtime(factory()))
+            .collect();
+        let uuid = members[0].read().unwrap().get_uuid();
+        self.pools.write().unwrap().insert(uuid.clone(), members.iter().map(PoolMember::new).collect());
+        self.pool_factories.write().unwrap().insert(uuid, Arc::new(factory));
+        members
+    }
+
+    /// Moves every hostname and service name bound to `old` over to `new` in one step,
+    /// then drains and removes `old`.
+    pub fn replace_runtime(&self, old: Uuid, new: Uuid) -> Result<(), RuntimeManagerError> {
+        let old_string = old.to_simple().to_string();
+        let new_string = new.to_simple().to_string();
+        {
+            let mut hostnames = self.hostname_to_uuid.write().unwrap();
+            let mut servicenames = self.servicename_to_uuid.write().unwrap();
+            for v in hostnames.values_mut().chain(servicenames.values_mut()) {
+                if *v == old_string {
+                    *v = new_string.clone();
+                }
+            }
+        }
+        self.remove_runtime(old)
+    }
+
+    /// Every runtime currently running, evicted ones aside.
+    pub fn live_runtimes(&self) -> Vec<Arc<RwLock<Box<Runtime>>>> {
+        self.uuid_to_runtime.read().unwrap().values().cloned().collect()
+    }
+
+    /// Looks a live runtime up by uuid, bound hostname or name. Unlike get_by_uuid, a pool
+    /// member's uuid returns that exact isolate.
+    pub fn find_runtime(&self, id: &str) -> Option<Arc<RwLock<Box<Runtime>>>> {
+        if let Ok(uuid) = Uuid::parse_str(id) {
+            if let Some(v) = self.uuid_to_runtime.read().unwrap().get(&uuid.to_simple().to_string()) {
+                return Some(v.clone());
+            }
+        }
+        if let Ok(Some(v)) = self.get_by_hostname(id) {
+            return Some(v);
+        }
+        self.live_runtimes().into_iter().find(|rt| rt.read().unwrap().name == id)
+    }
+
+    /// Sets the queue depth gauge of every pool's isolates. The reaper does it, lookups
+    /// only read the depths.
+    pub fn update_pool_gauges(&self) {
+        for members in self.pools.read().unwrap().values() {
+            for (i, member) in members.iter().enumerate() {
+                let rt_lock = member.rt.read().unwrap();
+                RUNTIME_POOL_QUEUE_DEPTH
+                    .with_label_values(&[rt_lock.name.as_str(), rt_lock.version.as_str(), &i.to_string()])
+                    .set(member.queue_depth.load(Ordering::SeqCst) as i64);
+            }
+        }
+    }
+
+    /// Every few seconds, updates pool gauges and, with a `ttl`, evicts evictable runtimes
+    /// and pools which haven't received an event in that long.
+    pub fn start_reaper(&self, ttl: Option<Duration>) {
+        let man_arc = match &self.self_ref {
+            Some(v) => v.clone(),
+            None => {
+                warn!("Self ref missing, not starting reaper.");
+                return;
+            },
+        };
+        let interval = match ttl {
+            Some(ttl) if ttl < REAPER_INTERVAL => ttl,
+            _ => REAPER_INTERVAL,
+        };
+        thread::Builder::new()
+            .name("runtime-reaper".to_string())
+            .spawn(move || loop {
+                thread::sleep(interval);
+                // drained without holding the manager lock, lookups and bindings carry on
+                let (evicted, drain_timeout) = {
+                    let man_lock = man_arc.read().unwrap();
+                    man_lock.update_pool_gauges();
+                    let evicted = match ttl {
+                        Some(ttl) => man_lock.evict_idle(ttl),
+                        None => vec![],
+                    };
+                    (evicted, man_lock.drain_timeout)
+                };
+                for rt in evicted {
+                    shutdown_runtime(&rt, drain_timeout);
+                }
+            })
+            .unwrap();
+    }
+
+    /// Detaches evictable runtimes and pools which haven't received an event in `ttl`,
+    /// they're recreated the next time they're looked up. The returned runtimes still have
+    /// to be shut down.
+    pub fn evict_idle(&self, ttl: Duration) -> Vec<Arc<RwLock<Box<Runtime>>>> {
+        let idle: Vec<String> = {
+            let factories = self.factories.read().unwrap();
+            self.uuid_to_runtime.read().unwrap()
+                .iter()
+                .filter(|(uuid, rt)| factories.contains_key(*uuid) && is_idle(rt, ttl))
+                .map(|(uuid, _)| uuid.clone())
+                .collect()
+        };
+        let idle_pools: Vec<String> = {
+            let pool_factories = self.pool_factories.read().unwrap();
+            self.pools.read().unwrap()
+                .iter()
+                .filter(|(uuid, members)| pool_factories.contains_key(*uuid) && is_pool_idle(members, ttl))
+                .map(|(uuid, _)| uuid.clone())
+                .collect()
+        };
+
+        let mut evicted_runtimes = vec![];
+        for uuid in idle {
+            let mut evicted = self.evicted.write().unwrap();
+            let mut runtimes = self.uuid_to_runtime.write().unwrap();
+            // looked up since, lookups touch the runtime under the map lock
+            if !runtimes.get(&uuid).map_or(false, |rt| is_idle(rt, ttl)) {
+                continue;
+            }
+            let rt = runtimes.remove(&uuid).unwrap();
+            evicted.insert(uuid.clone(), 1);
+            debug!("Evicting idle runtime {}.", uuid);
+            RUNTIMES_LIVE_GAUGE.dec();
+            RUNTIMES_EVICTED_GAUGE.inc();
+            evicted_runtimes.push(rt);
+        }
+        for uuid in idle_pools {
+            let mut evicted = self.evicted.write().unwrap();
+            let mut pools = self.pools.write().unwrap();
+            // same as above, lookups touch the isolate they pick under the pool lock
+            if !pools.get(&uuid).map_or(false, |members| is_pool_idle(members, ttl)) {
+                continue;
+            }
+            let members = pools.remove(&uuid).unwrap();
+            let mut runtimes = self.uuid_to_runtime.write().unwrap();
+            for member in members.iter() {
+                runtimes.remove(&member.rt.read().unwrap().get_uuid());
+                RUNTIMES_LIVE_GAUGE.dec();
+            }
+            evicted.insert(uuid.clone(), members.len());
+            debug!("Evicting idle runtime pool {}.", uuid);
+            RUNTIMES_EVICTED_GAUGE.inc();
+            evicted_runtimes.extend(members.into_iter().map(|m| m.rt));
+        }
+        evicted_runtimes
+    }
+
+    fn revive(&self, uuid_string: &str) -> Option<Arc<RwLock<Box<Runtime>>>> {
+        let (slot, size) = {
+            let evicted = self.evicted.read().unwrap();
+            let size = match evicted.get(uuid_string) {
+                Some(size) => *size,
+                // revived while we were waiting for the lock, or never evicted
+                None => return self.uuid_to_runtime.read().unwrap().get(uuid_string).cloned(),
+            };
+            let slot = self.reviving.lock().unwrap()
+                .entry(uuid_string.to_string())
+                .or_insert_with(|| Arc::new(Mutex::new(None)))
+                .clone();
+            (slot, size)
+        };
+        // only the first caller builds it, outside the manager's locks
+        let mut slot_lock = slot.lock().unwrap();
+        if let Some(ref rt) = *slot_lock {
+            return Some(rt.clone());
+        }
+        let is_pool = self.pool_factories.read().unwrap().contains_key(uuid_string);
+        let factory = if is_pool {
+            self.pool_factories.read().unwrap().get(uuid_string).cloned()
+        } else {
+            self.factories.read().unwrap().get(uuid_string).cloned()
+        };
+        let mut runtimes: Vec<Box<Runtime>> = match factory {
+            Some(factory) => (0..size).map(|_| factory()).collect(),
+            None => {
+                self.reviving.lock().unwrap().remove(uuid_string);
+                return None;
+            },
+        };
+        debug!("Recreating evicted runtime {}.", uuid_string);
+        // a pool is known by its first isolate's uuid
+        runtimes[0].set_uuid(uuid_string.to_string());
+        let rt = {
+            let mut evicted = self.evicted.write().unwrap();
+            if evicted.remove(uuid_string).is_none() {
+                // removed while it was being built
+                drop(evicted);
+                self.reviving.lock().unwrap().remove(uuid_string);
+                for mut runtime in runtimes {
+                    runtime.shutdown(Duration::from_secs(0));
+                }
+                return None;
+            }
+            let members: Vec<Arc<RwLock<Box<Runtime>>>> = runtimes
+                .into_iter()
+                .map(|runtime| self.register_runtime(runtime))
+                .collect();
+            if is_pool {
+                self.pools.write().unwrap()
+                    .insert(uuid_string.to_string(), members.iter().map(PoolMember::new).collect());
+            }
+            members[0].clone()
+        };
+        RUNTIMES_EVICTED_GAUGE.dec();
+        *slot_lock = Some(rt.clone());
+        self.reviving.lock().unwrap().remove(uuid_string);
+        Some(rt)
+    }
+
+    // The factory that built `rt`, pool members are built by their pool's.
+    fn factory_for(&self, uuid: &str, rt: &Arc<RwLock<Box<Runtime>>>) -> Option<Arc<RuntimeFactory>> {
+        if let Some(factory) = self.factories.read().unwrap().get(uuid) {
+            return Some(factory.clone());
+        }
+        let pool_uuid = self.pools.read().unwrap()
+            .iter()
+            .find(|(_, members)| members.iter().any(|m| Arc::ptr_eq(&m.rt, rt)))
+            .map(|(pool_uuid, _)| pool_uuid.clone())?;
+        self.pool_factories.read().unwrap().get(&pool_uuid).cloned()
+    }
+
+    /// Swaps a runtime that ran out of heap for a new one from its factory, in place so
+    /// every map and pool keeps pointing at it. Runtimes without a factory are reset.
+    fn replace_poisoned(&self, rt: &Arc<RwLock<Box<Runtime>>>) {
+        let uuid = rt.read().unwrap().get_uuid();
+        // built without holding the lock, lookups meanwhile get the poisoned runtime which
+        // fails events right away
+        let replacement = self.factory_for(&uuid, rt).map(|factory| {
+            let mut runtime = factory();
+            runtime.set_uuid(uuid.clone());
+            runtime
+        });
+        let mut old = {
+            let mut rt_lock = rt.write().unwrap();
+            if !rt_lock.is_poisoned() {
+                // someone else replaced it first
+                if let Some(mut runtime) = replacement {
+                    runtime.shutdown(Duration::from_secs(0));
+                }
+                return;
+            }
+            match replacement {
+                Some(runtime) => mem::replace(&mut *rt_lock, runtime),
+                None => {
+                    debug!("Resetting runtime {} after it ran out of heap.", uuid);
+                    rt_lock.reset();
+                    return;
+                },
+            }
+        };
+        debug!("Replaced runtime {} after it ran out of heap.", uuid);
+        self.register_callbacks(rt);
+        // the replacement counts its own pending events
+        let queue_depth = rt.read().unwrap().queue_depth();
+        for members in self.pools.write().unwrap().values_mut() {
+            for member in members.iter_mut().filter(|m| Arc::ptr_eq(&m.rt, rt)) {
+                member.queue_depth = queue_depth.clone();
+            }
+        }
+        old.shutdown(Duration::from_secs(0));
+    }
+
+    fn register_runtime(&self, runtime: Box<Runtime>) -> Arc<RwLock<Box<Runtime>>> {
         let uuid = runtime.get_uuid();
         let rt_arc = Arc::new(RwLock::new(runtime));
-        uuid_map_lock.insert(uuid, rt_arc.clone());
+        self.uuid_to_runtime.write().unwrap().insert(uuid, rt_arc.clone());
+        RUNTIMES_LIVE_GAUGE.inc();
+        self.register_callbacks(&rt_arc);
+        rt_arc
+    }
+
+    fn register_callbacks(&self, rt_arc: &Arc<RwLock<Box<Runtime>>>) {
         let man_arc = match &self.self_ref {
             Some(v) => v.clone(),
             None => {
@@ -96,19 +425,50 @@ impl RuntimeManager for StandardRuntimeManager {
                 Err(err) => Err(FlyError::from(err)), 
             };
         });
-        let rt_mut_clone = rt_arc.clone();
-        {
-            let rt_lock = rt_arc.read().unwrap();
-            let rt_mut = rt_lock.ptr.to_runtime();
-            rt_mut.register_rt_manager_callbacks(RuntimeManagerCallbacks {
-                send_message,
-                uuid_by_servicename,
-            });
-        }
-        rt_arc.clone()
+        let rt_lock = rt_arc.read().unwrap();
+        let rt_mut = rt_lock.ptr.to_runtime();
+        rt_mut.register_rt_manager_callbacks(RuntimeManagerCallbacks {
+            send_message,
+            uuid_by_servicename,
+        });
+    }
+}
+
+impl RuntimeManager for StandardRuntimeManager {
+    fn new_runtime(
+        &mut self,
+        config: RuntimeConfig,
+    ) -> Arc<RwLock<Box<Runtime>>> {
+        self.register_runtime(Runtime::new(config))
     }
     fn remove_runtime(&self, uuid: Uuid) -> Result<(), RuntimeManagerError> {
-        Err(RuntimeManagerError::Failure("Not implemented".to_string()))
+        let uuid_string = uuid.to_simple().to_string();
+        self.pool_factories.write().unwrap().remove(&uuid_string);
+        let pool = self.pools.write().unwrap().remove(&uuid_string);
+        if let Some(members) = pool {
+            for member in members.iter().skip(1) {
+                let member_uuid = member.rt.read().unwrap().get_uuid();
+                self.remove_runtime(uuid::Uuid::parse_str(&member_uuid).unwrap())?;
+            }
+        }
+        self.factories.write().unwrap().remove(&uuid_string);
+        let was_evicted = self.evicted.write().unwrap().remove(&uuid_string).is_some();
+        self.hostname_to_uuid.write().unwrap().retain(|_, v| *v != uuid_string);
+        self.servicename_to_uuid.write().unwrap().retain(|_, v| *v != uuid_string);
+        let rt = match self.uuid_to_runtime.write().unwrap().remove(&uuid_string) {
+            Some(rt) => rt,
+            None if was_evicted => {
+                RUNTIMES_EVICTED_GAUGE.dec();
+                return Ok(());
+            },
+            None => return Err(RuntimeManagerError::Failure(format!("Runtime {} not found.", uuid_string))),
+        };
+        RUNTIMES_LIVE_GAUGE.dec();
+
+        if !shutdown_runtime(&rt, self.drain_timeout) {
+            warn!("Runtime {} did not drain within {:?}.", uuid_string, self.drain_timeout);
+        }
+        Ok(())
     }
     fn bind_servicename_to(&mut self, uuid: Uuid, servicename: &str) -> Result<(), RuntimeManagerError> {
         let uuid_string = uuid.to_simple().to_string();
@@ -124,8 +484,12 @@ impl RuntimeManager for StandardRuntimeManager {
         Ok(())
     }
     fn get_by_hostname(&self, hostname: &str) -> Result<Option<Arc<RwLock<Box<Runtime>>>>, RuntimeManagerError> {
-        return match self.hostname_to_uuid.read().unwrap().get(hostname) {
-            Some(v) => self.get_by_uuid(uuid::Uuid::parse_str(v).unwrap()),
+        let uuid_string = {
+            let hostnames = self.hostname_to_uuid.read().unwrap();
+            hostnames.get(hostname).or_else(|| hostnames.get(CATCH_ALL_HOSTNAME)).cloned()
+        };
+        return match uuid_string {
+            Some(v) => self.get_by_uuid(uuid::Uuid::parse_str(&v).unwrap()),
             None => Ok(None),
         };
     }
@@ -136,9 +500,50 @@ impl RuntimeManager for StandardRuntimeManager {
         };
     }
     fn get_by_uuid(&self, uuid: Uuid) -> Result<Option<Arc<RwLock<Box<Runtime>>>>, RuntimeManagerError> {
-        return match self.uuid_to_runtime.read().unwrap().get(&uuid.to_simple().to_string()) {
-            Some(v) => Ok(Some(v.clone())),
-            None => Ok(None),
-        };
+        let uuid_string = uuid.to_simple().to_string();
+        let pooled = self.pools.read().unwrap().get(&uuid_string).map(|members| {
+            // only dispatching counts, lookups that never dispatch (admin, routing
+            // checks) don't make an isolate look busy
+            let member = least_busy(members);
+            member.rt.read().unwrap().touch();
+            member.rt.clone()
+        });
+        let rt = pooled
+            .or_else(|| {
+                self.uuid_to_runtime.read().unwrap().get(&uuid_string).map(|rt| {
+                    rt.read().unwrap().touch();
+                    rt.clone()
+                })
+            })
+            .or_else(|| self.revive(&uuid_string));
+        if let Some(ref rt) = rt {
+            if rt.read().unwrap().is_poisoned() {
+                self.replace_poisoned(rt);
+            }
+        }
+        Ok(rt)
     }
 }
+
+fn is_idle(rt: &Arc<RwLock<Box<Runtime>>>, ttl: Duration) -> bool {
+    let rt_lock = rt.read().unwrap();
+    rt_lock.idle_for() >= ttl && rt_lock.pending_events() == 0
+}
+
+// Drains under a read lock, lookups that still hold one aren't held up, then stops it.
+fn shutdown_runtime(rt: &Arc<RwLock<Box<Runtime>>>, timeout: Duration) -> bool {
+    rt.read().unwrap().drain(timeout);
+    rt.write().unwrap().shutdown(timeout)
+}
+
+fn is_pool_idle(members: &[PoolMember], ttl: Duration) -> bool {
+    members.iter().all(|m| is_idle(&m.rt, ttl))
+}
+
+// The isolate with the fewest pending events, read without locking any of them.
+fn least_busy(members: &[PoolMember]) -> &PoolMember {
+    members
+        .iter()
+        .min_by_key(|m| m.queue_depth.load(Ordering::SeqCst))
+        .unwrap()
+}
@@ -0,0 +1,334 @@
+//! Flow control for bodies streamed between JS and Rust.
+//!
+//! Chunks sent from JS go through bounded channels, JS waits for each chunk to be
+//! accepted before sending the next. Chunks sent to JS are held back until JS credits
+//! the stream for what it has read. Either way, buffered bytes count against the
+//! runtime's `BufferBudget` until they're consumed.
+
+use futures::{sync::mpsc, task, Async, Future, Poll, Sink, Stream};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::errors::{FlyError, FlyResult};
+
+/// Chunks buffered in a channel before its sender has to wait.
+pub const CHANNEL_CHUNKS: usize = 8;
+/// Bytes sent to JS on a stream before waiting for JS to credit some back.
+pub const CREDIT_WINDOW: usize = 256 * 1024;
+/// Bytes buffered across all of a runtime's streams, when not set in `Settings`.
+pub const DEFAULT_BUFFER_LIMIT_MB: usize = 64;
+
+/// A number of bytes shared between streams. Acquiring waits until enough has been
+/// released. A single chunk bigger than the limit goes through once nothing else is held.
+/// Clones share the same budget.
+#[derive(Clone)]
+pub struct BufferBudget {
+    inner: Arc<BudgetInner>,
+}
+
+struct BudgetInner {
+    limit: usize,
+    used: AtomicUsize,
+    closed: AtomicBool,
+    waiting: Mutex<Vec<task::Task>>,
+}
+
+impl BufferBudget {
+    pub fn new(limit: usize) -> BufferBudget {
+        BufferBudget {
+            inner: Arc::new(BudgetInner {
+                limit,
+                used: AtomicUsize::new(0),
+                closed: AtomicBool::new(false),
+                waiting: Mutex::new(vec![]),
+            }),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.inner.limit
+    }
+
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `n` bytes are acquired, fails if the budget is closed first.
+    pub fn acquire(&self, n: usize) -> Acquire {
+        Acquire {
+            budget: self.clone(),
+            n,
+        }
+    }
+
+    pub fn try_acquire(&self, n: usize) -> bool {
+        let mut used = self.used();
+        loop {
+            if used > 0 && used + n > self.inner.limit {
+                return false;
+            }
+            match self.inner.used.compare_exchange(
+                used,
+                used + n,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => used = actual,
+            }
+        }
+    }
+
+    pub fn release(&self, n: usize) {
+        self.release_up_to(n);
+    }
+
+    /// Releases `n` bytes, or whatever is held if that's less. Returns what was released.
+    pub fn release_up_to(&self, n: usize) -> usize {
+        let mut used = self.used();
+        let released = loop {
+            let released = n.min(used);
+            match self.inner.used.compare_exchange(
+                used,
+                used - released,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => break released,
+                Err(actual) => used = actual,
+            }
+        };
+        self.wake();
+        released
+    }
+
+    /// Fails every pending and future acquire.
+    pub fn close(&self) {
+        self.inner.closed.store(true, Ordering::SeqCst);
+        self.wake();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::SeqCst)
+    }
+
+    fn wake(&self) {
+        for t in self.inner.waiting.lock().unwrap().drain(..) {
+            t.notify();
+        }
+    }
+}
+
+pub struct Acquire {
+    budget: BufferBudget,
+    n: usize,
+}
+
+/// Credits a stream sent to JS for `n` bytes read, in its window and the runtime's budget.
+/// JS can't credit more than the stream has outstanding, what other streams hold stays
+/// counted.
+pub fn credit(window: &BufferBudget, budget: &BufferBudget, n: usize) {
+    budget.release(window.release_up_to(n));
+}
+
+impl Future for Acquire {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        if self.budget.is_closed() {
+            return Err(());
+        }
+        if self.budget.try_acquire(self.n) {
+            return Ok(Async::Ready(()));
+        }
+        self.budget
+            .inner
+            .waiting
+            .lock()
+            .unwrap()
+            .push(task::current());
+        // released between the first try and parking
+        if self.budget.try_acquire(self.n) {
+            return Ok(Async::Ready(()));
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+// Gives its bytes back to the budget once consumed, or dropped with the channel.
+struct Chunk {
+    data: Vec<u8>,
+    len: usize,
+    budget: BufferBudget,
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        self.budget.release(self.len);
+    }
+}
+
+/// A bounded channel of body chunks, accounted against `budget`.
+pub fn channel(budget: &BufferBudget) -> (StreamSender, StreamReceiver) {
+    let (tx, rx) = mpsc::channel(CHANNEL_CHUNKS);
+    (
+        StreamSender {
+            tx,
+            budget: budget.clone(),
+        },
+        StreamReceiver { rx },
+    )
+}
+
+#[derive(Clone)]
+pub struct StreamSender {
+    tx: mpsc::Sender<Chunk>,
+    budget: BufferBudget,
+}
+
+impl StreamSender {
+    /// Resolves once the chunk is buffered, waiting for room in the channel and budget.
+    pub fn send(&self, data: Vec<u8>) -> Box<Future<Item = (), Error = FlyError> + Send> {
+        let tx = self.tx.clone();
+        let budget = self.budget.clone();
+        let len = data.len();
+        Box::new(
+            self.budget
+                .acquire(len)
+                .map_err(|_| FlyError::from("stream buffer closed".to_string()))
+                .and_then(move |_| {
+                    tx.send(Chunk { data, len, budget })
+                        .map(|_| ())
+                        .map_err(|_| FlyError::from("stream closed".to_string()))
+                }),
+        )
+    }
+
+    /// Buffers the chunk if there's room right away.
+    pub fn try_send(&mut self, data: Vec<u8>) -> FlyResult<()> {
+        let len = data.len();
+        if !self.budget.try_acquire(len) {
+            return Err(FlyError::from("stream buffer limit reached".to_string()));
+        }
+        let chunk = Chunk {
+            data,
+            len,
+            budget: self.budget.clone(),
+        };
+        self.tx
+            .try_send(chunk)
+            .map_err(|e| FlyError::from(format!("error sending chunk: {}", e)))
+    }
+}
+
+pub struct StreamReceiver {
+    rx: mpsc::Receiver<Chunk>,
+}
+
+impl Stream for StreamReceiver {
+    type Item = Vec<u8>;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Vec<u8>>, ()> {
+        match self.rx.poll()? {
+            Async::Ready(Some(mut chunk)) => {
+                Ok(Async::Ready(Some(mem::replace(&mut chunk.data, vec![]))))
+            }
+            Async::Ready(None) => Ok(Async::Ready(None)),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+
+    #[test]
+    fn test_budget() {
+        let budget = BufferBudget::new(10);
+        assert!(budget.try_acquire(6));
+        assert!(!budget.try_acquire(6));
+        assert!(budget.try_acquire(4));
+        budget.release(6);
+        assert_eq!(budget.used(), 4);
+        assert!(budget.try_acquire(6));
+    }
+
+    #[test]
+    fn test_budget_oversized_chunk() {
+        let budget = BufferBudget::new(10);
+        assert!(budget.try_acquire(20));
+        assert!(!budget.try_acquire(1));
+        budget.release(20);
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn test_acquire_waits_for_release() {
+        let budget = BufferBudget::new(10);
+        assert!(budget.try_acquire(10));
+        let mut acquire = budget.acquire(5);
+        future::lazy(|| {
+            assert_eq!(acquire.poll(), Ok(Async::NotReady));
+            budget.release(10);
+            assert_eq!(acquire.poll(), Ok(Async::Ready(())));
+            Ok::<(), ()>(())
+        })
+        .wait()
+        .unwrap();
+        assert_eq!(budget.used(), 5);
+    }
+
+    #[test]
+    fn test_acquire_fails_when_closed() {
+        let budget = BufferBudget::new(10);
+        assert!(budget.try_acquire(10));
+        budget.close();
+        assert_eq!(budget.acquire(5).wait(), Err(()));
+    }
+
+    #[test]
+    fn test_over_credit_keeps_other_streams_counted() {
+        let budget = BufferBudget::new(1024);
+        let (a, b) = (BufferBudget::new(256), BufferBudget::new(256));
+        for (window, n) in &[(&a, 100), (&b, 200)] {
+            assert!(window.try_acquire(*n));
+            assert!(budget.try_acquire(*n));
+        }
+
+        credit(&a, &budget, 1 << 30);
+        assert_eq!(a.used(), 0);
+        assert_eq!(budget.used(), 200);
+
+        credit(&b, &budget, 50);
+        assert_eq!(budget.used(), 150);
+    }
+
+    #[test]
+    fn test_channel_releases_consumed_chunks() {
+        let budget = BufferBudget::new(1024);
+        let (tx, rx) = channel(&budget);
+        tx.send(vec![1, 2, 3]).wait().unwrap();
+        tx.send(vec![4, 5]).wait().unwrap();
+        assert_eq!(budget.used(), 5);
+        drop(tx);
+        let chunks: Vec<Vec<u8>> = rx.collect().wait().unwrap();
+        assert_eq!(chunks, vec![vec![1, 2, 3], vec![4, 5]]);
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn test_dropped_receiver_releases_buffered_chunks() {
+        let budget = BufferBudget::new(1024);
+        let (mut tx, rx) = channel(&budget);
+        tx.try_send(vec![0; 100]).unwrap();
+        assert_eq!(budget.used(), 100);
+        drop(rx);
+        assert_eq!(budget.used(), 0);
+        assert!(tx.try_send(vec![0; 10]).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+//! TLS termination for the http server: certificates loaded from PEM, picked per hostname from
+//! the name the client asks for (SNI), with ALPN offering h2.
+
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{
+    self, AlpnError, NameType, SniError, SslAcceptor, SslContext, SslContextBuilder, SslMethod,
+    SslSessionCacheMode,
+};
+use openssl::x509::X509;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::errors::{FlyError, FlyResult};
+
+/// Protocols offered through ALPN, h2 preferred.
+pub const ALPN_PROTOCOLS: &[u8] = b"\x02h2\x08http/1.1";
+
+// Certificate files in a certs directory are named `<hostname>.crt` and `<hostname>.key`. `*`
+// is awkward in file names, so `_wildcard.example.com.crt` holds the cert for `*.example.com`.
+const CERT_EXT: &str = "crt";
+const KEY_EXT: &str = "key";
+const WILDCARD_PREFIX: &str = "_wildcard.";
+
+/// Settings every server context shares: no session tickets, h2 or http/1.1 through ALPN.
+pub fn setup_base_ctx(builder: &mut SslContextBuilder) {
+    builder.set_options(ssl::SslOptions::NO_TICKET);
+    builder.set_alpn_select_callback(|_, client| {
+        ssl::select_next_proto(ALPN_PROTOCOLS, client).ok_or(AlpnError::NOACK)
+    });
+}
+
+/// The wildcard name covering `servername`, `*.example.com` for `www.example.com`. Wildcards
+/// only stand for one label, and never for a whole registered domain.
+pub fn wildcard_name(servername: &str) -> Option<String> {
+    let mut labels = servername.splitn(2, '.');
+    let first = labels.next()?;
+    let rest = labels.next()?;
+    if first.is_empty() || first == "*" || !rest.contains('.') {
+        return None;
+    }
+    Some(format!("*.{}", rest))
+}
+
+/// A certificate, its chain and private key.
+pub struct Identity {
+    cert: X509,
+    chain: Vec<X509>,
+    key: PKey<Private>,
+}
+
+impl Identity {
+    /// `cert` holds the certificate followed by its chain, `key` the private key.
+    pub fn from_pem(cert: &[u8], key: &[u8]) -> FlyResult<Self> {
+        let mut certs = X509::stack_from_pem(cert)
+            .map_err(|e| FlyError::from(format!("invalid certificate: {}", e)))?
+            .into_iter();
+        let cert = match certs.next() {
+            Some(c) => c,
+            None => return Err(FlyError::from("no certificate found".to_string())),
+        };
+        let key = PKey::private_key_from_pem(key)
+            .map_err(|e| FlyError::from(format!("invalid private key: {}", e)))?;
+        Ok(Identity {
+            cert,
+            chain: certs.collect(),
+            key,
+        })
+    }
+
+    pub fn from_files(cert_path: &str, key_path: &str) -> FlyResult<Self> {
+        let cert = fs::read(cert_path).map_err(|e| tls_error(cert_path, e))?;
+        let key = fs::read(key_path).map_err(|e| tls_error(key_path, e))?;
+        Identity::from_pem(&cert, &key).map_err(|e| tls_error(cert_path, e))
+    }
+
+    /// Serves this identity from `builder`, failing if the key doesn't match the certificate.
+    pub fn apply(&self, builder: &mut SslContextBuilder) -> FlyResult<()> {
+        builder
+            .set_certificate(&self.cert)
+            .map_err(|e| FlyError::from(format!("error setting certificate: {}", e)))?;
+        for cert in &self.chain {
+            builder
+                .add_extra_chain_cert(cert.clone())
+                .map_err(|e| FlyError::from(format!("error adding chain certificate: {}", e)))?;
+        }
+        builder
+            .set_private_key(&self.key)
+            .map_err(|e| FlyError::from(format!("error setting private key: {}", e)))?;
+        builder
+            .check_private_key()
+            .map_err(|e| FlyError::from(format!("private key doesn't match certificate: {}", e)))
+    }
+
+    /// A server context for this identity, switched to when a client asks for its hostname.
+    pub fn context(&self) -> FlyResult<SslContext> {
+        let mut builder = SslContextBuilder::new(SslMethod::tls())
+            .map_err(|e| FlyError::from(format!("error creating ssl context: {}", e)))?;
+        setup_base_ctx(&mut builder);
+        self.apply(&mut builder)?;
+        Ok(builder.build())
+    }
+}
+
+/// Server contexts by hostname, wildcards included.
+#[derive(Default)]
+pub struct CertStore {
+    contexts: HashMap<String, SslContext>,
+}
+
+impl CertStore {
+    pub fn new() -> Self {
+        CertStore::default()
+    }
+
+    /// Loads every `<hostname>.crt` in `dir`, along with its `<hostname>.key`.
+    pub fn from_dir(dir: &str) -> FlyResult<Self> {
+        let mut store = CertStore::new();
+        let entries = fs::read_dir(dir).map_err(|e| tls_error(dir, e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| tls_error(dir, e))?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(CERT_EXT) {
+                continue;
+            }
+            let hostname = match hostname_for(&path) {
+                Some(h) => h,
+                None => continue,
+            };
+            let key_path = path.with_extension(KEY_EXT);
+            let identity =
+                Identity::from_files(&path.to_string_lossy(), &key_path.to_string_lossy())?;
+            debug!(
+                "loaded certificate for {} from {}",
+                hostname,
+                path.display()
+            );
+            store.insert(&hostname, identity.context()?);
+        }
+        Ok(store)
+    }
+
+    pub fn insert(&mut self, hostname: &str, ctx: SslContext) {
+        self.contexts.insert(hostname.to_lowercase(), ctx);
+    }
+
+    /// The context for `servername`, or for the wildcard covering it.
+    pub fn get(&self, servername: &str) -> Option<&SslContext> {
+        let name = servername.to_lowercase();
+        self.contexts
+            .get(&name)
+            .or_else(|| wildcard_name(&name).and_then(|wildcard| self.contexts.get(&wildcard)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.contexts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty()
+    }
+}
+
+fn hostname_for(cert_path: &Path) -> Option<String> {
+    let stem = cert_path.file_stem()?.to_str()?;
+    if stem.starts_with(WILDCARD_PREFIX) {
+        Some(format!("*.{}", &stem[WILDCARD_PREFIX.len()..]))
+    } else {
+        Some(stem.to_string())
+    }
+}
+
+/// An acceptor serving certificates from `store` to clients asking for their hostname, and
+/// `default` to everyone else.
+pub fn acceptor(default: &Identity, store: Arc<CertStore>) -> FlyResult<SslAcceptor> {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())
+        .map_err(|e| FlyError::from(format!("error creating ssl acceptor: {}", e)))?;
+    setup_base_ctx(&mut builder);
+    default.apply(&mut builder)?;
+    builder.set_session_cache_mode(SslSessionCacheMode::SERVER);
+    builder.set_servername_callback(move |ssl_ref, _alert| {
+        let ctx = match ssl_ref.servername(NameType::HOST_NAME) {
+            Some(name) => store.get(name),
+            None => None,
+        };
+        if let Some(ctx) = ctx {
+            ssl_ref.set_ssl_context(ctx).map_err(|e| {
+                error!("error switching ssl context: {}", e);
+                SniError::ALERT_FATAL
+            })?;
+        }
+        Ok(())
+    });
+    Ok(builder.build())
+}
+
+fn tls_error<E: Display>(path: &str, e: E) -> FlyError {
+    FlyError::from(format!("error loading {}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::rsa::Rsa;
+    use openssl::x509::X509NameBuilder;
+
+    fn self_signed(name: &str) -> (Vec<u8>, Vec<u8>) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut subject = X509NameBuilder::new().unwrap();
+        subject.append_entry_by_text("CN", name).unwrap();
+        let subject = subject.build();
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&subject).unwrap();
+        builder.set_issuer_name(&subject).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        (
+            builder.build().to_pem().unwrap(),
+            key.private_key_to_pem_pkcs8().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_wildcard_name() {
+        assert_eq!(
+            wildcard_name("www.example.com"),
+            Some("*.example.com".to_string())
+        );
+        assert_eq!(
+            wildcard_name("a.b.example.com"),
+            Some("*.b.example.com".to_string())
+        );
+        assert_eq!(wildcard_name("example.com"), None);
+        assert_eq!(wildcard_name("*.example.com"), None);
+        assert_eq!(wildcard_name("localhost"), None);
+    }
+
+    #[test]
+    fn test_identity_key_mismatch() {
+        let (cert, _) = self_signed("example.com");
+        let (_, key) = self_signed("example.com");
+        let identity = Identity::from_pem(&cert, &key).unwrap();
+        assert!(identity.context().is_err());
+
+        assert!(Identity::from_pem(b"", &key).is_err());
+    }
+
+    #[test]
+    fn test_store_lookup() {
+        let (cert, key) = self_signed("example.com");
+        let ctx = Identity::from_pem(&cert, &key).unwrap().context().unwrap();
+        let mut store = CertStore::new();
+        store.insert("Example.com", ctx.clone());
+        store.insert("*.example.com", ctx);
+
+        assert!(store.get("example.com").is_some());
+        assert!(store.get("WWW.example.com").is_some());
+        assert!(store.get("a.b.example.com").is_none());
+        assert!(store.get("example.org").is_none());
+    }
+
+    #[test]
+    fn test_store_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in &["example.com", "_wildcard.example.org"] {
+            let (cert, key) = self_signed(name);
+            fs::write(dir.path().join(format!("{}.crt", name)), cert).unwrap();
+            fs::write(dir.path().join(format!("{}.key", name)), key).unwrap();
+        }
+        fs::write(dir.path().join("README"), "not a cert").unwrap();
+
+        let store = CertStore::from_dir(&dir.path().to_string_lossy()).unwrap();
+        assert_eq!(store.len(), 2);
+        assert!(store.get("example.com").is_some());
+        assert!(store.get("www.example.org").is_some());
+        assert!(store.get("example.org").is_none());
+
+        fs::remove_file(dir.path().join("example.com.key")).unwrap();
+        assert!(CertStore::from_dir(&dir.path().to_string_lossy()).is_err());
+    }
+}
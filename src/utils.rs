@@ -2,6 +2,7 @@ use crate::errors::FlyError;
 use crate::js::*;
 use crate::msg;
 use crate::runtime::{JsRuntime, Runtime};
+use crate::stream_channel::{BufferBudget, CREDIT_WINDOW};
 use flatbuffers::FlatBufferBuilder;
 use futures::{
   future,
@@ -91,39 +92,67 @@ pub fn send_body_stream(ptr: JsRuntime, req_id: u32, stream: JsBody) {
   let rt = ptr.to_runtime();
 
   match stream {
-    JsBody::BoxedStream(s) => rt.spawn(
-      s.map_err(|e| error!("error sending boxed stream: {}", e))
-        .for_each(move |v| {
-          send_stream_chunk(ptr, req_id, v.as_ptr() as *mut u8, v.len(), false);
-          Ok(())
-        })
-        .and_then(move |_| {
-          send_done_stream(ptr, req_id);
-          Ok(())
-        }),
-    ),
+    JsBody::BoxedStream(s) => send_credited_stream(ptr, req_id, s),
     JsBody::Static(v) => {
       rt.spawn(future::lazy(move || {
         send_stream_chunk(ptr, req_id, v.as_ptr() as *mut u8, v.len(), true);
         Ok(())
       }));
     }
-    JsBody::Stream(rx) => {
-      rt.spawn(
-        rx.map_err(move |e| error!("error reading from stream channel: {:?}", e))
-          .for_each(move |v| {
-            send_stream_chunk(ptr, req_id, v.as_ptr() as *mut u8, v.len(), false);
-            Ok(())
-          })
-          .and_then(move |_| {
-            send_done_stream(ptr, req_id);
-            Ok(())
-          }),
-      );
-    }
+    JsBody::Stream(rx) => send_credited_stream(
+      ptr,
+      req_id,
+      rx.map_err(|_| FlyError::from("error reading from stream channel".to_string())),
+    ),
   };
 }
 
+// Sends chunks no faster than JS credits the stream for what it has read. Sent bytes
+// count against the runtime's buffer budget until they're credited.
+fn send_credited_stream<S>(ptr: JsRuntime, req_id: u32, stream: S)
+where
+  S: Stream<Item = Vec<u8>, Error = FlyError> + Send + 'static,
+{
+  let rt = ptr.to_runtime();
+  let window = BufferBudget::new(CREDIT_WINDOW);
+  rt.stream_credits
+    .lock()
+    .unwrap()
+    .insert(req_id, window.clone());
+
+  let budget = rt.stream_buffer.clone();
+  let finished_window = window.clone();
+  let finished_budget = budget.clone();
+  rt.spawn(
+    stream
+      .map_err(Some)
+      .for_each(move |v| {
+        let len = v.len();
+        let budget = budget.clone();
+        // only fails when JS cancelled the stream, nothing left to tell it
+        window
+          .acquire(len)
+          .and_then(move |_| budget.acquire(len))
+          .map_err(|_| None)
+          .map(move |_| send_stream_chunk(ptr, req_id, v.as_ptr() as *mut u8, len, false))
+      })
+      .then(move |res| {
+        ptr.to_runtime().stream_credits.lock().unwrap().remove(&req_id);
+        // JS stops crediting once the stream is done
+        finished_budget.release(finished_window.used());
+        match res {
+          Ok(_) => send_done_stream(ptr, req_id),
+          Err(Some(e)) => {
+            debug!("stream {} failed: {}", req_id, e);
+            send_stream_error(ptr, req_id, &e);
+          }
+          Err(None) => debug!("stream {} ended early", req_id),
+        };
+        Ok(())
+      }),
+  );
+}
+
 pub fn send_stream_chunk(ptr: JsRuntime, req_id: u32, chunk: *mut u8, len: usize, done: bool) {
   let builder = &mut FlatBufferBuilder::new();
   let chunk_msg = msg::StreamChunk::create(
@@ -131,6 +160,7 @@ pub fn send_stream_chunk(ptr: JsRuntime, req_id: u32, chunk: *mut u8, len: usize
     &msg::StreamChunkArgs {
       id: req_id,
       done: done,
+      ..Default::default()
     },
   );
   ptr.send(
@@ -162,6 +192,37 @@ pub fn send_done_stream(ptr: JsRuntime, req_id: u32) {
     &msg::StreamChunkArgs {
       id: req_id,
       done: true,
+      ..Default::default()
+    },
+  );
+  ptr.send(
+    fly_buf_from(
+      serialize_response(
+        0,
+        builder,
+        msg::BaseArgs {
+          msg: Some(chunk_msg.as_union_value()),
+          msg_type: msg::Any::StreamChunk,
+          ..Default::default()
+        },
+      )
+      .unwrap(),
+    ),
+    None,
+  );
+}
+
+// Ends a stream with an error JS will error its reader with.
+pub fn send_stream_error(ptr: JsRuntime, req_id: u32, err: &FlyError) {
+  let builder = &mut FlatBufferBuilder::new();
+  let error = builder.create_string(&format!("{}", err));
+  let chunk_msg = msg::StreamChunk::create(
+    builder,
+    &msg::StreamChunkArgs {
+      id: req_id,
+      done: true,
+      error_kind: err.kind(),
+      error: Some(error),
     },
   );
   ptr.send(
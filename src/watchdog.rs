@@ -0,0 +1,229 @@
+use libfly::{js_runtime_cancel_terminate, js_runtime_terminate};
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(not(target_os = "linux"))]
+use std::time::Instant;
+
+use crate::runtime::JsRuntime;
+
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(10);
+
+// a watch goes from armed to either fired or disarmed, whichever happens first
+const ARMED: usize = 0;
+const FIRED: usize = 1;
+const DISARMED: usize = 2;
+
+static NEXT_WATCH_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+lazy_static! {
+  static ref WATCHES: Arc<Mutex<HashMap<usize, Watch>>> = {
+    let watches = Arc::new(Mutex::new(HashMap::new()));
+    let thread_watches = watches.clone();
+    thread::Builder::new()
+      .name("runtime-watchdog".to_string())
+      .spawn(move || watch_loop(thread_watches))
+      .unwrap();
+    watches
+  };
+}
+
+/// Maximum CPU time, in milliseconds, javascript may spend handling a single event.
+/// Everything else sent into javascript (timers, resolved promises, stream chunks) is
+/// held to `callback_ms`.
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(default)]
+pub struct CpuBudget {
+  pub fetch_ms: u64,
+  pub resolv_ms: u64,
+  pub serve_ms: u64,
+  pub callback_ms: u64,
+}
+
+impl CpuBudget {
+  pub fn for_event(&self, event: WatchedEvent) -> Duration {
+    Duration::from_millis(match event {
+      WatchedEvent::Fetch => self.fetch_ms,
+      WatchedEvent::Resolv => self.resolv_ms,
+      WatchedEvent::Serve => self.serve_ms,
+      WatchedEvent::Callback => self.callback_ms,
+    })
+  }
+}
+
+impl Default for CpuBudget {
+  fn default() -> Self {
+    CpuBudget {
+      fetch_ms: 5000,
+      resolv_ms: 1000,
+      serve_ms: 5000,
+      callback_ms: 5000,
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WatchedEvent {
+  Fetch,
+  Resolv,
+  Serve,
+  Callback,
+}
+
+impl WatchedEvent {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      WatchedEvent::Fetch => "fetch",
+      WatchedEvent::Resolv => "resolv",
+      WatchedEvent::Serve => "serve",
+      WatchedEvent::Callback => "callback",
+    }
+  }
+}
+
+struct Watch {
+  ptr: JsRuntime,
+  clock: CpuClock,
+  budget: Duration,
+  state: Arc<AtomicUsize>,
+}
+
+/// Disarms the watch when dropped. Must be created on the thread running the isolate,
+/// that's the thread whose CPU time is measured.
+pub struct WatchGuard {
+  id: Option<usize>,
+  ptr: JsRuntime,
+  state: Arc<AtomicUsize>,
+}
+
+impl WatchGuard {
+  /// Stops watching. Returns whether execution was terminated for exceeding the budget.
+  pub fn disarm(mut self) -> bool {
+    self.stop()
+  }
+
+  fn stop(&mut self) -> bool {
+    if let Some(id) = self.id.take() {
+      // the watchdog terminates while holding the lock, it can't anymore once removed
+      match WATCHES.lock() {
+        Ok(mut watches) => watches.remove(&id),
+        Err(poisoned) => poisoned.into_inner().remove(&id),
+      };
+      if self
+        .state
+        .compare_and_swap(ARMED, DISARMED, Ordering::SeqCst)
+        == FIRED
+      {
+        // the termination may have come in after javascript returned, it would cut the
+        // next send short
+        unsafe { js_runtime_cancel_terminate(self.ptr.0) };
+      }
+    }
+    self.state.load(Ordering::SeqCst) == FIRED
+  }
+}
+
+impl Drop for WatchGuard {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+pub fn arm(ptr: JsRuntime, budget: Duration) -> WatchGuard {
+  let id = NEXT_WATCH_ID.fetch_add(1, Ordering::SeqCst);
+  let state = Arc::new(AtomicUsize::new(ARMED));
+  let watch = Watch {
+    ptr,
+    clock: CpuClock::current_thread(),
+    budget,
+    state: state.clone(),
+  };
+  match WATCHES.lock() {
+    Ok(mut watches) => watches.insert(id, watch),
+    Err(poisoned) => poisoned.into_inner().insert(id, watch),
+  };
+  WatchGuard {
+    id: Some(id),
+    ptr,
+    state,
+  }
+}
+
+fn watch_loop(watches: Arc<Mutex<HashMap<usize, Watch>>>) {
+  loop {
+    thread::sleep(WATCHDOG_INTERVAL);
+    let watches = match watches.lock() {
+      Ok(w) => w,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    for watch in watches.values() {
+      if watch.clock.elapsed() < watch.budget {
+        continue;
+      }
+      if watch.state.compare_and_swap(ARMED, FIRED, Ordering::SeqCst) == ARMED {
+        unsafe { js_runtime_terminate(watch.ptr.0) };
+      }
+    }
+  }
+}
+
+// Linux lets another thread read a thread's CPU clock, elsewhere we fall back to
+// wall clock time which is stricter but still catches runaway loops.
+#[cfg(target_os = "linux")]
+struct CpuClock {
+  id: libc::clockid_t,
+  start: Duration,
+}
+
+#[cfg(target_os = "linux")]
+impl CpuClock {
+  fn current_thread() -> Self {
+    let mut id: libc::clockid_t = libc::CLOCK_MONOTONIC;
+    if unsafe { libc::pthread_getcpuclockid(libc::pthread_self(), &mut id) } != 0 {
+      warn!("could not get thread cpu clock, using monotonic clock");
+      id = libc::CLOCK_MONOTONIC;
+    }
+    CpuClock {
+      id,
+      start: read_clock(id),
+    }
+  }
+
+  fn elapsed(&self) -> Duration {
+    read_clock(self.id)
+      .checked_sub(self.start)
+      .unwrap_or_default()
+  }
+}
+
+#[cfg(target_os = "linux")]
+fn read_clock(id: libc::clockid_t) -> Duration {
+  let mut ts = libc::timespec {
+    tv_sec: 0,
+    tv_nsec: 0,
+  };
+  unsafe { libc::clock_gettime(id, &mut ts) };
+  Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+#[cfg(not(target_os = "linux"))]
+struct CpuClock {
+  start: Instant,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl CpuClock {
+  fn current_thread() -> Self {
+    CpuClock {
+      start: Instant::now(),
+    }
+  }
+
+  fn elapsed(&self) -> Duration {
+    self.start.elapsed()
+  }
+}
@@ -0,0 +1,431 @@
+//! Just enough of RFC 6455 to speak WebSocket over an upgraded hyper connection:
+//! the opening handshake and a frame codec.
+
+use bytes::{BufMut, BytesMut};
+use hyper::{header, Body, Request, Response, StatusCode};
+use sha1::Digest as Sha1Digest; // puts trait in scope
+use sha1::Sha1;
+use std::io;
+use tokio::codec::{Decoder, Encoder};
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+// close, ping and pong frames can't be any bigger
+const MAX_CONTROL_PAYLOAD: u64 = 125;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<(u16, String)>),
+}
+
+/// Which end of the connection we are, clients mask what they send and servers don't.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    Server,
+    Client,
+}
+
+pub fn is_upgrade_request<T>(req: &Request<T>) -> bool {
+    let has_token = |name: header::HeaderName, token: &str| {
+        req.headers()
+            .get_all(name)
+            .iter()
+            .any(|v| match v.to_str() {
+                Ok(s) => s.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)),
+                Err(_) => false,
+            })
+    };
+    has_token(header::CONNECTION, "upgrade") && has_token(header::UPGRADE, "websocket")
+}
+
+pub fn accept_key(key: &[u8]) -> String {
+    let mut h = Sha1::default();
+    h.input(key);
+    h.input(ACCEPT_GUID.as_bytes());
+    base64_encode(h.result().as_slice())
+}
+
+/// Response completing the opening handshake, or a 400 if the request isn't a websocket upgrade.
+pub fn upgrade_response<T>(req: &Request<T>) -> Response<Body> {
+    match req.headers().get(header::SEC_WEBSOCKET_KEY) {
+        Some(key) if is_upgrade_request(req) => Response::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(header::UPGRADE, "websocket")
+            .header(header::CONNECTION, "Upgrade")
+            .header(
+                header::SEC_WEBSOCKET_ACCEPT,
+                accept_key(key.as_bytes()).as_str(),
+            )
+            .body(Body::empty())
+            .unwrap(),
+        _ => Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from("expected a websocket upgrade"))
+            .unwrap(),
+    }
+}
+
+/// Whether a peer may send `code` in a close frame. 1005, 1006 and 1015 only describe
+/// closes locally, they never go over the wire.
+pub fn is_valid_close_code(code: u16) -> bool {
+    match code {
+        1000..=1003 | 1007..=1011 | 3000..=4999 => true,
+        _ => false,
+    }
+}
+
+pub struct Codec {
+    role: Role,
+    // opcode and payload of a fragmented message being reassembled
+    fragments: Option<(u8, Vec<u8>)>,
+}
+
+impl Codec {
+    pub fn new(role: Role) -> Self {
+        Codec {
+            role,
+            fragments: None,
+        }
+    }
+}
+
+fn protocol_error(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+fn parse_frame(src: &mut BytesMut, expect_masked: bool) -> Result<Option<Frame>, io::Error> {
+    if src.len() < 2 {
+        return Ok(None);
+    }
+    let fin = src[0] & 0x80 != 0;
+    if src[0] & 0x70 != 0 {
+        return Err(protocol_error("reserved bits set"));
+    }
+    let opcode = src[0] & 0x0F;
+    let masked = src[1] & 0x80 != 0;
+    if masked != expect_masked {
+        return Err(protocol_error(if expect_masked {
+            "client frames must be masked"
+        } else {
+            "server frames must not be masked"
+        }));
+    }
+
+    let (len, mut header_len) = match src[1] & 0x7F {
+        126 => {
+            if src.len() < 4 {
+                return Ok(None);
+            }
+            ((u64::from(src[2]) << 8 | u64::from(src[3])), 4)
+        }
+        127 => {
+            if src.len() < 10 {
+                return Ok(None);
+            }
+            let mut len = 0u64;
+            for b in &src[2..10] {
+                len = len << 8 | u64::from(*b);
+            }
+            (len, 10)
+        }
+        n => (u64::from(n), 2),
+    };
+    if len > MAX_MESSAGE_SIZE as u64 {
+        return Err(protocol_error("frame too large"));
+    }
+    if opcode & 0x08 != 0 && len > MAX_CONTROL_PAYLOAD {
+        return Err(protocol_error("control frame too large"));
+    }
+    let len = len as usize;
+
+    let mut mask = [0u8; 4];
+    if masked {
+        if src.len() < header_len + 4 {
+            return Ok(None);
+        }
+        mask.copy_from_slice(&src[header_len..header_len + 4]);
+        header_len += 4;
+    }
+
+    if src.len() < header_len + len {
+        src.reserve(header_len + len - src.len());
+        return Ok(None);
+    }
+
+    src.split_to(header_len);
+    let mut payload = src.split_to(len).to_vec();
+    if masked {
+        apply_mask(&mut payload, mask);
+    }
+    Ok(Some(Frame {
+        fin,
+        opcode,
+        payload,
+    }))
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, b) in payload.iter_mut().enumerate() {
+        *b ^= mask[i % 4];
+    }
+}
+
+fn to_message(opcode: u8, payload: Vec<u8>) -> Result<Message, io::Error> {
+    Ok(match opcode {
+        OP_TEXT => Message::Text(
+            String::from_utf8(payload)
+                .map_err(|_| protocol_error("invalid utf-8 in text message"))?,
+        ),
+        OP_BINARY => Message::Binary(payload),
+        OP_PING => Message::Ping(payload),
+        OP_PONG => Message::Pong(payload),
+        OP_CLOSE => {
+            if payload.len() == 1 {
+                return Err(protocol_error("close frame with a partial status code"));
+            }
+            if payload.len() >= 2 {
+                let code = u16::from(payload[0]) << 8 | u16::from(payload[1]);
+                Message::Close(Some((
+                    code,
+                    String::from_utf8_lossy(&payload[2..]).into_owned(),
+                )))
+            } else {
+                Message::Close(None)
+            }
+        }
+        _ => return Err(protocol_error("unknown opcode")),
+    })
+}
+
+impl Decoder for Codec {
+    type Item = Message;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Message>, io::Error> {
+        loop {
+            let frame = match parse_frame(src, self.role == Role::Server)? {
+                Some(f) => f,
+                None => return Ok(None),
+            };
+            match frame.opcode {
+                OP_CONTINUATION => {
+                    let (opcode, mut buf) = match self.fragments.take() {
+                        Some(f) => f,
+                        None => return Err(protocol_error("unexpected continuation frame")),
+                    };
+                    buf.extend_from_slice(&frame.payload);
+                    if buf.len() > MAX_MESSAGE_SIZE {
+                        return Err(protocol_error("message too large"));
+                    }
+                    if frame.fin {
+                        return to_message(opcode, buf).map(Some);
+                    }
+                    self.fragments = Some((opcode, buf));
+                }
+                OP_TEXT | OP_BINARY => {
+                    if self.fragments.is_some() {
+                        return Err(protocol_error("expected continuation frame"));
+                    }
+                    if frame.fin {
+                        return to_message(frame.opcode, frame.payload).map(Some);
+                    }
+                    self.fragments = Some((frame.opcode, frame.payload));
+                }
+                OP_CLOSE | OP_PING | OP_PONG => {
+                    if !frame.fin {
+                        return Err(protocol_error("fragmented control frame"));
+                    }
+                    return to_message(frame.opcode, frame.payload).map(Some);
+                }
+                _ => return Err(protocol_error("unknown opcode")),
+            }
+        }
+    }
+}
+
+impl Encoder for Codec {
+    type Item = Message;
+    type Error = io::Error;
+
+    fn encode(&mut self, msg: Message, dst: &mut BytesMut) -> Result<(), io::Error> {
+        let (opcode, mut payload) = match msg {
+            Message::Text(s) => (OP_TEXT, s.into_bytes()),
+            Message::Binary(b) => (OP_BINARY, b),
+            Message::Ping(b) => (OP_PING, b),
+            Message::Pong(b) => (OP_PONG, b),
+            Message::Close(None) => (OP_CLOSE, vec![]),
+            Message::Close(Some((code, reason))) => {
+                let mut b = vec![(code >> 8) as u8, code as u8];
+                b.extend_from_slice(reason.as_bytes());
+                (OP_CLOSE, b)
+            }
+        };
+
+        let mask_bit = if self.role == Role::Client { 0x80 } else { 0 };
+        dst.reserve(payload.len() + 14);
+        dst.put_u8(0x80 | opcode);
+        if payload.len() < 126 {
+            dst.put_u8(mask_bit | payload.len() as u8);
+        } else if payload.len() <= 0xFFFF {
+            dst.put_u8(mask_bit | 126);
+            dst.put_u16_be(payload.len() as u16);
+        } else {
+            dst.put_u8(mask_bit | 127);
+            dst.put_u64_be(payload.len() as u64);
+        }
+        if self.role == Role::Client {
+            let mask: [u8; 4] = rand::random();
+            dst.put_slice(&mask);
+            apply_mask(&mut payload, mask);
+        }
+        dst.put_slice(&payload);
+        Ok(())
+    }
+}
+
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub(crate) fn base64_encode(input: &[u8]) -> String {
+    let mut out = String::with_capacity((input.len() + 2) / 3 * 4);
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(from: Role, to: Role, msg: Message) -> Message {
+        let mut buf = BytesMut::new();
+        Codec::new(from).encode(msg, &mut buf).unwrap();
+        Codec::new(to).decode(&mut buf).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // example from RFC 6455 section 1.3
+        assert_eq!(
+            accept_key(b"dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_base64_padding() {
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let text = Message::Text("hello".to_string());
+        assert_eq!(roundtrip(Role::Client, Role::Server, text.clone()), text);
+        assert_eq!(roundtrip(Role::Server, Role::Client, text.clone()), text);
+
+        let big = Message::Binary(vec![7u8; 70000]);
+        assert_eq!(roundtrip(Role::Client, Role::Server, big.clone()), big);
+
+        let close = Message::Close(Some((1000, "bye".to_string())));
+        assert_eq!(roundtrip(Role::Server, Role::Client, close.clone()), close);
+    }
+
+    #[test]
+    fn test_partial_frame() {
+        let mut buf = BytesMut::new();
+        Codec::new(Role::Server)
+            .encode(Message::Text("hello".to_string()), &mut buf)
+            .unwrap();
+        let mut partial = buf.split_to(3);
+        let mut codec = Codec::new(Role::Client);
+        assert_eq!(codec.decode(&mut partial).unwrap(), None);
+        partial.extend_from_slice(&buf);
+        assert_eq!(
+            codec.decode(&mut partial).unwrap(),
+            Some(Message::Text("hello".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_fragmented_message() {
+        let mut buf = BytesMut::from(vec![0x01, 0x03, b'h', b'e', b'l', 0x80, 0x02, b'l', b'o']);
+        assert_eq!(
+            Codec::new(Role::Client).decode(&mut buf).unwrap(),
+            Some(Message::Text("hello".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_close_codes() {
+        assert!(is_valid_close_code(1000));
+        assert!(is_valid_close_code(1011));
+        assert!(is_valid_close_code(4000));
+        assert!(!is_valid_close_code(1005));
+        assert!(!is_valid_close_code(1006));
+        assert!(!is_valid_close_code(1015));
+        assert!(!is_valid_close_code(2000));
+        assert!(!is_valid_close_code(5000));
+    }
+
+    #[test]
+    fn test_invalid_control_frames() {
+        // a close payload has to fit a status code
+        let mut buf = BytesMut::from(vec![0x88, 0x01, 0x03]);
+        assert!(Codec::new(Role::Client).decode(&mut buf).is_err());
+
+        let mut buf = BytesMut::from(vec![0x89, 0x7E, 0x00, 0x7E]);
+        buf.extend_from_slice(&[0u8; 126]);
+        assert!(Codec::new(Role::Client).decode(&mut buf).is_err());
+
+        // rejected from the header, before the payload arrives
+        let mut buf = BytesMut::from(vec![0x8A, 0x7E, 0x00, 0x7E]);
+        assert!(Codec::new(Role::Client).decode(&mut buf).is_err());
+
+        let mut buf = BytesMut::from(vec![0x89, 0x7D]);
+        buf.extend_from_slice(&[0u8; 125]);
+        assert_eq!(
+            Codec::new(Role::Client).decode(&mut buf).unwrap(),
+            Some(Message::Ping(vec![0u8; 125]))
+        );
+    }
+
+    #[test]
+    fn test_server_rejects_unmasked_frames() {
+        let mut buf = BytesMut::new();
+        Codec::new(Role::Server)
+            .encode(Message::Text("hello".to_string()), &mut buf)
+            .unwrap();
+        assert!(Codec::new(Role::Server).decode(&mut buf).is_err());
+    }
+}
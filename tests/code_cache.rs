@@ -0,0 +1,90 @@
+extern crate fly;
+extern crate futures;
+extern crate hyper;
+extern crate slog_scope;
+extern crate tempfile;
+
+use fly::js::{JsEvent, JsHttpRequest};
+use fly::metrics::CODE_CACHE_LOOKUPS_TOTAL;
+use fly::runtime::{Runtime, RuntimeConfig};
+use fly::settings::{CodeCacheStoreConfig, DiskCodeCacheConfig, Settings};
+use fly::utils::EventResponseChannel;
+use futures::Future;
+use hyper::{HeaderMap, Method, StatusCode};
+
+const APP: &str = r#"
+function greet(name) {
+  return "hello " + name
+}
+addEventListener("fetch", function (event) {
+  event.respondWith(new Response(greet("cache")))
+})
+"#;
+
+fn start(settings: &Settings) -> Box<Runtime> {
+    let mut rt = Runtime::new(RuntimeConfig {
+        name: Some("code-cache-test".to_string()),
+        version: None,
+        settings,
+        module_resolvers: None,
+        app_logger: &slog_scope::logger(),
+        msg_handler: None,
+        ops: None,
+        permissions: None,
+        cpu_budget: None,
+        heap_limits: None,
+        dev_tools: false,
+        snapshot: None,
+    });
+    assert!(rt.eval("app.js", APP));
+    let _ = rt.run();
+    rt
+}
+
+fn lookups(result: &str) -> i64 {
+    CODE_CACHE_LOOKUPS_TOTAL
+        .with_label_values(&["code-cache-test", "0", result])
+        .get()
+}
+
+fn fetch_status(rt: &Runtime) -> StatusCode {
+    let res = rt
+        .dispatch_event(
+            1,
+            JsEvent::Fetch(JsHttpRequest {
+                id: 1,
+                method: Method::GET,
+                remote_addr: "127.0.0.1:12345".parse().unwrap(),
+                url: "http://localhost/".to_string(),
+                headers: HeaderMap::new(),
+                body: None,
+            }),
+        )
+        .expect("no fetch listener")
+        .unwrap_or_else(|_| panic!("error dispatching fetch event"));
+    match res {
+        EventResponseChannel::Http(rx) => rx.wait().unwrap().status,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_second_runtime_uses_cached_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut settings = Settings::default();
+    settings.code_cache = Some(CodeCacheStoreConfig::Disk(DiskCodeCacheConfig {
+        dir: dir.path().to_str().unwrap().to_string(),
+    }));
+
+    let first = start(&settings);
+    assert_eq!(lookups("miss"), 1);
+    assert_eq!(lookups("hit"), 0);
+    assert_eq!(fetch_status(&first), StatusCode::OK);
+
+    // v8 accepted the data the first runtime produced
+    let second = start(&settings);
+    assert_eq!(lookups("miss"), 1);
+    assert_eq!(lookups("hit"), 1);
+    assert_eq!(lookups("rejected"), 0);
+    assert_eq!(fetch_status(&second), StatusCode::OK);
+}
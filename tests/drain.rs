@@ -0,0 +1,93 @@
+extern crate fly;
+extern crate futures;
+extern crate hyper;
+extern crate slog_scope;
+
+use fly::js::{JsEvent, JsHttpRequest, JsHttpResponse};
+use fly::runtime::{Runtime, RuntimeConfig};
+use fly::settings::Settings;
+use fly::utils::EventResponseChannel;
+use futures::sync::oneshot;
+use futures::Future;
+use hyper::{HeaderMap, Method, StatusCode};
+use std::time::{Duration, Instant};
+
+const APP: &str = r#"
+addEventListener("fetch", function (event) {
+  if (new URL(event.request.url).pathname === "/hang") {
+    event.respondWith(new Promise(function () {}))
+    return
+  }
+  event.respondWith(new Promise(function (resolve) {
+    setTimeout(function () { resolve(new Response("late")) }, 200)
+  }))
+})
+"#;
+
+fn start() -> Box<Runtime> {
+    let mut rt = Runtime::new(RuntimeConfig {
+        name: None,
+        version: None,
+        settings: &Settings::default(),
+        module_resolvers: None,
+        app_logger: &slog_scope::logger(),
+        msg_handler: None,
+        ops: None,
+        permissions: None,
+        cpu_budget: None,
+        heap_limits: None,
+        dev_tools: false,
+        snapshot: None,
+    });
+    assert!(rt.eval("app.js", APP));
+    let _ = rt.run();
+    rt
+}
+
+fn request(id: u32, path: &str) -> JsEvent {
+    JsEvent::Fetch(JsHttpRequest {
+        id,
+        method: Method::GET,
+        remote_addr: "127.0.0.1:12345".parse().unwrap(),
+        url: format!("http://localhost{}", path),
+        headers: HeaderMap::new(),
+        body: None,
+    })
+}
+
+fn dispatch(rt: &Runtime, id: u32, path: &str) -> oneshot::Receiver<JsHttpResponse> {
+    match rt.dispatch_event(id, request(id, path)) {
+        Some(Ok(EventResponseChannel::Http(rx))) => rx,
+        _ => panic!("error dispatching fetch event"),
+    }
+}
+
+#[test]
+fn test_drain_waits_for_pending_events() {
+    let mut rt = start();
+    let rx = dispatch(&rt, 1, "/");
+
+    let started = Instant::now();
+    assert!(rt.drain(Duration::from_secs(10)));
+    // woken up by the response, not by the timeout
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(rx.wait().unwrap().status, StatusCode::OK);
+
+    assert!(rt.dispatch_event(2, request(2, "/")).is_none());
+    assert!(rt.shutdown(Duration::from_secs(1)));
+}
+
+#[test]
+fn test_drain_fails_events_after_timeout() {
+    let mut rt = start();
+    let rx = dispatch(&rt, 1, "/hang");
+
+    let started = Instant::now();
+    assert!(!rt.drain(Duration::from_millis(200)));
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    assert_eq!(rx.wait().unwrap().status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(rt.pending_events(), 0);
+
+    // reported as timed out even though nothing is pending anymore
+    assert!(!rt.shutdown(Duration::from_secs(1)));
+}
@@ -0,0 +1,124 @@
+extern crate fly;
+extern crate futures;
+extern crate hyper;
+extern crate slog_scope;
+extern crate uuid;
+
+use fly::js::{JsEvent, JsHttpRequest};
+use fly::runtime::{Runtime, RuntimeConfig};
+use fly::settings::Settings;
+use fly::standard_runtime_manager::{RuntimeFactory, StandardRuntimeManager};
+use fly::utils::EventResponseChannel;
+use fly::RuntimeManager;
+use futures::Future;
+use hyper::{HeaderMap, Method, StatusCode};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+const APP: &str = r#"
+addEventListener("fetch", function (event) {
+  event.respondWith(new Response("ok"))
+})
+"#;
+
+fn counting_factory(built: Arc<AtomicUsize>) -> RuntimeFactory {
+    Box::new(move || {
+        built.fetch_add(1, Ordering::SeqCst);
+        let mut rt = Runtime::new(RuntimeConfig {
+            name: None,
+            version: None,
+            settings: &Settings::default(),
+            module_resolvers: None,
+            app_logger: &slog_scope::logger(),
+            msg_handler: None,
+            ops: None,
+            permissions: None,
+            cpu_budget: None,
+            heap_limits: None,
+            dev_tools: false,
+            snapshot: None,
+        });
+        rt.eval("app.js", APP);
+        let _ = rt.run();
+        rt
+    })
+}
+
+fn evict(manager: &Arc<RwLock<StandardRuntimeManager>>, ttl: Duration) -> usize {
+    let evicted = manager.read().unwrap().evict_idle(ttl);
+    let count = evicted.len();
+    for rt in evicted {
+        rt.write().unwrap().shutdown(Duration::from_secs(1));
+    }
+    count
+}
+
+fn fetch_status(rt: &Runtime, id: u32) -> StatusCode {
+    let res = rt
+        .dispatch_event(
+            id,
+            JsEvent::Fetch(JsHttpRequest {
+                id,
+                method: Method::GET,
+                remote_addr: "127.0.0.1:12345".parse().unwrap(),
+                url: "http://localhost/".to_string(),
+                headers: HeaderMap::new(),
+                body: None,
+            }),
+        )
+        .expect("no fetch listener")
+        .unwrap_or_else(|_| panic!("error dispatching fetch event"));
+    match res {
+        EventResponseChannel::Http(rx) => rx.wait().unwrap().status,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_evicted_runtime_is_revived_once() {
+    let manager = StandardRuntimeManager::new();
+    let built = Arc::new(AtomicUsize::new(0));
+    let rt = manager
+        .read()
+        .unwrap()
+        .new_evictable_runtime(counting_factory(built.clone()));
+    let uuid = Uuid::parse_str(&rt.read().unwrap().get_uuid()).unwrap();
+    drop(rt);
+
+    assert_eq!(evict(&manager, Duration::from_secs(0)), 1);
+    assert!(manager.read().unwrap().live_runtimes().is_empty());
+
+    let lookups: Vec<_> = (0..8)
+        .map(|_| {
+            let manager = manager.clone();
+            thread::spawn(move || manager.read().unwrap().get_by_uuid(uuid).unwrap().unwrap())
+        })
+        .collect();
+    let revived: Vec<_> = lookups.into_iter().map(|t| t.join().unwrap()).collect();
+
+    assert_eq!(built.load(Ordering::SeqCst), 2);
+    assert!(revived.iter().all(|rt| Arc::ptr_eq(rt, &revived[0])));
+    let rt = revived[0].read().unwrap();
+    assert_eq!(Uuid::parse_str(&rt.get_uuid()).unwrap(), uuid);
+    assert_eq!(fetch_status(&rt, 1), StatusCode::OK);
+}
+
+#[test]
+fn test_looked_up_runtime_is_not_evicted() {
+    let manager = StandardRuntimeManager::new();
+    let built = Arc::new(AtomicUsize::new(0));
+    let rt = manager
+        .read()
+        .unwrap()
+        .new_evictable_runtime(counting_factory(built.clone()));
+    let uuid = Uuid::parse_str(&rt.read().unwrap().get_uuid()).unwrap();
+
+    // looked up right before the reaper runs, the event is about to be dispatched
+    let rt = manager.read().unwrap().get_by_uuid(uuid).unwrap().unwrap();
+    assert_eq!(evict(&manager, Duration::from_secs(60)), 0);
+    assert_eq!(fetch_status(&rt.read().unwrap(), 1), StatusCode::OK);
+    assert_eq!(built.load(Ordering::SeqCst), 1);
+}
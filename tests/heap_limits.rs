@@ -0,0 +1,84 @@
+extern crate fly;
+extern crate futures;
+extern crate hyper;
+extern crate slog_scope;
+extern crate uuid;
+
+use fly::js::{JsEvent, JsHttpRequest, JsHttpResponse};
+use fly::runtime::{Runtime, RuntimeConfig};
+use fly::settings::{HeapLimits, Settings};
+use fly::standard_runtime_manager::StandardRuntimeManager;
+use fly::utils::EventResponseChannel;
+use fly::RuntimeManager;
+use futures::Future;
+use hyper::{HeaderMap, Method, StatusCode};
+
+const APP: &str = r#"
+const leaked = []
+addEventListener("fetch", function (event) {
+  if (new URL(event.request.url).pathname === "/leak") {
+    while (true) {
+      leaked.push(new Array(100000).fill("leak"))
+    }
+  }
+  event.respondWith(new Response("ok"))
+})
+"#;
+
+fn fetch(rt: &Runtime, id: u32, path: &str) -> JsHttpResponse {
+    let res = rt
+        .dispatch_event(
+            id,
+            JsEvent::Fetch(JsHttpRequest {
+                id,
+                method: Method::GET,
+                remote_addr: "127.0.0.1:12345".parse().unwrap(),
+                url: format!("http://localhost{}", path),
+                headers: HeaderMap::new(),
+                body: None,
+            }),
+        )
+        .expect("no fetch listener")
+        .unwrap_or_else(|_| panic!("error dispatching fetch event"));
+    match res {
+        EventResponseChannel::Http(rx) => rx.wait().unwrap(),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_runtime_out_of_heap_is_replaced() {
+    let manager = StandardRuntimeManager::new();
+    let rt = manager.read().unwrap().new_evictable_runtime(Box::new(|| {
+        let mut rt = Runtime::new(RuntimeConfig {
+            name: None,
+            version: None,
+            settings: &Settings::default(),
+            module_resolvers: None,
+            app_logger: &slog_scope::logger(),
+            msg_handler: None,
+            ops: None,
+            permissions: None,
+            cpu_budget: None,
+            heap_limits: Some(HeapLimits {
+                soft_mb: 32,
+                hard_mb: 64,
+            }),
+            dev_tools: false,
+            snapshot: None,
+        });
+        rt.eval("app.js", APP);
+        let _ = rt.run();
+        rt
+    }));
+    let uuid = uuid::Uuid::parse_str(&rt.read().unwrap().get_uuid()).unwrap();
+
+    let res = fetch(&rt.read().unwrap(), 1, "/leak");
+    assert_eq!(res.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(rt.read().unwrap().is_poisoned());
+
+    let rt = manager.read().unwrap().get_by_uuid(uuid).unwrap().unwrap();
+    assert!(!rt.read().unwrap().is_poisoned());
+    let res = fetch(&rt.read().unwrap(), 2, "/");
+    assert_eq!(res.status, StatusCode::OK);
+}
@@ -0,0 +1,91 @@
+extern crate fly;
+extern crate futures;
+extern crate hyper;
+extern crate slog_scope;
+extern crate tempfile;
+
+use fly::js::{JsBody, JsEvent, JsHttpRequest};
+use fly::runtime::{Runtime, RuntimeConfig};
+use fly::settings::Settings;
+use fly::utils::EventResponseChannel;
+use futures::{Future, Stream};
+use hyper::{HeaderMap, Method};
+use std::fs;
+
+const APP: &str = r#"
+var caught = {};
+function report(name) {
+  return function (e) {
+    caught[name] = e.code + " " + e.specifier + " " + (e.diagnostics ? "diagnostics" : "none");
+  };
+}
+import("./missing.js").catch(report("missing"));
+import("./bad.js").catch(report("bad"));
+
+addEventListener("fetch", function (event) {
+  event.respondWith(new Response(caught.missing + "\n" + caught.bad));
+});
+
+export {};
+"#;
+
+fn fetch_body(rt: &Runtime) -> String {
+    let res = rt
+        .dispatch_event(
+            1,
+            JsEvent::Fetch(JsHttpRequest {
+                id: 1,
+                method: Method::GET,
+                remote_addr: "127.0.0.1:12345".parse().unwrap(),
+                url: "http://localhost/".to_string(),
+                headers: HeaderMap::new(),
+                body: None,
+            }),
+        )
+        .expect("no fetch listener")
+        .unwrap_or_else(|_| panic!("error dispatching fetch event"));
+    let res = match res {
+        EventResponseChannel::Http(rx) => rx.wait().unwrap(),
+        _ => unreachable!(),
+    };
+    let body = match res.body {
+        Some(JsBody::Static(body)) => body,
+        Some(JsBody::Stream(rx)) => rx.concat2().wait().unwrap(),
+        Some(JsBody::BoxedStream(s)) => s.concat2().wait().unwrap(),
+        None => vec![],
+    };
+    String::from_utf8(body).unwrap()
+}
+
+#[test]
+fn test_app_catches_failed_imports() {
+    let dir = tempfile::tempdir().unwrap();
+    let app_path = dir.path().join("app.js");
+    fs::write(&app_path, APP).unwrap();
+    fs::write(dir.path().join("bad.js"), "export const broken = ;\n").unwrap();
+
+    let mut rt = Runtime::new(RuntimeConfig {
+        name: Some("module-errors-test".to_string()),
+        version: None,
+        settings: &Settings::default(),
+        module_resolvers: None,
+        app_logger: &slog_scope::logger(),
+        msg_handler: None,
+        ops: None,
+        permissions: None,
+        cpu_budget: None,
+        heap_limits: None,
+        dev_tools: true,
+        snapshot: None,
+    });
+    // neither failed import keeps the app from starting
+    assert!(rt.eval_file_with_dev_tools(app_path.to_str().unwrap()));
+    let _ = rt.run();
+
+    let body = fetch_body(&rt);
+    let mut lines = body.lines();
+    assert_eq!(lines.next(), Some("ERR_MODULE_NOT_FOUND ./missing.js none"));
+    let bad = lines.next().unwrap();
+    assert!(bad.starts_with("ERR_MODULE_COMPILE "), "{}", bad);
+    assert!(bad.ends_with("bad.js diagnostics"), "{}", bad);
+}
@@ -0,0 +1,148 @@
+extern crate fly;
+extern crate futures;
+extern crate hyper;
+extern crate slog_scope;
+extern crate uuid;
+
+use fly::js::{JsEvent, JsHttpRequest};
+use fly::runtime::{Runtime, RuntimeConfig};
+use fly::settings::Settings;
+use fly::standard_runtime_manager::{RuntimeFactory, StandardRuntimeManager};
+use fly::utils::EventResponseChannel;
+use fly::RuntimeManager;
+use futures::Future;
+use hyper::{HeaderMap, Method, StatusCode};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const APP: &str = r#"
+addEventListener("fetch", function (event) {
+  if (new URL(event.request.url).pathname === "/hang") {
+    event.respondWith(new Promise(function () {}))
+    return
+  }
+  event.respondWith(new Response("ok"))
+})
+"#;
+
+fn counting_factory(built: Arc<AtomicUsize>) -> RuntimeFactory {
+    Box::new(move || {
+        built.fetch_add(1, Ordering::SeqCst);
+        let mut rt = Runtime::new(RuntimeConfig {
+            name: None,
+            version: None,
+            settings: &Settings::default(),
+            module_resolvers: None,
+            app_logger: &slog_scope::logger(),
+            msg_handler: None,
+            ops: None,
+            permissions: None,
+            cpu_budget: None,
+            heap_limits: None,
+            dev_tools: false,
+            snapshot: None,
+        });
+        rt.eval("app.js", APP);
+        let _ = rt.run();
+        rt
+    })
+}
+
+fn request(id: u32, path: &str) -> JsEvent {
+    JsEvent::Fetch(JsHttpRequest {
+        id,
+        method: Method::GET,
+        remote_addr: "127.0.0.1:12345".parse().unwrap(),
+        url: format!("http://localhost{}", path),
+        headers: HeaderMap::new(),
+        body: None,
+    })
+}
+
+fn fetch_status(rt: &Runtime, id: u32) -> StatusCode {
+    let res = rt
+        .dispatch_event(id, request(id, "/"))
+        .expect("no fetch listener")
+        .unwrap_or_else(|_| panic!("error dispatching fetch event"));
+    match res {
+        EventResponseChannel::Http(rx) => rx.wait().unwrap().status,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_busy_isolates_are_skipped() {
+    let manager = StandardRuntimeManager::new();
+    let members = manager
+        .read()
+        .unwrap()
+        .new_runtime_pool(2, counting_factory(Arc::new(AtomicUsize::new(0))));
+    let uuid = Uuid::parse_str(&members[0].read().unwrap().get_uuid()).unwrap();
+
+    let busy = manager.read().unwrap().get_by_uuid(uuid).unwrap().unwrap();
+    let _rx = busy.read().unwrap().dispatch_event(1, request(1, "/hang"));
+    assert_eq!(busy.read().unwrap().queue_depth().load(Ordering::SeqCst), 1);
+
+    let rt = manager.read().unwrap().get_by_uuid(uuid).unwrap().unwrap();
+    assert!(!Arc::ptr_eq(&rt, &busy));
+    assert_eq!(fetch_status(&rt.read().unwrap(), 2), StatusCode::OK);
+    assert_eq!(rt.read().unwrap().queue_depth().load(Ordering::SeqCst), 0);
+
+    // the hanging event keeps the whole pool from being evicted
+    assert!(manager
+        .read()
+        .unwrap()
+        .evict_idle(Duration::from_secs(0))
+        .is_empty());
+}
+
+#[test]
+fn test_lookups_dont_change_queue_depth() {
+    let manager = StandardRuntimeManager::new();
+    let members = manager
+        .read()
+        .unwrap()
+        .new_runtime_pool(2, counting_factory(Arc::new(AtomicUsize::new(0))));
+    let uuid = Uuid::parse_str(&members[0].read().unwrap().get_uuid()).unwrap();
+
+    let first = manager.read().unwrap().get_by_uuid(uuid).unwrap().unwrap();
+    for _ in 0..5 {
+        let rt = manager.read().unwrap().get_by_uuid(uuid).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&rt, &first));
+    }
+    for rt in members.iter() {
+        assert_eq!(rt.read().unwrap().queue_depth().load(Ordering::SeqCst), 0);
+    }
+}
+
+#[test]
+fn test_idle_pool_is_evicted_and_revived() {
+    let manager = StandardRuntimeManager::new();
+    let built = Arc::new(AtomicUsize::new(0));
+    let members = manager
+        .read()
+        .unwrap()
+        .new_runtime_pool(2, counting_factory(built.clone()));
+    let uuid = Uuid::parse_str(&members[0].read().unwrap().get_uuid()).unwrap();
+    drop(members);
+
+    let evicted = manager.read().unwrap().evict_idle(Duration::from_secs(0));
+    assert_eq!(evicted.len(), 2);
+    for rt in evicted {
+        rt.write().unwrap().shutdown(Duration::from_secs(1));
+    }
+    assert!(manager.read().unwrap().live_runtimes().is_empty());
+
+    let rt = manager.read().unwrap().get_by_uuid(uuid).unwrap().unwrap();
+    assert_eq!(built.load(Ordering::SeqCst), 4);
+    assert_eq!(manager.read().unwrap().live_runtimes().len(), 2);
+    assert_eq!(fetch_status(&rt.read().unwrap(), 1), StatusCode::OK);
+
+    // still a pool, the next lookup goes to the other isolate
+    let busy = rt;
+    let _rx = busy.read().unwrap().dispatch_event(2, request(2, "/hang"));
+    let rt = manager.read().unwrap().get_by_uuid(uuid).unwrap().unwrap();
+    assert!(!Arc::ptr_eq(&rt, &busy));
+}
@@ -0,0 +1,120 @@
+extern crate fly;
+extern crate futures;
+extern crate hyper;
+extern crate slog_scope;
+extern crate uuid;
+
+use fly::js::{JsBody, JsEvent, JsHttpRequest};
+use fly::runtime::{Runtime, RuntimeConfig};
+use fly::settings::Settings;
+use fly::standard_runtime_manager::{StandardRuntimeManager, CATCH_ALL_HOSTNAME};
+use fly::utils::EventResponseChannel;
+use fly::RuntimeManager;
+use futures::{Future, Stream};
+use hyper::{HeaderMap, Method};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+fn start_app(manager: &Arc<RwLock<StandardRuntimeManager>>, source: &str) -> (Uuid, bool) {
+    let rt = manager.write().unwrap().new_runtime(RuntimeConfig {
+        name: None,
+        version: None,
+        settings: &Settings::default(),
+        module_resolvers: None,
+        app_logger: &slog_scope::logger(),
+        msg_handler: None,
+        ops: None,
+        permissions: None,
+        cpu_budget: None,
+        heap_limits: None,
+        dev_tools: false,
+        snapshot: None,
+    });
+    let mut rt = rt.write().unwrap();
+    let ok = rt.eval("app.js", source);
+    let _ = rt.run();
+    (Uuid::parse_str(&rt.get_uuid()).unwrap(), ok)
+}
+
+fn fetch(manager: &Arc<RwLock<StandardRuntimeManager>>, id: u32, host: &str) -> String {
+    let rt = manager
+        .read()
+        .unwrap()
+        .get_by_hostname(host)
+        .unwrap_or_else(|_| panic!("error looking up runtime"))
+        .expect("app not found");
+    let rt = rt.read().unwrap();
+    let res = rt
+        .dispatch_event(
+            id,
+            JsEvent::Fetch(JsHttpRequest {
+                id,
+                method: Method::GET,
+                remote_addr: "127.0.0.1:12345".parse().unwrap(),
+                url: format!("http://{}/", host),
+                headers: HeaderMap::new(),
+                body: None,
+            }),
+        )
+        .expect("no fetch listener")
+        .unwrap_or_else(|_| panic!("error dispatching fetch event"));
+    let res = match res {
+        EventResponseChannel::Http(rx) => rx.wait().unwrap(),
+        _ => unreachable!(),
+    };
+    let body = match res.body {
+        Some(JsBody::Static(body)) => body,
+        Some(JsBody::Stream(rx)) => rx.concat2().wait().unwrap(),
+        Some(JsBody::BoxedStream(s)) => s.concat2().wait().unwrap(),
+        None => vec![],
+    };
+    String::from_utf8(body).unwrap()
+}
+
+fn app(version: &str) -> String {
+    format!(
+        r#"addEventListener("fetch", function (event) {{
+  event.respondWith(new Response("{}"))
+}})"#,
+        version
+    )
+}
+
+#[test]
+fn test_reload_replaces_the_app() {
+    let manager = StandardRuntimeManager::new();
+    manager
+        .write()
+        .unwrap()
+        .set_drain_timeout(Duration::from_secs(1));
+
+    let (v1, ok) = start_app(&manager, &app("v1"));
+    assert!(ok);
+    manager
+        .write()
+        .unwrap()
+        .bind_hostname_to(v1, CATCH_ALL_HOSTNAME)
+        .unwrap_or_else(|_| panic!("error binding app"));
+    assert_eq!(fetch(&manager, 1, "localhost:8080"), "v1");
+    assert_eq!(fetch(&manager, 2, "example.com"), "v1");
+
+    let (v2, ok) = start_app(&manager, &app("v2"));
+    assert!(ok);
+    manager
+        .read()
+        .unwrap()
+        .replace_runtime(v1, v2)
+        .unwrap_or_else(|_| panic!("error replacing runtime"));
+    assert_eq!(fetch(&manager, 3, "localhost:8080"), "v2");
+
+    // a reload that doesn't evaluate is thrown away, the previous version keeps serving
+    let (v3, ok) = start_app(&manager, "addEventListener(");
+    assert!(!ok);
+    manager
+        .read()
+        .unwrap()
+        .remove_runtime(v3)
+        .unwrap_or_else(|_| panic!("error removing runtime"));
+    assert_eq!(fetch(&manager, 4, "localhost:8080"), "v2");
+}
@@ -0,0 +1,104 @@
+extern crate fly;
+extern crate futures;
+extern crate hyper;
+extern crate slog_scope;
+
+use fly::js::{JsEvent, JsHttpRequest};
+use fly::op_registry::{OpFuture, OpRegistry};
+use fly::runtime::{Runtime, RuntimeConfig};
+use fly::scheduler;
+use fly::settings::Settings;
+use fly::utils::EventResponseChannel;
+use futures::{future, Future};
+use hyper::{HeaderMap, Method, StatusCode};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+// waits on a native op that never completes, unless asked for /ok
+const BLOCKED_APP: &str = r#"
+addEventListener("fetch", function (event) {
+  if (new URL(event.request.url).pathname === "/ok") {
+    event.respondWith(new Response("ok"))
+    return
+  }
+  event.respondWith(fly.ops.call("hang").then(function () {
+    return new Response("done")
+  }))
+})
+"#;
+
+const APP: &str = r#"
+addEventListener("fetch", function (event) {
+  event.respondWith(new Response("ok"))
+})
+"#;
+
+fn start(source: &str) -> Box<Runtime> {
+    let mut ops = OpRegistry::new();
+    ops.register("hang", |_, _| -> OpFuture { Box::new(future::empty()) });
+    let mut rt = Runtime::new(RuntimeConfig {
+        name: None,
+        version: None,
+        settings: &Settings::default(),
+        module_resolvers: None,
+        app_logger: &slog_scope::logger(),
+        msg_handler: None,
+        ops: Some(ops),
+        permissions: None,
+        cpu_budget: None,
+        heap_limits: None,
+        dev_tools: false,
+        snapshot: None,
+    });
+    assert!(rt.eval("app.js", source));
+    let _ = rt.run();
+    rt
+}
+
+fn dispatch(rt: &Runtime, id: u32, path: &str) -> mpsc::Receiver<StatusCode> {
+    let res = rt
+        .dispatch_event(
+            id,
+            JsEvent::Fetch(JsHttpRequest {
+                id,
+                method: Method::GET,
+                remote_addr: "127.0.0.1:12345".parse().unwrap(),
+                url: format!("http://localhost{}", path),
+                headers: HeaderMap::new(),
+                body: None,
+            }),
+        )
+        .expect("no fetch listener")
+        .unwrap_or_else(|_| panic!("error dispatching fetch event"));
+    let rx = match res {
+        EventResponseChannel::Http(rx) => rx,
+        _ => unreachable!(),
+    };
+    let (tx, status) = mpsc::channel();
+    thread::spawn(move || {
+        if let Ok(res) = rx.wait() {
+            let _ = tx.send(res.status);
+        }
+    });
+    status
+}
+
+#[test]
+fn test_blocked_runtime_doesnt_hold_up_its_worker() {
+    // the only test in here, so both runtimes end up on the same worker
+    scheduler::set_worker_count(1).unwrap();
+    let blocked = start(BLOCKED_APP);
+    let other = start(APP);
+
+    let hanging = dispatch(&blocked, 1, "/");
+    assert_eq!(
+        dispatch(&other, 1, "/").recv_timeout(Duration::from_secs(5)),
+        Ok(StatusCode::OK)
+    );
+    assert_eq!(
+        dispatch(&blocked, 2, "/ok").recv_timeout(Duration::from_secs(5)),
+        Ok(StatusCode::OK)
+    );
+    assert!(hanging.try_recv().is_err());
+}
@@ -0,0 +1,85 @@
+extern crate fly;
+extern crate futures;
+extern crate hyper;
+extern crate slog_scope;
+
+use fly::js::{JsBody, JsEvent, JsHttpRequest};
+use fly::runtime::{Runtime, RuntimeConfig};
+use fly::settings::Settings;
+use fly::snapshot::AppSnapshot;
+use fly::utils::EventResponseChannel;
+use futures::{Future, Stream};
+use hyper::{HeaderMap, Method};
+use std::{env, fs, process};
+
+const APP: &str = r#"
+import { greeting } from "./greeting.js"
+
+addEventListener("fetch", function (event) {
+  event.respondWith(new Response(greeting + " " + new URL(event.request.url).pathname))
+})
+"#;
+
+const GREETING: &str = r#"export const greeting = "hello from the snapshot""#;
+
+#[test]
+fn test_serves_from_app_snapshot() {
+    let dir = env::temp_dir().join(format!("fly-snapshot-test-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("index.js"), APP).unwrap();
+    fs::write(dir.join("greeting.js"), GREETING).unwrap();
+    let app_path = dir.join("index.js");
+
+    let settings = Settings::default();
+    let snapshot = AppSnapshot::create(&settings, &[], app_path.to_str().unwrap())
+        .expect("error creating snapshot");
+    // the sources are gone, everything has to come from the snapshot
+    fs::remove_dir_all(&dir).unwrap();
+
+    let mut rt = Runtime::new(RuntimeConfig {
+        name: None,
+        version: None,
+        settings: &settings,
+        module_resolvers: None,
+        app_logger: &slog_scope::logger(),
+        msg_handler: None,
+        ops: None,
+        permissions: None,
+        cpu_budget: None,
+        heap_limits: None,
+        dev_tools: false,
+        snapshot: Some(snapshot),
+    });
+    let _ = rt.run();
+
+    let res = rt
+        .dispatch_event(
+            1,
+            JsEvent::Fetch(JsHttpRequest {
+                id: 1,
+                method: Method::GET,
+                remote_addr: "127.0.0.1:12345".parse().unwrap(),
+                url: "http://localhost/path".to_string(),
+                headers: HeaderMap::new(),
+                body: None,
+            }),
+        )
+        .expect("fetch listener wasn't restored from the snapshot")
+        .unwrap_or_else(|_| panic!("error dispatching fetch event"));
+
+    let res = match res {
+        EventResponseChannel::Http(rx) => rx.wait().unwrap(),
+        _ => unreachable!(),
+    };
+    assert_eq!(res.status, 200);
+    let body = match res.body {
+        Some(JsBody::Static(body)) => body,
+        Some(JsBody::Stream(rx)) => rx.concat2().wait().unwrap(),
+        Some(JsBody::BoxedStream(s)) => s.concat2().wait().unwrap(),
+        None => vec![],
+    };
+    assert_eq!(
+        String::from_utf8(body).unwrap(),
+        "hello from the snapshot /path"
+    );
+}
@@ -0,0 +1,97 @@
+extern crate fly;
+extern crate futures;
+extern crate hyper;
+extern crate slog_scope;
+
+use fly::js::{JsBody, JsEvent, JsHttpRequest, JsHttpResponse};
+use fly::runtime::{Runtime, RuntimeConfig};
+use fly::settings::Settings;
+use fly::utils::EventResponseChannel;
+use fly::watchdog::CpuBudget;
+use futures::{Future, Stream};
+use hyper::{HeaderMap, Method, StatusCode};
+
+const APP: &str = r#"
+addEventListener("fetch", function (event) {
+  const path = new URL(event.request.url).pathname
+  if (path === "/loop") {
+    while (true) {}
+  }
+  if (path === "/timer") {
+    setTimeout(function () { while (true) {} }, 0)
+  }
+  event.respondWith(new Response("ok " + path))
+})
+"#;
+
+fn fetch(rt: &Runtime, id: u32, path: &str) -> JsHttpResponse {
+    let res = rt
+        .dispatch_event(
+            id,
+            JsEvent::Fetch(JsHttpRequest {
+                id,
+                method: Method::GET,
+                remote_addr: "127.0.0.1:12345".parse().unwrap(),
+                url: format!("http://localhost{}", path),
+                headers: HeaderMap::new(),
+                body: None,
+            }),
+        )
+        .expect("no fetch listener")
+        .unwrap_or_else(|_| panic!("error dispatching fetch event"));
+    match res {
+        EventResponseChannel::Http(rx) => rx.wait().unwrap(),
+        _ => unreachable!(),
+    }
+}
+
+fn body(res: JsHttpResponse) -> String {
+    let body = match res.body {
+        Some(JsBody::Static(body)) => body,
+        Some(JsBody::Stream(rx)) => rx.concat2().wait().unwrap(),
+        Some(JsBody::BoxedStream(s)) => s.concat2().wait().unwrap(),
+        None => vec![],
+    };
+    String::from_utf8(body).unwrap()
+}
+
+#[test]
+fn test_runaway_javascript_is_terminated() {
+    let settings = Settings::default();
+    let mut rt = Runtime::new(RuntimeConfig {
+        name: None,
+        version: None,
+        settings: &settings,
+        module_resolvers: None,
+        app_logger: &slog_scope::logger(),
+        msg_handler: None,
+        ops: None,
+        permissions: None,
+        cpu_budget: Some(CpuBudget {
+            fetch_ms: 100,
+            resolv_ms: 100,
+            serve_ms: 100,
+            callback_ms: 100,
+        }),
+        heap_limits: None,
+        dev_tools: false,
+        snapshot: None,
+    });
+    assert!(rt.eval("app.js", APP));
+    let _ = rt.run();
+
+    let res = fetch(&rt, 1, "/loop");
+    assert_eq!(res.status, StatusCode::SERVICE_UNAVAILABLE);
+
+    let res = fetch(&rt, 2, "/");
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(body(res), "ok /");
+
+    // the loop runs in a timer after the response
+    let res = fetch(&rt, 3, "/timer");
+    assert_eq!(body(res), "ok /timer");
+
+    let res = fetch(&rt, 4, "/");
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(body(res), "ok /");
+}
@@ -0,0 +1,65 @@
+/**
+ * @module fetch
+ */
+import { AbortSignal } from "./dom_types";
+
+// tslint:disable-next-line:no-any
+type AbortListener = (this: AbortSignal, ev: any) => any
+
+/**
+ * Signals a fetch to stop. Aborting after the response arrived also errors its body.
+ */
+export class FlyAbortSignal implements AbortSignal {
+	aborted = false
+	// tslint:disable-next-line:no-any
+	onabort: ((this: AbortSignal, ev: any) => any) | null = null
+
+	private listeners: AbortListener[] = []
+
+	addEventListener(type: string, listener: AbortListener) {
+		if (type === "abort" && this.listeners.indexOf(listener) === -1)
+			this.listeners.push(listener)
+	}
+
+	removeEventListener(type: string, listener: AbortListener) {
+		if (type !== "abort")
+			return
+		const i = this.listeners.indexOf(listener)
+		if (i !== -1)
+			this.listeners.splice(i, 1)
+	}
+
+	// tslint:disable-next-line:no-any
+	dispatchEvent(ev: any): boolean {
+		if (ev.type !== "abort")
+			return true
+		if (this.onabort)
+			this.onabort.call(this, ev)
+		for (const listener of this.listeners.slice())
+			listener.call(this, ev)
+		return true
+	}
+}
+
+/**
+ * Aborts fetches given its `signal`.
+ *
+ * See https://developer.mozilla.org/en-US/docs/Web/API/AbortController
+ */
+export class FlyAbortController {
+	readonly signal = new FlyAbortSignal()
+
+	abort() {
+		if (this.signal.aborted)
+			return
+		this.signal.aborted = true
+		this.signal.dispatchEvent({ type: "abort", target: this.signal })
+	}
+}
+
+export class AbortError extends Error {
+	constructor(message = "The operation was aborted.") {
+		super(message)
+		this.name = "AbortError"
+	}
+}
@@ -15,10 +15,13 @@ import { ReadableStream, ReadableStreamSource, StreamStrategy } from "@stardazed
 import { DNSRequest, DNSQuery, DNSResponse, DNSDataA, DNSDataAAAA, DNSDataCNAME, DNSDataMX, DNSDataNS, DNSDataPTR, DNSDataSOA, DNSDataSRV, DNSDataTXT } from './dns';
 import { isAcmeChallengeRequest, handleAcmeChallenge } from "./acme";
 import { ServiceRequest, ServiceResponse } from "./service";
+import { serverWebSocket, handleWebSocketEvent } from "./websocket";
 
 let nextCmdId = 1; // 0 is for events
 const promiseTable = new Map<number, util.Resolvable<fbs.Base>>();
 const listenerTable = new Map<fbs.Any, Function>();
+// what the app listens to, kept in app snapshots to be sent again when a runtime boots
+const eventTypes: fbs.EventType[] = [];
 export const streams = new Map<number, (msg: fbs.StreamChunk, raw: Uint8Array) => void>();
 
 export function handleAsyncMsgFromRust(ui8: Uint8Array, raw: Uint8Array) {
@@ -51,6 +54,11 @@ function handleEvent(base: fbs.Base, raw: Uint8Array) {
     case fbs.Any.StreamChunk:
       handleBody(base, raw);
       break;
+    case fbs.Any.WebSocketOpen:
+    case fbs.Any.WebSocketMessage:
+    case fbs.Any.WebSocketClose:
+      handleWebSocketEvent(base, raw);
+      break;
     default:
       const ln = listenerTable.get(type);
       if (!ln) {
@@ -81,31 +89,10 @@ export function addEventListener(name: string, fn: Function) {
         base.msg(msg);
         let id = msg.id();
 
-        const headersInit: Array<[string, string]> = [];
-        // console.log("headers len:", msg.headersLength());
-        for (let i = 0; i < msg.headersLength(); i++) {
-          const h = msg.headers(i);
-          // console.log("header:", h.key(), h.value());
-          // Not null operators to appease the typescript gods. These should never be null as far as I can tell.
-          headersInit.push([h!.key()!, h!.value()!]);
-        }
-
         let req = new FlyRequest(msg.url(), {
           method: fbs.HttpMethod[msg.method()].toUpperCase(),
-          headers: headersInit,
-          body: msg.hasBody() ?
-            new ReadableStream({
-              start(controller) {
-                streams.set(id, (chunkMsg: fbs.StreamChunk, raw: Uint8Array) => {
-                  // console.log("got a chunk:", chunkMsg.bytesArray());
-                  controller.enqueue(raw);
-                  if (chunkMsg.done()) {
-                    controller.close()
-                    streams.delete(chunkMsg.id())
-                  }
-                })
-              }
-            }) : null
+          headers: headersFromMsg(msg),
+          body: msg.hasBody() ? streamFromRust(id) : null
         })
 
         req.remoteAddr = msg.remoteAddr();
@@ -219,6 +206,21 @@ export function addEventListener(name: string, fn: Function) {
       break;
     }
   }
+  if (eventTypes.indexOf(event_type) === -1)
+    eventTypes.push(event_type);
+  sendAddEventListener(event_type);
+}
+
+/**
+ * Adds the listeners to a runtime started from an app snapshot, the app added
+ * them while the snapshot was created. Nothing to add for other runtimes.
+ */
+export function restoreEventListeners() {
+  for (const event_type of eventTypes)
+    sendAddEventListener(event_type);
+}
+
+function sendAddEventListener(event_type: fbs.EventType) {
   const fbb = flatbuffers.createBuilder();
   fbs.AddEventListener.startAddEventListener(fbb);
   fbs.AddEventListener.addEvent(fbb, event_type);
@@ -419,7 +421,7 @@ export async function sendStreamChunks(id: number, stream: ReadableStream) {
       value = undefined
     else
       throw new TypeError(`wrong body type: ${typeof cur.value} -> ${cur.value}`)
-    sendStreamChunk(id, cur.done, value);
+    await sendStreamChunk(id, cur.done, value);
     if (cur.done)
       done = true
     else
@@ -427,12 +429,122 @@ export async function sendStreamChunks(id: number, stream: ReadableStream) {
   }
 }
 
+// Resolves once Rust has buffered the chunk, so senders wait while the stream is full.
 export function sendStreamChunk(id: number, done: boolean, value?: BufferSource) {
   const fbb = flatbuffers.createBuilder()
   fbs.StreamChunk.startStreamChunk(fbb)
   fbs.StreamChunk.addId(fbb, id);
   fbs.StreamChunk.addDone(fbb, done);
-  sendSync(fbb, fbs.Any.StreamChunk, fbs.StreamChunk.endStreamChunk(fbb), value)
+  return sendAsync(fbb, fbs.Any.StreamChunk, fbs.StreamChunk.endStreamChunk(fbb), value)
+}
+
+// A stream of the chunks Rust sends for `id`. Rust only sends as much as has been
+// credited back, which happens as the stream's queue is read from.
+export function streamFromRust(id: number): ReadableStream {
+  let pending = 0;
+  return new ReadableStream({
+    start(controller) {
+      streams.set(id, (chunkMsg: fbs.StreamChunk, raw: Uint8Array) => {
+        if (chunkMsg.errorKind() !== fbs.ErrorKind.NoError) {
+          controller.error(new errors.FlyError(chunkMsg.errorKind(), chunkMsg.error()))
+          streams.delete(chunkMsg.id())
+          return
+        }
+        controller.enqueue(raw);
+        if (chunkMsg.done()) {
+          controller.close()
+          streams.delete(chunkMsg.id())
+          return
+        }
+        pending += raw.byteLength
+        if (controller.desiredSize! > 0) {
+          sendStreamCredit(id, pending)
+          pending = 0
+        }
+      })
+    },
+    pull() {
+      if (pending > 0) {
+        sendStreamCredit(id, pending)
+        pending = 0
+      }
+    },
+    cancel() {
+      streams.delete(id)
+      sendStreamCredit(id, 0, true)
+    }
+  })
+}
+
+export function sendStreamCredit(id: number, bytes: number, cancel = false) {
+  const fbb = flatbuffers.createBuilder()
+  fbs.StreamCredit.startStreamCredit(fbb)
+  fbs.StreamCredit.addId(fbb, id);
+  fbs.StreamCredit.addBytes(fbb, bytes);
+  fbs.StreamCredit.addCancel(fbb, cancel);
+  sendSync(fbb, fbs.Any.StreamCredit, fbs.StreamCredit.endStreamCredit(fbb))
+}
+
+interface HeadersMsg {
+  headers(index: number, obj?: fbs.HttpHeader): fbs.HttpHeader | null
+  headersLength(): number
+}
+
+// Every value of a header, in order. Values that aren't ASCII come as bytes, which
+// become a byte string like browsers expose them.
+export function headersFromMsg(msg: HeadersMsg): Array<[string, string]> {
+  const headers: Array<[string, string]> = [];
+  for (let i = 0; i < msg.headersLength(); i++) {
+    const h = msg.headers(i)!;
+    const bytes = h.valueBytesArray();
+    headers.push([h.key()!, bytes ? byteString(bytes) : h.value() || ""]);
+  }
+  return headers;
+}
+
+// Byte strings go back as the bytes they came from, anything else as UTF-8.
+export function createHeaders(fbb: flatbuffers.Builder, headers: Iterable<[string, string]>): flatbuffers.Offset {
+  const offsets: flatbuffers.Offset[] = [];
+  for (const [n, v] of headers) {
+    const key = fbb.createString(n);
+    const bytes = isByteString(v) ? fbs.HttpHeader.createValueBytesVector(fbb, stringBytes(v)) : 0;
+    const value = bytes ? 0 : fbb.createString(v);
+    fbs.HttpHeader.startHttpHeader(fbb);
+    fbs.HttpHeader.addKey(fbb, key);
+    if (bytes)
+      fbs.HttpHeader.addValueBytes(fbb, bytes);
+    else
+      fbs.HttpHeader.addValue(fbb, value);
+    offsets.push(fbs.HttpHeader.endHttpHeader(fbb));
+  }
+  return fbs.HttpRequest.createHeadersVector(fbb, offsets);
+}
+
+function byteString(bytes: Uint8Array): string {
+  let s = "";
+  for (let i = 0; i < bytes.length; i++)
+    s += String.fromCharCode(bytes[i]);
+  return s;
+}
+
+// not plain ASCII, but nothing past a byte either
+function isByteString(s: string): boolean {
+  let ascii = true;
+  for (let i = 0; i < s.length; i++) {
+    const c = s.charCodeAt(i);
+    if (c > 0xff)
+      return false;
+    if (c > 0x7f)
+      ascii = false;
+  }
+  return !ascii;
+}
+
+function stringBytes(s: string): Uint8Array {
+  const bytes = new Uint8Array(s.length);
+  for (let i = 0; i < s.length; i++)
+    bytes[i] = s.charCodeAt(i);
+  return bytes;
 }
 
 async function handleRes(id: number, res: FlyResponse) {
@@ -440,31 +552,22 @@ async function handleRes(id: number, res: FlyResponse) {
     throw new Error("BODY HAS BEEN USED, NO PUEDO!")
   // console.log("respond with!", res);
 
-  const fbb = flatbuffers.createBuilder();
+  const socket = res.webSocket ? serverWebSocket(res.webSocket) : null
 
-  let fbbHeaders = Array<number>();
+  const fbb = flatbuffers.createBuilder();
 
   try {
     // console.log("trying stuff")
-    let i = 0;
-    for (const [n, v] of res.headers) {
-      const key = fbb.createString(n);
-      const value = fbb.createString(v);
-      fbs.HttpHeader.startHttpHeader(fbb);
-      fbs.HttpHeader.addKey(fbb, key);
-      fbs.HttpHeader.addValue(fbb, value);
-      fbbHeaders[i++] = fbs.HttpHeader.endHttpHeader(fbb);
-    }
-    // console.log(fbbHeaders);
-    let resHeaders = fbs.HttpResponse.createHeadersVector(fbb, fbbHeaders);
+    let resHeaders = createHeaders(fbb, res.headers);
 
     fbs.HttpResponse.startHttpResponse(fbb);
     fbs.HttpResponse.addId(fbb, id);
     fbs.HttpResponse.addHeaders(fbb, resHeaders);
     fbs.HttpResponse.addStatus(fbb, res.status);
     let resBody = res.body;
-    let hasBody = resBody != null && (!res.isStatic || res.isStatic && res.staticBody.byteLength > 0)
+    let hasBody = !socket && resBody != null && (!res.isStatic || res.isStatic && res.staticBody.byteLength > 0)
     fbs.HttpResponse.addHasBody(fbb, hasBody)
+    fbs.HttpResponse.addWebsocket(fbb, socket != null)
 
     const resMsg = fbs.HttpResponse.endHttpResponse(fbb);
 
@@ -473,6 +576,8 @@ async function handleRes(id: number, res: FlyResponse) {
       staticBody = res.staticBody
     sendSync(fbb, fbs.Any.HttpResponse, resMsg, staticBody); // sync so we can send body chunks when it's ready!
 
+    if (socket)
+      return socket.bind(id)
     if (staticBody || !hasBody)
       return
     await sendStreamChunks(id, resBody);
@@ -20,7 +20,7 @@ class CookieJar {
 		if (parent instanceof Request) {
 			this.cookies = parseCookies((parent.headers.get("Cookie") || "").split(";"))
 		} else if (parent instanceof Response) {
-			this.cookies = parseCookies((parent.headers.get("Set-Cookie") || "").split(","))
+			this.cookies = parseCookies(parent.headers.getAll("Set-Cookie"))
 		}
 	}
 
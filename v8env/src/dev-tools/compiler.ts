@@ -238,8 +238,14 @@ export class Compiler {
       const errMsg = ts.formatDiagnosticsWithColorAndContext(diagnostics, diagnosticHost);
       console.error("Compiler error", { errMsg });
 
-      throw new Error("typescript error, quit")
-      // this._os.exit(1);
+      // shaped like the errors of imports the runtime fails, so apps can catch either
+      throw Object.assign(new Error(`Could not compile ${originUrl}`), {
+        code: "ERR_MODULE_COMPILE",
+        specifier: originUrl,
+        referer: null,
+        attempts: [],
+        diagnostics: ts.formatDiagnostics(diagnostics, diagnosticHost),
+      });
     }
 
     assert(!output.emitSkipped, "The emit was skipped for an unknown reason.");
@@ -458,11 +464,19 @@ function createLanguageService(compiler: Compiler): ts.LanguageService {
     error(s: string): void {
       console.error("[compilerHost]", s)
     },
-    resolveModuleNames(moduleNames: string[], containingFile: string, reusedNames?: string[]): ts.ResolvedModule[] {
+    resolveModuleNames(moduleNames: string[], containingFile: string, reusedNames?: string[]): (ts.ResolvedModule | undefined)[] {
       trace("resolveModuleNames()", { moduleNames, containingFile, reusedNames });
 
       return moduleNames.map(moduleName => {
-        const moduleInfo = compiler.resolveModule(moduleName, fileNameToOriginUrl(containingFile))
+        let moduleInfo: ModuleInfo;
+        try {
+          moduleInfo = compiler.resolveModule(moduleName, fileNameToOriginUrl(containingFile))
+        } catch (e) {
+          // left unresolved, the import throws when it runs. A module that can't be found
+          // doesn't keep the one importing it from compiling, an app can catch the import.
+          trace("resolveModuleNames() failed", { moduleName, e });
+          return undefined
+        }
         // an empty string will cause typescript to bomb, maybe fail here instead?
         const resolvedFileName = moduleInfo && moduleInfo.fileName || ""
         const isExternalLibraryImport = false; // need cwd/cjs logic for this maybe?
@@ -4,17 +4,21 @@
 import { RequestInit, RequestInfo } from './dom_types';
 import { FlyResponse } from './response';
 import { FlyRequest } from './request';
-import { sendAsync, streams, sendStreamChunks } from './bridge';
+import { sendAsync, sendSync, streamFromRust, sendStreamChunks, createHeaders, headersFromMsg } from './bridge';
+import { AbortError } from './abort';
 
 import * as fbs from "./msg_generated";
 import * as flatbuffers from "./flatbuffers"
-import { ReadableStream } from '@stardazed/streams';
 
 import { libfly } from './libfly';
 
 export interface FlyRequestInit extends RequestInit {
+	/** Milliseconds to wait for response headers, connecting included, defaults to the runtime's setting. The connect timeout itself can't be set per request, connections are pooled by the runtime */
 	timeout?: number,
-	readTimeout?: number
+	/** Milliseconds to read the whole response body, defaults to the runtime's setting */
+	readTimeout?: number,
+	/** Times to retry an idempotent request after a network error or a 502, 503 or 504, with backoff. Defaults to 0 */
+	retries?: number
 }
 
 const fbsMethodMap: Map<String, fbs.HttpMethod> = new Map([
@@ -29,6 +33,22 @@ const fbsMethodMap: Map<String, fbs.HttpMethod> = new Map([
 	["TRACE", fbs.HttpMethod.Trace],
 ]);
 
+const fbsRedirectMap: Map<String, fbs.RedirectMode> = new Map([
+	["follow", fbs.RedirectMode.Follow],
+	["manual", fbs.RedirectMode.Manual],
+	["error", fbs.RedirectMode.Error],
+]);
+
+// Requests without a `cache` mode don't go through the HTTP cache at all.
+const fbsCacheMap: Map<String, fbs.HttpCacheMode> = new Map([
+	["default", fbs.HttpCacheMode.Default],
+	["no-store", fbs.HttpCacheMode.NoStore],
+	["reload", fbs.HttpCacheMode.Reload],
+	["no-cache", fbs.HttpCacheMode.NoCache],
+	["force-cache", fbs.HttpCacheMode.ForceCache],
+	["only-if-cached", fbs.HttpCacheMode.OnlyIfCached],
+]);
+
 /**
  * Starts the process of fetching a network request.
  * 
@@ -46,24 +66,17 @@ export function fetch(info: RequestInfo, init?: FlyRequestInit): Promise<FlyResp
 	if (!url)
 		throw new Error("fetch url required")
 
+	const signal = req.signal
+	if (signal && signal.aborted)
+		return Promise.reject(new AbortError())
+
 	let fbbMethod = fbsMethodMap.get(req.method.toUpperCase());
 	if (typeof fbbMethod === "undefined")
 		throw new Error(`unknown http method: ${req.method}`);
 	const fbb = flatbuffers.createBuilder();
 	const urlStr = fbb.createString(url);
 
-	let fbbHeaders = Array<number>();
-
-	let i = 0;
-	for (const header of req.headers) {
-		const key = fbb.createString(header[0]);
-		const value = fbb.createString(header[1]);
-		fbs.HttpHeader.startHttpHeader(fbb);
-		fbs.HttpHeader.addKey(fbb, key);
-		fbs.HttpHeader.addValue(fbb, value);
-		fbbHeaders[i++] = fbs.HttpHeader.endHttpHeader(fbb);
-	}
-	let reqHeaders = fbs.HttpRequest.createHeadersVector(fbb, fbbHeaders);
+	let reqHeaders = createHeaders(fbb, req.headers);
 	fbs.HttpRequest.startHttpRequest(fbb);
 	const reqId = libfly.getNextStreamId();
 	fbs.HttpRequest.addId(fbb, reqId);
@@ -74,39 +87,53 @@ export function fetch(info: RequestInfo, init?: FlyRequestInit): Promise<FlyResp
 	let reqBody = req.body;
 	let hasBody = reqBody != null && (!req.isStatic || req.isStatic && req.staticBody.byteLength > 0);
 	fbs.HttpRequest.addHasBody(fbb, hasBody);
+	fbs.HttpRequest.addHeadersTimeout(fbb, init && init.timeout || 0);
+	fbs.HttpRequest.addBodyTimeout(fbb, init && init.readTimeout || 0);
+	fbs.HttpRequest.addRedirect(fbb, fbsRedirectMap.get(req.redirect) || fbs.RedirectMode.Follow);
+	fbs.HttpRequest.addRetries(fbb, Math.min(Math.max(init && init.retries || 0, 0), 255));
+	fbs.HttpRequest.addCache(fbb, fbsCacheMap.get(req.cache) || fbs.HttpCacheMode.Off);
 
 	let staticBody: BufferSource;
 	if (hasBody && req.isStatic)
 		staticBody = req.staticBody
 
-	const prom = sendAsync(fbb, fbs.Any.HttpRequest, fbs.HttpRequest.endHttpRequest(fbb), staticBody).then((base) => {
+	let prom = sendAsync(fbb, fbs.Any.HttpRequest, fbs.HttpRequest.endHttpRequest(fbb), staticBody).then((base) => {
 		let msg = new fbs.FetchHttpResponse();
 		base.msg(msg);
-		const body = msg.hasBody() ?
-			new ReadableStream({
-				start(controller) {
-					streams.set(msg.id(), (chunkMsg: fbs.StreamChunk, raw: Uint8Array) => {
-						controller.enqueue(raw);
-						if (chunkMsg.done()) {
-							controller.close()
-							streams.delete(chunkMsg.id())
-						}
-					})
-				}
-			}) : null
-		const headersInit: Array<[string, string]> = [];
-		for (let i = 0; i < msg.headersLength(); i++) {
-			const h = msg.headers(i);
-			headersInit.push([h.key(), h.value()]);
-		}
-
-		return new FlyResponse(body, { headers: headersInit, status: msg.status() })
+		const body = msg.hasBody() ? streamFromRust(msg.id()) : null
+		const res = new FlyResponse(body, { headers: headersFromMsg(msg), status: msg.status() })
+		res.url = msg.url() || url
+		res.redirected = msg.redirected()
+		return res
 	});
 
 	if (!staticBody && hasBody) // must be a stream
-		sendStreamChunks(reqId, reqBody) // don't wait for it, just start sending.
+		sendStreamChunks(reqId, reqBody).catch(() => { }) // don't wait for it, just start sending.
+
+	if (signal) {
+		const onAbort = () => abortFetch(reqId)
+		signal.addEventListener("abort", onAbort)
+		prom = prom.then(res => {
+			// the body is still abortable, while it's being read
+			if (!res.body)
+				signal.removeEventListener("abort", onAbort)
+			return res
+		}, err => {
+			signal.removeEventListener("abort", onAbort)
+			if (signal.aborted)
+				throw new AbortError()
+			throw err
+		})
+	}
 
 	return prom
 };
 
+function abortFetch(id: number) {
+	const fbb = flatbuffers.createBuilder();
+	fbs.FetchAbort.startFetchAbort(fbb);
+	fbs.FetchAbort.addId(fbb, id);
+	sendSync(fbb, fbs.Any.FetchAbort, fbs.FetchAbort.endFetchAbort(fbb));
+}
+
 export class TimeoutError extends Error { }
\ No newline at end of file
@@ -63,6 +63,19 @@ export declare module fly {
      */
     export function expire(key: string, ttl: number): Promise<boolean>
   }
+  /**
+   * Native ops registered by the application embedding fly
+   */
+  export namespace ops {
+    /**
+     * Calls a native op. `ArrayBuffer` and typed array payloads are passed as bytes,
+     * anything else as JSON.
+     * @param name The name the op was registered as
+     * @param payload What to call the op with
+     * @returns An ArrayBuffer or the decoded JSON, depending on what the op returned
+     */
+    export function call(name: string, payload?: any): Promise<any>
+  }
   //export const streams: any
   /**
    * An image manipulation library. Useful for resizing and optimizing images.
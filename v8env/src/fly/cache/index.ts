@@ -17,7 +17,7 @@
  * @module fly/cache
  */
 
-import { sendAsync, streams, sendStreamChunks, sendStreamChunk } from '../../bridge'
+import { sendAsync, streamFromRust, sendStreamChunks, sendStreamChunk } from '../../bridge'
 import * as fbs from "../../msg_generated";
 import * as flatbuffers from "../../flatbuffers";
 import { ReadableStream as WhatWGReadableStream } from '@stardazed/streams';
@@ -69,18 +69,7 @@ function _get(key: string): Promise<[ReadableStream | null, string | null]> {
   return sendAsync(fbb, fbs.Any.CacheGet, fbs.CacheGet.endCacheGet(fbb)).then(baseMsg => {
     const msg = new fbs.CacheGetReady();
     baseMsg.msg(msg);
-    const stream = msg.stream() ?
-      new WhatWGReadableStream({
-        start(controller) {
-          streams.set(msg.id(), (chunkMsg: fbs.StreamChunk, raw: Uint8Array) => {
-            controller.enqueue(raw);
-            if (chunkMsg.done()) {
-              controller.close()
-              streams.delete(chunkMsg.id())
-            }
-          })
-        }
-      }) : null;
+    const stream = msg.stream() ? streamFromRust(msg.id()) : null;
     return <[ReadableStream | null, string | null]>[stream, msg.meta()]
   })
 }
@@ -167,7 +156,7 @@ export function set(key: string, value: string | ArrayBuffer | ArrayBufferView |
       } else {
         buf = value
       }
-      sendStreamChunk(id, true, buf);
+      await sendStreamChunk(id, true, buf);
     }
     return true
   })
@@ -0,0 +1,42 @@
+/**
+ * Native ops registered by the embedding application.
+ * @module fly/ops
+ */
+
+import * as fbs from "../msg_generated";
+import * as flatbuffers from "../flatbuffers";
+import { sendAsync } from "../bridge";
+
+/**
+ * Calls the native op registered as `name`. `ArrayBuffer` and typed array payloads are
+ * passed as bytes, anything else as JSON. Resolves to an `ArrayBuffer` or to the
+ * decoded JSON, depending on what the op returned.
+ * @param name name of the op
+ * @param payload what to call the op with
+ */
+export function call(name: string, payload?: any): Promise<any> {
+  const fbb = flatbuffers.createBuilder();
+  const nameOffset = fbb.createString(name);
+  let raw: ArrayBufferView;
+  let json = false;
+  if (payload instanceof ArrayBuffer) {
+    raw = new Uint8Array(payload);
+  } else if (ArrayBuffer.isView(payload)) {
+    raw = payload;
+  } else {
+    json = true;
+    raw = new TextEncoder().encode(JSON.stringify(payload === undefined ? null : payload));
+  }
+  fbs.OpCall.startOpCall(fbb);
+  fbs.OpCall.addName(fbb, nameOffset);
+  fbs.OpCall.addJson(fbb, json);
+  return sendAsync(fbb, fbs.Any.OpCall, fbs.OpCall.endOpCall(fbb), raw).then(baseRes => {
+    const msg = new fbs.OpCallReady();
+    baseRes.msg(msg);
+    const u8 = msg.payloadArray() || new Uint8Array(0);
+    if (msg.json()) {
+      return JSON.parse(new TextDecoder("utf-8").decode(u8));
+    }
+    return u8.buffer.slice(u8.byteOffset, u8.byteOffset + u8.byteLength);
+  });
+}
@@ -18,10 +18,13 @@ import { Image } from "./fly/image";
 
 import * as url from './url';
 import { FlyRequest } from "./request";
+import { FlyAbortController } from "./abort";
+import { WebSocketPair as FlyWebSocketPair } from "./websocket";
 import * as flyData from './fly/data';
 import * as flyCache from './fly/cache';
 import * as flyResponseCache from './fly/response';
 import flyHttp from './fly/http'
+import * as flyOps from './fly/ops';
 import { loadModule } from "./module_loader";
 import { installDevTools } from "./dev-tools";
 import * as streams from "./streams";
@@ -53,6 +56,8 @@ declare global {
 
   const Response: typeof FlyResponse;
   const Request: typeof FlyRequest;
+  const AbortController: typeof FlyAbortController;
+  const WebSocketPair: typeof FlyWebSocketPair;
 
   const fetch: typeof fetch_.fetch;
 
@@ -74,6 +79,7 @@ declare global {
     responseCache: typeof flyResponseCache
     data: typeof flyData
     http: typeof flyHttp
+    ops: typeof flyOps
     Image: typeof Image
     app: AppRelease;
     runtime: Runtime;
@@ -108,6 +114,8 @@ window.clearInterval = timers.clearTimer;
 window.setImmediate = timers.setImmediate;
 window.Response = FlyResponse;
 window.Request = FlyRequest;
+window.AbortController = FlyAbortController;
+window.WebSocketPair = FlyWebSocketPair;
 
 window.addEventListener = bridge.addEventListener;
 
@@ -137,6 +145,7 @@ window.fly = {
   responseCache: flyResponseCache,
   data: flyData,
   http: flyHttp,
+  ops: flyOps,
   Image: Image,
   runtime,
 }
@@ -11,8 +11,25 @@ function isHeaders(value: any): value is domTypes.Headers {
 
 
 const headerMap = Symbol("header map");
+const headerValues = Symbol("header values");
 class HeadersBase {
-	private readonly [headerMap]: Map<string, string> = new Map();
+	// every value of a name, in the order they were added
+	private readonly [headerValues]: Map<string, string[]> = new Map();
+
+	// What gets iterated: a name's values combined, except set-cookie values which
+	// can't be without changing their meaning.
+	private get [headerMap](): Array<[string, string]> {
+		const entries: Array<[string, string]> = [];
+		for (const [name, values] of this[headerValues]) {
+			if (name === "set-cookie") {
+				for (const value of values)
+					entries.push([name, value]);
+			} else {
+				entries.push([name, values.join(", ")]);
+			}
+		}
+		return entries;
+	}
 
 	private _normalizeParams(name: string, value?: string): string[] {
 		name = String(name).toLowerCase();
@@ -25,56 +42,54 @@ class HeadersBase {
 			throw new TypeError(
 				"Failed to construct 'Headers'; The provided value was not valid"
 			);
-		} else if (isHeaders(init)) {
-			this[headerMap] = new Map(init);
-		} else {
-			this[headerMap] = new Map();
-			if (Array.isArray(init)) {
-				for (const [rawName, rawValue] of init) {
-					const [name, value] = this._normalizeParams(rawName, rawValue);
-					const existingValue = this[headerMap].get(name);
-					this[headerMap].set(
-						name,
-						existingValue ? `${existingValue}, ${value}` : value
-					);
-				}
-			} else if (init) {
-				const names = Object.keys(init);
-				for (const rawName of names) {
-					const rawValue = init[rawName];
-					const [name, value] = this._normalizeParams(rawName, rawValue);
-					this[headerMap].set(name, value);
-				}
+		} else if (isHeaders(init) || Array.isArray(init)) {
+			for (const [rawName, rawValue] of init) {
+				this.append(rawName, rawValue);
+			}
+		} else if (init) {
+			const names = Object.keys(init);
+			for (const rawName of names) {
+				this.set(rawName, init[rawName]);
 			}
 		}
 	}
 
 	append(name: string, value: string): void {
 		const [newname, newvalue] = this._normalizeParams(name, value);
-		const v = this[headerMap].get(newname);
-		const str = v ? `${v}, ${newvalue}` : newvalue;
-		this[headerMap].set(newname, str);
+		const values = this[headerValues].get(newname);
+		if (values) {
+			values.push(newvalue);
+		} else {
+			this[headerValues].set(newname, [newvalue]);
+		}
 	}
 
 	delete(name: string): void {
 		const [newname] = this._normalizeParams(name);
-		this[headerMap].delete(newname);
+		this[headerValues].delete(newname);
 	}
 
 	get(name: string): string | null {
 		const [newname] = this._normalizeParams(name);
-		const value = this[headerMap].get(newname);
-		return value || null;
+		const values = this[headerValues].get(newname);
+		return values ? values.join(", ") : null;
+	}
+
+	/** Each value of a header, uncombined. */
+	getAll(name: string): string[] {
+		const [newname] = this._normalizeParams(name);
+		const values = this[headerValues].get(newname);
+		return values ? values.slice() : [];
 	}
 
 	has(name: string): boolean {
 		const [newname] = this._normalizeParams(name);
-		return this[headerMap].has(newname);
+		return this[headerValues].has(newname);
 	}
 
 	set(name: string, value: string): void {
 		const [newname, newvalue] = this._normalizeParams(name, value);
-		this[headerMap].set(newname, newvalue);
+		this[headerValues].set(newname, [newvalue]);
 	}
 }
 
@@ -4,7 +4,7 @@
  */
 
 import { libfly } from './libfly'
-import { handleAsyncMsgFromRust } from "./bridge"
+import { handleAsyncMsgFromRust, restoreEventListeners } from "./bridge"
 import * as sourceMaps from "./source_maps";
 
 import "./globals";
@@ -24,4 +24,5 @@ export default function flyMain() {
   libfly.setGlobalErrorHandler(onGlobalError);
 
   sourceMaps.install();
+  restoreEventListeners();
 }
\ No newline at end of file
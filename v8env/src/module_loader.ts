@@ -35,13 +35,7 @@ function loadModuleWithoutReferer(specifierUrl: string): LoadedModule {
   // Write message data to handle
   resp.msg(msg);
   // Transform data into local format and return.
-  return {
-    originUrl: msg.originUrl(),
-    loadedSource: {
-      isWasm: false,
-      source: msg.sourceCode(),
-    },
-  };
+  return loadedModule(msg);
 }
 
 function loadModuleStandard(specifierUrl: string, refererOriginUrl: string): LoadedModule {
@@ -60,6 +54,17 @@ function loadModuleStandard(specifierUrl: string, refererOriginUrl: string): Loa
   // Write message data to handle
   resp.msg(msg);
   // Return data from handle
+  return loadedModule(msg);
+}
+
+// Throws why the module couldn't be loaded like a failed import does, an Error with the
+// code, specifier, referer, attempts and diagnostics of the runtime's ModuleError.
+function loadedModule(msg: fbs.LoadModuleResp): LoadedModule {
+  const error = msg.error();
+  if (error) {
+    const details = JSON.parse(error);
+    throw Object.assign(new Error(details.message), details);
+  }
   return {
     originUrl: msg.originUrl(),
     loadedSource: {
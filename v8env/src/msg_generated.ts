@@ -171,7 +171,14 @@ export enum Any{
   ServiceResponse= 43,
   RequestServiceRequest= 44,
   RequestServiceResponse= 45,
-  OsExit= 46
+  OsExit= 46,
+  OpCall= 47,
+  OpCallReady= 48,
+  StreamCredit= 49,
+  FetchAbort= 50,
+  WebSocketOpen= 51,
+  WebSocketMessage= 52,
+  WebSocketClose= 53
 };
 
 /**
@@ -212,7 +219,8 @@ export enum ErrorKind{
   HttpClosed= 31,
   HttpCanceled= 32,
   HttpParse= 33,
-  HttpOther= 34
+  HttpOther= 34,
+  EgressDenied= 35
 };
 
 /**
@@ -230,6 +238,28 @@ export enum HttpMethod{
   Trace= 8
 };
 
+/**
+ * @enum
+ */
+export enum RedirectMode{
+  Follow= 0,
+  Manual= 1,
+  Error= 2
+};
+
+/**
+ * @enum
+ */
+export enum HttpCacheMode{
+  Off= 0,
+  Default= 1,
+  NoStore= 2,
+  Reload= 3,
+  NoCache= 4,
+  ForceCache= 5,
+  OnlyIfCached= 6
+};
+
 /**
  * @enum
  */
@@ -4530,6 +4560,229 @@ static endOsExit(builder:flatbuffers.Builder):flatbuffers.Offset {
   return offset;
 };
 
+}
+/**
+ * @constructor
+ */
+export class OpCall {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns OpCall
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):OpCall {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param OpCall= obj
+ * @returns OpCall
+ */
+static getRootAsOpCall(bb:flatbuffers.ByteBuffer, obj?:OpCall):OpCall {
+  return (obj || new OpCall).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+name():string|null
+name(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+name(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @returns boolean
+ */
+json():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_json(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startOpCall(builder:flatbuffers.Builder) {
+  builder.startObject(2);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset nameOffset
+ */
+static addName(builder:flatbuffers.Builder, nameOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, nameOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean json
+ */
+static addJson(builder:flatbuffers.Builder, json:boolean) {
+  builder.addFieldInt8(1, +json, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endOpCall(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class OpCallReady {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns OpCallReady
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):OpCallReady {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param OpCallReady= obj
+ * @returns OpCallReady
+ */
+static getRootAsOpCallReady(bb:flatbuffers.ByteBuffer, obj?:OpCallReady):OpCallReady {
+  return (obj || new OpCallReady).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns boolean
+ */
+json():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_json(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @param number index
+ * @returns number
+ */
+payload(index: number):number|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.readUint8(this.bb!.__vector(this.bb_pos + offset) + index) : 0;
+};
+
+/**
+ * @returns number
+ */
+payloadLength():number {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @returns Uint8Array
+ */
+payloadArray():Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? new Uint8Array(this.bb!.bytes().buffer, this.bb!.bytes().byteOffset + this.bb!.__vector(this.bb_pos + offset), this.bb!.__vector_len(this.bb_pos + offset)) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startOpCallReady(builder:flatbuffers.Builder) {
+  builder.startObject(2);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean json
+ */
+static addJson(builder:flatbuffers.Builder, json:boolean) {
+  builder.addFieldInt8(0, +json, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset payloadOffset
+ */
+static addPayload(builder:flatbuffers.Builder, payloadOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, payloadOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param Array.<number> data
+ * @returns flatbuffers.Offset
+ */
+static createPayloadVector(builder:flatbuffers.Builder, data:number[] | Uint8Array):flatbuffers.Offset {
+  builder.startVector(1, data.length, 1);
+  for (var i = data.length - 1; i >= 0; i--) {
+    builder.addInt8(data[i]);
+  }
+  return builder.endVector();
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number numElems
+ */
+static startPayloadVector(builder:flatbuffers.Builder, numElems:number) {
+  builder.startVector(1, numElems, 1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endOpCallReady(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
 }
 /**
  * @constructor
@@ -5079,11 +5332,36 @@ value(optionalEncoding?:any):string|Uint8Array|null {
   return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
 };
 
+/**
+ * @param number index
+ * @returns number
+ */
+valueBytes(index: number):number|null {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.readUint8(this.bb!.__vector(this.bb_pos + offset) + index) : 0;
+};
+
+/**
+ * @returns number
+ */
+valueBytesLength():number {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @returns Uint8Array
+ */
+valueBytesArray():Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? new Uint8Array(this.bb!.bytes().buffer, this.bb!.bytes().byteOffset + this.bb!.__vector(this.bb_pos + offset), this.bb!.__vector_len(this.bb_pos + offset)) : null;
+};
+
 /**
  * @param flatbuffers.Builder builder
  */
 static startHttpHeader(builder:flatbuffers.Builder) {
-  builder.startObject(2);
+  builder.startObject(3);
 };
 
 /**
@@ -5102,6 +5380,35 @@ static addValue(builder:flatbuffers.Builder, valueOffset:flatbuffers.Offset) {
   builder.addFieldOffset(1, valueOffset, 0);
 };
 
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset valueBytesOffset
+ */
+static addValueBytes(builder:flatbuffers.Builder, valueBytesOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(2, valueBytesOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param Array.<number> data
+ * @returns flatbuffers.Offset
+ */
+static createValueBytesVector(builder:flatbuffers.Builder, data:number[] | Uint8Array):flatbuffers.Offset {
+  builder.startVector(1, data.length, 1);
+  for (var i = data.length - 1; i >= 0; i--) {
+    builder.addInt8(data[i]);
+  }
+  return builder.endVector();
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number numElems
+ */
+static startValueBytesVector(builder:flatbuffers.Builder, numElems:number) {
+  builder.startVector(1, numElems, 1);
+};
+
 /**
  * @param flatbuffers.Builder builder
  * @returns flatbuffers.Offset
@@ -5186,15 +5493,194 @@ mutate_done(value:boolean):boolean {
 };
 
 /**
- * @param flatbuffers.Builder builder
+ * @returns ErrorKind
  */
-static startStreamChunk(builder:flatbuffers.Builder) {
-  builder.startObject(2);
+errorKind():ErrorKind {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? /**  */ (this.bb!.readInt8(this.bb_pos + offset)) : ErrorKind.NoError;
 };
 
 /**
- * @param flatbuffers.Builder builder
- * @param number id
+ * @param ErrorKind value
+ * @returns boolean
+ */
+mutate_error_kind(value:ErrorKind):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+error():string|null
+error(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+error(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startStreamChunk(builder:flatbuffers.Builder) {
+  builder.startObject(4);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number id
+ */
+static addId(builder:flatbuffers.Builder, id:number) {
+  builder.addFieldInt32(0, id, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean done
+ */
+static addDone(builder:flatbuffers.Builder, done:boolean) {
+  builder.addFieldInt8(1, +done, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param ErrorKind errorKind
+ */
+static addErrorKind(builder:flatbuffers.Builder, errorKind:ErrorKind) {
+  builder.addFieldInt8(2, errorKind, ErrorKind.NoError);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset errorOffset
+ */
+static addError(builder:flatbuffers.Builder, errorOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(3, errorOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endStreamChunk(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class StreamCredit {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns StreamCredit
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):StreamCredit {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param StreamCredit= obj
+ * @returns StreamCredit
+ */
+static getRootAsStreamCredit(bb:flatbuffers.ByteBuffer, obj?:StreamCredit):StreamCredit {
+  return (obj || new StreamCredit).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns number
+ */
+id():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_id(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+bytes():number {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_bytes(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns boolean
+ */
+cancel():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_cancel(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startStreamCredit(builder:flatbuffers.Builder) {
+  builder.startObject(3);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number id
  */
 static addId(builder:flatbuffers.Builder, id:number) {
   builder.addFieldInt32(0, id, 0);
@@ -5202,17 +5688,402 @@ static addId(builder:flatbuffers.Builder, id:number) {
 
 /**
  * @param flatbuffers.Builder builder
- * @param boolean done
+ * @param number bytes
+ */
+static addBytes(builder:flatbuffers.Builder, bytes:number) {
+  builder.addFieldInt32(1, bytes, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean cancel
+ */
+static addCancel(builder:flatbuffers.Builder, cancel:boolean) {
+  builder.addFieldInt8(2, +cancel, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endStreamCredit(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class HttpRequest {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns HttpRequest
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):HttpRequest {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param HttpRequest= obj
+ * @returns HttpRequest
+ */
+static getRootAsHttpRequest(bb:flatbuffers.ByteBuffer, obj?:HttpRequest):HttpRequest {
+  return (obj || new HttpRequest).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns number
+ */
+id():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_id(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns HttpMethod
+ */
+method():HttpMethod {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? /**  */ (this.bb!.readInt8(this.bb_pos + offset)) : HttpMethod.Get;
+};
+
+/**
+ * @param HttpMethod value
+ * @returns boolean
+ */
+mutate_method(value:HttpMethod):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+url():string|null
+url(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+url(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param number index
+ * @param HttpHeader= obj
+ * @returns HttpHeader
+ */
+headers(index: number, obj?:HttpHeader):HttpHeader|null {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? (obj || new HttpHeader).__init(this.bb!.__indirect(this.bb!.__vector(this.bb_pos + offset) + index * 4), this.bb!) : null;
+};
+
+/**
+ * @returns number
+ */
+headersLength():number {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+remoteAddr():string|null
+remoteAddr(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+remoteAddr(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @returns boolean
+ */
+hasBody():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_has_body(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+headersTimeout():number {
+  var offset = this.bb!.__offset(this.bb_pos, 16);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_headers_timeout(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 16);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+bodyTimeout():number {
+  var offset = this.bb!.__offset(this.bb_pos, 18);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_body_timeout(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 18);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns RedirectMode
+ */
+redirect():RedirectMode {
+  var offset = this.bb!.__offset(this.bb_pos, 20);
+  return offset ? /**  */ (this.bb!.readInt8(this.bb_pos + offset)) : RedirectMode.Follow;
+};
+
+/**
+ * @param RedirectMode value
+ * @returns boolean
+ */
+mutate_redirect(value:RedirectMode):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 20);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+retries():number {
+  var offset = this.bb!.__offset(this.bb_pos, 22);
+  return offset ? this.bb!.readUint8(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_retries(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 22);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint8(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns HttpCacheMode
+ */
+cache():HttpCacheMode {
+  var offset = this.bb!.__offset(this.bb_pos, 24);
+  return offset ? /**  */ (this.bb!.readInt8(this.bb_pos + offset)) : HttpCacheMode.Off;
+};
+
+/**
+ * @param HttpCacheMode value
+ * @returns boolean
+ */
+mutate_cache(value:HttpCacheMode):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 24);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startHttpRequest(builder:flatbuffers.Builder) {
+  builder.startObject(11);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number id
+ */
+static addId(builder:flatbuffers.Builder, id:number) {
+  builder.addFieldInt32(0, id, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param HttpMethod method
+ */
+static addMethod(builder:flatbuffers.Builder, method:HttpMethod) {
+  builder.addFieldInt8(1, method, HttpMethod.Get);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset urlOffset
+ */
+static addUrl(builder:flatbuffers.Builder, urlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(2, urlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset headersOffset
+ */
+static addHeaders(builder:flatbuffers.Builder, headersOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(3, headersOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param Array.<flatbuffers.Offset> data
+ * @returns flatbuffers.Offset
+ */
+static createHeadersVector(builder:flatbuffers.Builder, data:flatbuffers.Offset[]):flatbuffers.Offset {
+  builder.startVector(4, data.length, 4);
+  for (var i = data.length - 1; i >= 0; i--) {
+    builder.addOffset(data[i]);
+  }
+  return builder.endVector();
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number numElems
+ */
+static startHeadersVector(builder:flatbuffers.Builder, numElems:number) {
+  builder.startVector(4, numElems, 4);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset remoteAddrOffset
+ */
+static addRemoteAddr(builder:flatbuffers.Builder, remoteAddrOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(4, remoteAddrOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean hasBody
+ */
+static addHasBody(builder:flatbuffers.Builder, hasBody:boolean) {
+  builder.addFieldInt8(5, +hasBody, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number headersTimeout
+ */
+static addHeadersTimeout(builder:flatbuffers.Builder, headersTimeout:number) {
+  builder.addFieldInt32(6, headersTimeout, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number bodyTimeout
  */
-static addDone(builder:flatbuffers.Builder, done:boolean) {
-  builder.addFieldInt8(1, +done, +false);
+static addBodyTimeout(builder:flatbuffers.Builder, bodyTimeout:number) {
+  builder.addFieldInt32(7, bodyTimeout, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param RedirectMode redirect
+ */
+static addRedirect(builder:flatbuffers.Builder, redirect:RedirectMode) {
+  builder.addFieldInt8(8, redirect, RedirectMode.Follow);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number retries
+ */
+static addRetries(builder:flatbuffers.Builder, retries:number) {
+  builder.addFieldInt8(9, retries, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param HttpCacheMode cache
+ */
+static addCache(builder:flatbuffers.Builder, cache:HttpCacheMode) {
+  builder.addFieldInt8(10, cache, HttpCacheMode.Off);
 };
 
 /**
  * @param flatbuffers.Builder builder
  * @returns flatbuffers.Offset
  */
-static endStreamChunk(builder:flatbuffers.Builder):flatbuffers.Offset {
+static endHttpRequest(builder:flatbuffers.Builder):flatbuffers.Offset {
   var offset = builder.endObject();
   return offset;
 };
@@ -5221,16 +6092,16 @@ static endStreamChunk(builder:flatbuffers.Builder):flatbuffers.Offset {
 /**
  * @constructor
  */
-export class HttpRequest {
+export class HttpResponse {
   bb: flatbuffers.ByteBuffer|null = null;
 
   bb_pos:number = 0;
 /**
  * @param number i
  * @param flatbuffers.ByteBuffer bb
- * @returns HttpRequest
+ * @returns HttpResponse
  */
-__init(i:number, bb:flatbuffers.ByteBuffer):HttpRequest {
+__init(i:number, bb:flatbuffers.ByteBuffer):HttpResponse {
   this.bb_pos = i;
   this.bb = bb;
   return this;
@@ -5238,11 +6109,11 @@ __init(i:number, bb:flatbuffers.ByteBuffer):HttpRequest {
 
 /**
  * @param flatbuffers.ByteBuffer bb
- * @param HttpRequest= obj
- * @returns HttpRequest
+ * @param HttpResponse= obj
+ * @returns HttpResponse
  */
-static getRootAsHttpRequest(bb:flatbuffers.ByteBuffer, obj?:HttpRequest):HttpRequest {
-  return (obj || new HttpRequest).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+static getRootAsHttpResponse(bb:flatbuffers.ByteBuffer, obj?:HttpResponse):HttpResponse {
+  return (obj || new HttpResponse).__init(bb.readInt32(bb.position()) + bb.position(), bb);
 };
 
 /**
@@ -5269,73 +6140,51 @@ mutate_id(value:number):boolean {
 };
 
 /**
- * @returns HttpMethod
+ * @param number index
+ * @param HttpHeader= obj
+ * @returns HttpHeader
  */
-method():HttpMethod {
+headers(index: number, obj?:HttpHeader):HttpHeader|null {
   var offset = this.bb!.__offset(this.bb_pos, 6);
-  return offset ? /**  */ (this.bb!.readInt8(this.bb_pos + offset)) : HttpMethod.Get;
+  return offset ? (obj || new HttpHeader).__init(this.bb!.__indirect(this.bb!.__vector(this.bb_pos + offset) + index * 4), this.bb!) : null;
 };
 
 /**
- * @param HttpMethod value
- * @returns boolean
+ * @returns number
  */
-mutate_method(value:HttpMethod):boolean {
+headersLength():number {
   var offset = this.bb!.__offset(this.bb_pos, 6);
-
-  if (offset === 0) {
-    return false;
-  }
-
-  this.bb!.writeInt8(this.bb_pos + offset, value);
-  return true;
+  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
 };
 
 /**
- * @param flatbuffers.Encoding= optionalEncoding
- * @returns string|Uint8Array|null
+ * @returns number
  */
-url():string|null
-url(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
-url(optionalEncoding?:any):string|Uint8Array|null {
+status():number {
   var offset = this.bb!.__offset(this.bb_pos, 8);
-  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+  return offset ? this.bb!.readUint16(this.bb_pos + offset) : 0;
 };
 
 /**
- * @param number index
- * @param HttpHeader= obj
- * @returns HttpHeader
+ * @param number value
+ * @returns boolean
  */
-headers(index: number, obj?:HttpHeader):HttpHeader|null {
-  var offset = this.bb!.__offset(this.bb_pos, 10);
-  return offset ? (obj || new HttpHeader).__init(this.bb!.__indirect(this.bb!.__vector(this.bb_pos + offset) + index * 4), this.bb!) : null;
-};
+mutate_status(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
 
-/**
- * @returns number
- */
-headersLength():number {
-  var offset = this.bb!.__offset(this.bb_pos, 10);
-  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
-};
+  if (offset === 0) {
+    return false;
+  }
 
-/**
- * @param flatbuffers.Encoding= optionalEncoding
- * @returns string|Uint8Array|null
- */
-remoteAddr():string|null
-remoteAddr(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
-remoteAddr(optionalEncoding?:any):string|Uint8Array|null {
-  var offset = this.bb!.__offset(this.bb_pos, 12);
-  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+  this.bb!.writeUint16(this.bb_pos + offset, value);
+  return true;
 };
 
 /**
  * @returns boolean
  */
 hasBody():boolean {
-  var offset = this.bb!.__offset(this.bb_pos, 14);
+  var offset = this.bb!.__offset(this.bb_pos, 10);
   return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
 };
 
@@ -5344,7 +6193,7 @@ hasBody():boolean {
  * @returns boolean
  */
 mutate_has_body(value:boolean):boolean {
-  var offset = this.bb!.__offset(this.bb_pos, 14);
+  var offset = this.bb!.__offset(this.bb_pos, 10);
 
   if (offset === 0) {
     return false;
@@ -5355,34 +6204,41 @@ mutate_has_body(value:boolean):boolean {
 };
 
 /**
- * @param flatbuffers.Builder builder
+ * @returns boolean
  */
-static startHttpRequest(builder:flatbuffers.Builder) {
-  builder.startObject(6);
+websocket():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
 };
 
 /**
- * @param flatbuffers.Builder builder
- * @param number id
+ * @param boolean value
+ * @returns boolean
  */
-static addId(builder:flatbuffers.Builder, id:number) {
-  builder.addFieldInt32(0, id, 0);
+mutate_websocket(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
 };
 
 /**
  * @param flatbuffers.Builder builder
- * @param HttpMethod method
  */
-static addMethod(builder:flatbuffers.Builder, method:HttpMethod) {
-  builder.addFieldInt8(1, method, HttpMethod.Get);
+static startHttpResponse(builder:flatbuffers.Builder) {
+  builder.startObject(5);
 };
 
 /**
  * @param flatbuffers.Builder builder
- * @param flatbuffers.Offset urlOffset
+ * @param number id
  */
-static addUrl(builder:flatbuffers.Builder, urlOffset:flatbuffers.Offset) {
-  builder.addFieldOffset(2, urlOffset, 0);
+static addId(builder:flatbuffers.Builder, id:number) {
+  builder.addFieldInt32(0, id, 0);
 };
 
 /**
@@ -5390,7 +6246,7 @@ static addUrl(builder:flatbuffers.Builder, urlOffset:flatbuffers.Offset) {
  * @param flatbuffers.Offset headersOffset
  */
 static addHeaders(builder:flatbuffers.Builder, headersOffset:flatbuffers.Offset) {
-  builder.addFieldOffset(3, headersOffset, 0);
+  builder.addFieldOffset(1, headersOffset, 0);
 };
 
 /**
@@ -5416,10 +6272,10 @@ static startHeadersVector(builder:flatbuffers.Builder, numElems:number) {
 
 /**
  * @param flatbuffers.Builder builder
- * @param flatbuffers.Offset remoteAddrOffset
+ * @param number status
  */
-static addRemoteAddr(builder:flatbuffers.Builder, remoteAddrOffset:flatbuffers.Offset) {
-  builder.addFieldOffset(4, remoteAddrOffset, 0);
+static addStatus(builder:flatbuffers.Builder, status:number) {
+  builder.addFieldInt16(2, status, 0);
 };
 
 /**
@@ -5427,14 +6283,22 @@ static addRemoteAddr(builder:flatbuffers.Builder, remoteAddrOffset:flatbuffers.O
  * @param boolean hasBody
  */
 static addHasBody(builder:flatbuffers.Builder, hasBody:boolean) {
-  builder.addFieldInt8(5, +hasBody, +false);
+  builder.addFieldInt8(3, +hasBody, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean websocket
+ */
+static addWebsocket(builder:flatbuffers.Builder, websocket:boolean) {
+  builder.addFieldInt8(4, +websocket, +false);
 };
 
 /**
  * @param flatbuffers.Builder builder
  * @returns flatbuffers.Offset
  */
-static endHttpRequest(builder:flatbuffers.Builder):flatbuffers.Offset {
+static endHttpResponse(builder:flatbuffers.Builder):flatbuffers.Offset {
   var offset = builder.endObject();
   return offset;
 };
@@ -5443,16 +6307,16 @@ static endHttpRequest(builder:flatbuffers.Builder):flatbuffers.Offset {
 /**
  * @constructor
  */
-export class HttpResponse {
+export class HttpRequestStart {
   bb: flatbuffers.ByteBuffer|null = null;
 
   bb_pos:number = 0;
 /**
  * @param number i
  * @param flatbuffers.ByteBuffer bb
- * @returns HttpResponse
+ * @returns HttpRequestStart
  */
-__init(i:number, bb:flatbuffers.ByteBuffer):HttpResponse {
+__init(i:number, bb:flatbuffers.ByteBuffer):HttpRequestStart {
   this.bb_pos = i;
   this.bb = bb;
   return this;
@@ -5460,11 +6324,86 @@ __init(i:number, bb:flatbuffers.ByteBuffer):HttpResponse {
 
 /**
  * @param flatbuffers.ByteBuffer bb
- * @param HttpResponse= obj
- * @returns HttpResponse
+ * @param HttpRequestStart= obj
+ * @returns HttpRequestStart
  */
-static getRootAsHttpResponse(bb:flatbuffers.ByteBuffer, obj?:HttpResponse):HttpResponse {
-  return (obj || new HttpResponse).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+static getRootAsHttpRequestStart(bb:flatbuffers.ByteBuffer, obj?:HttpRequestStart):HttpRequestStart {
+  return (obj || new HttpRequestStart).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns number
+ */
+id():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_id(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startHttpRequestStart(builder:flatbuffers.Builder) {
+  builder.startObject(1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number id
+ */
+static addId(builder:flatbuffers.Builder, id:number) {
+  builder.addFieldInt32(0, id, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endHttpRequestStart(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class FetchHttpResponse {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns FetchHttpResponse
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):FetchHttpResponse {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param FetchHttpResponse= obj
+ * @returns FetchHttpResponse
+ */
+static getRootAsFetchHttpResponse(bb:flatbuffers.ByteBuffer, obj?:FetchHttpResponse):FetchHttpResponse {
+  return (obj || new FetchHttpResponse).__init(bb.readInt32(bb.position()) + bb.position(), bb);
 };
 
 /**
@@ -5517,25 +6456,59 @@ status():number {
 };
 
 /**
- * @param number value
+ * @param number value
+ * @returns boolean
+ */
+mutate_status(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint16(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns boolean
+ */
+hasBody():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
  * @returns boolean
  */
-mutate_status(value:number):boolean {
-  var offset = this.bb!.__offset(this.bb_pos, 8);
+mutate_has_body(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
 
   if (offset === 0) {
     return false;
   }
 
-  this.bb!.writeUint16(this.bb_pos + offset, value);
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
   return true;
 };
 
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+url():string|null
+url(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+url(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
 /**
  * @returns boolean
  */
-hasBody():boolean {
-  var offset = this.bb!.__offset(this.bb_pos, 10);
+redirected():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
   return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
 };
 
@@ -5543,8 +6516,8 @@ hasBody():boolean {
  * @param boolean value
  * @returns boolean
  */
-mutate_has_body(value:boolean):boolean {
-  var offset = this.bb!.__offset(this.bb_pos, 10);
+mutate_redirected(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
 
   if (offset === 0) {
     return false;
@@ -5557,8 +6530,8 @@ mutate_has_body(value:boolean):boolean {
 /**
  * @param flatbuffers.Builder builder
  */
-static startHttpResponse(builder:flatbuffers.Builder) {
-  builder.startObject(4);
+static startFetchHttpResponse(builder:flatbuffers.Builder) {
+  builder.startObject(6);
 };
 
 /**
@@ -5614,11 +6587,27 @@ static addHasBody(builder:flatbuffers.Builder, hasBody:boolean) {
   builder.addFieldInt8(3, +hasBody, +false);
 };
 
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset urlOffset
+ */
+static addUrl(builder:flatbuffers.Builder, urlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(4, urlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean redirected
+ */
+static addRedirected(builder:flatbuffers.Builder, redirected:boolean) {
+  builder.addFieldInt8(5, +redirected, +false);
+};
+
 /**
  * @param flatbuffers.Builder builder
  * @returns flatbuffers.Offset
  */
-static endHttpResponse(builder:flatbuffers.Builder):flatbuffers.Offset {
+static endFetchHttpResponse(builder:flatbuffers.Builder):flatbuffers.Offset {
   var offset = builder.endObject();
   return offset;
 };
@@ -5627,16 +6616,16 @@ static endHttpResponse(builder:flatbuffers.Builder):flatbuffers.Offset {
 /**
  * @constructor
  */
-export class HttpRequestStart {
+export class FetchAbort {
   bb: flatbuffers.ByteBuffer|null = null;
 
   bb_pos:number = 0;
 /**
  * @param number i
  * @param flatbuffers.ByteBuffer bb
- * @returns HttpRequestStart
+ * @returns FetchAbort
  */
-__init(i:number, bb:flatbuffers.ByteBuffer):HttpRequestStart {
+__init(i:number, bb:flatbuffers.ByteBuffer):FetchAbort {
   this.bb_pos = i;
   this.bb = bb;
   return this;
@@ -5644,11 +6633,11 @@ __init(i:number, bb:flatbuffers.ByteBuffer):HttpRequestStart {
 
 /**
  * @param flatbuffers.ByteBuffer bb
- * @param HttpRequestStart= obj
- * @returns HttpRequestStart
+ * @param FetchAbort= obj
+ * @returns FetchAbort
  */
-static getRootAsHttpRequestStart(bb:flatbuffers.ByteBuffer, obj?:HttpRequestStart):HttpRequestStart {
-  return (obj || new HttpRequestStart).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+static getRootAsFetchAbort(bb:flatbuffers.ByteBuffer, obj?:FetchAbort):FetchAbort {
+  return (obj || new FetchAbort).__init(bb.readInt32(bb.position()) + bb.position(), bb);
 };
 
 /**
@@ -5677,7 +6666,7 @@ mutate_id(value:number):boolean {
 /**
  * @param flatbuffers.Builder builder
  */
-static startHttpRequestStart(builder:flatbuffers.Builder) {
+static startFetchAbort(builder:flatbuffers.Builder) {
   builder.startObject(1);
 };
 
@@ -5693,7 +6682,7 @@ static addId(builder:flatbuffers.Builder, id:number) {
  * @param flatbuffers.Builder builder
  * @returns flatbuffers.Offset
  */
-static endHttpRequestStart(builder:flatbuffers.Builder):flatbuffers.Offset {
+static endFetchAbort(builder:flatbuffers.Builder):flatbuffers.Offset {
   var offset = builder.endObject();
   return offset;
 };
@@ -5702,16 +6691,16 @@ static endHttpRequestStart(builder:flatbuffers.Builder):flatbuffers.Offset {
 /**
  * @constructor
  */
-export class FetchHttpResponse {
+export class WebSocketOpen {
   bb: flatbuffers.ByteBuffer|null = null;
 
   bb_pos:number = 0;
 /**
  * @param number i
  * @param flatbuffers.ByteBuffer bb
- * @returns FetchHttpResponse
+ * @returns WebSocketOpen
  */
-__init(i:number, bb:flatbuffers.ByteBuffer):FetchHttpResponse {
+__init(i:number, bb:flatbuffers.ByteBuffer):WebSocketOpen {
   this.bb_pos = i;
   this.bb = bb;
   return this;
@@ -5719,11 +6708,11 @@ __init(i:number, bb:flatbuffers.ByteBuffer):FetchHttpResponse {
 
 /**
  * @param flatbuffers.ByteBuffer bb
- * @param FetchHttpResponse= obj
- * @returns FetchHttpResponse
+ * @param WebSocketOpen= obj
+ * @returns WebSocketOpen
  */
-static getRootAsFetchHttpResponse(bb:flatbuffers.ByteBuffer, obj?:FetchHttpResponse):FetchHttpResponse {
-  return (obj || new FetchHttpResponse).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+static getRootAsWebSocketOpen(bb:flatbuffers.ByteBuffer, obj?:WebSocketOpen):WebSocketOpen {
+  return (obj || new WebSocketOpen).__init(bb.readInt32(bb.position()) + bb.position(), bb);
 };
 
 /**
@@ -5750,51 +6739,85 @@ mutate_id(value:number):boolean {
 };
 
 /**
- * @param number index
- * @param HttpHeader= obj
- * @returns HttpHeader
+ * @param flatbuffers.Builder builder
  */
-headers(index: number, obj?:HttpHeader):HttpHeader|null {
-  var offset = this.bb!.__offset(this.bb_pos, 6);
-  return offset ? (obj || new HttpHeader).__init(this.bb!.__indirect(this.bb!.__vector(this.bb_pos + offset) + index * 4), this.bb!) : null;
+static startWebSocketOpen(builder:flatbuffers.Builder) {
+  builder.startObject(1);
 };
 
 /**
- * @returns number
+ * @param flatbuffers.Builder builder
+ * @param number id
  */
-headersLength():number {
-  var offset = this.bb!.__offset(this.bb_pos, 6);
-  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
+static addId(builder:flatbuffers.Builder, id:number) {
+  builder.addFieldInt32(0, id, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endWebSocketOpen(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class WebSocketMessage {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns WebSocketMessage
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):WebSocketMessage {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param WebSocketMessage= obj
+ * @returns WebSocketMessage
+ */
+static getRootAsWebSocketMessage(bb:flatbuffers.ByteBuffer, obj?:WebSocketMessage):WebSocketMessage {
+  return (obj || new WebSocketMessage).__init(bb.readInt32(bb.position()) + bb.position(), bb);
 };
 
 /**
  * @returns number
  */
-status():number {
-  var offset = this.bb!.__offset(this.bb_pos, 8);
-  return offset ? this.bb!.readUint16(this.bb_pos + offset) : 0;
+id():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
 };
 
 /**
  * @param number value
  * @returns boolean
  */
-mutate_status(value:number):boolean {
-  var offset = this.bb!.__offset(this.bb_pos, 8);
+mutate_id(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
 
   if (offset === 0) {
     return false;
   }
 
-  this.bb!.writeUint16(this.bb_pos + offset, value);
+  this.bb!.writeUint32(this.bb_pos + offset, value);
   return true;
 };
 
 /**
  * @returns boolean
  */
-hasBody():boolean {
-  var offset = this.bb!.__offset(this.bb_pos, 10);
+binary():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
   return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
 };
 
@@ -5802,8 +6825,8 @@ hasBody():boolean {
  * @param boolean value
  * @returns boolean
  */
-mutate_has_body(value:boolean):boolean {
-  var offset = this.bb!.__offset(this.bb_pos, 10);
+mutate_binary(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
 
   if (offset === 0) {
     return false;
@@ -5816,8 +6839,8 @@ mutate_has_body(value:boolean):boolean {
 /**
  * @param flatbuffers.Builder builder
  */
-static startFetchHttpResponse(builder:flatbuffers.Builder) {
-  builder.startObject(4);
+static startWebSocketMessage(builder:flatbuffers.Builder) {
+  builder.startObject(2);
 };
 
 /**
@@ -5830,54 +6853,142 @@ static addId(builder:flatbuffers.Builder, id:number) {
 
 /**
  * @param flatbuffers.Builder builder
- * @param flatbuffers.Offset headersOffset
+ * @param boolean binary
  */
-static addHeaders(builder:flatbuffers.Builder, headersOffset:flatbuffers.Offset) {
-  builder.addFieldOffset(1, headersOffset, 0);
+static addBinary(builder:flatbuffers.Builder, binary:boolean) {
+  builder.addFieldInt8(1, +binary, +false);
 };
 
 /**
  * @param flatbuffers.Builder builder
- * @param Array.<flatbuffers.Offset> data
  * @returns flatbuffers.Offset
  */
-static createHeadersVector(builder:flatbuffers.Builder, data:flatbuffers.Offset[]):flatbuffers.Offset {
-  builder.startVector(4, data.length, 4);
-  for (var i = data.length - 1; i >= 0; i--) {
-    builder.addOffset(data[i]);
+static endWebSocketMessage(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class WebSocketClose {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns WebSocketClose
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):WebSocketClose {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param WebSocketClose= obj
+ * @returns WebSocketClose
+ */
+static getRootAsWebSocketClose(bb:flatbuffers.ByteBuffer, obj?:WebSocketClose):WebSocketClose {
+  return (obj || new WebSocketClose).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns number
+ */
+id():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_id(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
   }
-  return builder.endVector();
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+code():number {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.readUint16(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_code(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint16(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+reason():string|null
+reason(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+reason(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
 };
 
 /**
  * @param flatbuffers.Builder builder
- * @param number numElems
  */
-static startHeadersVector(builder:flatbuffers.Builder, numElems:number) {
-  builder.startVector(4, numElems, 4);
+static startWebSocketClose(builder:flatbuffers.Builder) {
+  builder.startObject(3);
 };
 
 /**
  * @param flatbuffers.Builder builder
- * @param number status
+ * @param number id
  */
-static addStatus(builder:flatbuffers.Builder, status:number) {
-  builder.addFieldInt16(2, status, 0);
+static addId(builder:flatbuffers.Builder, id:number) {
+  builder.addFieldInt32(0, id, 0);
 };
 
 /**
  * @param flatbuffers.Builder builder
- * @param boolean hasBody
+ * @param number code
  */
-static addHasBody(builder:flatbuffers.Builder, hasBody:boolean) {
-  builder.addFieldInt8(3, +hasBody, +false);
+static addCode(builder:flatbuffers.Builder, code:number) {
+  builder.addFieldInt16(1, code, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset reasonOffset
+ */
+static addReason(builder:flatbuffers.Builder, reasonOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(2, reasonOffset, 0);
 };
 
 /**
  * @param flatbuffers.Builder builder
  * @returns flatbuffers.Offset
  */
-static endFetchHttpResponse(builder:flatbuffers.Builder):flatbuffers.Offset {
+static endWebSocketClose(builder:flatbuffers.Builder):flatbuffers.Offset {
   var offset = builder.endObject();
   return offset;
 };
@@ -6749,11 +7860,22 @@ sourceCode(optionalEncoding?:any):string|Uint8Array|null {
   return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
 };
 
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+error():string|null
+error(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+error(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
 /**
  * @param flatbuffers.Builder builder
  */
 static startLoadModuleResp(builder:flatbuffers.Builder) {
-  builder.startObject(2);
+  builder.startObject(3);
 };
 
 /**
@@ -6772,6 +7894,14 @@ static addSourceCode(builder:flatbuffers.Builder, sourceCodeOffset:flatbuffers.O
   builder.addFieldOffset(1, sourceCodeOffset, 0);
 };
 
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset errorOffset
+ */
+static addError(builder:flatbuffers.Builder, errorOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(2, errorOffset, 0);
+};
+
 /**
  * @param flatbuffers.Builder builder
  * @returns flatbuffers.Offset
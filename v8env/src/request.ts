@@ -91,6 +91,9 @@ export class FlyRequest extends FlyBody implements Request {
 		// readonly attribute RequestCredentials credentials;
 		this.credentials = 'omit';
 
+		// readonly attribute RequestRedirect redirect;
+		this.redirect = 'follow';
+
 		if (input instanceof FlyRequest) {
 			if (input.bodyUsed) throw TypeError();
 			this.method = input.method;
@@ -101,6 +104,9 @@ export class FlyRequest extends FlyBody implements Request {
 			this.remoteAddr = input.remoteAddr;
 			this.referrer = input.referrer;
 			this.mode = input.mode;
+			this.signal = input.signal;
+			this.redirect = input.redirect;
+			this.cache = input.cache;
 		} else {
 			this.headers = new FlyHeaders({})
 			this.url = <string>input
@@ -124,6 +130,18 @@ export class FlyRequest extends FlyBody implements Request {
 			this.headers = new FlyHeaders(init.headers);
 		}
 
+		if ('signal' in init) {
+			this.signal = init.signal
+		}
+
+		if ('redirect' in init &&
+			(['follow', 'error', 'manual'].indexOf(init.redirect) !== -1))
+			this.redirect = init.redirect;
+
+		if ('cache' in init &&
+			(['default', 'no-store', 'reload', 'no-cache', 'force-cache', 'only-if-cached'].indexOf(init.cache) !== -1))
+			this.cache = init.cache;
+
 		if ('credentials' in init &&
 			(['omit', 'same-origin', 'include'].indexOf(init.credentials) !== -1))
 			this.credentials = init.credentials;
@@ -159,7 +177,10 @@ export class FlyRequest extends FlyBody implements Request {
 			remoteAddr: this.remoteAddr,
 			method: this.method,
 			headers: headersList,
-			credentials: this.credentials
+			credentials: this.credentials,
+			redirect: this.redirect,
+			cache: this.cache,
+			signal: this.signal
 		})
 		return cloned
 	}
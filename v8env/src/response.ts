@@ -6,9 +6,15 @@ import FlyBody from './body_mixin'
 import { FlyHeaders } from './headers';
 import { Response, Headers, ResponseType, ResponseInit, BodyInit } from './dom_types';
 import { ReadableStream } from '@stardazed/streams';
+import { FlyWebSocket } from './websocket';
 
 function ushort(x) { return x & 0xFFFF; }
 
+export interface FlyResponseInit extends ResponseInit {
+	/** the client end of a `WebSocketPair`, accepting an upgrade request */
+	webSocket?: FlyWebSocket
+}
+
 /**
  * Class representing a fetch response.
  */
@@ -21,6 +27,7 @@ export class FlyResponse extends FlyBody implements Response {
 	type: ResponseType
 	redirected: boolean
 	trailer: Promise<Headers>
+	webSocket: FlyWebSocket | null
 	private cookieJar: CookieJar
 
 	static redirect(url, status = 302) {
@@ -32,7 +39,7 @@ export class FlyResponse extends FlyBody implements Response {
 		})
 	}
 
-	constructor(body: BodyInit, init: ResponseInit | FlyResponse) {
+	constructor(body: BodyInit, init: FlyResponseInit | FlyResponse) {
 		if (arguments.length < 1)
 			body = '';
 
@@ -59,7 +66,15 @@ export class FlyResponse extends FlyBody implements Response {
 
 		// readonly attribute unsigned short status;
 		var status = 'status' in init ? ushort(init.status) : 200;
-		if (status < 200 || status > 599) throw RangeError();
+		var webSocket = init.webSocket || null;
+		if (webSocket ? status !== 101 : status < 200 || status > 599) throw RangeError();
+
+		/**
+		 * @public
+		 * @type {FlyWebSocket}
+		 * @readonly
+		 */
+		this.webSocket = webSocket;
 
 		/**
 		 * @public
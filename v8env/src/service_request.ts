@@ -1,6 +1,6 @@
 import * as fbs from "./msg_generated";
 import * as flatbuffers from "./flatbuffers"
-import { sendSync } from "./bridge";
+import { sendAsync } from "./bridge";
 
 export interface ServiceResponse {
     success: boolean;
@@ -11,8 +11,9 @@ export interface ServiceResponse {
  * 
  * @param destinationName The `ServiceName` of the service you want to send this to.
  * @param data The data retured by said service.
+ * @returns A promise, the service may be running on the same thread as the caller.
  */
-export function serviceRequest(destinationName: string, data: any): ServiceResponse {
+export function serviceRequest(destinationName: string, data: any): Promise<ServiceResponse> {
     const fbb = flatbuffers.createBuilder();
 
     const destinationNameString = fbb.createString(destinationName);
@@ -22,14 +23,14 @@ export function serviceRequest(destinationName: string, data: any): ServiceRespo
     fbs.RequestServiceRequest.addDestinationName(fbb, destinationNameString);
     fbs.RequestServiceRequest.addData(fbb, dataString);
 
-    const resp = sendSync(fbb, fbs.Any.RequestServiceRequest, fbs.RequestServiceRequest.endRequestServiceRequest(fbb));
+    return sendAsync(fbb, fbs.Any.RequestServiceRequest, fbs.RequestServiceRequest.endRequestServiceRequest(fbb)).then(resp => {
+        const msg = new fbs.RequestServiceResponse();
+        // Write message data to handle
+        resp.msg(msg);
 
-    const msg = new fbs.RequestServiceResponse();
-    // Write message data to handle
-    resp.msg(msg);
-
-    return {
-        success: msg.success(),
-        data: JSON.parse(msg.data()),
-    }
+        return {
+            success: msg.success(),
+            data: JSON.parse(msg.data()),
+        }
+    });
 }
\ No newline at end of file
@@ -0,0 +1,269 @@
+/**
+ * @module fetch
+ */
+import * as flatbuffers from "./flatbuffers";
+import * as fbs from "./msg_generated";
+import { sendAsync, sendStreamCredit } from "./bridge";
+import { TextDecoder, TextEncoder } from "./text-encoding";
+
+// tslint:disable-next-line:no-any
+type WebSocketListener = (this: FlyWebSocket, ev: any) => any
+
+const CONNECTING = 0
+const OPEN = 1
+const CLOSING = 2
+const CLOSED = 3
+
+// the longest reason that fits in a close frame's control payload
+const MAX_REASON_BYTES = 123
+
+// sockets bound to the request they answer, by request id
+const sockets = new Map<number, FlyWebSocket>()
+
+/**
+ * One end of a WebSocket. The server end of a `WebSocketPair` talks to the client
+ * once the client end was returned in a 101 response.
+ */
+export class FlyWebSocket {
+	static readonly CONNECTING = CONNECTING
+	static readonly OPEN = OPEN
+	static readonly CLOSING = CLOSING
+	static readonly CLOSED = CLOSED
+
+	readyState = CONNECTING
+	bufferedAmount = 0
+	binaryType = "arraybuffer"
+
+	onopen: WebSocketListener | null = null
+	onmessage: WebSocketListener | null = null
+	onclose: WebSocketListener | null = null
+	onerror: WebSocketListener | null = null
+
+	/** @internal the other end of the pair */
+	peer: FlyWebSocket | null = null
+	/** @internal */
+	accepted = false
+
+	private id: number | null = null
+	private listeners = new Map<string, WebSocketListener[]>()
+	// frames sent before the socket was bound to a request, a close goes last
+	private queue: Array<() => void> = []
+	// frames go out one at a time, in order
+	private sending: Promise<void> = Promise.resolve()
+
+	/**
+	 * Starts handling the socket in this isolate. Only the server end is accepted.
+	 */
+	accept() {
+		if (this.accepted)
+			throw new Error("WebSocket already accepted")
+		this.accepted = true
+	}
+
+	send(data: string | ArrayBuffer | ArrayBufferView) {
+		if (!this.accepted)
+			throw new Error("WebSocket must be accepted before sending")
+		if (this.readyState >= CLOSING)
+			throw new Error("WebSocket is closed")
+
+		let binary = true
+		let payload: Uint8Array
+		if (typeof data === "string") {
+			binary = false
+			payload = new TextEncoder().encode(data)
+		} else if (data instanceof ArrayBuffer) {
+			payload = new Uint8Array(data)
+		} else if (ArrayBuffer.isView(data)) {
+			payload = new Uint8Array(data.buffer, data.byteOffset, data.byteLength)
+		} else {
+			throw new TypeError(`unsupported message type: ${typeof data}`)
+		}
+
+		this.bufferedAmount += payload.byteLength
+		this.whenBound(() => this.sendFrame(binary, payload))
+	}
+
+	/**
+	 * Starts the closing handshake. `code` is 1000 or in 3000-4999, a close without
+	 * one tells the client no status.
+	 */
+	close(code?: number, reason = "") {
+		if (code !== undefined && code !== 1000 && (code < 3000 || code > 4999))
+			throw new RangeError(`invalid close code: ${code}`)
+		const reasonBytes = new TextEncoder().encode(reason)
+		if (reasonBytes.byteLength > MAX_REASON_BYTES)
+			throw new RangeError(`close reason is longer than ${MAX_REASON_BYTES} bytes`)
+		if (this.readyState >= CLOSING)
+			return
+		this.readyState = CLOSING
+		this.whenBound(() => {
+			const id = this.id!
+			this.sending = this.sending.then(() => sendClose(id, code || 0, reason))
+		})
+	}
+
+	addEventListener(type: string, listener: WebSocketListener) {
+		const listeners = this.listeners.get(type) || []
+		if (listeners.indexOf(listener) === -1)
+			listeners.push(listener)
+		this.listeners.set(type, listeners)
+	}
+
+	removeEventListener(type: string, listener: WebSocketListener) {
+		const listeners = this.listeners.get(type)
+		if (!listeners)
+			return
+		const i = listeners.indexOf(listener)
+		if (i !== -1)
+			listeners.splice(i, 1)
+	}
+
+	// tslint:disable-next-line:no-any
+	dispatchEvent(ev: any): boolean {
+		const handler = this.handlers()[ev.type]
+		if (handler)
+			handler.call(this, ev)
+		for (const listener of (this.listeners.get(ev.type) || []).slice())
+			listener.call(this, ev)
+		return true
+	}
+
+	/** @internal */
+	bind(id: number) {
+		this.id = id
+		sockets.set(id, this)
+		for (const frame of this.queue)
+			frame()
+		this.queue = []
+	}
+
+	private handlers(): { [type: string]: WebSocketListener | null } {
+		return { open: this.onopen, message: this.onmessage, close: this.onclose, error: this.onerror }
+	}
+
+	private whenBound(frame: () => void) {
+		if (this.id === null)
+			this.queue.push(frame)
+		else
+			frame()
+	}
+
+	private sendFrame(binary: boolean, payload: Uint8Array) {
+		const id = this.id!
+		this.sending = this.sending
+			.then(() => sendMessage(id, binary, payload))
+			// the close event tells why the socket is gone
+			.catch(() => undefined)
+			.then(() => { this.bufferedAmount -= payload.byteLength })
+	}
+}
+
+/**
+ * Two connected WebSocket ends. `0` goes in the response answering the upgrade,
+ * `1` is accepted and used by the app.
+ *
+ * @example
+ * const pair = new WebSocketPair()
+ * pair[1].accept()
+ * pair[1].addEventListener("message", ev => pair[1].send(ev.data))
+ * return new Response(null, { status: 101, webSocket: pair[0] })
+ */
+export class WebSocketPair {
+	readonly 0: FlyWebSocket
+	readonly 1: FlyWebSocket
+
+	constructor() {
+		const client = new FlyWebSocket()
+		const server = new FlyWebSocket()
+		client.peer = server
+		server.peer = client
+		this[0] = client
+		this[1] = server
+	}
+}
+
+/**
+ * The server end of `client`'s pair, to bind to the request the response answers.
+ * @internal
+ */
+export function serverWebSocket(client: FlyWebSocket): FlyWebSocket {
+	const server = client.peer
+	if (!server || !server.accepted)
+		throw new Error("the server end of the WebSocketPair must be accepted before responding")
+	return server
+}
+
+/**
+ * Delivers a socket event from Rust. Message payloads count against the socket's
+ * stream credit until they've been dispatched.
+ * @internal
+ */
+export function handleWebSocketEvent(base: fbs.Base, raw: Uint8Array) {
+	switch (base.msgType()) {
+		case fbs.Any.WebSocketOpen: {
+			const msg = new fbs.WebSocketOpen()
+			base.msg(msg)
+			const socket = sockets.get(msg.id())
+			if (!socket || socket.readyState !== CONNECTING)
+				return
+			socket.readyState = OPEN
+			socket.dispatchEvent({ type: "open", target: socket })
+			break
+		}
+		case fbs.Any.WebSocketMessage: {
+			const msg = new fbs.WebSocketMessage()
+			base.msg(msg)
+			const id = msg.id()
+			const socket = sockets.get(id)
+			if (!socket)
+				return
+			const payload = raw || new Uint8Array(0)
+			const data = msg.binary() ? payload.slice().buffer : new TextDecoder().decode(payload)
+			try {
+				socket.dispatchEvent({ type: "message", target: socket, data })
+			} finally {
+				sendStreamCredit(id, payload.byteLength)
+			}
+			break
+		}
+		case fbs.Any.WebSocketClose: {
+			const msg = new fbs.WebSocketClose()
+			base.msg(msg)
+			const socket = sockets.get(msg.id())
+			if (!socket)
+				return
+			sockets.delete(msg.id())
+			socket.readyState = CLOSED
+			const code = msg.code()
+			if (code === 1006)
+				socket.dispatchEvent({ type: "error", target: socket })
+			socket.dispatchEvent({
+				type: "close",
+				target: socket,
+				code,
+				reason: msg.reason() || "",
+				wasClean: code !== 1006
+			})
+			break
+		}
+	}
+}
+
+function sendMessage(id: number, binary: boolean, payload: Uint8Array) {
+	const fbb = flatbuffers.createBuilder()
+	fbs.WebSocketMessage.startWebSocketMessage(fbb)
+	fbs.WebSocketMessage.addId(fbb, id)
+	fbs.WebSocketMessage.addBinary(fbb, binary)
+	return sendAsync(fbb, fbs.Any.WebSocketMessage, fbs.WebSocketMessage.endWebSocketMessage(fbb), payload)
+}
+
+function sendClose(id: number, code: number, reason: string) {
+	const fbb = flatbuffers.createBuilder()
+	const reasonOffset = fbb.createString(reason)
+	fbs.WebSocketClose.startWebSocketClose(fbb)
+	fbs.WebSocketClose.addId(fbb, id)
+	fbs.WebSocketClose.addCode(fbb, code)
+	fbs.WebSocketClose.addReason(fbb, reasonOffset)
+	return sendAsync(fbb, fbs.Any.WebSocketClose, fbs.WebSocketClose.endWebSocketClose(fbb))
+		.then(() => undefined, () => undefined)
+}
@@ -12,4 +12,21 @@ describe("Headers", () => {
 
     expect(headers.get("test")).to.eq("value1, value2, value3")
   })
-})
\ No newline at end of file
+  it("keeps set-cookie values apart", () => {
+    const headers = new Headers([
+      ["Set-Cookie", "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT"],
+      ["Via", "1.1 one"],
+      ["Set-Cookie", "b=2"],
+      ["Via", "1.1 two"],
+    ])
+
+    expect(headers.getAll("set-cookie")).to.deep.eq(["a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT", "b=2"])
+    expect(headers.get("via")).to.eq("1.1 one, 1.1 two")
+    expect(Array.from(headers)).to.deep.eq([
+      ["set-cookie", "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT"],
+      ["set-cookie", "b=2"],
+      ["via", "1.1 one, 1.1 two"],
+    ])
+    expect(new Headers(headers).getAll("set-cookie").length).to.eq(2)
+  })
+})
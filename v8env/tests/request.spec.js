@@ -13,6 +13,48 @@ describe("Request", () => {
     expect(req.bodySource).to.eq(r.bodySource)
   })
 
+  it("keeps the abort signal", () => {
+    const controller = new AbortController()
+    const r = new Request("https://example.com", { signal: controller.signal })
+
+    expect(new Request(r).signal).to.eq(controller.signal)
+    expect(r.clone().signal).to.eq(controller.signal)
+  })
+
+  it("keeps the redirect mode", () => {
+    expect(new Request("https://example.com").redirect).to.eq("follow")
+
+    const r = new Request("https://example.com", { redirect: "manual" })
+    expect(r.redirect).to.eq("manual")
+    expect(new Request(r).redirect).to.eq("manual")
+    expect(r.clone().redirect).to.eq("manual")
+    expect(new Request(r, { redirect: "error" }).redirect).to.eq("error")
+  })
+
+  it("keeps the cache mode", () => {
+    expect(new Request("https://example.com").cache).to.eq(undefined)
+
+    const r = new Request("https://example.com", { cache: "no-cache" })
+    expect(r.cache).to.eq("no-cache")
+    expect(new Request(r).cache).to.eq("no-cache")
+    expect(r.clone().cache).to.eq("no-cache")
+    expect(new Request("https://example.com", { cache: "bogus" }).cache).to.eq(undefined)
+  })
+
+  it("rejects fetches already aborted", async () => {
+    const controller = new AbortController()
+    controller.abort()
+    expect(controller.signal.aborted).to.eq(true)
+
+    let err
+    try {
+      await fetch("https://example.com", { signal: controller.signal })
+    } catch (e) {
+      err = e
+    }
+    expect(err.name).to.eq("AbortError")
+  })
+
   it('returns an ArrayBuffer given a Int8Array', async () => {
     const r = new Response(new Int8Array(buffer))
 
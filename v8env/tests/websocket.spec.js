@@ -0,0 +1,57 @@
+describe("WebSocketPair", () => {
+  it("creates two connecting ends", () => {
+    const pair = new WebSocketPair()
+    expect(pair[0]).not.to.eq(pair[1])
+    expect(pair[0].readyState).to.eq(0)
+    expect(pair[1].readyState).to.eq(0)
+  })
+
+  it("only sends once accepted", () => {
+    const pair = new WebSocketPair()
+    expect(() => pair[1].send("hello")).to.throw(/accepted/)
+    pair[1].accept()
+    expect(() => pair[1].accept()).to.throw(/already accepted/)
+    pair[1].send("hello")
+    expect(pair[1].bufferedAmount).to.eq(5)
+  })
+
+  it("validates close codes and reasons", () => {
+    const pair = new WebSocketPair()
+    pair[1].accept()
+    expect(() => pair[1].close(1001)).to.throw(RangeError)
+    expect(() => pair[1].close(5000)).to.throw(RangeError)
+    expect(() => pair[1].close(1000, "x".repeat(124))).to.throw(RangeError)
+
+    pair[1].close(4000, "bye")
+    expect(pair[1].readyState).to.eq(2)
+    expect(() => pair[1].send("too late")).to.throw(/closed/)
+  })
+
+  it("dispatches to handlers and listeners", () => {
+    const socket = new WebSocketPair()[1]
+    const seen = []
+    socket.onmessage = ev => seen.push(["on", ev.data])
+    const listener = ev => seen.push(["listener", ev.data])
+    socket.addEventListener("message", listener)
+    socket.dispatchEvent({ type: "message", data: "a" })
+    socket.removeEventListener("message", listener)
+    socket.dispatchEvent({ type: "message", data: "b" })
+    expect(seen).to.deep.eq([["on", "a"], ["listener", "a"], ["on", "b"]])
+  })
+})
+
+describe("Response with a webSocket", () => {
+  it("answers with a 101", () => {
+    const pair = new WebSocketPair()
+    const res = new Response(null, { status: 101, webSocket: pair[0] })
+    expect(res.status).to.eq(101)
+    expect(res.webSocket).to.eq(pair[0])
+  })
+
+  it("only allows 101 with a webSocket", () => {
+    const pair = new WebSocketPair()
+    expect(() => new Response(null, { status: 101 })).to.throw(RangeError)
+    expect(() => new Response(null, { status: 200, webSocket: pair[0] })).to.throw(RangeError)
+    expect(new Response("hi").webSocket).to.eq(null)
+  })
+})